@@ -10,9 +10,10 @@ use parking_lot::RwLock;
 use pwnagotchi_macros::hookable;
 use pwnagotchi_shared::{
   config::config_read,
+  identity::Identity,
   logger::LOGGER,
   models::{
-    agent::RunningMode,
+    agent::{PowerAction, RunningMode},
     bettercap::BettercapSession,
     net::{AccessPoint, Station},
   },
@@ -28,7 +29,9 @@ use pwnagotchi_shared::{
   types::epoch::Activity,
 };
 use serde::{Deserialize, Serialize};
-use tokio::task::JoinHandle;
+use tokio::{sync::mpsc::UnboundedSender, task::JoinHandle};
+
+use crate::mesh::coordination;
 
 pub struct AgentComponent {
   agent: Option<Arc<dyn AgentTrait + Send + Sync>>,
@@ -90,7 +93,9 @@ pub struct Agent {
   pub bettercap: Arc<dyn BettercapTrait + Send + Sync>,
   pub epoch: Arc<RwLock<Epoch>>,
   pub view: Arc<dyn ViewTrait + Send + Sync>,
+  pub identity: Arc<RwLock<Identity>>,
   pub mode: RunningMode,
+  pub power: UnboundedSender<PowerAction>,
 }
 
 impl CoreModule for Agent {
@@ -146,8 +151,12 @@ impl AgentTrait for Agent {
     self.start_pwnagotchi();
   }
 
-  fn reboot(&self) {
-    self.reboot();
+  fn shutdown(&self) {
+    self.shutdown();
+  }
+
+  fn reboot(&self, mode: Option<RunningMode>) {
+    self.reboot(mode);
   }
 
   fn restart(&self, mode: Option<RunningMode>) {
@@ -163,6 +172,8 @@ impl Agent {
     epoch: Arc<RwLock<Epoch>>,
     view: Arc<dyn ViewTrait + Send + Sync>,
     sm: Arc<SessionManager>,
+    identity: Arc<RwLock<Identity>>,
+    power: UnboundedSender<PowerAction>,
   ) -> Self {
     Self {
       observer,
@@ -170,7 +181,9 @@ impl Agent {
       epoch,
       view,
       sm,
+      identity,
       mode: RunningMode::Manual,
+      power,
     }
   }
 
@@ -522,6 +535,11 @@ impl Agent {
 
     grouped_vec.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.0.cmp(&b.0)));
 
+    let grouped_vec = self.coordinate(grouped_vec);
+
+    self.sm.get_session().write().state.working_channels =
+      grouped_vec.iter().map(|(ch, _)| *ch).collect();
+
     grouped_vec
   }
 
@@ -531,13 +549,62 @@ impl Agent {
     self.observer.set_ready();
   }
 
-  fn reboot(&self) {
+  fn shutdown(&self) {
+    LOGGER.log_info("Agent", "Shutting down agent...");
+    self.request_power_action(PowerAction::Shutdown);
+  }
+
+  fn reboot(&self, mode: Option<RunningMode>) {
     LOGGER.log_info("Agent", "Rebooting agent...");
     self.observer.set_rebooting();
+    self.request_power_action(PowerAction::Reboot(mode));
   }
 
   fn restart(&self, mode: Option<RunningMode>) {
-    let _mode = mode.unwrap_or(RunningMode::Auto);
+    let mode = mode.unwrap_or(RunningMode::Auto);
+    LOGGER.log_info("Agent", &format!("Restarting agent in {mode:?} mode..."));
+    self.request_power_action(PowerAction::Restart(mode));
+  }
+
+  fn request_power_action(&self, action: PowerAction) {
+    if let Err(e) = self.power.send(action) {
+      LOGGER.log_error("Agent", &format!("Failed to request '{action}': {e}"));
+    }
+  }
+}
+
+impl Agent {
+  /// Leaves channels and targets to nearby peers that already cover them.
+  fn coordinate(&self, channels: Vec<(u8, Vec<AccessPoint>)>) -> Vec<(u8, Vec<AccessPoint>)> {
+    let (cooperate, min_rssi) = {
+      let cfg = config_read();
+      (cfg.grid.cooperate, cfg.grid.cooperate_rssi)
+    };
+
+    let session = self.sm.get_session();
+    let peers = session.read().state.peers.clone();
+    if !cooperate || peers.is_empty() {
+      session.write().state.ceded_channels.clear();
+      return channels;
+    }
+
+    let fingerprint = self.identity.read().fingerprint().to_string();
+    let plan = coordination::plan(&fingerprint, &peers, min_rssi, channels);
+
+    if plan.skipped > 0 {
+      LOGGER.log_info("Agent", &format!("Skipping {} APs pwned by peers", plan.skipped));
+    }
+
+    let changed = session.read().state.ceded_channels != plan.ceded;
+    if changed && !plan.ceded.is_empty() {
+      LOGGER.log_info("Agent", &format!("Leaving channels {:?} to nearby peers", plan.ceded));
+      if let Some((channel, _)) = plan.channels.first() {
+        self.view.on_free_channel(*channel);
+      }
+    }
+    session.write().state.ceded_channels = plan.ceded;
+
+    plan.channels
   }
 }
 
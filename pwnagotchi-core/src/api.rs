@@ -0,0 +1,239 @@
+use std::{
+  fmt::Display,
+  sync::{Arc, LazyLock},
+  time::{Duration, Instant},
+};
+
+use parking_lot::{Mutex, RwLock};
+use pwnagotchi_shared::{config::config_read, identity::Identity, logger::LOGGER};
+use ureq::{
+  Agent, Body,
+  config::Config,
+  http::{Response, StatusCode},
+};
+
+static API_CONFIG: LazyLock<Config> = LazyLock::new(|| {
+  Agent::config_builder()
+    .timeout_global(Some(Duration::from_secs(10)))
+    .http_status_as_error(false)
+    .build()
+});
+
+static CLIENT: LazyLock<Agent> = LazyLock::new(|| Agent::new_with_config(API_CONFIG.clone()));
+
+pub fn user_agent() -> String {
+  format!("pwnagotchi-rs/{}", env!("CARGO_PKG_VERSION"))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+  Get,
+  Post,
+}
+
+#[derive(Debug)]
+pub enum ApiError {
+  /// The request never got an answer, worth retrying later.
+  Unreachable(String),
+  /// The grid did not hand out a token.
+  Enrollment(String),
+}
+
+impl Display for ApiError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Unreachable(e) => write!(f, "grid unreachable: {e}"),
+      Self::Enrollment(e) => write!(f, "enrollment failed: {e}"),
+    }
+  }
+}
+
+impl std::error::Error for ApiError {}
+
+#[derive(Debug, Clone)]
+pub struct ClientOptions {
+  pub address: String,
+  pub authenticated: bool,
+  pub token_ttl: Duration,
+  pub enroll_endpoint: String,
+  pub name: String,
+}
+
+impl ClientOptions {
+  pub fn from_config() -> Self {
+    let cfg = config_read();
+    Self {
+      address: cfg.grid.api_address.to_string(),
+      authenticated: cfg.grid.authenticated,
+      token_ttl: Duration::from_secs(cfg.grid.token_ttl),
+      enroll_endpoint: cfg.grid.endpoints.enroll.to_string(),
+      name: cfg.main.name.to_string(),
+    }
+  }
+}
+
+struct Token {
+  value: String,
+  obtained_at: Instant,
+}
+
+/// HTTP client for the grid API. With `authenticated` set, it enrolls with a
+/// signed identity and attaches the token it receives to every request, the
+/// same way the pwngrid daemon does.
+pub struct ApiClient {
+  identity: Arc<RwLock<Identity>>,
+  options: Option<ClientOptions>,
+  token: Mutex<Option<Token>>,
+  data: Mutex<serde_json::Value>,
+}
+
+impl ApiClient {
+  /// Client that follows the `[grid]` config section.
+  pub fn new(identity: Arc<RwLock<Identity>>) -> Self {
+    Self {
+      identity,
+      options: None,
+      token: Mutex::new(None),
+      data: Mutex::new(serde_json::json!({})),
+    }
+  }
+
+  pub fn with_options(identity: Arc<RwLock<Identity>>, options: ClientOptions) -> Self {
+    Self {
+      options: Some(options),
+      ..Self::new(identity)
+    }
+  }
+
+  pub const fn identity(&self) -> &Arc<RwLock<Identity>> {
+    &self.identity
+  }
+
+  pub fn options(&self) -> ClientOptions {
+    self.options.clone().unwrap_or_else(ClientOptions::from_config)
+  }
+
+  pub fn url(&self, endpoint: &str) -> String {
+    let address = self.options().address;
+    format!("{}/{}", address.trim_end_matches('/'), endpoint.trim_start_matches('/'))
+  }
+
+  /// Enrolls with the grid, `data` replaces the session data sent along with
+  /// this and every later enrollment.
+  pub fn enroll(&self, data: Option<serde_json::Value>) -> Result<(), ApiError> {
+    if let Some(data) = data {
+      *self.data.lock() = data;
+    }
+
+    let options = self.options();
+
+    let enrollment = {
+      let identity = self.identity.read();
+      let public_key = identity
+        .public_key_b64()
+        .ok_or_else(|| ApiError::Enrollment("keys not loaded".to_string()))?
+        .to_string();
+      let unit = format!("{}@{}", options.name, identity.fingerprint());
+      let signature = identity.sign(&unit).map_err(ApiError::Enrollment)?;
+
+      serde_json::json!({
+        "identity": unit,
+        "public_key": public_key,
+        "signature": signature,
+        "data": *self.data.lock(),
+      })
+    };
+
+    LOGGER.log_debug("GRID", "Refreshing API token");
+
+    let mut response =
+      self.send(Method::Post, &options.enroll_endpoint, Some(&enrollment), None)?;
+    if response.status() != StatusCode::OK {
+      return Err(ApiError::Enrollment(format!("status {}", response.status())));
+    }
+
+    let token = response
+      .body_mut()
+      .read_json::<serde_json::Value>()
+      .ok()
+      .and_then(|v| v.get("token").and_then(|t| t.as_str()).map(str::to_string))
+      .ok_or_else(|| ApiError::Enrollment("no token in response".to_string()))?;
+
+    *self.token.lock() = Some(Token {
+      value: token,
+      obtained_at: Instant::now(),
+    });
+    Ok(())
+  }
+
+  /// Sends a request, enrolling first when the token is missing or expired
+  /// and once more if the grid rejects it.
+  pub fn request(
+    &self,
+    method: Method,
+    endpoint: &str,
+    body: Option<&serde_json::Value>,
+  ) -> Result<Response<Body>, ApiError> {
+    let options = self.options();
+    if !options.authenticated {
+      return self.send(method, endpoint, body, None);
+    }
+
+    let token = self.token(&options)?;
+    let response = self.send(method, endpoint, body, Some(&token))?;
+    if response.status() != StatusCode::UNAUTHORIZED {
+      return Ok(response);
+    }
+
+    LOGGER.log_debug("GRID", "Token rejected, enrolling again");
+    *self.token.lock() = None;
+    let token = self.token(&options)?;
+    self.send(method, endpoint, body, Some(&token))
+  }
+
+  fn token(&self, options: &ClientOptions) -> Result<String, ApiError> {
+    if let Some(token) = self.token.lock().as_ref()
+      && token.obtained_at.elapsed() < options.token_ttl
+    {
+      return Ok(token.value.clone());
+    }
+
+    self.enroll(None)?;
+    self
+      .token
+      .lock()
+      .as_ref()
+      .map(|t| t.value.clone())
+      .ok_or_else(|| ApiError::Enrollment("no token".to_string()))
+  }
+
+  fn send(
+    &self,
+    method: Method,
+    endpoint: &str,
+    body: Option<&serde_json::Value>,
+    token: Option<&str>,
+  ) -> Result<Response<Body>, ApiError> {
+    let url = self.url(endpoint);
+    let auth = token.map(|t| format!("Bearer {t}"));
+
+    let res = match method {
+      Method::Get => {
+        let mut req = CLIENT.get(&url).header("User-Agent", user_agent());
+        if let Some(auth) = &auth {
+          req = req.header("Authorization", auth);
+        }
+        req.call()
+      }
+      Method::Post => {
+        let mut req = CLIENT.post(&url).header("User-Agent", user_agent());
+        if let Some(auth) = &auth {
+          req = req.header("Authorization", auth);
+        }
+        req.send_json(body.unwrap_or(&serde_json::Value::Null))
+      }
+    };
+
+    res.map_err(|e| ApiError::Unreachable(e.to_string()))
+  }
+}
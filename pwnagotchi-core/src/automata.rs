@@ -239,15 +239,13 @@ impl Automata {
       self.set_grateful();
     }
 
-    // Blindness
-    if blind_for >= 5 {
+    // Blindness, recovery is handled by the InterfaceManager once per window
+    let max_blind = config_read().main.mon_max_blind_epochs;
+    if blind_for > 0 && max_blind > 0 && blind_for % max_blind == 0 {
       LOGGER.log_fatal(
         "Personality",
         format!("{blind_for} epochs without visible access points -> we are blind!").as_str(),
       );
-
-      //self.restart();
-      self.epoch.write().blind_for = 0;
     }
   }
 }
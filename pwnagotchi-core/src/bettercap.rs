@@ -1,5 +1,4 @@
 use std::{
-  borrow::Cow,
   sync::{
     Arc,
     atomic::{AtomicBool, Ordering},
@@ -11,19 +10,21 @@ use anyhow::Result;
 use base64::{Engine, engine::general_purpose};
 use futures_util::{SinkExt, StreamExt};
 use pwnagotchi_shared::{
-  config::config_read,
+  config::{config_read, reload},
   logger::LOGGER,
   models::bettercap::BettercapSession,
   traits::{
     bettercap::{BettercapCommand, BettercapTrait},
     general::{Component, CoreModule, CoreModules, Dependencies},
   },
+  types::components::RestartPolicy,
 };
 use tokio::{sync::broadcast, task::JoinHandle};
 use tokio_tungstenite::{
   connect_async,
   tungstenite::{client::IntoClientRequest, protocol::Message},
 };
+use tokio_util::sync::CancellationToken;
 use ureq::{
   Agent, Body, SendBody,
   http::{Request, Response, header::HeaderValue},
@@ -69,6 +70,7 @@ impl BettercapTrait for Bettercap {
 
 pub struct BettercapComponent {
   bettercap: Option<Arc<dyn BettercapTrait + Send + Sync>>,
+  shutdown: CancellationToken,
 }
 
 impl Default for BettercapComponent {
@@ -79,7 +81,10 @@ impl Default for BettercapComponent {
 
 impl BettercapComponent {
   pub fn new() -> Self {
-    Self { bettercap: None }
+    Self {
+      bettercap: None,
+      shutdown: CancellationToken::new(),
+    }
   }
 }
 
@@ -97,6 +102,7 @@ impl Dependencies for BettercapComponent {
 impl Component for BettercapComponent {
   async fn init(&mut self, ctx: &CoreModules) -> Result<()> {
     self.bettercap = Some(Arc::clone(&ctx.bettercap));
+    self.shutdown = ctx.shutdown.clone();
     Ok(())
   }
 
@@ -106,14 +112,22 @@ impl Component for BettercapComponent {
     {
       LOGGER.log_info("Bettercap", "Starting Bettercap WebSocket connection...");
       let bc = Arc::clone(bc);
+      let shutdown = self.shutdown.clone();
       let handle = tokio::spawn(async move {
-        bc.run_websocket().await;
+        tokio::select! {
+          () = bc.run_websocket() => {},
+          () = shutdown.cancelled() => {},
+        }
       });
 
       return Ok(Some(handle));
     }
     Ok(None)
   }
+
+  fn restart_policy(&self) -> RestartPolicy {
+    RestartPolicy::default().critical()
+  }
 }
 
 #[derive(Debug, Clone)]
@@ -126,14 +140,6 @@ pub struct Bettercap {
   pub max_sleep: f64,
   pub is_ready: Arc<AtomicBool>,
 
-  hostname: Cow<'static, str>,
-  port: u16,
-  username: Cow<'static, str>,
-  password: Cow<'static, str>,
-  url: String,
-  websocket_url: String,
-  scheme: Cow<'static, str>,
-
   event_tx: broadcast::Sender<String>,
   req_client: Agent,
 }
@@ -163,10 +169,31 @@ fn bettercap_add_authorization(
   next.handle(req)
 }
 
+/// Where the bettercap API lives, read from the config on every use so
+/// changes apply without a restart.
+struct Endpoint {
+  api: String,
+  events: String,
+  authorization: String,
+}
+
+impl Endpoint {
+  fn from_config() -> Self {
+    let cfg = config_read();
+    let bc = &cfg.bettercap;
+    let (scheme, ws_scheme) = if bc.port == 443 { ("https", "wss") } else { ("http", "ws") };
+    let credentials = format!("{}:{}", bc.username, bc.password);
+
+    Self {
+      api: format!("{scheme}://{credentials}@{}:{}/api", bc.hostname, bc.port),
+      events: format!("{ws_scheme}://{credentials}@{}:{}/api/events", bc.hostname, bc.port),
+      authorization: format!("Basic {}", general_purpose::STANDARD.encode(credentials)),
+    }
+  }
+}
+
 impl Bettercap {
   pub fn new() -> Self {
-    let scheme = if config_read().bettercap.port == 443 { "https" } else { "http" };
-    let ws_scheme = if config_read().bettercap.port == 443 { "wss" } else { "ws" };
     let max_queue = 10_000usize;
     let (event_tx, _rx) = broadcast::channel(max_queue);
 
@@ -183,27 +210,6 @@ impl Bettercap {
       max_queue,
       min_sleep: 0.5,
       max_sleep: 5.0,
-      hostname: config_read().bettercap.hostname.clone(),
-      port: config_read().bettercap.port,
-      username: config_read().bettercap.username.clone(),
-      password: config_read().bettercap.password.clone(),
-      url: format!(
-        "{}://{}:{}@{}:{}/api",
-        scheme,
-        config_read().bettercap.username,
-        config_read().bettercap.password,
-        config_read().bettercap.hostname,
-        config_read().bettercap.port
-      ),
-      websocket_url: format!(
-        "{}://{}:{}@{}:{}/api/events",
-        ws_scheme,
-        config_read().bettercap.username,
-        config_read().bettercap.password,
-        config_read().bettercap.hostname,
-        config_read().bettercap.port
-      ),
-      scheme: Cow::Borrowed(scheme),
       is_ready: Arc::new(AtomicBool::new(false)),
       event_tx,
       req_client,
@@ -216,15 +222,7 @@ impl Bettercap {
 
   pub fn session(&self, sess: Option<&str>) -> Option<BettercapSession> {
     let sess = sess.unwrap_or("session");
-    let base = self
-      .url
-      .replace("%{scheme}", &self.scheme)
-      .replace("%{username}", &self.username)
-      .replace("%{password}", &self.password)
-      .replace("%{hostname}", &self.hostname)
-      .replace("%{port}", &self.port.to_string());
-
-    let url = format!("{base}/{sess}");
+    let url = format!("{}/{sess}", Endpoint::from_config().api);
     let cli = self.req_client.get(url).call();
 
     cli.map_or(None, |mut resp| match resp.body_mut().read_json::<BettercapSession>() {
@@ -237,28 +235,39 @@ impl Bettercap {
   }
 
   pub async fn run_websocket(&self) {
-    let ws_url = self.websocket_url.clone();
     let min_sleep = self.min_sleep;
     let max_sleep = self.max_sleep;
+    let mut config_changes = reload::subscribe();
 
     LOGGER.log_info("Bettercap", "Connecting to Event WebSocket");
 
-    // Prepare authorization header value
-    let auth_header = format!(
-      "Basic {}",
-      general_purpose::STANDARD.encode(format!("{}:{}", self.username, self.password))
-    );
-
     loop {
-      let mut req = ws_url.clone().into_client_request().unwrap();
-      req.headers_mut().insert("Authorization", auth_header.parse().unwrap());
+      let endpoint = Endpoint::from_config();
+      let mut req = endpoint.events.into_client_request().unwrap();
+      req
+        .headers_mut()
+        .insert("Authorization", endpoint.authorization.parse().unwrap());
 
       match connect_async(req).await {
         Ok((ws_stream, _)) => {
           self.is_ready.store(true, Ordering::SeqCst);
           LOGGER.log_info("Bettercap", "Event WebSocket connected");
           let (mut write, mut read) = ws_stream.split();
-          while let Some(msg) = read.next().await {
+          loop {
+            let msg = tokio::select! {
+              msg = read.next() => msg,
+              Ok(changed) = config_changes.recv() => {
+                if changed.touches("/bettercap") {
+                  LOGGER.log_info("Bettercap", "Config changed, reconnecting Event WebSocket");
+                  self.is_ready.store(false, Ordering::SeqCst);
+                  break;
+                }
+                continue;
+              }
+            };
+            let Some(msg) = msg else {
+              break;
+            };
             match msg {
               Ok(Message::Text(txt)) => {
                 let _ = self.event_tx.send(txt.to_string());
@@ -295,14 +304,7 @@ impl Bettercap {
   }
 
   pub async fn run(&self, cmd: &str) -> Result<(), anyhow::Error> {
-    let url = self
-      .url
-      .replace("%{scheme}", &self.scheme)
-      .replace("%{username}", &self.username)
-      .replace("%{password}", &self.password)
-      .replace("%{hostname}", &self.hostname)
-      .replace("%{port}", &self.port.to_string())
-      + "/session".trim_end_matches('/');
+    let url = Endpoint::from_config().api + "/session";
 
     let mut retries_left = self.retries;
 
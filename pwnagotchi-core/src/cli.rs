@@ -7,6 +7,7 @@ use pwnagotchi_shared::{
   traits::general::CoreModules, types::events::EventPayload,
 };
 use tokio::time::sleep;
+use tracing::Instrument;
 
 pub struct Cli {
   pub core: Arc<CoreModules>,
@@ -26,46 +27,52 @@ impl Cli {
     self.core.agent.start_pwnagotchi();
 
     loop {
-      self.core.agent.recon().await;
+      // everything logged during the epoch carries its number
+      let epoch = self.core.epoch.read().epoch;
+      self.run_epoch().instrument(tracing::info_span!("epoch", epoch)).await;
+    }
+  }
 
-      let aps = self.core.agent.get_access_points_by_channel().await;
+  async fn run_epoch(&self) {
+    self.core.agent.recon().await;
 
-      for (ch, aps) in aps {
-        sleep(Duration::from_secs(1)).await;
-        self.core.agent.set_channel(ch).await;
+    let aps = self.core.agent.get_access_points_by_channel().await;
 
-        if !self.core.automata.is_stale() && self.core.automata.any_activity() {
-          LOGGER.log_info("Pwnagotchi", format!("{} APs on channel {ch}", aps.len()).as_str());
-        }
+    for (ch, aps) in aps {
+      sleep(Duration::from_secs(1)).await;
+      self.core.agent.set_channel(ch).await;
+
+      if !self.core.automata.is_stale() && self.core.automata.any_activity() {
+        LOGGER.log_info("Pwnagotchi", format!("{} APs on channel {ch}", aps.len()).as_str());
+      }
 
-        for ap in aps {
-          self.core.agent.associate(&ap, None).await;
+      for ap in aps {
+        self.core.agent.associate(&ap, None).await;
 
-          for sta in &ap.clients {
-            self.core.agent.deauth(&ap, sta, None).await;
-            // Shoo Nexmon Bugs!
-            sleep(Duration::from_secs(1)).await;
-          }
+        for sta in &ap.clients {
+          self.core.agent.deauth(&ap, sta, None).await;
+          // Shoo Nexmon Bugs!
+          sleep(Duration::from_secs(1)).await;
         }
       }
+    }
 
-      self.core.automata.next_epoch();
-
-      if self.core.grid.is_connected() {
-        let last_session = &self.core.session_manager.get_last_session();
+    self.core.automata.next_epoch();
 
-        let Some(stats) = last_session.read().stats.clone() else {
-          LOGGER.log_debug("GRID", "No session stats available to upload.");
-          continue;
-        };
+    if self.core.grid.is_connected() {
+      let last_session = &self.core.session_manager.get_last_session();
 
-        let _ = self
-          .core
-          .events
-          .emit_payload("internet_available", EventPayload::new::<SessionStats>(&stats).unwrap())
-          .await;
-        drop(stats);
-      }
+      let Some(stats) = last_session.read().stats.clone() else {
+        LOGGER.log_debug("GRID", "No session stats available to upload.");
+        return;
+      };
+
+      let _ = self
+        .core
+        .events
+        .emit_payload("internet_available", EventPayload::new::<SessionStats>(&stats).unwrap())
+        .await;
+      drop(stats);
     }
   }
 
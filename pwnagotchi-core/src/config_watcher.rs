@@ -0,0 +1,175 @@
+use std::{
+  path::PathBuf,
+  sync::Arc,
+  time::{Duration, SystemTime},
+};
+
+use anyhow::Result;
+use pwnagotchi_shared::{
+  config::{
+    CONFIG_PATH, config_read, layers,
+    reload::{self, ConfigChanged},
+  },
+  logger::{LOGGER, layer},
+  traits::{
+    bettercap::BettercapTrait,
+    events::EventBus,
+    general::{Component, CoreModules, Dependencies},
+  },
+  types::events::EventPayload,
+};
+use tokio::{
+  signal::unix::{SignalKind, signal},
+  sync::broadcast::error::RecvError,
+  task::JoinHandle,
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::setup::apply_personality;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Settings bettercap was handed at setup, see [`apply_personality`].
+const BETTERCAP_PERSONALITY: [&str; 3] = [
+  "/personality/ap_ttl",
+  "/personality/sta_ttl",
+  "/personality/min_rssi",
+];
+
+/// Reloads the config when one of its files changes or on SIGHUP, and applies
+/// changes to the parts that don't read the config on every use.
+pub struct ConfigWatcherComponent {
+  bc: Option<Arc<dyn BettercapTrait + Send + Sync>>,
+  events: Option<Arc<dyn EventBus + Send + Sync>>,
+  shutdown: CancellationToken,
+}
+
+impl Dependencies for ConfigWatcherComponent {
+  fn name(&self) -> &'static str {
+    "ConfigWatcherComponent"
+  }
+
+  fn dependencies(&self) -> &[&str] {
+    &["Bettercap", "SetupComponent"]
+  }
+}
+
+#[async_trait::async_trait]
+impl Component for ConfigWatcherComponent {
+  async fn init(&mut self, ctx: &CoreModules) -> Result<()> {
+    self.bc = Some(Arc::clone(&ctx.bettercap));
+    self.events = Some(Arc::clone(&ctx.events));
+    self.shutdown = ctx.shutdown.clone();
+    Ok(())
+  }
+
+  async fn start(&self) -> Result<Option<JoinHandle<()>>> {
+    let (Some(bc), Some(events)) = (&self.bc, &self.events) else {
+      return Ok(None);
+    };
+    if CONFIG_PATH.get().is_none() {
+      return Ok(None);
+    }
+
+    let (bc, events) = (Arc::clone(bc), Arc::clone(events));
+    let shutdown = self.shutdown.clone();
+    let mut changes = reload::subscribe();
+    let mut hangup = signal(SignalKind::hangup())?;
+    let mut last_modified = modified();
+
+    let handle = tokio::spawn(async move {
+      loop {
+        tokio::select! {
+          () = shutdown.cancelled() => break,
+          _ = hangup.recv() => {
+            LOGGER.log_info("CONFIG", "Got SIGHUP, reloading config");
+            reload_config().await;
+          }
+          () = tokio::time::sleep(POLL_INTERVAL) => {
+            let current = modified();
+            if current != last_modified {
+              last_modified = current;
+              reload_config().await;
+            }
+          }
+          changed = changes.recv() => match changed {
+            Ok(changed) => on_config_changed(&bc, &events, &changed).await,
+            Err(RecvError::Lagged(n)) => {
+              LOGGER.log_warning("CONFIG", &format!("Missed {n} config changes"));
+            }
+            Err(RecvError::Closed) => break,
+          },
+        }
+      }
+    });
+
+    Ok(Some(handle))
+  }
+}
+
+impl Default for ConfigWatcherComponent {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl ConfigWatcherComponent {
+  pub fn new() -> Self {
+    Self {
+      bc: None,
+      events: None,
+      shutdown: CancellationToken::new(),
+    }
+  }
+}
+
+/// Modification time and size of every file the config is made of, and of
+/// the fragment directory so added or removed fragments count too.
+fn modified() -> Vec<(PathBuf, Option<(SystemTime, u64)>)> {
+  let files = layers::current().map(|stack| stack.files()).unwrap_or_default();
+  files
+    .into_iter()
+    .map(|path| {
+      let meta = std::fs::metadata(&path).ok();
+      let stamp = meta.and_then(|meta| Some((meta.modified().ok()?, meta.len())));
+      (path, stamp)
+    })
+    .collect()
+}
+
+async fn reload_config() {
+  match tokio::task::spawn_blocking(reload::reload).await {
+    Ok(Ok(_)) => {}
+    Ok(Err(e)) => LOGGER.log_error("CONFIG", &format!("Not reloading config: {e}")),
+    Err(e) => LOGGER.log_error("CONFIG", &format!("Config reload failed: {e}")),
+  }
+}
+
+async fn on_config_changed(
+  bc: &Arc<dyn BettercapTrait + Send + Sync>,
+  events: &Arc<dyn EventBus + Send + Sync>,
+  changed: &ConfigChanged,
+) {
+  if BETTERCAP_PERSONALITY.iter().any(|key| changed.touches(key)) {
+    LOGGER.log_info("CONFIG", "Applying personality changes to bettercap");
+    apply_personality(bc).await;
+  }
+  if changed.touches("/main/whitelist") {
+    LOGGER.log_info("CONFIG", "Whitelist changed, applies from the next scan");
+  }
+  if changed.touches("/log/filters") {
+    layer::set_filters(&config_read().log);
+  }
+  if changed.touches("/log/sinks") {
+    LOGGER.log_info("CONFIG", "Log sinks changed, reconnecting");
+    let log = config_read().log.clone();
+    let _ = tokio::task::spawn_blocking(move || LOGGER.sinks().configure(&log)).await;
+  }
+
+  match EventPayload::new(changed) {
+    Ok(payload) => {
+      let _ = events.emit_payload("config_changed", payload).await;
+    }
+    Err(e) => LOGGER.log_error("CONFIG", &format!("Failed to build config_changed event: {e}")),
+  }
+}
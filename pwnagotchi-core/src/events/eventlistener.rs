@@ -4,6 +4,7 @@ use anyhow::Result;
 use parking_lot::RwLock;
 use pwnagotchi_shared::{
   config::config_read,
+  live::{self, LiveEvent},
   logger::LOGGER,
   models::net::Handshake,
   sessions::manager::SessionManager,
@@ -14,16 +15,18 @@ use pwnagotchi_shared::{
     general::{Component, CoreModules, Dependencies},
     ui::ViewTrait,
   },
-  types::epoch::Activity,
+  types::{components::RestartPolicy, epoch::Activity},
   utils::general::{hostname_or_mac, total_unique_handshakes},
 };
 use serde_json::Value;
 use tokio::{sync::mpsc, task::JoinHandle};
+use tokio_util::sync::CancellationToken;
 
 use crate::agent::find_ap_sta_in_session;
 
 pub struct EventListenerComponent {
   eventlistener: Option<Arc<EventListener>>,
+  shutdown: CancellationToken,
 }
 
 impl Default for EventListenerComponent {
@@ -34,7 +37,10 @@ impl Default for EventListenerComponent {
 
 impl EventListenerComponent {
   pub fn new() -> Self {
-    Self { eventlistener: None }
+    Self {
+      eventlistener: None,
+      shutdown: CancellationToken::new(),
+    }
   }
 }
 
@@ -64,6 +70,7 @@ impl Component for EventListenerComponent {
     let events = Arc::clone(&ctx.events);
     let eventlistener = EventListener::new(sm, bettercap, epoch, view, events);
     self.eventlistener = Some(Arc::new(eventlistener));
+    self.shutdown = ctx.shutdown.clone();
 
     Ok(())
   }
@@ -72,13 +79,18 @@ impl Component for EventListenerComponent {
     if let Some(ev) = &self.eventlistener {
       LOGGER.log_info("Agent", "Starting EventListener component");
       let ev = Arc::clone(ev);
+      let shutdown = self.shutdown.clone();
       let handle = tokio::spawn(async move {
-        start_event_loop(&ev.sm, &ev.bettercap, &ev.epoch, &ev.view, &ev.events).await;
+        start_event_loop(&ev.sm, &ev.bettercap, &ev.epoch, &ev.view, &ev.events, &shutdown).await;
       });
       return Ok(Some(handle));
     }
     Ok(None)
   }
+
+  fn restart_policy(&self) -> RestartPolicy {
+    RestartPolicy::default().critical()
+  }
 }
 
 pub struct EventListener {
@@ -107,6 +119,7 @@ pub async fn start_event_loop(
   epoch: &Arc<RwLock<Epoch>>,
   view: &Arc<dyn ViewTrait + Send + Sync>,
   events: &Arc<dyn EventBus>,
+  shutdown: &CancellationToken,
 ) {
   LOGGER.log_info("Agent", "Starting event loop");
   let (tx, mut rx) = tokio::sync::mpsc::channel::<String>(1000);
@@ -114,6 +127,7 @@ pub async fn start_event_loop(
   tokio::spawn({
     let tx = tx;
     let bc = Arc::clone(bc);
+    let shutdown = shutdown.clone();
     async move {
       let (bc_tx, bc_rx) = tokio::sync::oneshot::channel();
       bc.send(BettercapCommand::SubscribeEvents { respond_to: bc_tx })
@@ -125,7 +139,16 @@ pub async fn start_event_loop(
         return;
       };
 
-      while let Ok(msg) = bettercap_rx.recv().await {
+      loop {
+        let msg = tokio::select! {
+          msg = bettercap_rx.recv() => msg,
+          () = shutdown.cancelled() => break,
+        };
+
+        let Ok(msg) = msg else {
+          break;
+        };
+
         if tx.send(msg).await.is_err() {
           LOGGER.log_error("Agent", "Agent inbox dropped, stopping event forwarder");
           break;
@@ -205,13 +228,19 @@ pub async fn handle_handshake_event(
     std::collections::hash_map::Entry::Vacant(entry) => {
       entry.insert(Handshake {
         mac: ap_mac.clone(),
-        filename,
+        filename: filename.clone(),
         timestamp: std::time::SystemTime::now(),
       });
     }
   }
   drop(session_mut);
 
+  live::publish(LiveEvent::Handshake {
+    ap: ap_mac.clone(),
+    station: sta_mac.clone(),
+    file: filename,
+  });
+
   let last_pwned_hostname = find_ap_sta_in_session(&session, &sta_mac, &ap_mac)
     .map(|(ap, sta)| {
       LOGGER.log_info(
@@ -1,26 +1,54 @@
-const API_ADDRESS: &str = "http://127.0.0.1:8666/api/v1";
-
-use std::{process::Command, sync::LazyLock};
+use std::{
+  path::PathBuf,
+  process::Command,
+  sync::{
+    Arc, LazyLock, Weak,
+    atomic::{AtomicBool, Ordering},
+  },
+  time::Duration,
+};
 
+use base64::{Engine, engine::general_purpose};
+use parking_lot::{Mutex, RwLock};
 use pwnagotchi_shared::{
   config::config_read,
+  identity::{
+    Identity,
+    crypto::{encrypt_for, public_key_from_b64, verify},
+  },
   logger::LOGGER,
   models::grid::PeerResponse,
   sessions::session_stats::SessionStats,
-  traits::{general::CoreModule, grid::GridTrait},
+  traits::{
+    general::CoreModule,
+    grid::{GridTrait, count_unread},
+  },
   types::grid::GridDataResponse,
 };
+use rsa::RsaPublicKey;
 use ureq::{Agent, config::Config};
 
-static GRID_CONFIG: LazyLock<Config> = LazyLock::new(|| {
+use crate::api::{ApiClient, Method, user_agent};
+
+static CHECK_CONFIG: LazyLock<Config> = LazyLock::new(|| {
   Agent::config_builder()
     .timeout_global(Some(std::time::Duration::from_secs(10)))
     .build()
 });
 
-static CLIENT: LazyLock<Agent> = LazyLock::new(|| Agent::new_with_config(GRID_CONFIG.clone()));
+static CHECK_CLIENT: LazyLock<Agent> =
+  LazyLock::new(|| Agent::new_with_config(CHECK_CONFIG.clone()));
+
+struct GridState {
+  connected: AtomicBool,
+  queue: OfflineQueue,
+  client: ApiClient,
+  unread: Mutex<Option<u32>>,
+}
 
-pub struct Grid;
+pub struct Grid {
+  state: Arc<GridState>,
+}
 
 impl CoreModule for Grid {
   fn name(&self) -> &'static str {
@@ -28,33 +56,71 @@ impl CoreModule for Grid {
   }
 }
 
-impl Default for Grid {
-  fn default() -> Self {
-    Self::new()
+impl Grid {
+  pub fn new(identity: Arc<RwLock<Identity>>) -> Self {
+    let queue_file = config_read().grid.queue_file.to_string();
+    let state = Arc::new(GridState {
+      connected: AtomicBool::new(false),
+      queue: OfflineQueue::new(queue_file),
+      client: ApiClient::new(identity),
+      unread: Mutex::new(None),
+    });
+
+    let weak = Arc::downgrade(&state);
+    let spawned = std::thread::Builder::new()
+      .name("grid-connectivity".to_string())
+      .spawn(move || connectivity_loop(&weak));
+
+    if let Err(e) = spawned {
+      LOGGER.log_error("GRID", &format!("Failed to start connectivity checks: {e}"));
+    }
+
+    Self { state }
   }
-}
 
-impl Grid {
-  pub fn new() -> Self {
-    Self
+  /// Sends `report` right away when the grid is reachable, otherwise keeps it
+  /// on disk until connectivity returns.
+  fn submit(&self, report: QueuedReport) {
+    if self.is_connected() && report.deliver(&self.state.client) {
+      return;
+    }
+
+    LOGGER.log_debug("GRID", "Grid unreachable, queueing report");
+    self.state.queue.push(report);
+  }
+
+  /// Looks up the public key another unit enrolled with.
+  fn unit_key(&self, fingerprint: &str) -> Result<RsaPublicKey, String> {
+    let endpoint = config_read().grid.endpoints.unit.clone();
+    let mut response = self
+      .state
+      .client
+      .request(Method::Get, &format!("{endpoint}/{fingerprint}"), None)
+      .map_err(|e| e.to_string())?;
+
+    if response.status() != 200 {
+      return Err(format!("unit {fingerprint} not found ({})", response.status().as_u16()));
+    }
+
+    let unit = response
+      .body_mut()
+      .read_json::<serde_json::Value>()
+      .map_err(|e| e.to_string())?;
+    let pem = unit
+      .get("public_key")
+      .and_then(serde_json::Value::as_str)
+      .ok_or_else(|| format!("unit {fingerprint} has no public key"))?;
+    public_key_from_b64(pem).map_err(|e| e.to_string())
   }
 
   fn call<T>(&self, endpoint: &str, data: &serde_json::Value) -> Option<T>
   where
     T: serde::de::DeserializeOwned,
   {
-    let url = format!("{API_ADDRESS}/{endpoint}");
-
     let req = if data.is_null() {
-      CLIENT
-        .get(&url)
-        .header("User-Agent", format!("pwnagotchi-rs/{}", env!("CARGO_PKG_VERSION")))
-        .call()
+      self.state.client.request(Method::Get, endpoint, None)
     } else {
-      CLIENT
-        .post(&url)
-        .header("User-Agent", format!("pwnagotchi-rs/{}", env!("CARGO_PKG_VERSION")))
-        .send_json(data)
+      self.state.client.request(Method::Post, endpoint, Some(data))
     };
 
     match req {
@@ -90,43 +156,33 @@ impl Grid {
 #[async_trait::async_trait]
 impl GridTrait for Grid {
   fn is_connected(&self) -> bool {
-    let host = "https://api.opwngrid.xyz/api/v1/uptime";
-    let req = CLIENT
-      .get(host)
-      .header("User-Agent", format!("pwnagotchi-rs/{}", env!("CARGO_PKG_VERSION")))
-      .call();
-
-    match req {
-      Ok(mut response) => {
-        let json = response.body_mut().read_json::<serde_json::Value>();
-        match json {
-          Ok(value) => value.get("isUp").and_then(|v| v.as_bool()).unwrap_or(false),
-          Err(_) => false,
-        }
-      }
-      Err(_) => false,
-    }
+    self.state.connected.load(Ordering::Relaxed)
   }
 
   fn advertise(&self, enabled: Option<bool>) -> Option<serde_json::Value> {
     let enabled = enabled.unwrap_or(true);
-    self.call(&format!("mesh/{enabled}"), &serde_json::Value::Null)
+    let endpoint = config_read().grid.endpoints.mesh.clone();
+    self.call(&format!("{endpoint}/{enabled}"), &serde_json::Value::Null)
   }
 
   fn set_advertisement_data(&self, data: serde_json::Value) -> Option<serde_json::Value> {
-    self.call("mesh/data", &data)
+    let endpoint = config_read().grid.endpoints.mesh_data.clone();
+    self.call(&endpoint, &data)
   }
 
   fn get_advertisement_data(&self) -> Option<serde_json::Value> {
-    self.call("mesh/data", &serde_json::Value::Null)
+    let endpoint = config_read().grid.endpoints.mesh_data.clone();
+    self.call(&endpoint, &serde_json::Value::Null)
   }
 
   fn memory(&self) -> Option<serde_json::Value> {
-    self.call("system/memory", &serde_json::Value::Null)
+    let endpoint = config_read().grid.endpoints.memory.clone();
+    self.call(&endpoint, &serde_json::Value::Null)
   }
 
   async fn peers(&self) -> Option<Vec<PeerResponse>> {
-    self.call("mesh/peers", &serde_json::Value::Null)
+    let endpoint = config_read().grid.endpoints.mesh_peers.clone();
+    self.call(&endpoint, &serde_json::Value::Null)
   }
 
   /// Returns the closest peer from the list of peers, if any.
@@ -182,7 +238,7 @@ impl GridTrait for Grid {
       &format!("Updating Grid Data: {}", serde_json::to_string(&data).unwrap_or_default()),
     );
 
-    self.call::<GridDataResponse>("data", &data);
+    self.submit(QueuedReport::Data(data));
   }
 
   fn report_ap(&self, essid: &str, bssid: &str) {
@@ -192,26 +248,347 @@ impl GridTrait for Grid {
     });
 
     LOGGER.log_debug("GRID", &format!("Reporting AP {essid} ({bssid})"));
-    self.call::<()>("report/ap", &data);
+    self.submit(QueuedReport::ReportAp(data));
   }
 
   fn inbox(&self, page: Option<u32>, with_pager: Option<bool>) -> Option<serde_json::Value> {
     let page = page.unwrap_or(1);
     let with_pager = with_pager.unwrap_or(false);
-    let res = self.call(&format!("inbox?p={page}"), &serde_json::Value::Null);
+    let endpoint = config_read().grid.endpoints.inbox.clone();
+    let res = self.call(&format!("{endpoint}?p={page}"), &serde_json::Value::Null);
     if with_pager { res } else { res.and_then(|r| r.get("messages").cloned()) }
   }
 
   fn inbox_message(&self, id: &str) -> Option<serde_json::Value> {
-    self.call(&format!("inbox/{id}"), &serde_json::Value::Null)
+    let endpoint = config_read().grid.endpoints.inbox.clone();
+    let mut message: serde_json::Value =
+      self.call(&format!("{endpoint}/{id}"), &serde_json::Value::Null)?;
+
+    let sender = message.get("sender").and_then(serde_json::Value::as_str);
+    let key = sender.and_then(|fingerprint| match self.unit_key(fingerprint) {
+      Ok(key) => Some(key),
+      Err(e) => {
+        LOGGER.log_debug("GRID", &format!("Can't verify message {id}: {e}"));
+        None
+      }
+    });
+
+    open_message(&self.state.client.identity().read(), key.as_ref(), &mut message);
+    Some(message)
+  }
+
+  /// The count from the last background inbox check, so callers never wait
+  /// on the grid for it.
+  fn unread_messages(&self) -> Option<u32> {
+    *self.state.unread.lock()
   }
 
   fn mark_message(&self, id: &str, mark: &str) -> Option<serde_json::Value> {
-    self.call(&format!("inbox/{id}/mark"), &serde_json::json!({ "read": mark }))
+    let endpoint = config_read().grid.endpoints.inbox.clone();
+    self.call(&format!("{endpoint}/{id}/{mark}"), &serde_json::Value::Null)
+  }
+
+  fn send_message(&self, to: &str, message: &str) -> Result<(), String> {
+    if message.trim().is_empty() {
+      return Err("aborted: empty message".to_string());
+    }
+
+    let key = self.unit_key(to)?;
+    let body = seal_message(&self.state.client.identity().read(), &key, message)?;
+
+    let endpoint = config_read().grid.endpoints.unit.clone();
+    match self
+      .state
+      .client
+      .request(Method::Post, &format!("{endpoint}/{to}/inbox"), Some(&body))
+    {
+      Ok(response) if response.status() == 200 => {
+        LOGGER.log_info("GRID", &format!("Sent message to {to}"));
+        Ok(())
+      }
+      Ok(response) => Err(format!("grid rejected message with status {}", response.status())),
+      Err(e) => Err(e.to_string()),
+    }
+  }
+}
+
+/// Encrypts `message` for `recipient` and signs the ciphertext, ready to be
+/// posted to the recipient's inbox.
+pub fn seal_message(
+  identity: &Identity,
+  recipient: &RsaPublicKey,
+  message: &str,
+) -> Result<serde_json::Value, String> {
+  let data = encrypt_for(message.as_bytes(), recipient).map_err(|e| e.to_string())?;
+  let signature = identity.sign_data(&data)?;
+
+  Ok(serde_json::json!({
+    "data": general_purpose::STANDARD.encode(&data),
+    "signature": signature,
+  }))
+}
+
+/// Replaces the encrypted `data` of an inbox message with its cleartext and
+/// sets `verified` to whether it was signed by `sender`. Messages we can't
+/// decrypt are left untouched.
+pub fn open_message(
+  identity: &Identity,
+  sender: Option<&RsaPublicKey>,
+  message: &mut serde_json::Value,
+) {
+  let Some(obj) = message.as_object_mut() else {
+    return;
+  };
+
+  let data = obj.get("data").and_then(serde_json::Value::as_str);
+  let Some(packed) = data.and_then(|d| general_purpose::STANDARD.decode(d).ok()) else {
+    obj.insert("verified".into(), false.into());
+    return;
+  };
+
+  let signature = obj.get("signature").and_then(serde_json::Value::as_str).unwrap_or_default();
+  let verified = sender.is_some_and(|key| verify(key, &packed, signature));
+
+  match identity.decrypt(&packed) {
+    Ok(cleartext) => {
+      obj.insert("data".into(), String::from_utf8_lossy(&cleartext).into_owned().into());
+    }
+    Err(e) => LOGGER.log_debug("GRID", &format!("Failed to decrypt message: {e}")),
   }
 
-  /*pub async fn send_message(to: &str, message: &str) -> Option<serde_json::Value> {
-    Self::call(&format!("unit/{to}/send")).await
+  obj.insert("verified".into(), verified.into());
+}
+
+/// Checks connectivity in the background so callers never block on it, and
+/// flushes the offline queue and counts unread messages whenever the grid is
+/// reachable.
+fn connectivity_loop(state: &Weak<GridState>) {
+  loop {
+    let Some(state) = state.upgrade() else {
+      return;
+    };
+
+    let (check_url, interval) = {
+      let cfg = config_read();
+      (
+        cfg.grid.connectivity_url.to_string(),
+        Duration::from_secs(cfg.grid.connectivity_interval.max(5)),
+      )
+    };
+
+    let online = check_connectivity(&check_url);
+    if state.connected.swap(online, Ordering::Relaxed) != online {
+      if online {
+        LOGGER.log_info("GRID", "Grid is reachable");
+      } else {
+        LOGGER.log_warning("GRID", "Grid is unreachable, reports will be queued");
+      }
+    }
+
+    if online {
+      flush_queue(&state.queue, &state.client);
+      *state.unread.lock() = fetch_unread(&state.client);
+    }
+
+    drop(state);
+    std::thread::sleep(interval);
+  }
+}
+
+fn check_connectivity(url: &str) -> bool {
+  match CHECK_CLIENT.get(url).header("User-Agent", user_agent()).call() {
+    Ok(mut response) if response.status() == 200 => response
+      .body_mut()
+      .read_json::<serde_json::Value>()
+      .ok()
+      .and_then(|v| v.get("isUp").and_then(serde_json::Value::as_bool))
+      .unwrap_or(false),
+    _ => false,
+  }
+}
+
+fn fetch_unread(client: &ApiClient) -> Option<u32> {
+  let endpoint = config_read().grid.endpoints.inbox.clone();
+  match client.request(Method::Get, &format!("{endpoint}?p=1"), None) {
+    Ok(mut response) if response.status() == 200 => {
+      let page = response.body_mut().read_json::<serde_json::Value>().ok()?;
+      count_unread(page.get("messages")?)
+    }
+    Ok(response) => {
+      LOGGER.log_debug("GRID", &format!("Inbox check failed with status: {}", response.status()));
+      None
+    }
+    Err(e) => {
+      LOGGER.log_debug("GRID", &format!("Inbox check failed: {e}"));
+      None
+    }
+  }
+}
+
+fn flush_queue(queue: &OfflineQueue, client: &ApiClient) {
+  let sent = queue.flush(|report| report.deliver(client));
+  if sent > 0 {
+    LOGGER.log_info("GRID", &format!("Sent {sent} queued reports"));
+  }
+}
+
+/// A report that has to reach the grid eventually.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "kind", content = "data", rename_all = "snake_case")]
+pub enum QueuedReport {
+  Data(serde_json::Value),
+  ReportAp(serde_json::Value),
+}
+
+impl QueuedReport {
+  /// Returns false if the API could not be reached and the report should be
+  /// kept, a rejected report is logged and dropped.
+  pub fn deliver(&self, client: &ApiClient) -> bool {
+    let (endpoint, data) = {
+      let cfg = config_read();
+      match self {
+        Self::Data(data) => (cfg.grid.endpoints.data.clone(), data),
+        Self::ReportAp(data) => (cfg.grid.endpoints.report_ap.clone(), data),
+      }
+    };
+
+    // the grid server takes session data as part of the enrollment
+    let res = if let Self::Data(data) = self
+      && client.options().authenticated
+    {
+      client.enroll(Some(data.clone())).map(|()| None)
+    } else {
+      client.request(Method::Post, &endpoint, Some(data)).map(Some)
+    };
+
+    match res {
+      Ok(None) => true,
+      Ok(Some(mut response)) if response.status() == 200 => {
+        if let Self::Data(_) = self
+          && response.body_mut().read_json::<GridDataResponse>().is_err()
+        {
+          LOGGER.log_debug("GRID", "Unexpected response to data update");
+        }
+        true
+      }
+      Ok(Some(response)) => {
+        LOGGER.log_warning(
+          "GRID",
+          &format!("Grid rejected report to {endpoint} with status {}", response.status()),
+        );
+        true
+      }
+      Err(e) => {
+        LOGGER.log_debug("GRID", &format!("Failed to deliver report: {e}"));
+        false
+      }
+    }
+  }
+}
+
+/// Reports waiting for connectivity, stored as JSON lines so they survive a
+/// restart.
+pub struct OfflineQueue {
+  path: PathBuf,
+  lock: Mutex<()>,
+}
+
+impl OfflineQueue {
+  pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+    Self { path: path.into(), lock: Mutex::new(()) }
+  }
+
+  /// Queues `report`, only the latest data update is kept and duplicate AP
+  /// reports are dropped.
+  pub fn push(&self, report: QueuedReport) {
+    let _guard = self.lock.lock();
+    let mut reports = self.load();
+
+    match &report {
+      QueuedReport::Data(_) => reports.retain(|r| !matches!(r, QueuedReport::Data(_))),
+      QueuedReport::ReportAp(_) if reports.contains(&report) => return,
+      QueuedReport::ReportAp(_) => {}
+    }
+
+    reports.push(report);
+    self.save(&reports);
+  }
+
+  /// Hands queued reports to `deliver` in order until it fails, the rest is
+  /// put back. Returns how many were delivered.
+  pub fn flush<F: FnMut(&QueuedReport) -> bool>(&self, mut deliver: F) -> usize {
+    let mut reports = self.take().into_iter();
+    let mut sent = 0;
+
+    while let Some(report) = reports.next() {
+      if !deliver(&report) {
+        self.requeue(std::iter::once(report).chain(reports).collect());
+        break;
+      }
+      sent += 1;
+    }
+
+    sent
+  }
+
+  /// Puts `failed` back in front of whatever was queued since it was taken,
+  /// a data update queued in the meantime is newer and wins.
+  fn requeue(&self, failed: Vec<QueuedReport>) {
+    let _guard = self.lock.lock();
+    let queued = self.load();
+    let has_data = queued.iter().any(|r| matches!(r, QueuedReport::Data(_)));
+
+    let mut reports = failed
+      .into_iter()
+      .filter(|r| match r {
+        QueuedReport::Data(_) => !has_data,
+        QueuedReport::ReportAp(_) => !queued.contains(r),
+      })
+      .collect::<Vec<_>>();
+    reports.extend(queued);
+    self.save(&reports);
+  }
+
+  /// Removes and returns everything that is queued.
+  pub fn take(&self) -> Vec<QueuedReport> {
+    let _guard = self.lock.lock();
+    let reports = self.load();
+    if !reports.is_empty()
+      && let Err(e) = std::fs::remove_file(&self.path)
+    {
+      LOGGER.log_error("GRID", &format!("Failed to clear queue {}: {e}", self.path.display()));
+    }
+    reports
+  }
+
+  pub fn len(&self) -> usize {
+    let _guard = self.lock.lock();
+    self.load().len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  fn load(&self) -> Vec<QueuedReport> {
+    let Ok(content) = std::fs::read_to_string(&self.path) else {
+      return Vec::new();
+    };
+
+    content.lines().filter_map(|line| serde_json::from_str(line).ok()).collect()
+  }
+
+  fn save(&self, reports: &[QueuedReport]) {
+    let content = reports
+      .iter()
+      .filter_map(|r| serde_json::to_string(r).ok())
+      .map(|line| line + "\n")
+      .collect::<String>();
+
+    let tmp = self.path.with_extension("tmp");
+    let res = std::fs::write(&tmp, content).and_then(|()| std::fs::rename(&tmp, &self.path));
+    if let Err(e) = res {
+      LOGGER.log_error("GRID", &format!("Failed to write queue {}: {e}", self.path.display()));
+    }
   }
-  */
 }
@@ -1,17 +1,37 @@
 #![feature(stmt_expr_attributes)]
 
 pub mod agent;
+pub mod api;
 pub mod automata;
 pub mod bettercap;
 pub mod cli;
+pub mod config_watcher;
 pub mod grid;
+pub mod memfs;
+pub mod monitor;
 pub mod setup;
 pub mod utils;
+pub mod watchdog;
 
 pub mod mesh {
   pub mod advertiser;
+  pub mod coordination;
+  pub mod native;
+  pub mod sharing;
+  pub mod wire;
 }
 
 pub mod events {
   pub mod eventlistener;
 }
+
+#[cfg(test)]
+pub mod tests {
+  pub mod api;
+  pub mod coordination;
+  pub mod grid;
+  pub mod memfs;
+  pub mod mesh;
+  pub mod messages;
+  pub mod monitor;
+}
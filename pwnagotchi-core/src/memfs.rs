@@ -0,0 +1,156 @@
+use std::{
+  sync::{Arc, LazyLock},
+  time::Duration,
+};
+
+use anyhow::Result;
+use parking_lot::RwLock;
+use pwnagotchi_hw::memfs::MemoryMount;
+use pwnagotchi_shared::{
+  config::{FSConfig, config_read},
+  logger::LOGGER,
+  traits::general::{Component, CoreModules, Dependencies},
+};
+use tokio::{task::JoinHandle, time::Instant};
+use tokio_util::sync::CancellationToken;
+
+/// Mounts set up by [`MemoryFsComponent`], flushed by [`flush`].
+static MOUNTS: LazyLock<RwLock<Vec<Arc<MemoryMount>>>> = LazyLock::new(|| RwLock::new(Vec::new()));
+
+/// Keeps the directories of `fs.memory.mounts` in memory and syncs them back
+/// to the SD card every `sync` seconds and on shutdown.
+pub struct MemoryFsComponent {
+  shutdown: CancellationToken,
+}
+
+impl Dependencies for MemoryFsComponent {
+  fn name(&self) -> &'static str {
+    "MemoryFsComponent"
+  }
+}
+
+#[async_trait::async_trait]
+impl Component for MemoryFsComponent {
+  async fn init(&mut self, ctx: &CoreModules) -> Result<()> {
+    self.shutdown = ctx.shutdown.clone();
+
+    let config = config_read().fs.clone();
+    tokio::task::spawn_blocking(move || setup(&config)).await?;
+    Ok(())
+  }
+
+  async fn start(&self) -> Result<Option<JoinHandle<()>>> {
+    let mounts = MOUNTS
+      .read()
+      .iter()
+      .filter_map(|mount| Some((Arc::clone(mount), mount.sync_interval()?)))
+      .collect::<Vec<_>>();
+
+    if mounts.is_empty() {
+      return Ok(None);
+    }
+
+    let shutdown = self.shutdown.clone();
+    let handle = tokio::spawn(async move {
+      run(mounts, &shutdown).await;
+    });
+
+    Ok(Some(handle))
+  }
+
+  async fn stop(&self) -> Result<()> {
+    flush().await;
+    Ok(())
+  }
+}
+
+impl Default for MemoryFsComponent {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl MemoryFsComponent {
+  pub fn new() -> Self {
+    Self { shutdown: CancellationToken::new() }
+  }
+}
+
+/// Sets up the mounts `config` enables, the ones that fail are logged and
+/// left out. They are the ones [`flush`] syncs from then on.
+pub(crate) fn setup(config: &FSConfig) {
+  let mounts = MemoryMount::from_config(config)
+    .into_iter()
+    .filter_map(|mount| match mount.setup() {
+      Ok(backing) => {
+        LOGGER.log_info(
+          "MemoryFS",
+          &format!("Keeping {} in memory ({})", mount.mount().display(), backing.label()),
+        );
+        Some(Arc::new(mount))
+      }
+      Err(e) => {
+        LOGGER.log_error(
+          "MemoryFS",
+          &format!("Failed to keep {} in memory: {e}", mount.mount().display()),
+        );
+        None
+      }
+    })
+    .collect::<Vec<_>>();
+
+  *MOUNTS.write() = mounts;
+}
+
+/// Syncs every mount once its interval has passed, until shutdown.
+pub(crate) async fn run(mounts: Vec<(Arc<MemoryMount>, Duration)>, shutdown: &CancellationToken) {
+  let now = Instant::now();
+  let mut due = mounts.iter().map(|(_, interval)| now + *interval).collect::<Vec<_>>();
+
+  loop {
+    let Some(next) = due.iter().min().copied() else {
+      return;
+    };
+
+    tokio::select! {
+      () = shutdown.cancelled() => break,
+      () = tokio::time::sleep_until(next) => {}
+    }
+
+    for ((mount, interval), due) in mounts.iter().zip(due.iter_mut()) {
+      if *due <= Instant::now() {
+        sync(Arc::clone(mount)).await;
+        *due = Instant::now() + *interval;
+      }
+    }
+  }
+}
+
+/// Syncs every memory mount to the SD card. Teardown calls this once more
+/// after everything else stopped, so the last lines logged are kept.
+pub async fn flush() {
+  let mounts = MOUNTS.read().clone();
+  for mount in mounts {
+    sync(mount).await;
+  }
+}
+
+async fn sync(mount: Arc<MemoryMount>) {
+  let result = tokio::task::spawn_blocking(move || {
+    let result = mount.sync();
+    (mount, result)
+  })
+  .await;
+
+  match result {
+    Ok((mount, Ok(()))) => LOGGER.log_debug(
+      "MemoryFS",
+      &format!("Synced {} to {}", mount.mount().display(), mount.disk().display()),
+    ),
+    Ok((mount, Err(e))) => LOGGER.log_error(
+      "MemoryFS",
+      &format!("Failed to sync {} to {}: {e}", mount.mount().display(), mount.disk().display()),
+    ),
+    Err(e) => LOGGER.log_error("MemoryFS", &format!("Sync task failed: {e}")),
+  }
+}
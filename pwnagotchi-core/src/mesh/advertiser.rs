@@ -1,14 +1,27 @@
 #![allow(clippy::cast_possible_truncation)]
 
-use std::{collections::HashMap, mem, sync::Arc, time::Duration};
+use std::{
+  collections::HashMap,
+  mem,
+  sync::Arc,
+  time::{Duration, Instant},
+};
 
 use anyhow::Result;
 use parking_lot::{Mutex, RwLock};
 use pwnagotchi_shared::{
   config::config_read,
   identity::Identity,
-  mesh::peer::Peer,
-  models::grid::{Advertisement, PeerResponse},
+  live::{self, LiveEvent},
+  logger::LOGGER,
+  mesh::{
+    peer::{Peer, PeerRegistry},
+    peerdb::PeerDb,
+  },
+  models::{
+    grid::{Advertisement, PeerResponse},
+    net::Handshake,
+  },
   sessions::manager::SessionManager,
   traits::{
     epoch::Epoch,
@@ -18,10 +31,21 @@ use pwnagotchi_shared::{
   },
   utils::general::total_unique_handshakes,
 };
+use time::OffsetDateTime;
 use tokio::{sync::Mutex as AsyncMutex, task::JoinHandle, time::sleep};
+use tokio_util::sync::CancellationToken;
+
+use crate::mesh::coordination::ADVERTISED_HANDSHAKES;
+
+const PEER_DB_SAVE_INTERVAL: Duration = Duration::from_secs(60);
+const MAX_POLL_GAP: Duration = Duration::from_secs(30);
 
 pub struct AdvertiserComponent {
   advertiser: Option<Arc<AsyncMutex<dyn AdvertiserTrait + Send + Sync>>>,
+  grid: Option<Arc<dyn GridTrait + Send + Sync>>,
+  peer_db: Option<Arc<PeerDb>>,
+  peers: PeerRegistry,
+  shutdown: CancellationToken,
 }
 
 impl Dependencies for AdvertiserComponent {
@@ -48,7 +72,12 @@ impl Component for AdvertiserComponent {
       Arc::clone(&ctx.view),
       Arc::clone(&ctx.grid),
       Arc::clone(&ctx.session_manager),
+      Arc::clone(&ctx.peer_db),
+      Arc::clone(&self.peers),
     ))));
+    self.grid = Some(Arc::clone(&ctx.grid));
+    self.peer_db = Some(Arc::clone(&ctx.peer_db));
+    self.shutdown = ctx.shutdown.clone();
     Ok(())
   }
 
@@ -57,12 +86,30 @@ impl Component for AdvertiserComponent {
       let advertiser = Arc::clone(ad);
       advertiser.lock().await.start_advertising().await;
       let advertiser = Arc::clone(ad);
-      let handle =
-        tokio::spawn(async move { advertiser.lock().await.peer_and_advertisement_updater().await });
+      let shutdown = self.shutdown.clone();
+      let handle = tokio::spawn(async move {
+        let mut advertiser = advertiser.lock().await;
+        tokio::select! {
+          () = advertiser.peer_and_advertisement_updater() => {},
+          () = shutdown.cancelled() => {},
+        }
+      });
       return Ok(Some(handle));
     }
     Ok(None)
   }
+
+  async fn stop(&self) -> Result<()> {
+    if let Some(grid) = &self.grid
+      && config_read().personality.advertise
+    {
+      grid.advertise(Some(false));
+    }
+    if let Some(peer_db) = &self.peer_db {
+      save_peer_db(peer_db);
+    }
+    Ok(())
+  }
 }
 
 impl Default for AdvertiserComponent {
@@ -72,8 +119,19 @@ impl Default for AdvertiserComponent {
 }
 
 impl AdvertiserComponent {
-  pub const fn new() -> Self {
-    Self { advertiser: None }
+  pub fn new() -> Self {
+    Self {
+      advertiser: None,
+      grid: None,
+      peer_db: None,
+      peers: PeerRegistry::default(),
+      shutdown: CancellationToken::new(),
+    }
+  }
+
+  /// Peers the advertiser currently sees, shared with the web UI.
+  pub fn peers(&self) -> PeerRegistry {
+    Arc::clone(&self.peers)
   }
 }
 
@@ -85,6 +143,9 @@ pub struct AsyncAdvertiser {
   pub grid: Arc<dyn GridTrait + Send + Sync>,
   pub peers: HashMap<String, Peer>,
   pub closest_peer: Option<String>,
+  peer_db: Arc<PeerDb>,
+  registry: PeerRegistry,
+  last_poll: Option<Instant>,
 }
 
 impl CoreModule for AsyncAdvertiser {
@@ -131,10 +192,17 @@ impl AdvertiserTrait for AsyncAdvertiser {
 
   async fn peer_and_advertisement_updater(&mut self) {
     sleep(Duration::from_secs(20)).await;
+    let mut last_save = Instant::now();
     loop {
       let peers = self.grid.peers().await.unwrap_or_default();
       self.merge_peers(peers);
       self.update_advertisement().await;
+
+      if last_save.elapsed() >= PEER_DB_SAVE_INTERVAL {
+        save_peer_db(&self.peer_db);
+        last_save = Instant::now();
+      }
+
       sleep(Duration::from_secs(3)).await;
     }
   }
@@ -147,6 +215,8 @@ impl AsyncAdvertiser {
     view: Arc<dyn ViewTrait + Send + Sync>,
     grid: Arc<dyn GridTrait + Send + Sync>,
     sm: Arc<SessionManager>,
+    peer_db: Arc<PeerDb>,
+    registry: PeerRegistry,
   ) -> Self {
     let epoch_data = Arc::clone(&epoch);
 
@@ -167,6 +237,8 @@ impl AsyncAdvertiser {
       uptime: 0,
       epoch: epoch_num,
       policy: config.personality.clone(),
+      channels: vec![],
+      handshakes: vec![],
     };
     let adv_lock = Arc::new(Mutex::new(advertisement));
 
@@ -178,6 +250,9 @@ impl AsyncAdvertiser {
       grid,
       peers: HashMap::new(),
       closest_peer: None,
+      peer_db,
+      registry,
+      last_poll: None,
     }
   }
 
@@ -196,6 +271,8 @@ impl AsyncAdvertiser {
     ad_mut.pwnd_total = total_unique_handshakes(&config_read().bettercap.handshakes) as u32;
     ad_mut.uptime = uptime.unwrap_or_default().as_secs() as u32;
     ad_mut.epoch = self.epoch.read().epoch;
+    ad_mut.channels.clone_from(&session.read().state.working_channels);
+    ad_mut.handshakes = recent_handshakes(&session.read().state.handshakes);
 
     drop(ad_mut);
 
@@ -210,10 +287,12 @@ impl AsyncAdvertiser {
   }
 
   fn on_new_peer(&self, peer: &Peer) {
+    live::publish(LiveEvent::PeerNew { peer: Box::new(peer.clone()) });
     self.view.on_new_peer(peer);
   }
 
   fn on_lost_peer(&self, peer: &Peer) {
+    live::publish(LiveEvent::PeerLost { peer: Box::new(peer.clone()) });
     self.view.on_lost_peer(peer);
   }
 
@@ -223,6 +302,12 @@ impl AsyncAdvertiser {
     let mut updated_peers = HashMap::with_capacity(responses.len());
     let mut closest: Option<(String, i16)> = None;
 
+    let now = OffsetDateTime::now_utc();
+    // time in range since the last poll, capped so a stalled loop doesn't
+    // count as time together
+    let together = self.last_poll.map_or(Duration::ZERO, |at| at.elapsed().min(MAX_POLL_GAP));
+    self.last_poll = Some(Instant::now());
+
     for response in responses {
       let Some(fingerprint) = response.fingerprint.clone() else {
         continue;
@@ -233,7 +318,12 @@ impl AsyncAdvertiser {
       if let Some(mut existing) = previous_peers.remove(&fingerprint) {
         existing.update(&peer);
         peer = existing;
+        // lifetime encounters replace the ones reported by the mesh
+        if let Some(encounters) = self.peer_db.observe(&peer, together, now) {
+          peer.encounters = encounters;
+        }
       } else {
+        peer.encounters = self.peer_db.record_encounter(&peer, now);
         self.on_new_peer(&peer);
       }
 
@@ -249,10 +339,29 @@ impl AsyncAdvertiser {
     }
 
     self.closest_peer = closest.map(|(fingerprint, _)| fingerprint);
+    self.registry.write().clone_from(&updated_peers);
+    self.sm.get_session().write().state.peers = updated_peers.values().cloned().collect();
     self.peers = updated_peers;
   }
 }
 
+/// BSSIDs of the latest handshakes, newest first.
+fn recent_handshakes(handshakes: &HashMap<String, Handshake>) -> Vec<String> {
+  let mut recent = handshakes.iter().collect::<Vec<_>>();
+  recent.sort_by_key(|(_, h)| std::cmp::Reverse(h.timestamp));
+  recent
+    .into_iter()
+    .take(ADVERTISED_HANDSHAKES)
+    .map(|(mac, _)| mac.to_lowercase())
+    .collect()
+}
+
+fn save_peer_db(peer_db: &PeerDb) {
+  if let Err(e) = peer_db.save() {
+    LOGGER.log_error("Advertiser", &format!("Failed to save peer database: {e}"));
+  }
+}
+
 fn on_face_change(
   grid: Arc<dyn GridTrait + Send + Sync>,
   ad: Arc<Mutex<Advertisement>>,
@@ -0,0 +1,81 @@
+//! Splits the work between units that are close enough to hear the same
+//! access points. Every unit advertises the channels it works and the BSSIDs
+//! it pwned, channels both want go to whichever unit ranks first for it so
+//! both sides come to the same answer without talking to each other.
+
+use std::collections::HashSet;
+
+use pwnagotchi_shared::{mesh::peer::Peer, models::net::AccessPoint};
+use sha2::{Digest, Sha256};
+
+/// Recent handshakes included in the advertisement.
+pub const ADVERTISED_HANDSHAKES: usize = 20;
+
+#[derive(Debug, Default, Clone)]
+pub struct Plan {
+  /// Channels left to work, in the order they were given.
+  pub channels: Vec<(u8, Vec<AccessPoint>)>,
+  /// Channels a closer peer is covering.
+  pub ceded: Vec<u8>,
+  /// Access points skipped because a peer already pwned them.
+  pub skipped: usize,
+}
+
+/// Drops access points peers already pwned and channels that belong to a
+/// peer within `min_rssi`.
+pub fn plan(
+  own_fingerprint: &str,
+  peers: &[Peer],
+  min_rssi: i16,
+  channels: Vec<(u8, Vec<AccessPoint>)>,
+) -> Plan {
+  let pwned = pwned_by_peers(peers);
+  let close = peers.iter().filter(|p| p.rssi >= min_rssi).collect::<Vec<_>>();
+
+  let mut plan = Plan::default();
+  for (channel, aps) in channels {
+    if !owns_channel(own_fingerprint, &close, channel) {
+      plan.ceded.push(channel);
+      continue;
+    }
+
+    let before = aps.len();
+    let aps = aps
+      .into_iter()
+      .filter(|ap| !pwned.contains(&ap.mac.to_lowercase()))
+      .collect::<Vec<_>>();
+    plan.skipped += before - aps.len();
+
+    if !aps.is_empty() {
+      plan.channels.push((channel, aps));
+    }
+  }
+
+  plan
+}
+
+/// BSSIDs any peer advertised a handshake for, lowercased.
+pub fn pwned_by_peers(peers: &[Peer]) -> HashSet<String> {
+  peers
+    .iter()
+    .flat_map(|p| p.adv.handshakes.iter())
+    .map(|mac| mac.to_lowercase())
+    .collect()
+}
+
+fn owns_channel(own_fingerprint: &str, close: &[&Peer], channel: u8) -> bool {
+  let own_rank = rank(own_fingerprint, channel);
+  close
+    .iter()
+    .filter(|p| p.adv.channels.contains(&channel))
+    .all(|p| own_rank <= rank(&p.adv.identity, channel))
+}
+
+/// Stable per channel ordering of units, so contested channels are spread
+/// evenly instead of all going to the same unit.
+fn rank(fingerprint: &str, channel: u8) -> [u8; 32] {
+  let mut hasher = Sha256::new();
+  hasher.update(fingerprint.as_bytes());
+  hasher.update([channel]);
+  hasher.finalize().into()
+}
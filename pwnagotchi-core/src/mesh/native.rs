@@ -0,0 +1,350 @@
+use std::{
+  collections::HashMap,
+  io,
+  sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+  },
+  time::{Duration, Instant},
+};
+
+use parking_lot::{Mutex, RwLock};
+use pnet_datalink::{Channel, DataLinkReceiver, DataLinkSender};
+use pwnagotchi_shared::{
+  config::config_read,
+  identity::Identity,
+  logger::LOGGER,
+  models::grid::{Advertisement, PeerResponse},
+  sessions::session_stats::SessionStats,
+  traits::{general::CoreModule, grid::GridTrait},
+};
+use time::{OffsetDateTime, format_description::BorrowedFormatItem, macros::format_description};
+
+use crate::{
+  grid::Grid,
+  mesh::wire::{Sighting, encode_beacon, format_mac, parse_frame},
+};
+
+const READ_TIMEOUT: Duration = Duration::from_millis(100);
+const REOPEN_DELAY: Duration = Duration::from_secs(5);
+/// Peers kept at most, the ones heard longest ago make room for new ones.
+pub const MAX_PEERS: usize = 256;
+const PWNGRID_TIME: &[BorrowedFormatItem<'_>] =
+  format_description!("[year]-[month]-[day]T[hour]:[minute]:[second]Z");
+
+/// Peers heard over the air, keyed by fingerprint.
+pub struct PeerTable {
+  ttl: time::Duration,
+  peers: HashMap<String, PeerRecord>,
+}
+
+struct PeerRecord {
+  session_id: String,
+  met_at: OffsetDateTime,
+  detected_at: OffsetDateTime,
+  prev_seen_at: Option<OffsetDateTime>,
+  seen_at: OffsetDateTime,
+  encounters: u32,
+  channel: Option<u8>,
+  rssi: Option<i16>,
+  advertisement: Advertisement,
+}
+
+impl PeerTable {
+  pub fn new(ttl: Duration) -> Self {
+    Self {
+      ttl: time::Duration::try_from(ttl).unwrap_or(time::Duration::MINUTE),
+      peers: HashMap::new(),
+    }
+  }
+
+  pub fn observe(&mut self, sighting: Sighting, now: OffsetDateTime) {
+    let fingerprint = sighting.advertisement.identity.clone();
+    let session_id = format_mac(&sighting.session_id);
+
+    // peers that went away are forgotten, like pwngrid does
+    self.peers.retain(|_, record| now - record.seen_at <= self.ttl);
+
+    if let Some(record) = self.peers.get_mut(&fingerprint) {
+      // a peer that restarted counts as a new encounter
+      if record.session_id != session_id {
+        record.encounters += 1;
+        record.prev_seen_at = Some(record.seen_at);
+        record.detected_at = now;
+      }
+
+      record.session_id = session_id;
+      record.seen_at = now;
+      record.channel = sighting.channel.or(record.channel);
+      record.rssi = sighting.rssi.or(record.rssi);
+      record.advertisement = sighting.advertisement;
+      return;
+    }
+
+    if self.peers.len() >= MAX_PEERS
+      && let Some(oldest) = self
+        .peers
+        .iter()
+        .min_by_key(|(_, record)| record.seen_at)
+        .map(|(f, _)| f.clone())
+    {
+      self.peers.remove(&oldest);
+    }
+
+    self.peers.insert(
+      fingerprint,
+      PeerRecord {
+        session_id,
+        met_at: now,
+        detected_at: now,
+        prev_seen_at: None,
+        seen_at: now,
+        encounters: 1,
+        channel: sighting.channel,
+        rssi: sighting.rssi,
+        advertisement: sighting.advertisement,
+      },
+    );
+  }
+
+  /// Peers heard within the TTL, closest first.
+  pub fn peers(&self, now: OffsetDateTime) -> Vec<PeerResponse> {
+    let mut peers = self
+      .peers
+      .iter()
+      .filter(|(_, record)| now - record.seen_at <= self.ttl)
+      .map(|(fingerprint, record)| PeerResponse {
+        fingerprint: Some(fingerprint.clone()),
+        met_at: Some(format_time(record.met_at)),
+        encounters: Some(record.encounters),
+        prev_seen_at: record.prev_seen_at.map(format_time),
+        detected_at: Some(format_time(record.detected_at)),
+        seen_at: Some(format_time(record.seen_at)),
+        channel: record.channel,
+        rssi: record.rssi,
+        session_id: Some(record.session_id.clone()),
+        advertisement: Some(record.advertisement.clone()),
+      })
+      .collect::<Vec<_>>();
+
+    peers.sort_by_key(|p| std::cmp::Reverse(p.rssi.unwrap_or(i16::MIN)));
+    peers
+  }
+}
+
+/// Formats timestamps the way pwngrid reports them.
+fn format_time(at: OffsetDateTime) -> String {
+  at.format(PWNGRID_TIME).unwrap_or_default()
+}
+
+struct MeshState {
+  advertising: AtomicBool,
+  closed: AtomicBool,
+  advertisement: RwLock<Option<Advertisement>>,
+  table: Mutex<PeerTable>,
+}
+
+/// Mesh implementation that injects and parses beacons on the monitor
+/// interface itself instead of going through the pwngrid daemon. Everything
+/// that needs the grid API is still handled by [`Grid`].
+pub struct NativeGrid {
+  api: Grid,
+  state: Arc<MeshState>,
+}
+
+impl CoreModule for NativeGrid {
+  fn name(&self) -> &'static str {
+    "Grid"
+  }
+}
+
+impl NativeGrid {
+  pub fn new(identity: Arc<RwLock<Identity>>) -> Self {
+    let ttl = Duration::from_secs(config_read().grid.peer_ttl);
+    let state = Arc::new(MeshState {
+      advertising: AtomicBool::new(false),
+      closed: AtomicBool::new(false),
+      advertisement: RwLock::new(None),
+      table: Mutex::new(PeerTable::new(ttl)),
+    });
+
+    let radio_state = Arc::clone(&state);
+    let spawned = std::thread::Builder::new()
+      .name("mesh-radio".to_string())
+      .spawn(move || radio_loop(&radio_state));
+
+    if let Err(e) = spawned {
+      LOGGER.log_error("Mesh", &format!("Failed to start radio thread: {e}"));
+    }
+
+    Self { api: Grid::new(identity), state }
+  }
+}
+
+impl Drop for NativeGrid {
+  fn drop(&mut self) {
+    self.state.closed.store(true, Ordering::Relaxed);
+  }
+}
+
+#[async_trait::async_trait]
+impl GridTrait for NativeGrid {
+  fn is_connected(&self) -> bool {
+    self.api.is_connected()
+  }
+
+  fn advertise(&self, enabled: Option<bool>) -> Option<serde_json::Value> {
+    let enabled = enabled.unwrap_or(true);
+    self.state.advertising.store(enabled, Ordering::Relaxed);
+    Some(serde_json::json!({ "advertise": enabled }))
+  }
+
+  fn set_advertisement_data(&self, data: serde_json::Value) -> Option<serde_json::Value> {
+    match serde_json::from_value::<Advertisement>(data.clone()) {
+      Ok(adv) => {
+        *self.state.advertisement.write() = Some(adv);
+        Some(data)
+      }
+      Err(e) => {
+        LOGGER.log_error("Mesh", &format!("Invalid advertisement: {e}"));
+        None
+      }
+    }
+  }
+
+  fn get_advertisement_data(&self) -> Option<serde_json::Value> {
+    let adv = self.state.advertisement.read();
+    adv.as_ref().and_then(|adv| serde_json::to_value(adv).ok())
+  }
+
+  fn memory(&self) -> Option<serde_json::Value> {
+    self.api.memory()
+  }
+
+  async fn peers(&self) -> Option<Vec<PeerResponse>> {
+    Some(self.state.table.lock().peers(OffsetDateTime::now_utc()))
+  }
+
+  async fn closest_peer(&self) -> Option<PeerResponse> {
+    self.peers().await?.into_iter().next()
+  }
+
+  fn update_data(&self, last_session: &SessionStats) {
+    self.api.update_data(last_session);
+  }
+
+  fn report_ap(&self, essid: &str, bssid: &str) {
+    self.api.report_ap(essid, bssid);
+  }
+
+  fn inbox(&self, page: Option<u32>, with_pager: Option<bool>) -> Option<serde_json::Value> {
+    self.api.inbox(page, with_pager)
+  }
+
+  fn inbox_message(&self, id: &str) -> Option<serde_json::Value> {
+    self.api.inbox_message(id)
+  }
+
+  fn mark_message(&self, id: &str, mark: &str) -> Option<serde_json::Value> {
+    self.api.mark_message(id, mark)
+  }
+
+  fn send_message(&self, to: &str, message: &str) -> Result<(), String> {
+    self.api.send_message(to, message)
+  }
+}
+
+/// Locally administered unicast address identifying this run.
+fn random_session_id() -> [u8; 6] {
+  let mut id = [0u8; 6];
+  fastrand::fill(&mut id);
+  id[0] = (id[0] & 0xfe) | 0x02;
+  id
+}
+
+fn open_channel(iface: &str) -> io::Result<(Box<dyn DataLinkSender>, Box<dyn DataLinkReceiver>)> {
+  let interface = pnet_datalink::interfaces()
+    .into_iter()
+    .find(|i| i.name == iface)
+    .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no interface {iface}")))?;
+
+  let config = pnet_datalink::Config {
+    read_timeout: Some(READ_TIMEOUT),
+    ..Default::default()
+  };
+
+  match pnet_datalink::channel(&interface, config)? {
+    Channel::Ethernet(tx, rx) => Ok((tx, rx)),
+    _ => Err(io::Error::other("unsupported channel type")),
+  }
+}
+
+/// Keeps a raw socket open on the monitor interface, reopening it whenever
+/// the interface goes away, e.g. while monitor mode is being restarted.
+fn radio_loop(state: &MeshState) {
+  let session_id = random_session_id();
+  LOGGER.log_info("Mesh", &format!("Native mesh session {}", format_mac(&session_id)));
+
+  while !state.closed.load(Ordering::Relaxed) {
+    let iface = config_read().main.iface.to_string();
+
+    match open_channel(&iface) {
+      Ok((tx, rx)) => {
+        LOGGER.log_debug("Mesh", &format!("Listening for peers on {iface}"));
+        if let Err(e) = run_radio(state, &session_id, tx, rx) {
+          LOGGER.log_warning("Mesh", &format!("Radio on {iface} failed: {e}"));
+          // the socket may open fine and fail right away again
+          std::thread::sleep(REOPEN_DELAY);
+        }
+      }
+      Err(e) => {
+        LOGGER.log_debug("Mesh", &format!("Can't open {iface}: {e}"));
+        std::thread::sleep(REOPEN_DELAY);
+      }
+    }
+  }
+}
+
+fn run_radio(
+  state: &MeshState,
+  session_id: &[u8; 6],
+  mut tx: Box<dyn DataLinkSender>,
+  mut rx: Box<dyn DataLinkReceiver>,
+) -> io::Result<()> {
+  let interval = Duration::from_millis(config_read().grid.beacon_interval.max(100));
+  let mut last_beacon: Option<Instant> = None;
+
+  while !state.closed.load(Ordering::Relaxed) {
+    match rx.next() {
+      Ok(frame) => {
+        if let Some(sighting) = parse_frame(frame)
+          && sighting.session_id != *session_id
+        {
+          state.table.lock().observe(sighting, OffsetDateTime::now_utc());
+        }
+      }
+      Err(e) if matches!(e.kind(), io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock) => {}
+      Err(e) => return Err(e),
+    }
+
+    if !state.advertising.load(Ordering::Relaxed)
+      || last_beacon.is_some_and(|at| at.elapsed() < interval)
+    {
+      continue;
+    }
+
+    let frame = {
+      let adv = state.advertisement.read();
+      adv.as_ref().and_then(|adv| encode_beacon(session_id, adv).ok())
+    };
+
+    if let Some(frame) = frame
+      && let Some(Err(e)) = tx.send_to(&frame, None)
+    {
+      return Err(e);
+    }
+    last_beacon = Some(Instant::now());
+  }
+
+  Ok(())
+}
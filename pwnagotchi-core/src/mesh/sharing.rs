@@ -0,0 +1,196 @@
+use std::{
+  sync::{Arc, LazyLock},
+  time::Duration,
+};
+
+use anyhow::{Result, anyhow, bail};
+use parking_lot::RwLock;
+use pwnagotchi_shared::{
+  config::config_read,
+  identity::{Identity, crypto::verify},
+  logger::LOGGER,
+  mesh::sharing::{
+    CAPTURES_PATH, HandshakeDigest, INVENTORY_PATH, Inventory, SHA256_HEADER, SIGNATURE_HEADER,
+    SignedRequest, capture_payload, local_inventory, store_capture,
+  },
+  traits::general::{Component, CoreModules, Dependencies},
+};
+use rsa::RsaPublicKey;
+use time::OffsetDateTime;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use ureq::{Agent, Body, http::Response};
+
+use crate::api::user_agent;
+
+/// Captures are small, anything bigger than this is not a handshake.
+const MAX_CAPTURE_SIZE: u64 = 16 * 1024 * 1024;
+
+static CLIENT: LazyLock<Agent> = LazyLock::new(|| {
+  Agent::config_builder()
+    .timeout_global(Some(Duration::from_secs(30)))
+    .http_status_as_error(false)
+    .build()
+    .into()
+});
+
+pub struct SharingComponent {
+  identity: Option<Arc<RwLock<Identity>>>,
+  shutdown: CancellationToken,
+}
+
+impl Dependencies for SharingComponent {
+  fn name(&self) -> &'static str {
+    "SharingComponent"
+  }
+
+  fn dependencies(&self) -> &[&str] {
+    &["IdentityComponent"]
+  }
+}
+
+#[async_trait::async_trait]
+impl Component for SharingComponent {
+  async fn init(&mut self, ctx: &CoreModules) -> Result<()> {
+    self.identity = Some(Arc::clone(&ctx.identity));
+    self.shutdown = ctx.shutdown.clone();
+    Ok(())
+  }
+
+  async fn start(&self) -> Result<Option<JoinHandle<()>>> {
+    let (enabled, interval) = {
+      let cfg = config_read();
+      (cfg.sharing.enabled, Duration::from_secs(cfg.sharing.interval.max(60)))
+    };
+
+    let Some(identity) = &self.identity else {
+      return Ok(None);
+    };
+    if !enabled {
+      return Ok(None);
+    }
+
+    let identity = Arc::clone(identity);
+    let shutdown = self.shutdown.clone();
+    let handle = tokio::spawn(async move {
+      loop {
+        let identity = Arc::clone(&identity);
+        if let Err(e) = tokio::task::spawn_blocking(move || pull_all(&identity)).await {
+          LOGGER.log_error("Sharing", &format!("Pull task failed: {e}"));
+        }
+
+        tokio::select! {
+          () = shutdown.cancelled() => break,
+          () = tokio::time::sleep(interval) => {}
+        }
+      }
+    });
+
+    Ok(Some(handle))
+  }
+}
+
+impl Default for SharingComponent {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl SharingComponent {
+  pub fn new() -> Self {
+    Self {
+      identity: None,
+      shutdown: CancellationToken::new(),
+    }
+  }
+}
+
+fn pull_all(identity: &Arc<RwLock<Identity>>) {
+  let peers = config_read().sharing.peers.clone();
+  for peer in peers {
+    match pull(identity, peer.trim_end_matches('/')) {
+      Ok(0) => LOGGER.log_debug("Sharing", &format!("Nothing new from {peer}")),
+      Ok(n) => LOGGER.log_info("Sharing", &format!("Received {n} handshakes from {peer}")),
+      Err(e) => LOGGER.log_warning("Sharing", &format!("Pulling from {peer} failed: {e}")),
+    }
+  }
+}
+
+/// Fetches every capture `base` has that we don't and returns how many were
+/// stored.
+pub fn pull(identity: &Arc<RwLock<Identity>>, base: &str) -> Result<usize> {
+  let (trusted, max_skew, dir) = {
+    let cfg = config_read();
+    (cfg.sharing.trusted.clone(), cfg.sharing.max_clock_skew, cfg.bettercap.handshakes.clone())
+  };
+
+  let inventory = get(identity, base, INVENTORY_PATH)?.body_mut().read_json::<Inventory>()?;
+  let key = inventory.verify(&trusted, now(), max_skew)?;
+  if inventory.fingerprint.eq_ignore_ascii_case(identity.read().fingerprint()) {
+    bail!("{base} is ourselves");
+  }
+
+  let local = local_inventory(dir.as_ref());
+  let mut stored = 0;
+  for digest in inventory.missing(&local) {
+    let res = fetch(identity, base, &key, digest)
+      .and_then(|data| store_capture(dir.as_ref(), digest, &data, &inventory.fingerprint, now()));
+    match res {
+      Ok(path) => {
+        LOGGER.log_debug("Sharing", &format!("Stored {}", path.display()));
+        stored += 1;
+      }
+      Err(e) => LOGGER.log_warning("Sharing", &format!("Skipping {}: {e}", digest.name)),
+    }
+  }
+
+  Ok(stored)
+}
+
+/// Downloads a capture and checks it against the digest and the sender's
+/// signature.
+fn fetch(
+  identity: &Arc<RwLock<Identity>>,
+  base: &str,
+  key: &RsaPublicKey,
+  digest: &HandshakeDigest,
+) -> Result<Vec<u8>> {
+  let mut res = get(identity, base, &format!("{CAPTURES_PATH}/{}", digest.name))?;
+  let header = |name| {
+    res
+      .headers()
+      .get(name)
+      .and_then(|v| v.to_str().ok())
+      .map(str::to_string)
+      .unwrap_or_default()
+  };
+  let (sha256, signature) = (header(SHA256_HEADER), header(SIGNATURE_HEADER));
+
+  if !sha256.eq_ignore_ascii_case(&digest.sha256) {
+    bail!("served capture does not match the inventory");
+  }
+  if !verify(key, capture_payload(&digest.name, &sha256).as_bytes(), &signature) {
+    bail!("bad capture signature");
+  }
+
+  Ok(res.body_mut().with_config().limit(MAX_CAPTURE_SIZE).read_to_vec()?)
+}
+
+fn get(identity: &Arc<RwLock<Identity>>, base: &str, path: &str) -> Result<Response<Body>> {
+  let auth = SignedRequest::sign(&identity.read(), path, now())?;
+
+  let mut req = CLIENT.get(format!("{base}{path}")).header("User-Agent", user_agent());
+  for (name, value) in auth.headers() {
+    req = req.header(name, value);
+  }
+
+  let res = req.call()?;
+  if !res.status().is_success() {
+    return Err(anyhow!("{path} returned {}", res.status()));
+  }
+  Ok(res)
+}
+
+fn now() -> i64 {
+  OffsetDateTime::now_utc().unix_timestamp()
+}
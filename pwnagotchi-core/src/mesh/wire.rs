@@ -0,0 +1,207 @@
+//! Beacon frames used by the pwngrid mesh. The advertisement is carried as
+//! JSON split across vendor information elements, peers are recognized by
+//! the signature address in the BSSID field.
+
+use std::io::Read;
+
+use flate2::read::GzDecoder;
+use pwnagotchi_shared::models::grid::Advertisement;
+
+pub const SIGNATURE_ADDR: [u8; 6] = [0xde, 0xad, 0xbe, 0xef, 0xde, 0xad];
+pub const BROADCAST_ADDR: [u8; 6] = [0xff; 6];
+
+const IE_PAYLOAD: u8 = 222;
+const IE_COMPRESSION: u8 = 223;
+const IE_MAX_LEN: usize = 255;
+/// Far more than any advertisement, keeps a crafted payload from inflating
+/// without bound.
+const MAX_PAYLOAD_LEN: u64 = 64 * 1024;
+
+const FRAME_BEACON: [u8; 2] = [0x80, 0x00];
+const BEACON_INTERVAL: u16 = 100;
+const CAPABILITIES: u16 = 0x0411;
+
+const DOT11_HEADER_LEN: usize = 24;
+const BEACON_FIXED_LEN: usize = 12;
+
+const RADIOTAP_FLAGS_FCS: u8 = 0x10;
+/// (bit, alignment, size) for TSFT, Flags, Rate, Channel, FHSS and antenna
+/// signal.
+const RADIOTAP_FIELDS: [(u32, usize, usize); 6] = [
+  (0, 8, 8),
+  (1, 1, 1),
+  (2, 1, 1),
+  (3, 2, 4),
+  (4, 1, 2),
+  (5, 1, 1),
+];
+
+/// A peer beacon heard on the monitor interface.
+#[derive(Debug, Clone)]
+pub struct Sighting {
+  pub session_id: [u8; 6],
+  pub channel: Option<u8>,
+  pub rssi: Option<i16>,
+  pub advertisement: Advertisement,
+}
+
+/// Builds a radiotap encapsulated beacon carrying `adv`, ready to inject.
+pub fn encode_beacon(session_id: &[u8; 6], adv: &Advertisement) -> serde_json::Result<Vec<u8>> {
+  let payload = serde_json::to_vec(adv)?;
+
+  let mut frame = Vec::with_capacity(8 + DOT11_HEADER_LEN + BEACON_FIXED_LEN + payload.len() * 2);
+
+  // radiotap header without any fields
+  frame.extend_from_slice(&[
+    0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00,
+  ]);
+
+  frame.extend_from_slice(&FRAME_BEACON);
+  frame.extend_from_slice(&[0x00, 0x00]); // duration
+  frame.extend_from_slice(&BROADCAST_ADDR);
+  frame.extend_from_slice(session_id);
+  frame.extend_from_slice(&SIGNATURE_ADDR);
+  frame.extend_from_slice(&[0x00, 0x00]); // sequence control
+
+  frame.extend_from_slice(&[0u8; 8]); // timestamp, filled in by the driver
+  frame.extend_from_slice(&BEACON_INTERVAL.to_le_bytes());
+  frame.extend_from_slice(&CAPABILITIES.to_le_bytes());
+
+  for chunk in payload.chunks(IE_MAX_LEN) {
+    frame.push(IE_PAYLOAD);
+    #[allow(clippy::cast_possible_truncation)]
+    frame.push(chunk.len() as u8);
+    frame.extend_from_slice(chunk);
+  }
+
+  Ok(frame)
+}
+
+/// Parses a radiotap encapsulated frame, returning `None` for anything that
+/// is not a mesh beacon.
+pub fn parse_frame(frame: &[u8]) -> Option<Sighting> {
+  let radiotap = parse_radiotap(frame)?;
+  let mut dot11 = frame.get(radiotap.len..)?;
+  if radiotap.has_fcs {
+    dot11 = dot11.get(..dot11.len().checked_sub(4)?)?;
+  }
+
+  if dot11.len() < DOT11_HEADER_LEN + BEACON_FIXED_LEN || dot11[..2] != FRAME_BEACON {
+    return None;
+  }
+
+  if dot11[16..22] != SIGNATURE_ADDR {
+    return None;
+  }
+
+  let mut session_id = [0u8; 6];
+  session_id.copy_from_slice(&dot11[10..16]);
+
+  let mut payload = Vec::new();
+  let mut compressed = false;
+  let mut ies = &dot11[DOT11_HEADER_LEN + BEACON_FIXED_LEN..];
+
+  while let [id, len, rest @ ..] = ies {
+    let len = usize::from(*len);
+    let info = rest.get(..len)?;
+
+    match *id {
+      IE_PAYLOAD => payload.extend_from_slice(info),
+      IE_COMPRESSION => compressed = info.first().is_some_and(|c| *c != 0),
+      _ => {}
+    }
+
+    ies = &rest[len..];
+  }
+
+  // pwngrid gzips payloads when that makes them smaller
+  if compressed {
+    let mut inflated = Vec::new();
+    GzDecoder::new(payload.as_slice())
+      .take(MAX_PAYLOAD_LEN)
+      .read_to_end(&mut inflated)
+      .ok()?;
+    payload = inflated;
+  }
+
+  let advertisement = serde_json::from_slice::<Advertisement>(&payload).ok()?;
+
+  Some(Sighting {
+    session_id,
+    channel: radiotap.channel,
+    rssi: radiotap.rssi,
+    advertisement,
+  })
+}
+
+pub fn format_mac(mac: &[u8; 6]) -> String {
+  mac.iter().map(|b| format!("{b:02x}")).collect::<Vec<_>>().join(":")
+}
+
+struct Radiotap {
+  len: usize,
+  has_fcs: bool,
+  channel: Option<u8>,
+  rssi: Option<i16>,
+}
+
+/// Reads the fields we care about from the radiotap header, they all live in
+/// the first presence bitmap and have to be walked in order with their
+/// alignment.
+fn parse_radiotap(frame: &[u8]) -> Option<Radiotap> {
+  if frame.len() < 8 || frame[0] != 0 {
+    return None;
+  }
+
+  let len = usize::from(u16::from_le_bytes([frame[2], frame[3]]));
+  let header = frame.get(..len)?;
+
+  let present = u32::from_le_bytes(header.get(4..8)?.try_into().ok()?);
+
+  // skip extended presence bitmaps
+  let mut offset = 4;
+  loop {
+    let word = u32::from_le_bytes(header.get(offset..offset + 4)?.try_into().ok()?);
+    offset += 4;
+    if word & (1 << 31) == 0 {
+      break;
+    }
+  }
+
+  let mut radiotap = Radiotap {
+    len,
+    has_fcs: false,
+    channel: None,
+    rssi: None,
+  };
+
+  for (bit, align, size) in RADIOTAP_FIELDS {
+    if present & (1 << bit) == 0 {
+      continue;
+    }
+
+    offset = offset.next_multiple_of(align);
+    let field = header.get(offset..offset + size)?;
+    offset += size;
+
+    match bit {
+      1 => radiotap.has_fcs = field[0] & RADIOTAP_FLAGS_FCS != 0,
+      3 => radiotap.channel = frequency_to_channel(u16::from_le_bytes([field[0], field[1]])),
+      #[allow(clippy::cast_possible_wrap)]
+      5 => radiotap.rssi = Some(i16::from(field[0] as i8)),
+      _ => {}
+    }
+  }
+
+  Some(radiotap)
+}
+
+fn frequency_to_channel(freq: u16) -> Option<u8> {
+  let channel = match freq {
+    2484 => 14,
+    2412..=2472 => (freq - 2407) / 5,
+    5000..=5900 => (freq - 5000) / 5,
+    _ => return None,
+  };
+  u8::try_from(channel).ok()
+}
@@ -0,0 +1,344 @@
+use std::{fmt::Display, path::Path, sync::Arc, time::Duration};
+
+use anyhow::Result;
+use parking_lot::{Mutex, RwLock};
+use pwnagotchi_shared::{
+  config::config_read,
+  logger::LOGGER,
+  sessions::manager::SessionManager,
+  traits::{
+    agent::AgentTrait,
+    bettercap::BettercapTrait,
+    epoch::Epoch,
+    general::{Component, CoreModules, Dependencies},
+  },
+};
+use tokio::{task::JoinHandle, time::sleep};
+use tokio_util::sync::CancellationToken;
+
+use crate::{agent::restart_module, setup::perform_bettercap_setup};
+
+const WIFI_RECON: &str = "wifi.recon";
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+const SETUP_TIMEOUT: Duration = Duration::from_secs(120);
+
+pub struct InterfaceManagerComponent {
+  manager: Option<Arc<InterfaceManager>>,
+  shutdown: CancellationToken,
+}
+
+impl Dependencies for InterfaceManagerComponent {
+  fn name(&self) -> &'static str {
+    "InterfaceManagerComponent"
+  }
+
+  fn dependencies(&self) -> &[&str] {
+    &[
+      "Agent",
+      "Bettercap",
+      "Epoch",
+      "SessionManager",
+      "SetupComponent",
+    ]
+  }
+}
+
+#[async_trait::async_trait]
+impl Component for InterfaceManagerComponent {
+  async fn init(&mut self, ctx: &CoreModules) -> Result<()> {
+    let manager = InterfaceManager::new(
+      Arc::clone(&ctx.bettercap),
+      Arc::clone(&ctx.session_manager),
+      Arc::clone(&ctx.epoch),
+      Arc::clone(&ctx.agent),
+    );
+    self.manager = Some(Arc::new(manager));
+    self.shutdown = ctx.shutdown.clone();
+    Ok(())
+  }
+
+  async fn start(&self) -> Result<Option<JoinHandle<()>>> {
+    if let Some(manager) = &self.manager {
+      let manager = Arc::clone(manager);
+      let shutdown = self.shutdown.clone();
+      let handle = tokio::spawn(async move {
+        tokio::select! {
+          () = manager.watch() => {},
+          () = shutdown.cancelled() => {},
+        }
+      });
+      return Ok(Some(handle));
+    }
+    Ok(None)
+  }
+}
+
+impl Default for InterfaceManagerComponent {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl InterfaceManagerComponent {
+  pub fn new() -> Self {
+    Self {
+      manager: None,
+      shutdown: CancellationToken::new(),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterfaceState {
+  Up,
+  Down,
+  Missing,
+}
+
+impl Display for InterfaceState {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let s = match self {
+      Self::Up => "up",
+      Self::Down => "down",
+      Self::Missing => "missing",
+    };
+    write!(f, "{s}")
+  }
+}
+
+/// The next recovery step taken when the unit stays blind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Escalation {
+  Monitor,
+  Bettercap,
+  System,
+}
+
+impl Display for Escalation {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let s = match self {
+      Self::Monitor => "monitor mode restart",
+      Self::Bettercap => "bettercap restart",
+      Self::System => "pwnagotchi restart",
+    };
+    write!(f, "{s}")
+  }
+}
+
+/// What to do about the blind epochs seen so far.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlindCheck {
+  Fine,
+  /// Blind, but not for long enough since the last step.
+  Waiting,
+  Escalate,
+  /// Access points are back after a recovery step.
+  Recovered,
+}
+
+/// Counts the blind epochs since the last recovery step, so each step gets a
+/// full window of `mon_max_blind_epochs` before the next one. The epoch's own
+/// `blind_for` keeps counting for the personality and the rewards.
+#[derive(Debug, Default)]
+pub struct BlindWatch {
+  last_step: u32,
+  escalated: bool,
+}
+
+impl BlindWatch {
+  pub fn observe(&mut self, blind_for: u32, max_blind: u32) -> BlindCheck {
+    if blind_for < self.last_step {
+      self.last_step = 0;
+    }
+
+    if blind_for == 0 {
+      return if std::mem::take(&mut self.escalated) {
+        BlindCheck::Recovered
+      } else {
+        BlindCheck::Fine
+      };
+    }
+
+    if blind_for - self.last_step < max_blind {
+      return BlindCheck::Waiting;
+    }
+
+    self.last_step = blind_for;
+    self.escalated = true;
+    BlindCheck::Escalate
+  }
+}
+
+pub struct InterfaceManager {
+  bc: Arc<dyn BettercapTrait + Send + Sync>,
+  sm: Arc<SessionManager>,
+  epoch: Arc<RwLock<Epoch>>,
+  agent: Arc<dyn AgentTrait + Send + Sync>,
+  stage: Mutex<Escalation>,
+}
+
+impl InterfaceManager {
+  pub fn new(
+    bc: Arc<dyn BettercapTrait + Send + Sync>,
+    sm: Arc<SessionManager>,
+    epoch: Arc<RwLock<Epoch>>,
+    agent: Arc<dyn AgentTrait + Send + Sync>,
+  ) -> Self {
+    Self {
+      bc,
+      sm,
+      epoch,
+      agent,
+      stage: Mutex::new(Escalation::Monitor),
+    }
+  }
+
+  pub fn stage(&self) -> Escalation {
+    *self.stage.lock()
+  }
+
+  pub async fn interface_state(&self) -> InterfaceState {
+    let iface = config_read().main.iface.clone();
+
+    let known_to_bettercap = match self.bc.session().await {
+      Ok(Some(session)) => session.interfaces.iter().any(|i| i.name == *iface),
+      _ => false,
+    };
+
+    let operstate = Path::new("/sys/class/net").join(iface.as_ref()).join("operstate");
+
+    match std::fs::read_to_string(operstate) {
+      Ok(state) if state.trim() == "down" => InterfaceState::Down,
+      Ok(_) => InterfaceState::Up,
+      Err(_) if known_to_bettercap => InterfaceState::Up,
+      Err(_) => InterfaceState::Missing,
+    }
+  }
+
+  async fn watch(&self) {
+    let mut last_epoch = self.epoch.read().epoch;
+    let mut blind = BlindWatch::default();
+
+    loop {
+      sleep(POLL_INTERVAL).await;
+
+      let (epoch, blind_for) = {
+        let e = self.epoch.read();
+        (e.epoch, e.blind_for)
+      };
+
+      if epoch == last_epoch {
+        continue;
+      }
+      last_epoch = epoch;
+
+      let max_blind = config_read().main.mon_max_blind_epochs;
+      match blind.observe(blind_for, max_blind) {
+        BlindCheck::Recovered => {
+          *self.stage.lock() = Escalation::Monitor;
+          self.record("Access points are visible again, recovery succeeded");
+        }
+        BlindCheck::Escalate => self.escalate(blind_for).await,
+        BlindCheck::Fine | BlindCheck::Waiting => {}
+      }
+    }
+  }
+
+  pub async fn escalate(&self, blind_for: u32) {
+    let state = self.interface_state().await;
+    let stage = self.stage();
+
+    self.record(&format!("Blind for {blind_for} epochs, interface is {state}, attempting {stage}"));
+
+    let next = match stage {
+      Escalation::Monitor => {
+        self.restart_monitor_mode().await;
+        Escalation::Bettercap
+      }
+      Escalation::Bettercap => {
+        self.restart_bettercap().await;
+        Escalation::System
+      }
+      Escalation::System => {
+        self.restart_system();
+        Escalation::System
+      }
+    };
+
+    *self.stage.lock() = next;
+  }
+
+  pub async fn restart_monitor_mode(&self) -> bool {
+    let (mon_stop_cmd, mon_start_cmd) = {
+      let cfg = config_read();
+      (cfg.main.mon_stop_cmd.clone(), cfg.main.mon_start_cmd.clone())
+    };
+
+    if !mon_stop_cmd.trim().is_empty() {
+      run_command(&mon_stop_cmd).await;
+    }
+
+    if !mon_start_cmd.trim().is_empty() && !run_command(&mon_start_cmd).await {
+      self.record("Monitor mode command failed");
+      return false;
+    }
+
+    // Give the driver time to bring the interface back
+    sleep(Duration::from_secs(2)).await;
+
+    let state = self.interface_state().await;
+    if state == InterfaceState::Missing {
+      self.record("Interface did not come back after restarting monitor mode");
+      return false;
+    }
+
+    restart_module(&self.bc, WIFI_RECON).await;
+    self.record(&format!("Monitor mode restarted, interface is {state}"));
+    true
+  }
+
+  pub async fn restart_bettercap(&self) -> bool {
+    let restart_cmd = config_read().bettercap.restart_cmd.clone();
+
+    if restart_cmd.trim().is_empty() || !run_command(&restart_cmd).await {
+      self.record("Bettercap restart command failed");
+      return false;
+    }
+
+    if tokio::time::timeout(SETUP_TIMEOUT, perform_bettercap_setup(&self.bc))
+      .await
+      .is_err()
+    {
+      self.record("Bettercap did not finish setup after restart");
+      return false;
+    }
+
+    self.record("Bettercap restarted");
+    true
+  }
+
+  pub fn restart_system(&self) {
+    let mode = self.sm.get_session().read().mode;
+    self.record(&format!("Restarting pwnagotchi in {mode:?} mode"));
+    self.agent.restart(Some(mode));
+  }
+
+  fn record(&self, message: &str) {
+    LOGGER.log_warning("Interface", message);
+    self.sm.journal("Interface", message);
+  }
+}
+
+pub async fn run_command(cmd: &str) -> bool {
+  match tokio::process::Command::new("sh").arg("-c").arg(cmd).status().await {
+    Ok(status) if status.success() => true,
+    Ok(status) => {
+      LOGGER.log_error("Interface", &format!("Command '{cmd}' failed with status: {status}"));
+      false
+    }
+    Err(e) => {
+      LOGGER.log_error("Interface", &format!("Failed to run command '{cmd}': {e}"));
+      false
+    }
+  }
+}
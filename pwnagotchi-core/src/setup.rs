@@ -10,6 +10,7 @@ use pwnagotchi_shared::{
   },
 };
 use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 
 use crate::agent::{is_module_running, restart_module, start_module};
 
@@ -17,6 +18,7 @@ const WIFI_RECON: &str = "wifi.recon";
 
 pub struct SetupComponent {
   setup: Option<Arc<dyn SetupTrait>>,
+  shutdown: CancellationToken,
 }
 
 impl Dependencies for SetupComponent {
@@ -35,13 +37,17 @@ impl Component for SetupComponent {
     let bc = &ctx.bettercap;
     let setup = Setup::new(Arc::clone(bc));
     self.setup = Some(Arc::new(setup));
+    self.shutdown = ctx.shutdown.clone();
 
     Ok(())
   }
 
   async fn start(&self) -> Result<Option<JoinHandle<()>>> {
     if let Some(setup) = &self.setup {
-      setup.perform_setup().await;
+      tokio::select! {
+        () = setup.perform_setup() => {},
+        () = self.shutdown.cancelled() => {},
+      }
     }
     Ok(None)
   }
@@ -55,7 +61,10 @@ impl Default for SetupComponent {
 
 impl SetupComponent {
   pub fn new() -> Self {
-    Self { setup: None }
+    Self {
+      setup: None,
+      shutdown: CancellationToken::new(),
+    }
   }
 }
 
@@ -114,17 +123,7 @@ async fn setup_events(bc: &Arc<dyn BettercapTrait + Send + Sync>) {
 }
 
 async fn reset_wifi_settings(bc: &Arc<dyn BettercapTrait + Send + Sync>) {
-  let (interface, ap_ttl, sta_ttl, min_rssi) = {
-    let cfg = config_read();
-    (
-      cfg.main.iface.clone(),
-      cfg.personality.ap_ttl,
-      cfg.personality.sta_ttl,
-      cfg.personality.min_rssi,
-    )
-  };
-  let (ap_tx, ap_rx) = tokio::sync::oneshot::channel();
-  let (sta_tx, sta_rx) = tokio::sync::oneshot::channel();
+  let interface = config_read().main.iface.clone();
   let (tx, rx) = tokio::sync::oneshot::channel();
 
   let _ = bc
@@ -135,43 +134,56 @@ async fn reset_wifi_settings(bc: &Arc<dyn BettercapTrait + Send + Sync>) {
     LOGGER.log_error("Agent", &format!("Failed to set wifi.interface: {e}"));
   }
 
+  apply_personality(bc).await;
+
+  let (tx, rx) = tokio::sync::oneshot::channel();
+  let path = &config_read().bettercap.handshakes.clone();
   let _ = bc
-    .send(BettercapCommand::run(format!("set wifi.ap.ttl {ap_ttl}"), Some(ap_tx)))
+    .send(BettercapCommand::run(format!("set wifi.handshakes.file {path}"), Some(tx)))
     .await;
-  if let Err(e) = ap_rx.await {
-    LOGGER.log_error("Agent", &format!("Failed to set wifi.ap.ttl: {e}"));
+  if let Err(e) = rx.await {
+    LOGGER.log_error("Agent", &format!("Failed to set wifi.handshakes.file: {e}"));
   }
 
+  let (tx, rx) = tokio::sync::oneshot::channel();
   let _ = bc
-    .send(BettercapCommand::run(format!("set wifi.sta.ttl {sta_ttl}"), Some(sta_tx)))
+    .send(BettercapCommand::run("set wifi.handshakes.aggregate false", Some(tx)))
     .await;
-  if let Err(e) = sta_rx.await {
-    LOGGER.log_error("Agent", &format!("Failed to set wifi.sta.ttl: {e}"));
+  if let Err(e) = rx.await {
+    LOGGER.log_error("Agent", &format!("Failed to set wifi.handshakes.aggregate: {e}"));
   }
+}
+
+/// Hands the personality settings bettercap filters by to it, again whenever
+/// they change.
+pub async fn apply_personality(bc: &Arc<dyn BettercapTrait + Send + Sync>) {
+  let (ap_ttl, sta_ttl, min_rssi) = {
+    let cfg = config_read();
+    (cfg.personality.ap_ttl, cfg.personality.sta_ttl, cfg.personality.min_rssi)
+  };
+  let (ap_tx, ap_rx) = tokio::sync::oneshot::channel();
+  let (sta_tx, sta_rx) = tokio::sync::oneshot::channel();
 
-  let (tx, rx) = tokio::sync::oneshot::channel();
   let _ = bc
-    .send(BettercapCommand::run(format!("set wifi.rssi.min {min_rssi}"), Some(tx)))
+    .send(BettercapCommand::run(format!("set wifi.ap.ttl {ap_ttl}"), Some(ap_tx)))
     .await;
-  if let Err(e) = rx.await {
-    LOGGER.log_error("Agent", &format!("Failed to set wifi.rssi.min: {e}"));
+  if let Err(e) = ap_rx.await {
+    LOGGER.log_error("Agent", &format!("Failed to set wifi.ap.ttl: {e}"));
   }
 
-  let (tx, rx) = tokio::sync::oneshot::channel();
-  let path = &config_read().bettercap.handshakes.clone();
   let _ = bc
-    .send(BettercapCommand::run(format!("set wifi.handshakes.file {path}"), Some(tx)))
+    .send(BettercapCommand::run(format!("set wifi.sta.ttl {sta_ttl}"), Some(sta_tx)))
     .await;
-  if let Err(e) = rx.await {
-    LOGGER.log_error("Agent", &format!("Failed to set wifi.handshakes.file: {e}"));
+  if let Err(e) = sta_rx.await {
+    LOGGER.log_error("Agent", &format!("Failed to set wifi.sta.ttl: {e}"));
   }
 
   let (tx, rx) = tokio::sync::oneshot::channel();
   let _ = bc
-    .send(BettercapCommand::run("set wifi.handshakes.aggregate false", Some(tx)))
+    .send(BettercapCommand::run(format!("set wifi.rssi.min {min_rssi}"), Some(tx)))
     .await;
   if let Err(e) = rx.await {
-    LOGGER.log_error("Agent", &format!("Failed to set wifi.handshakes.aggregate: {e}"));
+    LOGGER.log_error("Agent", &format!("Failed to set wifi.rssi.min: {e}"));
   }
 }
 
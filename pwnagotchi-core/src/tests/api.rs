@@ -0,0 +1,238 @@
+use std::{
+  io::{BufRead, BufReader, Read, Write},
+  net::{TcpListener, TcpStream},
+  sync::{Arc, LazyLock},
+  time::Duration,
+};
+
+use base64::{Engine, engine::general_purpose};
+use parking_lot::{Mutex, RwLock};
+use pwnagotchi_shared::identity::Identity;
+use rsa::{
+  Pss, RsaPrivateKey, RsaPublicKey, pkcs8::DecodePublicKey, rand_core::OsRng,
+  traits::SignatureScheme,
+};
+use sha2::{Digest, Sha256};
+
+use crate::api::{ApiClient, ApiError, ClientOptions, Method};
+
+static KEY: LazyLock<RsaPrivateKey> =
+  LazyLock::new(|| RsaPrivateKey::new(&mut OsRng, 1024).expect("failed to generate key"));
+
+#[derive(Default)]
+struct ServerState {
+  issued: u32,
+  token: Option<String>,
+  enrollments: Vec<serde_json::Value>,
+  authorizations: Vec<Option<String>>,
+}
+
+/// Minimal stand-in for the grid server: hands out tokens for validly signed
+/// enrollments and rejects everything else without the current token.
+struct StandIn {
+  address: String,
+  state: Arc<Mutex<ServerState>>,
+}
+
+impl StandIn {
+  fn spawn() -> Self {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = format!("http://{}/api/v1", listener.local_addr().unwrap());
+    let state = Arc::new(Mutex::new(ServerState::default()));
+
+    let server_state = Arc::clone(&state);
+    std::thread::spawn(move || {
+      for stream in listener.incoming().flatten() {
+        handle(stream, &server_state);
+      }
+    });
+
+    Self { address, state }
+  }
+
+  fn revoke(&self) {
+    self.state.lock().token = None;
+  }
+
+  fn client(&self, authenticated: bool, token_ttl: Duration) -> ApiClient {
+    let identity = Identity::from_private_key(KEY.clone()).unwrap();
+    ApiClient::with_options(
+      Arc::new(RwLock::new(identity)),
+      ClientOptions {
+        address: self.address.clone(),
+        authenticated,
+        token_ttl,
+        enroll_endpoint: "unit/enroll".to_string(),
+        name: "alpha".to_string(),
+      },
+    )
+  }
+}
+
+fn handle(stream: TcpStream, state: &Mutex<ServerState>) {
+  let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+  let mut request_line = String::new();
+  reader.read_line(&mut request_line).unwrap();
+  let path = request_line.split_whitespace().nth(1).unwrap_or_default().to_string();
+
+  let mut content_length = 0;
+  let mut authorization = None;
+  loop {
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    let line = line.trim_end();
+    if line.is_empty() {
+      break;
+    }
+    if let Some((name, value)) = line.split_once(':') {
+      match name.to_ascii_lowercase().as_str() {
+        "content-length" => content_length = value.trim().parse().unwrap_or(0),
+        "authorization" => authorization = Some(value.trim().to_string()),
+        _ => {}
+      }
+    }
+  }
+
+  let mut body = vec![0u8; content_length];
+  reader.read_exact(&mut body).unwrap();
+
+  let (status, response) = if path == "/api/v1/unit/enroll" {
+    let enrollment: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    if verify_enrollment(&enrollment) {
+      let mut state = state.lock();
+      state.issued += 1;
+      let token = format!("token-{}", state.issued);
+      state.token = Some(token.clone());
+      state.enrollments.push(enrollment);
+      (200, serde_json::json!({ "token": token }))
+    } else {
+      (400, serde_json::json!({ "error": "bad signature" }))
+    }
+  } else {
+    let mut state = state.lock();
+    state.authorizations.push(authorization.clone());
+    let expected = state.token.as_ref().map(|t| format!("Bearer {t}"));
+    if expected.is_some() && authorization == expected {
+      (200, serde_json::json!({ "success": true }))
+    } else {
+      (401, serde_json::json!({ "error": "unauthorized" }))
+    }
+  };
+
+  let body = response.to_string();
+  let reason = if status == 200 { "OK" } else { "Error" };
+  let mut stream = stream;
+  let _ = write!(
+    stream,
+    "HTTP/1.1 {status} {reason}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+    body.len()
+  );
+}
+
+fn verify_enrollment(enrollment: &serde_json::Value) -> bool {
+  let field = |name: &str| enrollment.get(name).and_then(|v| v.as_str()).unwrap_or_default();
+
+  let Ok(pem) = general_purpose::STANDARD.decode(field("public_key")) else {
+    return false;
+  };
+  let pem = String::from_utf8_lossy(&pem).replace("RSA PUBLIC KEY", "PUBLIC KEY");
+  let Ok(key) = RsaPublicKey::from_public_key_pem(&pem) else {
+    return false;
+  };
+  let Ok(signature) = general_purpose::STANDARD.decode(field("signature")) else {
+    return false;
+  };
+
+  let hashed = Sha256::digest(field("identity").as_bytes());
+  Pss::new_with_salt::<Sha256>(16).verify(&key, &hashed, &signature).is_ok()
+}
+
+#[test]
+fn enrollment_is_signed_with_identity() {
+  let server = StandIn::spawn();
+  let client = server.client(true, Duration::from_secs(60));
+
+  client.enroll(Some(serde_json::json!({ "epochs": 3 }))).unwrap();
+
+  let state = server.state.lock();
+  let enrollment = &state.enrollments[0];
+  let identity = enrollment["identity"].as_str().unwrap();
+  assert!(identity.starts_with("alpha@"));
+  assert_eq!(identity.len(), "alpha@".len() + 64);
+  assert_eq!(enrollment["data"]["epochs"], 3);
+}
+
+#[test]
+fn token_is_attached_and_reused() {
+  let server = StandIn::spawn();
+  let client = server.client(true, Duration::from_secs(60));
+
+  for _ in 0..3 {
+    let response = client
+      .request(Method::Post, "unit/report/ap", Some(&serde_json::json!({})))
+      .unwrap();
+    assert_eq!(response.status(), 200);
+  }
+
+  let state = server.state.lock();
+  assert_eq!(state.enrollments.len(), 1);
+  assert!(state.authorizations.iter().all(|a| a.as_deref() == Some("Bearer token-1")));
+}
+
+#[test]
+fn expired_token_is_refreshed() {
+  let server = StandIn::spawn();
+  let client = server.client(true, Duration::ZERO);
+
+  client.request(Method::Get, "unit/inbox", None).unwrap();
+  let response = client.request(Method::Get, "unit/inbox", None).unwrap();
+
+  assert_eq!(response.status(), 200);
+  assert_eq!(server.state.lock().enrollments.len(), 2);
+}
+
+#[test]
+fn rejected_token_triggers_enrollment() {
+  let server = StandIn::spawn();
+  let client = server.client(true, Duration::from_secs(60));
+
+  client.request(Method::Get, "unit/inbox", None).unwrap();
+  server.revoke();
+  let response = client.request(Method::Get, "unit/inbox", None).unwrap();
+
+  assert_eq!(response.status(), 200);
+  let state = server.state.lock();
+  assert_eq!(state.enrollments.len(), 2);
+  assert_eq!(state.authorizations.last().cloned().flatten().as_deref(), Some("Bearer token-2"));
+}
+
+#[test]
+fn unauthenticated_client_sends_no_token() {
+  let server = StandIn::spawn();
+  let client = server.client(false, Duration::from_secs(60));
+
+  let response = client.request(Method::Get, "unit/inbox", None).unwrap();
+
+  assert_eq!(response.status(), 401);
+  let state = server.state.lock();
+  assert!(state.enrollments.is_empty());
+  assert_eq!(state.authorizations, vec![None]);
+}
+
+#[test]
+fn unreachable_server_is_reported() {
+  let identity = Identity::from_private_key(KEY.clone()).unwrap();
+  let client = ApiClient::with_options(
+    Arc::new(RwLock::new(identity)),
+    ClientOptions {
+      address: "http://127.0.0.1:9/api/v1".to_string(),
+      authenticated: true,
+      token_ttl: Duration::from_secs(60),
+      enroll_endpoint: "unit/enroll".to_string(),
+      name: "alpha".to_string(),
+    },
+  );
+
+  assert!(matches!(client.request(Method::Get, "unit/inbox", None), Err(ApiError::Unreachable(_))));
+}
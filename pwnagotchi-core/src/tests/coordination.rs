@@ -0,0 +1,123 @@
+use pwnagotchi_shared::{
+  mesh::peer::Peer,
+  models::{
+    grid::{Advertisement, PeerResponse},
+    net::AccessPoint,
+  },
+};
+
+use crate::mesh::coordination::{plan, pwned_by_peers};
+
+const MIN_RSSI: i16 = -70;
+
+fn peer(identity: &str, rssi: i16, channels: &[u8], handshakes: &[&str]) -> Peer {
+  Peer::new(&PeerResponse {
+    fingerprint: Some(identity.to_string()),
+    met_at: None,
+    encounters: Some(1),
+    prev_seen_at: None,
+    detected_at: None,
+    seen_at: None,
+    channel: Some(1),
+    rssi: Some(rssi),
+    session_id: None,
+    advertisement: Some(Advertisement {
+      name: identity.to_string(),
+      identity: identity.to_string(),
+      channels: channels.to_vec(),
+      handshakes: handshakes.iter().map(ToString::to_string).collect(),
+      ..Advertisement::default()
+    }),
+  })
+}
+
+fn ap(mac: &str, channel: u8) -> AccessPoint {
+  serde_json::from_value(serde_json::json!({
+    "ipv4": "", "ipv6": "", "mac": mac, "hostname": mac, "alias": "", "vendor": "",
+    "first_seen": "", "last_seen": "", "meta": { "values": {} }, "frequency": 2412,
+    "channel": channel, "rssi": -50, "sent": 0, "received": 0, "encryption": "WPA2",
+    "cipher": "CCMP", "authentication": "PSK", "wps": {}, "clients": [], "handshake": false,
+  }))
+  .unwrap()
+}
+
+fn channels(list: &[u8]) -> Vec<(u8, Vec<AccessPoint>)> {
+  list
+    .iter()
+    .map(|ch| {
+      (
+        *ch,
+        vec![ap(
+          &format!("00:00:00:00:00:{ch:02x}"),
+          *ch,
+        )],
+      )
+    })
+    .collect()
+}
+
+fn worked(plan: &crate::mesh::coordination::Plan) -> Vec<u8> {
+  plan.channels.iter().map(|(ch, _)| *ch).collect()
+}
+
+#[test]
+fn contested_channels_are_split_without_overlap() {
+  let all = [1, 6, 11, 2, 3, 4, 5, 7];
+
+  let alpha = plan("alpha", &[peer("beta", -40, &all, &[])], MIN_RSSI, channels(&all));
+  let beta = plan("beta", &[peer("alpha", -40, &all, &[])], MIN_RSSI, channels(&all));
+
+  let (alpha, beta) = (worked(&alpha), worked(&beta));
+  assert!(!alpha.is_empty() && !beta.is_empty());
+  assert!(alpha.iter().all(|ch| !beta.contains(ch)));
+
+  let mut union = alpha.iter().chain(&beta).copied().collect::<Vec<_>>();
+  union.sort_unstable();
+  let mut expected = all.to_vec();
+  expected.sort_unstable();
+  assert_eq!(union, expected);
+}
+
+#[test]
+fn distant_peers_and_uncovered_channels_are_kept() {
+  let far = peer("beta", -85, &[1, 6, 11], &[]);
+  let plan = plan("alpha", &[far], MIN_RSSI, channels(&[1, 6, 11]));
+
+  assert_eq!(worked(&plan), [1, 6, 11]);
+  assert!(plan.ceded.is_empty());
+
+  let close = peer("beta", -40, &[], &[]);
+  let plan = crate::mesh::coordination::plan("alpha", &[close], MIN_RSSI, channels(&[1, 6]));
+  assert_eq!(worked(&plan), [1, 6]);
+}
+
+#[test]
+fn plan_keeps_channel_order() {
+  // beta ranks ahead of alpha on channel 6
+  let alpha = plan("alpha", &[peer("beta", -40, &[6], &[])], MIN_RSSI, channels(&[11, 6, 1]));
+  assert_eq!(worked(&alpha), [11, 1]);
+  assert_eq!(alpha.ceded, [6]);
+
+  let beta = plan("beta", &[peer("alpha", -40, &[6], &[])], MIN_RSSI, channels(&[11, 6, 1]));
+  assert_eq!(worked(&beta), [11, 6, 1]);
+  assert!(beta.ceded.is_empty());
+}
+
+#[test]
+fn aps_pwned_by_any_peer_are_skipped() {
+  let peers = [peer(
+    "beta",
+    -90,
+    &[],
+    &["00:00:00:00:00:06", "AA:BB:CC:DD:EE:FF"],
+  )];
+  let mut list = channels(&[1, 6]);
+  list[0].1.push(ap("aa:bb:cc:dd:ee:ff", 1));
+
+  let plan = plan("alpha", &peers, MIN_RSSI, list);
+
+  assert_eq!(plan.skipped, 2);
+  assert_eq!(worked(&plan), [1]);
+  assert_eq!(plan.channels[0].1.len(), 1);
+  assert!(pwned_by_peers(&peers).contains("aa:bb:cc:dd:ee:ff"));
+}
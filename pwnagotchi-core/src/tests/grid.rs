@@ -0,0 +1,176 @@
+use std::{
+  io::{BufRead, BufReader, Read, Write},
+  net::TcpListener,
+  sync::Arc,
+  time::Duration,
+};
+
+use parking_lot::{Mutex, RwLock};
+use pwnagotchi_shared::identity::Identity;
+
+use crate::{
+  api::{ApiClient, ClientOptions},
+  grid::{OfflineQueue, QueuedReport},
+};
+
+fn ap(essid: &str) -> QueuedReport {
+  QueuedReport::ReportAp(serde_json::json!({ "essid": essid, "bssid": "00:11:22:33:44:55" }))
+}
+
+#[test]
+fn queue_persists_reports_in_order() {
+  let tmp = tempfile::tempdir().unwrap();
+  let path = tmp.path().join("queue.json");
+  let queue = OfflineQueue::new(&path);
+
+  queue.push(ap("first"));
+  queue.push(ap("second"));
+
+  // a new instance sees the same reports, e.g. after a restart
+  let reopened = OfflineQueue::new(&path);
+  assert_eq!(reopened.take(), vec![ap("first"), ap("second")]);
+  assert!(reopened.is_empty());
+  assert!(!path.exists());
+}
+
+#[test]
+fn queue_keeps_latest_data_and_skips_duplicates() {
+  let tmp = tempfile::tempdir().unwrap();
+  let path = tmp.path().join("queue.json");
+  let queue = OfflineQueue::new(&path);
+
+  queue.push(QueuedReport::Data(serde_json::json!({ "epochs": 1 })));
+  queue.push(ap("home"));
+  queue.push(ap("home"));
+  queue.push(QueuedReport::Data(serde_json::json!({ "epochs": 2 })));
+
+  assert_eq!(
+    queue.take(),
+    vec![
+      ap("home"),
+      QueuedReport::Data(serde_json::json!({ "epochs": 2 }))
+    ]
+  );
+}
+
+#[test]
+fn queue_ignores_corrupt_lines() {
+  let tmp = tempfile::tempdir().unwrap();
+  let path = tmp.path().join("queue.json");
+  let line = serde_json::to_string(&ap("ok")).unwrap();
+  std::fs::write(&path, format!("not json\n{line}\n")).unwrap();
+
+  let queue = OfflineQueue::new(&path);
+  assert_eq!(queue.len(), 1);
+  assert_eq!(queue.take(), vec![ap("ok")]);
+}
+
+#[test]
+fn failed_flush_keeps_order_and_newer_reports() {
+  let tmp = tempfile::tempdir().unwrap();
+  let path = tmp.path().join("queue.json");
+  let queue = OfflineQueue::new(&path);
+
+  queue.push(ap("first"));
+  queue.push(QueuedReport::Data(serde_json::json!({ "epochs": 1 })));
+  queue.push(ap("second"));
+
+  let mut delivered = Vec::new();
+  let sent = queue.flush(|report| {
+    if delivered.is_empty() {
+      delivered.push(report.clone());
+      // the agent keeps reporting while the flush is under way
+      queue.push(QueuedReport::Data(serde_json::json!({ "epochs": 2 })));
+      queue.push(ap("second"));
+      queue.push(ap("third"));
+      return true;
+    }
+    false
+  });
+
+  assert_eq!(sent, 1);
+  assert_eq!(delivered, vec![ap("first")]);
+  assert_eq!(
+    queue.take(),
+    vec![
+      QueuedReport::Data(serde_json::json!({ "epochs": 2 })),
+      ap("second"),
+      ap("third"),
+    ]
+  );
+}
+
+/// Answers every request with 200 and records the paths it was sent to.
+fn grid_stand_in() -> (String, Arc<Mutex<Vec<String>>>) {
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let address = format!("http://{}/api/v1", listener.local_addr().unwrap());
+  let paths = Arc::new(Mutex::new(Vec::new()));
+
+  let seen = Arc::clone(&paths);
+  std::thread::spawn(move || {
+    for mut stream in listener.incoming().flatten() {
+      let mut reader = BufReader::new(stream.try_clone().unwrap());
+      let mut request_line = String::new();
+      reader.read_line(&mut request_line).unwrap();
+
+      let mut content_length = 0;
+      loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let line = line.trim_end();
+        if line.is_empty() {
+          break;
+        }
+        if let Some((name, value)) = line.split_once(':')
+          && name.eq_ignore_ascii_case("content-length")
+        {
+          content_length = value.trim().parse().unwrap_or(0);
+        }
+      }
+      let mut body = vec![0u8; content_length];
+      reader.read_exact(&mut body).unwrap();
+
+      seen
+        .lock()
+        .push(request_line.split_whitespace().nth(1).unwrap_or_default().to_string());
+      let _ = write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{{}}"
+      );
+    }
+  });
+
+  (address, paths)
+}
+
+fn client(address: &str) -> ApiClient {
+  ApiClient::with_options(
+    Arc::new(RwLock::new(Identity::with_path("/nonexistent"))),
+    ClientOptions {
+      address: address.to_string(),
+      authenticated: false,
+      token_ttl: Duration::from_secs(90),
+      enroll_endpoint: "unit/enroll".to_string(),
+      name: "alpha".to_string(),
+    },
+  )
+}
+
+#[test]
+fn queue_is_flushed_once_the_grid_is_back() {
+  let tmp = tempfile::tempdir().unwrap();
+  let path = tmp.path().join("queue.json");
+  let queue = OfflineQueue::new(&path);
+  queue.push(ap("home"));
+  queue.push(QueuedReport::Data(serde_json::json!({ "epochs": 1 })));
+
+  let offline = client("http://127.0.0.1:9/api/v1");
+  assert_eq!(queue.flush(|report| report.deliver(&offline)), 0);
+  assert_eq!(queue.len(), 2);
+
+  let (address, paths) = grid_stand_in();
+  let online = client(&address);
+  assert_eq!(queue.flush(|report| report.deliver(&online)), 2);
+  assert!(queue.is_empty());
+  assert_eq!(*paths.lock(), ["/api/v1/report/ap", "/api/v1/data"]);
+}
@@ -0,0 +1,90 @@
+use std::{
+  fs,
+  path::Path,
+  sync::Arc,
+  time::{Duration, Instant},
+};
+
+use pwnagotchi_hw::memfs::MemoryMount;
+use pwnagotchi_shared::config::{FSConfig, FSMemoryConfig, MountConfig, MountsConfig};
+use tokio_util::sync::CancellationToken;
+
+use crate::memfs::{flush, run, setup};
+
+fn mount(dir: &Path, name: &str) -> MountConfig {
+  MountConfig {
+    mount: dir.join(name).to_string_lossy().into_owned(),
+    ..MountConfig::default()
+  }
+}
+
+fn dry_run(dir: &Path) -> FSMemoryConfig {
+  FSMemoryConfig {
+    enabled: true,
+    disk: dir.join("disk").to_string_lossy().into_owned(),
+    dry_run: true,
+    mounts: MountsConfig {
+      log: mount(dir, "log"),
+      data: mount(dir, "data"),
+    },
+  }
+}
+
+async fn wait_for(what: &str, done: impl Fn() -> bool) {
+  let deadline = Instant::now() + Duration::from_secs(10);
+  while !done() {
+    assert!(Instant::now() < deadline, "timed out waiting for {what}");
+    tokio::time::sleep(Duration::from_millis(20)).await;
+  }
+}
+
+#[tokio::test]
+async fn run_syncs_each_mount_on_its_own_interval() {
+  let tmp = tempfile::tempdir().unwrap();
+  let memory = dry_run(tmp.path());
+  let often = MemoryMount::new("log", &memory.mounts.log, &memory).unwrap();
+  let rarely = MemoryMount::new("data", &memory.mounts.data, &memory).unwrap();
+  for mount in [&often, &rarely] {
+    mount.setup().unwrap();
+    fs::write(mount.mount().join("new"), "kept").unwrap();
+  }
+  let (often, rarely) = (Arc::new(often), Arc::new(rarely));
+
+  let shutdown = CancellationToken::new();
+  let mounts = vec![
+    (Arc::clone(&often), Duration::from_millis(50)),
+    (Arc::clone(&rarely), Duration::from_secs(3600)),
+  ];
+  let running = tokio::spawn({
+    let shutdown = shutdown.clone();
+    async move { run(mounts, &shutdown).await }
+  });
+
+  wait_for("the first sync", || often.disk().join("new").exists()).await;
+  fs::write(often.mount().join("new"), "changed").unwrap();
+  wait_for("the next sync", || {
+    fs::read_to_string(often.disk().join("new")).is_ok_and(|s| s == "changed")
+  })
+  .await;
+  assert!(!rarely.disk().join("new").exists());
+
+  shutdown.cancel();
+  tokio::time::timeout(Duration::from_secs(5), running).await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn flush_syncs_every_mount_set_up() {
+  let tmp = tempfile::tempdir().unwrap();
+  let mut config = FSConfig { memory: dry_run(tmp.path()) };
+  config.memory.mounts.data.enabled = false;
+
+  setup(&config);
+  fs::write(tmp.path().join("log/pwnagotchi.log"), "last lines\n").unwrap();
+  fs::create_dir_all(tmp.path().join("data")).unwrap();
+  fs::write(tmp.path().join("data/not-mounted"), "").unwrap();
+  flush().await;
+
+  let disk = tmp.path().join("disk");
+  assert_eq!(fs::read_to_string(disk.join("log/pwnagotchi.log")).unwrap(), "last lines\n");
+  assert!(!disk.join("data").exists());
+}
@@ -0,0 +1,167 @@
+use std::time::Duration;
+
+use pwnagotchi_shared::{mesh::peer::Peer, models::grid::Advertisement};
+use time::OffsetDateTime;
+
+use crate::mesh::{
+  native::{MAX_PEERS, PeerTable},
+  wire::{Sighting, encode_beacon, parse_frame},
+};
+
+/// Radiotap frames packed the way pwngrid packs them: a regular access
+/// point, two peers with plain payloads, one with the FCS kept, a peer whose
+/// payload is gzipped behind the compression element and a truncated frame.
+const FIXTURE: &[u8] = include_bytes!("fixtures/mesh_beacons.pcap");
+const LINKTYPE_RADIOTAP: u32 = 127;
+
+/// Yields the frames of a little endian pcap capture.
+fn pcap_frames(data: &[u8]) -> Vec<&[u8]> {
+  assert_eq!(u32::from_le_bytes(data[0..4].try_into().unwrap()), 0xa1b2_c3d4);
+  assert_eq!(u32::from_le_bytes(data[20..24].try_into().unwrap()), LINKTYPE_RADIOTAP);
+
+  let mut frames = Vec::new();
+  let mut rest = &data[24..];
+  while rest.len() >= 16 {
+    let caplen = u32::from_le_bytes(rest[8..12].try_into().unwrap()) as usize;
+    frames.push(&rest[16..16 + caplen]);
+    rest = &rest[16 + caplen..];
+  }
+  frames
+}
+
+fn sightings() -> Vec<Sighting> {
+  pcap_frames(FIXTURE).into_iter().filter_map(parse_frame).collect()
+}
+
+fn advertisement(name: &str, identity: &str) -> Advertisement {
+  Advertisement {
+    name: name.to_string(),
+    version: "1.0.0".to_string(),
+    identity: identity.to_string(),
+    face: "(◕‿‿◕)".to_string(),
+    ..Default::default()
+  }
+}
+
+#[test]
+fn fixture_yields_only_mesh_peers() {
+  let frames = pcap_frames(FIXTURE);
+  assert_eq!(frames.len(), 5);
+
+  let sightings = sightings();
+  let names = sightings.iter().map(|s| s.advertisement.name.as_str()).collect::<Vec<_>>();
+  assert_eq!(names, ["alpha", "beta", "gamma"]);
+}
+
+#[test]
+fn fixture_radiotap_fields_are_parsed() {
+  let sightings = sightings();
+
+  let alpha = &sightings[0];
+  assert_eq!(alpha.session_id, [0x02, 0xaa, 0xbb, 0xcc, 0xdd, 0x01]);
+  assert_eq!(alpha.channel, Some(6));
+  assert_eq!(alpha.rssi, Some(-52));
+  assert_eq!(alpha.advertisement.pwnd_total, 42);
+  assert_eq!(alpha.advertisement.policy.channels, vec![1, 6, 11]);
+
+  let beta = &sightings[1];
+  assert_eq!(beta.channel, Some(11));
+  assert_eq!(beta.rssi, Some(-70));
+  assert_eq!(beta.advertisement.identity, "b2".repeat(32));
+}
+
+#[test]
+fn fixture_compressed_payload_is_inflated() {
+  let gamma = sightings().remove(2);
+  assert_eq!(gamma.session_id, [0x02, 0xaa, 0xbb, 0xcc, 0xdd, 0x03]);
+  assert_eq!(gamma.advertisement.identity, "c3".repeat(32));
+  assert_eq!(gamma.advertisement.face, "(⌐■_■)");
+  assert_eq!(gamma.advertisement.pwnd_total, 7);
+  assert_eq!(gamma.advertisement.policy.channels, vec![1, 6, 11]);
+}
+
+#[test]
+fn encoded_beacon_round_trips() {
+  let mut adv = advertisement("gamma", &"c3".repeat(32));
+  adv.name = "g".repeat(600);
+  let session_id = [0x02, 0x01, 0x02, 0x03, 0x04, 0x05];
+
+  let frame = encode_beacon(&session_id, &adv).unwrap();
+  let sighting = parse_frame(&frame).unwrap();
+
+  assert_eq!(sighting.session_id, session_id);
+  assert_eq!(sighting.advertisement.name, adv.name);
+  assert_eq!(sighting.channel, None);
+  assert_eq!(sighting.rssi, None);
+}
+
+#[test]
+fn peer_table_produces_peer_responses() {
+  let now = OffsetDateTime::now_utc();
+  let mut table = PeerTable::new(Duration::from_secs(120));
+
+  for sighting in sightings() {
+    table.observe(sighting, now);
+  }
+
+  let peers = table.peers(now);
+  assert_eq!(peers.len(), 3);
+
+  // closest first
+  let closest = Peer::new(&peers[0]);
+  assert_eq!(closest.name(), "alpha");
+  assert_eq!(closest.rssi, -52);
+  assert_eq!(closest.last_channel, 6);
+  assert_eq!(closest.encounters, 1);
+  assert_eq!(closest.session_id, "02:aa:bb:cc:dd:01");
+}
+
+#[test]
+fn peer_table_counts_encounters_and_expires() {
+  let start = OffsetDateTime::now_utc();
+  let at = |secs| start + time::Duration::seconds(secs);
+  let mut table = PeerTable::new(Duration::from_secs(60));
+  let mut sightings = sightings();
+  let (alpha, beta) = (sightings.remove(0), sightings.remove(0));
+
+  table.observe(alpha.clone(), start);
+  table.observe(alpha.clone(), at(30));
+  assert_eq!(table.peers(at(30))[0].encounters, Some(1));
+
+  // restarted, new session id
+  let mut restarted = alpha.clone();
+  restarted.session_id[5] = 0x09;
+  table.observe(restarted, at(50));
+  let peers = table.peers(at(50));
+  assert_eq!(peers[0].encounters, Some(2));
+  assert!(peers[0].prev_seen_at.is_some());
+
+  // gone for longer than the ttl
+  assert!(table.peers(at(200)).is_empty());
+
+  // and forgotten once anything else is heard
+  table.observe(beta, at(200));
+  table.observe(alpha, at(201));
+  let peers = table.peers(at(201));
+  assert_eq!(peers.len(), 2);
+  assert_eq!(peers[0].encounters, Some(1));
+  assert!(peers[0].prev_seen_at.is_none());
+}
+
+#[test]
+fn peer_table_is_capped() {
+  let start = OffsetDateTime::now_utc();
+  let mut table = PeerTable::new(Duration::from_secs(3600));
+  let alpha = sightings().remove(0);
+
+  for i in 0..MAX_PEERS + 10 {
+    let mut sighting = alpha.clone();
+    sighting.advertisement.identity = format!("{i:064x}");
+    table.observe(sighting, start + time::Duration::seconds(i64::try_from(i).unwrap()));
+  }
+
+  let peers = table.peers(start + time::Duration::seconds(600));
+  assert_eq!(peers.len(), MAX_PEERS);
+  // the first ones heard made room
+  assert!(!peers.iter().any(|p| p.fingerprint == Some(format!("{:064x}", 0))));
+}
@@ -0,0 +1,157 @@
+use std::sync::LazyLock;
+
+use base64::{Engine, engine::general_purpose};
+use pwnagotchi_shared::{
+  identity::{
+    Identity,
+    crypto::{encrypt_for, public_key_from_b64},
+  },
+  mesh::sharing::fingerprint_of,
+};
+use rsa::{RsaPrivateKey, RsaPublicKey, rand_core::OsRng};
+
+use crate::grid::{open_message, seal_message};
+
+/// A message as the grid hands it out, sealed the way pwngrid seals it, for
+/// the recipient key below. The keys are 4096 bits like pwngrid's own.
+const ENVELOPE: &str = include_str!("fixtures/messages/envelope.json");
+const RECIPIENT_PRIV: &str = include_str!("fixtures/messages/recipient_id_rsa");
+const RECIPIENT_PUB: &str = include_str!("fixtures/messages/recipient_id_rsa.pub");
+const SENDER_PUB: &str = include_str!("fixtures/messages/sender_id_rsa.pub");
+
+static ALICE: LazyLock<Identity> = LazyLock::new(identity);
+static BOB: LazyLock<Identity> = LazyLock::new(identity);
+
+fn identity() -> Identity {
+  let key = RsaPrivateKey::new(&mut OsRng, 1024).expect("failed to generate key");
+  Identity::from_private_key(key).unwrap()
+}
+
+fn public_key(identity: &Identity) -> RsaPublicKey {
+  public_key_from_b64(identity.public_key_b64().unwrap()).unwrap()
+}
+
+/// What the grid hands out for a message that was posted with `sealed`.
+fn received(sealed: &serde_json::Value) -> serde_json::Value {
+  serde_json::json!({
+    "id": 1,
+    "sender": ALICE.fingerprint(),
+    "sender_name": "alice",
+    "seen_at": null,
+    "data": sealed["data"],
+    "signature": sealed["signature"],
+  })
+}
+
+#[test]
+fn sealed_message_opens_for_recipient() {
+  let sealed = seal_message(&ALICE, &public_key(&BOB), "hello bob").unwrap();
+  let mut message = received(&sealed);
+
+  open_message(&BOB, Some(&public_key(&ALICE)), &mut message);
+
+  assert_eq!(message["data"], "hello bob");
+  assert_eq!(message["verified"], true);
+}
+
+fn fixture_recipient() -> Identity {
+  let dir = tempfile::tempdir().unwrap();
+  std::fs::write(dir.path().join("id_rsa"), RECIPIENT_PRIV).unwrap();
+  std::fs::write(dir.path().join("id_rsa.pub"), RECIPIENT_PUB).unwrap();
+
+  let mut identity = Identity::with_path(dir.path().to_str().unwrap());
+  identity.load_keys().unwrap();
+  identity
+}
+
+fn fixture_sender() -> RsaPublicKey {
+  // the grid serves unit keys base64 encoded
+  public_key_from_b64(&general_purpose::STANDARD.encode(SENDER_PUB.trim_end())).unwrap()
+}
+
+#[test]
+fn pwngrid_envelope_opens_and_verifies() {
+  let mut message: serde_json::Value = serde_json::from_str(ENVELOPE).unwrap();
+  let sender = general_purpose::STANDARD.encode(SENDER_PUB.trim_end());
+  assert_eq!(message["sender"], fingerprint_of(&sender).unwrap());
+
+  open_message(&fixture_recipient(), Some(&fixture_sender()), &mut message);
+
+  assert_eq!(message["data"], "hey, it's alice. nice handshakes!");
+  assert_eq!(message["verified"], true);
+}
+
+#[test]
+fn pwngrid_envelope_with_tampered_data_is_not_verified() {
+  let mut message: serde_json::Value = serde_json::from_str(ENVELOPE).unwrap();
+  let mut packed = general_purpose::STANDARD.decode(message["data"].as_str().unwrap()).unwrap();
+  let last = packed.len() - 1;
+  packed[last] ^= 1;
+  message["data"] = general_purpose::STANDARD.encode(&packed).into();
+
+  open_message(&fixture_recipient(), Some(&fixture_sender()), &mut message);
+
+  assert_eq!(message["verified"], false);
+  assert_ne!(message["data"], "hey, it's alice. nice handshakes!");
+}
+
+#[test]
+fn envelope_layout_matches_pwngrid() {
+  let packed = encrypt_for(b"hi", &public_key(&BOB)).unwrap();
+
+  let nonce_len = u32::from_le_bytes(packed[..4].try_into().unwrap()) as usize;
+  let key_offset = 4 + nonce_len;
+  let key_len = u32::from_le_bytes(packed[key_offset..key_offset + 4].try_into().unwrap()) as usize;
+
+  assert_eq!(nonce_len, 12);
+  assert_eq!(key_len, 128);
+  // ciphertext plus the 16 byte GCM tag
+  assert_eq!(packed.len(), key_offset + 4 + key_len + 2 + 16);
+}
+
+#[test]
+fn forged_signature_is_not_verified() {
+  let sealed = seal_message(&BOB, &public_key(&BOB), "not from alice").unwrap();
+  let mut message = received(&sealed);
+
+  open_message(&BOB, Some(&public_key(&ALICE)), &mut message);
+
+  assert_eq!(message["data"], "not from alice");
+  assert_eq!(message["verified"], false);
+}
+
+#[test]
+fn unknown_sender_is_not_verified() {
+  let sealed = seal_message(&ALICE, &public_key(&BOB), "hello").unwrap();
+  let mut message = received(&sealed);
+
+  open_message(&BOB, None, &mut message);
+
+  assert_eq!(message["data"], "hello");
+  assert_eq!(message["verified"], false);
+}
+
+#[test]
+fn message_for_someone_else_stays_encrypted() {
+  let sealed = seal_message(&ALICE, &public_key(&ALICE), "for alice only").unwrap();
+  let mut message = received(&sealed);
+
+  open_message(&BOB, Some(&public_key(&ALICE)), &mut message);
+
+  assert_eq!(message["data"], sealed["data"]);
+}
+
+#[test]
+fn truncated_message_is_rejected() {
+  let packed = encrypt_for(b"hello", &public_key(&BOB)).unwrap();
+
+  assert!(BOB.decrypt(&packed[..20]).is_err());
+  assert!(BOB.decrypt(&[]).is_err());
+
+  let mut message = serde_json::json!({
+    "data": general_purpose::STANDARD.encode(&packed[..packed.len() - 1]),
+    "signature": "",
+  });
+  open_message(&BOB, None, &mut message);
+  assert_ne!(message["data"], "hello");
+}
@@ -0,0 +1,137 @@
+use std::sync::Arc;
+
+use parking_lot::{Mutex, RwLock};
+use pwnagotchi_shared::{
+  config::config_write,
+  models::{
+    agent::RunningMode,
+    bettercap::BettercapSession,
+    net::{AccessPoint, Station},
+  },
+  sessions::manager::SessionManager,
+  traits::{
+    agent::AgentTrait,
+    bettercap::{BettercapCommand, BettercapTrait},
+    epoch::Epoch,
+    general::CoreModule,
+  },
+};
+
+use crate::monitor::{BlindCheck, BlindWatch, Escalation, InterfaceManager, InterfaceState};
+
+/// Bettercap that isn't running.
+struct Offline;
+
+impl CoreModule for Offline {
+  fn name(&self) -> &'static str {
+    "Bettercap"
+  }
+}
+
+#[async_trait::async_trait]
+impl BettercapTrait for Offline {
+  async fn send(&self, _cmd: BettercapCommand) -> anyhow::Result<()> {
+    anyhow::bail!("offline")
+  }
+
+  async fn session(&self) -> anyhow::Result<Option<BettercapSession>> {
+    Ok(None)
+  }
+
+  async fn run_websocket(&self) {}
+
+  fn is_ready(&self) -> bool {
+    false
+  }
+
+  async fn run(&self, _cmd: &str) -> Result<(), anyhow::Error> {
+    anyhow::bail!("offline")
+  }
+}
+
+/// Remembers the modes it was asked to restart in.
+#[derive(Default)]
+struct Restarts(Mutex<Vec<Option<RunningMode>>>);
+
+impl CoreModule for Restarts {
+  fn name(&self) -> &'static str {
+    "Agent"
+  }
+}
+
+#[async_trait::async_trait]
+impl AgentTrait for Restarts {
+  async fn set_mode(&self, _mode: RunningMode) {}
+  async fn recon(&self) {}
+  async fn associate(&self, _ap: &AccessPoint, _throttle: Option<f32>) {}
+  async fn deauth(&self, _ap: &AccessPoint, _sta: &Station, _throttle: Option<f32>) {}
+  async fn set_channel(&self, _channel: u8) {}
+  async fn get_access_points_by_channel(&self) -> Vec<(u8, Vec<AccessPoint>)> {
+    Vec::new()
+  }
+  fn start_pwnagotchi(&self) {}
+  fn shutdown(&self) {}
+  fn reboot(&self, _mode: Option<RunningMode>) {}
+  fn restart(&self, mode: Option<RunningMode>) {
+    self.0.lock().push(mode);
+  }
+}
+
+#[test]
+fn each_recovery_step_gets_a_full_window() {
+  let mut watch = BlindWatch::default();
+  let seen = (0..=11).map(|blind_for| watch.observe(blind_for, 5)).collect::<Vec<_>>();
+
+  assert_eq!(seen[0], BlindCheck::Fine);
+  assert_eq!(seen[4], BlindCheck::Waiting);
+  assert_eq!(seen[5], BlindCheck::Escalate);
+  assert_eq!(seen[9], BlindCheck::Waiting);
+  assert_eq!(seen[10], BlindCheck::Escalate);
+  assert_eq!(seen[11], BlindCheck::Waiting);
+
+  assert_eq!(watch.observe(0, 5), BlindCheck::Recovered);
+  assert_eq!(watch.observe(0, 5), BlindCheck::Fine);
+  // counting starts over with the epoch's counter
+  assert_eq!(watch.observe(4, 5), BlindCheck::Waiting);
+  assert_eq!(watch.observe(5, 5), BlindCheck::Escalate);
+}
+
+#[tokio::test]
+async fn escalation_ends_in_a_restart_in_the_current_mode() {
+  {
+    let mut config = config_write();
+    config.main.iface = "pwnagotchi-test-missing0".into();
+    config.main.mon_stop_cmd = "".into();
+    config.main.mon_start_cmd = "false".into();
+    config.bettercap.restart_cmd = "false".into();
+  }
+
+  let sm = Arc::new(SessionManager::new());
+  sm.get_session().write().mode = RunningMode::Manual;
+  let agent = Arc::new(Restarts::default());
+  let manager = InterfaceManager::new(
+    Arc::new(Offline),
+    Arc::clone(&sm),
+    Arc::new(RwLock::new(Epoch::new())),
+    Arc::clone(&agent) as Arc<dyn AgentTrait + Send + Sync>,
+  );
+
+  assert_eq!(manager.interface_state().await, InterfaceState::Missing);
+
+  manager.escalate(5).await;
+  assert_eq!(manager.stage(), Escalation::Bettercap);
+  manager.escalate(10).await;
+  assert_eq!(manager.stage(), Escalation::System);
+  assert!(agent.0.lock().is_empty());
+
+  manager.escalate(15).await;
+  assert_eq!(manager.stage(), Escalation::System);
+  assert_eq!(*agent.0.lock(), [Some(RunningMode::Manual)]);
+
+  let journal = sm.get_session().read().state.journal.clone();
+  let messages = journal.iter().map(|e| e.message.as_str()).collect::<Vec<_>>();
+  assert!(messages.contains(&"Monitor mode command failed"));
+  assert!(messages.contains(&"Bettercap restart command failed"));
+  assert!(messages.contains(&"Restarting pwnagotchi in Manual mode"));
+  assert!(journal.iter().all(|e| e.origin == "Interface"));
+}
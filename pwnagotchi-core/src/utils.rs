@@ -3,6 +3,7 @@
 use std::process::Command;
 
 use pwnagotchi_macros::hookable;
+use pwnagotchi_shared::logger::LOGGER;
 use regex::Regex;
 
 #[hookable]
@@ -10,7 +11,7 @@ pub fn iface_channels(name: &str) -> Vec<u8> {
   let phy_out = match Command::new("/sbin/iw").args(["dev", name, "info"]).output() {
     Ok(output) => output,
     Err(e) => {
-      eprintln!("Failed to execute iw dev info: {e}");
+      LOGGER.log_error("Interface", &format!("Failed to execute iw dev info: {e}"));
 
       return vec![];
     }
@@ -37,7 +38,7 @@ pub fn iface_channels(name: &str) -> Vec<u8> {
   {
     Ok(output) => output,
     Err(e) => {
-      eprintln!("Failed to execute iw phy channels: {e}");
+      LOGGER.log_error("Interface", &format!("Failed to execute iw phy channels: {e}"));
 
       return vec![];
     }
@@ -52,7 +53,7 @@ pub fn iface_channels(name: &str) -> Vec<u8> {
   let re = match Regex::new(r"\[(\d+)\]") {
     Ok(regex) => regex,
     Err(e) => {
-      eprintln!("Failed to compile regex: {e}");
+      LOGGER.log_error("Interface", &format!("Failed to compile regex: {e}"));
 
       return vec![];
     }
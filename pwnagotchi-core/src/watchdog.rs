@@ -0,0 +1,169 @@
+use std::{
+  sync::Arc,
+  time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use parking_lot::RwLock;
+use pwnagotchi_hw::watchdog::{Liveness, LivenessCriteria, Watchdog};
+use pwnagotchi_shared::{
+  config::config_read,
+  logger::LOGGER,
+  models::agent::RunningMode,
+  sessions::manager::SessionManager,
+  traits::{
+    bettercap::BettercapTrait,
+    epoch::Epoch,
+    general::{Component, CoreModules, Dependencies},
+  },
+};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+const HEALTH_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct WatchdogComponent {
+  bc: Option<Arc<dyn BettercapTrait + Send + Sync>>,
+  sm: Option<Arc<SessionManager>>,
+  epoch: Option<Arc<RwLock<Epoch>>>,
+  shutdown: CancellationToken,
+}
+
+impl Dependencies for WatchdogComponent {
+  fn name(&self) -> &'static str {
+    "WatchdogComponent"
+  }
+
+  fn dependencies(&self) -> &[&str] {
+    &[
+      "Bettercap",
+      "Epoch",
+      "SessionManager",
+      "SetupComponent",
+    ]
+  }
+}
+
+#[async_trait::async_trait]
+impl Component for WatchdogComponent {
+  async fn init(&mut self, ctx: &CoreModules) -> Result<()> {
+    self.bc = Some(Arc::clone(&ctx.bettercap));
+    self.sm = Some(Arc::clone(&ctx.session_manager));
+    self.epoch = Some(Arc::clone(&ctx.epoch));
+    self.shutdown = ctx.shutdown.clone();
+    Ok(())
+  }
+
+  async fn start(&self) -> Result<Option<JoinHandle<()>>> {
+    let (enabled, device) = {
+      let cfg = config_read();
+      (cfg.watchdog.enabled, cfg.watchdog.device.clone())
+    };
+
+    if !enabled {
+      return Ok(None);
+    }
+
+    let (Some(bc), Some(sm), Some(epoch)) = (&self.bc, &self.sm, &self.epoch) else {
+      return Ok(None);
+    };
+
+    let watchdog = match Watchdog::open(device.as_ref()) {
+      Ok(wd) => wd,
+      Err(e) => {
+        LOGGER.log_error("Watchdog", &format!("Failed to open {device}: {e}"));
+        return Ok(None);
+      }
+    };
+
+    LOGGER.log_info("Watchdog", &format!("Watching {device}"));
+
+    let (bc, sm, epoch) = (Arc::clone(bc), Arc::clone(sm), Arc::clone(epoch));
+    let shutdown = self.shutdown.clone();
+    let handle = tokio::spawn(async move {
+      run(watchdog, &bc, &sm, &epoch, &shutdown).await;
+    });
+
+    Ok(Some(handle))
+  }
+}
+
+impl Default for WatchdogComponent {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl WatchdogComponent {
+  pub fn new() -> Self {
+    Self {
+      bc: None,
+      sm: None,
+      epoch: None,
+      shutdown: CancellationToken::new(),
+    }
+  }
+}
+
+/// Pets the watchdog for as long as the unit makes progress. Once it stops,
+/// the hardware timer runs out and resets the unit.
+async fn run(
+  mut watchdog: Watchdog,
+  bc: &Arc<dyn BettercapTrait + Send + Sync>,
+  sm: &Arc<SessionManager>,
+  epoch: &Arc<RwLock<Epoch>>,
+  shutdown: &CancellationToken,
+) {
+  let (criteria, interval, disarm_on_exit) = {
+    let cfg = config_read();
+    (
+      LivenessCriteria::from(&cfg.watchdog),
+      Duration::from_secs(cfg.watchdog.interval.max(1)),
+      cfg.watchdog.disarm_on_exit,
+    )
+  };
+
+  let mut liveness = Liveness::new(criteria, Instant::now());
+  let mut stalled = false;
+
+  loop {
+    tokio::select! {
+      () = shutdown.cancelled() => break,
+      () = tokio::time::sleep(interval) => {}
+    }
+
+    let healthy =
+      matches!(tokio::time::timeout(HEALTH_TIMEOUT, bc.session()).await, Ok(Ok(Some(_))));
+    let now = Instant::now();
+
+    if sm.get_session().read().mode == RunningMode::Manual {
+      liveness.reset_epoch(now);
+    } else {
+      liveness.observe_epoch(epoch.read().epoch, now);
+    }
+    liveness.observe_bettercap(healthy, now);
+
+    if let Some(stall) = liveness.stall(now) {
+      if !stalled {
+        stalled = true;
+        let msg = format!("Unit is stuck ({stall}), letting the watchdog reset it");
+        LOGGER.log_error("Watchdog", &msg);
+        sm.journal("Watchdog", &msg);
+      }
+      continue;
+    }
+
+    if stalled {
+      stalled = false;
+      LOGGER.log_info("Watchdog", "Unit recovered, petting the watchdog again");
+    }
+
+    if let Err(e) = watchdog.pet() {
+      LOGGER.log_error("Watchdog", &format!("Failed to pet {}: {e}", watchdog.path().display()));
+    }
+  }
+
+  if disarm_on_exit && let Err(e) = watchdog.disarm() {
+    LOGGER.log_warning("Watchdog", &format!("Failed to disarm watchdog: {e}"));
+  }
+}
@@ -8,6 +8,16 @@ pub enum Mode {
   Dev,
 }
 
+impl From<&str> for Mode {
+  fn from(device: &str) -> Self {
+    match device.to_lowercase().as_str() {
+      "pi" => Self::Pi,
+      "portable" => Self::Portable,
+      _ => Self::Dev,
+    }
+  }
+}
+
 pub struct Backend {
   pub hostname: Arc<dyn HostnameManager>,
   pub sysinfo: Arc<dyn SysInfo>,
@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use pwnagotchi_shared::config::config_read;
+use pwnagotchi_shared::{config::config_read, logger::LOGGER};
 use tiny_skia::Pixmap as RgbaImage;
 
 use crate::display::waveshare2in13b_v4::Waveshare2in13bV4;
@@ -95,15 +95,15 @@ impl DisplayTrait for DisplayImpl {
   }
 
   fn initialize(&self) {
-    println!("Not Implemented");
+    LOGGER.log_warning("Display", "Not Implemented");
   }
 
   fn render(&self, _canvas: &mut RgbaImage) {
-    println!("Not Implemented");
+    LOGGER.log_warning("Display", "Not Implemented");
   }
 
   fn clear(&self) {
-    println!("Not Implemented");
+    LOGGER.log_warning("Display", "Not Implemented");
   }
 }
 
@@ -6,5 +6,14 @@ pub mod display {
 pub mod backend;
 
 pub mod hostname;
+pub mod memfs;
 pub mod syscontrol;
 pub mod sysinfo;
+pub mod watchdog;
+
+#[cfg(test)]
+pub mod tests {
+  pub mod memfs;
+  pub mod syscontrol;
+  pub mod watchdog;
+}
@@ -0,0 +1,377 @@
+//! Keeps directories that are written to all the time, like the logs, in
+//! memory so they don't wear out the SD card. The directory on the card is
+//! bind mounted under `fs.memory.disk` to stay reachable, then a zram device
+//! or tmpfs is mounted over it and filled from the card. [`MemoryMount::sync`]
+//! copies changes back.
+//!
+//! In a dry run nothing is mounted, `<disk>/<name>` is a plain directory
+//! standing in for the card.
+
+use std::{
+  collections::HashSet,
+  ffi::OsStr,
+  fs::{self, File},
+  io,
+  os::unix::fs::symlink,
+  path::{Path, PathBuf},
+  process::Command,
+  sync::Mutex,
+  time::Duration,
+};
+
+use pwnagotchi_shared::{
+  config::{FSConfig, FSMemoryConfig, MountConfig},
+  logger::LOGGER,
+};
+
+const MOUNT_OPTIONS: &str = "nosuid,noexec,nodev";
+const ZRAM_CONTROL: &str = "/sys/class/zram-control";
+/// Suffix of files being copied, renamed over the target once complete.
+const PARTIAL: &str = ".memfs-partial";
+
+/// What holds a directory kept in memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backing {
+  Zram,
+  Tmpfs,
+  /// Mounted by an earlier run that didn't reboot since.
+  Existing,
+  /// Dry run, a plain directory.
+  Directory,
+}
+
+impl Backing {
+  pub const fn label(self) -> &'static str {
+    match self {
+      Self::Zram => "zram",
+      Self::Tmpfs => "tmpfs",
+      Self::Existing => "existing mount",
+      Self::Directory => "dry run",
+    }
+  }
+}
+
+/// One directory of `fs.memory.mounts`.
+#[derive(Debug)]
+pub struct MemoryMount {
+  name: String,
+  mount: PathBuf,
+  disk: PathBuf,
+  size: u64,
+  sync: u64,
+  zram: bool,
+  rsync: bool,
+  dry_run: bool,
+  /// Held while syncing, the periodic sync and the one on shutdown may run
+  /// at the same time.
+  syncing: Mutex<()>,
+}
+
+impl MemoryMount {
+  /// `None` if the size isn't valid, which validation reports.
+  pub fn new(name: &str, config: &MountConfig, memory: &FSMemoryConfig) -> Option<Self> {
+    Some(Self {
+      name: name.to_string(),
+      mount: PathBuf::from(&config.mount),
+      disk: Path::new(&memory.disk).join(name),
+      size: config.size_bytes()?,
+      sync: config.sync,
+      zram: config.zram,
+      rsync: config.rsync,
+      dry_run: memory.dry_run,
+      syncing: Mutex::new(()),
+    })
+  }
+
+  /// The enabled mounts, none when `fs.memory` is off.
+  pub fn from_config(config: &FSConfig) -> Vec<Self> {
+    let memory = &config.memory;
+    if !memory.enabled {
+      return Vec::new();
+    }
+    [
+      ("log", &memory.mounts.log),
+      ("data", &memory.mounts.data),
+    ]
+    .into_iter()
+    .filter(|(_, mount)| mount.enabled)
+    .filter_map(|(name, mount)| Self::new(name, mount, memory))
+    .collect()
+  }
+
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+
+  pub fn mount(&self) -> &Path {
+    &self.mount
+  }
+
+  /// Where the copy on the SD card is reachable.
+  pub fn disk(&self) -> &Path {
+    &self.disk
+  }
+
+  /// Time between syncs, `None` to sync on shutdown only.
+  pub fn sync_interval(&self) -> Option<Duration> {
+    (self.sync > 0).then(|| Duration::from_secs(self.sync))
+  }
+
+  /// Puts the directory into memory and fills it from the card. Falls back
+  /// to tmpfs when zram can't be set up.
+  pub fn setup(&self) -> io::Result<Backing> {
+    let fresh = !self.disk.exists();
+    fs::create_dir_all(&self.mount)?;
+    fs::create_dir_all(&self.disk)?;
+
+    if self.dry_run {
+      // like the bind mount would, a new copy starts with what is there
+      if fresh {
+        mirror(&self.mount, &self.disk)?;
+      } else {
+        self.restore()?;
+      }
+      return Ok(Backing::Directory);
+    }
+
+    if is_mounted(&self.mount)? {
+      if !is_mounted(&self.disk)? {
+        return Err(io::Error::other(format!(
+          "{} is mounted but its copy isn't reachable at {}",
+          self.mount.display(),
+          self.disk.display()
+        )));
+      }
+      return Ok(Backing::Existing);
+    }
+
+    run(
+      "mount",
+      [
+        OsStr::new("--bind"),
+        self.mount.as_os_str(),
+        self.disk.as_os_str(),
+      ],
+    )?;
+    run(
+      "mount",
+      [
+        OsStr::new("--make-private"),
+        self.disk.as_os_str(),
+      ],
+    )?;
+
+    let backing = if self.zram {
+      self.mount_zram().map(|()| Backing::Zram).or_else(|e| {
+        LOGGER.log_warning(
+          "MemoryFS",
+          &format!("zram for {} failed, using tmpfs: {e}", self.mount.display()),
+        );
+        self.mount_tmpfs().map(|()| Backing::Tmpfs)
+      })
+    } else {
+      self.mount_tmpfs().map(|()| Backing::Tmpfs)
+    };
+    let backing = match backing {
+      Ok(backing) => backing,
+      Err(e) => {
+        let _ = run("umount", [self.disk.as_os_str()]);
+        return Err(e);
+      }
+    };
+
+    if let Err(e) = self.restore() {
+      let _ = run("umount", [self.mount.as_os_str()]);
+      let _ = run("umount", [self.disk.as_os_str()]);
+      return Err(e);
+    }
+    Ok(backing)
+  }
+
+  /// Copies the memory side back to the card, removing what was deleted.
+  pub fn sync(&self) -> io::Result<()> {
+    let _syncing = self.syncing.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+    self.copy(&self.mount, &self.disk)?;
+    if !self.dry_run {
+      run("sync", [] as [&OsStr; 0])?;
+    }
+    Ok(())
+  }
+
+  /// Fills the memory side from the card.
+  fn restore(&self) -> io::Result<()> {
+    self.copy(&self.disk, &self.mount)
+  }
+
+  fn copy(&self, from: &Path, to: &Path) -> io::Result<()> {
+    if self.rsync && !self.dry_run {
+      let mut from = from.as_os_str().to_owned();
+      from.push("/");
+      let mut to = to.as_os_str().to_owned();
+      to.push("/");
+      let args = [
+        "-aX",
+        "--inplace",
+        "--no-whole-file",
+        "--delete-after",
+      ];
+      run("rsync", args.iter().map(OsStr::new).chain([from.as_os_str(), to.as_os_str()]))
+    } else {
+      mirror(from, to)
+    }
+  }
+
+  fn mount_tmpfs(&self) -> io::Result<()> {
+    let options = format!("{MOUNT_OPTIONS},mode=0755,size={}", self.size);
+    run(
+      "mount",
+      [
+        OsStr::new("-t"),
+        OsStr::new("tmpfs"),
+        OsStr::new("-o"),
+        OsStr::new(&options),
+        OsStr::new("pwnagotchi"),
+        self.mount.as_os_str(),
+      ],
+    )
+  }
+
+  /// A new zram device with room for twice `size` compressed into `size` of
+  /// memory, formatted as ext4.
+  fn mount_zram(&self) -> io::Result<()> {
+    if !Path::new(ZRAM_CONTROL).exists() {
+      run("modprobe", [OsStr::new("zram")])?;
+    }
+    let id = fs::read_to_string(Path::new(ZRAM_CONTROL).join("hot_add"))?.trim().to_string();
+    let block = PathBuf::from(format!("/sys/block/zram{id}"));
+    let device = format!("/dev/zram{id}");
+
+    let result = (|| {
+      // not every kernel has lz4, the default algorithm works too
+      let _ = fs::write(block.join("comp_algorithm"), "lz4");
+      fs::write(block.join("disksize"), (self.size * 2).to_string())?;
+      fs::write(block.join("mem_limit"), self.size.to_string())?;
+      run("mke2fs", ["-q", "-t", "ext4", device.as_str()].map(OsStr::new))?;
+      run(
+        "mount",
+        [
+          OsStr::new("-t"),
+          OsStr::new("ext4"),
+          OsStr::new("-o"),
+          OsStr::new(MOUNT_OPTIONS),
+          OsStr::new(&device),
+          self.mount.as_os_str(),
+        ],
+      )
+    })();
+
+    if result.is_err() {
+      let _ = fs::write(Path::new(ZRAM_CONTROL).join("hot_remove"), &id);
+    }
+    result
+  }
+}
+
+/// Makes `to` a copy of `from`. Files with the same size and modification
+/// time are taken as unchanged, the others are written next to the target
+/// and renamed over it, so a full card leaves the old copy intact.
+pub fn mirror(from: &Path, to: &Path) -> io::Result<()> {
+  fs::create_dir_all(to)?;
+  let mut seen = HashSet::new();
+
+  for entry in fs::read_dir(from)? {
+    let entry = entry?;
+    let name = entry.file_name();
+    if name.to_string_lossy().ends_with(PARTIAL) {
+      continue;
+    }
+    let source = entry.path();
+    let target = to.join(&name);
+    seen.insert(name.clone());
+
+    let meta = fs::symlink_metadata(&source)?;
+    let existing = fs::symlink_metadata(&target).ok();
+    let file_type = meta.file_type();
+
+    if file_type.is_symlink() {
+      let link = fs::read_link(&source)?;
+      if existing.as_ref().is_some_and(|e| e.file_type().is_symlink())
+        && fs::read_link(&target).is_ok_and(|l| l == link)
+      {
+        continue;
+      }
+      remove(&target)?;
+      symlink(link, &target)?;
+    } else if file_type.is_dir() {
+      if existing.as_ref().is_some_and(|e| !e.is_dir()) {
+        remove(&target)?;
+      }
+      mirror(&source, &target)?;
+      fs::set_permissions(&target, meta.permissions())?;
+    } else if file_type.is_file() {
+      let unchanged = existing.as_ref().is_some_and(|e| {
+        e.is_file() && e.len() == meta.len() && e.modified().ok() == meta.modified().ok()
+      });
+      if unchanged {
+        continue;
+      }
+      if existing.as_ref().is_some_and(|e| e.is_dir()) {
+        remove(&target)?;
+      }
+      let mut partial = name.clone();
+      partial.push(PARTIAL);
+      let partial = to.join(partial);
+      fs::copy(&source, &partial)?;
+      let file = File::options().write(true).open(&partial)?;
+      file.set_modified(meta.modified()?)?;
+      file.sync_all()?;
+      fs::rename(&partial, &target)?;
+    }
+    // sockets and fifos only mean something while running
+  }
+
+  for entry in fs::read_dir(to)? {
+    let entry = entry?;
+    if !seen.contains(&entry.file_name()) {
+      remove(&entry.path())?;
+    }
+  }
+  Ok(())
+}
+
+fn remove(path: &Path) -> io::Result<()> {
+  match fs::symlink_metadata(path) {
+    Ok(meta) if meta.is_dir() => fs::remove_dir_all(path),
+    Ok(_) => fs::remove_file(path),
+    Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+    Err(e) => Err(e),
+  }
+}
+
+/// Whether something is mounted at `path`.
+fn is_mounted(path: &Path) -> io::Result<bool> {
+  let path = path.canonicalize()?;
+  let mounts = fs::read_to_string("/proc/self/mounts")?;
+  Ok(mounts.lines().filter_map(|line| line.split(' ').nth(1)).any(|point| {
+    // spaces and friends are octal escaped
+    Path::new(&point.replace("\\040", " ").replace("\\011", "\t")) == path
+  }))
+}
+
+fn run<I, S>(program: &str, args: I) -> io::Result<()>
+where
+  I: IntoIterator<Item = S>,
+  S: AsRef<OsStr>,
+{
+  let mut command = Command::new(program);
+  command.args(args);
+  let output = command.output()?;
+  if output.status.success() {
+    return Ok(());
+  }
+  let args = command.get_args().map(|a| a.to_string_lossy()).collect::<Vec<_>>().join(" ");
+  Err(io::Error::other(format!(
+    "{program} {args} failed: {}",
+    String::from_utf8_lossy(&output.stderr).trim()
+  )))
+}
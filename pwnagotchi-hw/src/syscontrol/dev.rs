@@ -1,6 +1,6 @@
 use std::error::Error;
 
-use pwnagotchi_shared::models::agent::RunningMode;
+use pwnagotchi_shared::{logger::LOGGER, models::agent::RunningMode};
 
 use crate::syscontrol::SysControl;
 
@@ -8,14 +8,22 @@ pub struct DevSysControl;
 
 impl SysControl for DevSysControl {
   fn shutdown(&self) -> Result<(), Box<dyn Error>> {
+    LOGGER.log_warning("Pwnagotchi", "[dev] Simulating shutdown");
     Ok(())
   }
 
-  fn reboot(&self, _mode: Option<RunningMode>) -> Result<(), Box<dyn Error>> {
+  fn reboot(&self, mode: Option<RunningMode>) -> Result<(), Box<dyn Error>> {
+    LOGGER.log_warning("Pwnagotchi", &format!("[dev] Simulating reboot ({mode:?})"));
     Ok(())
   }
 
-  fn restart(&self, _mode: RunningMode) -> Result<(), Box<dyn Error>> {
+  fn restart(&self, mode: RunningMode) -> Result<(), Box<dyn Error>> {
+    LOGGER.log_warning("Pwnagotchi", &format!("[dev] Simulating restart in {mode:?} mode"));
+    Ok(())
+  }
+
+  fn mark_mode(&self, mode: RunningMode) -> Result<(), Box<dyn Error>> {
+    LOGGER.log_warning("Pwnagotchi", &format!("[dev] Simulating {mode:?} mode marker"));
     Ok(())
   }
 }
@@ -1,4 +1,6 @@
-use pwnagotchi_shared::models::agent::RunningMode;
+use std::{error::Error, path::Path};
+
+use pwnagotchi_shared::{config::config_read, models::agent::RunningMode};
 
 mod dev;
 mod pi;
@@ -8,8 +10,33 @@ pub use dev::DevSysControl;
 pub use pi::PiSysControl;
 pub use portable::PortableSysControl;
 
-pub trait SysControl {
+pub trait SysControl: Send + Sync {
   fn shutdown(&self) -> Result<(), Box<dyn std::error::Error>>;
   fn reboot(&self, mode: Option<RunningMode>) -> Result<(), Box<dyn std::error::Error>>;
   fn restart(&self, mode: RunningMode) -> Result<(), Box<dyn std::error::Error>>;
+  /// Leave a marker so the next start picks up `mode`.
+  fn mark_mode(&self, mode: RunningMode) -> Result<(), Box<dyn std::error::Error>>;
+}
+
+pub(crate) fn write_mode_marker(mode: RunningMode) -> Result<(), Box<dyn Error>> {
+  let marker_dir = config_read().system.marker_dir.clone();
+  write_marker(Path::new(marker_dir.as_ref()), mode)
+}
+
+/// Touches the marker for `mode` in `dir` and removes the other one.
+pub fn write_marker(dir: &Path, mode: RunningMode) -> Result<(), Box<dyn Error>> {
+  let (marker, stale) = if mode == RunningMode::Auto {
+    (".pwnagotchi-auto", ".pwnagotchi-manual")
+  } else {
+    (".pwnagotchi-manual", ".pwnagotchi-auto")
+  };
+
+  let _ = std::fs::remove_file(dir.join(stale));
+  std::fs::write(dir.join(marker), b"")?;
+  Ok(())
+}
+
+pub(crate) fn run_command(cmd: &str) -> Result<(), Box<dyn Error>> {
+  let status = std::process::Command::new("sh").arg("-c").arg(cmd).status()?;
+  if status.success() { Ok(()) } else { Err(format!("'{cmd}' exited with {status}").into()) }
 }
@@ -1,14 +1,19 @@
 use std::{error::Error, thread::sleep};
 
-use pwnagotchi_shared::{logger::LOGGER, models::agent::RunningMode};
+use pwnagotchi_shared::{config::config_read, logger::LOGGER, models::agent::RunningMode};
 
-use crate::syscontrol::SysControl;
+use crate::syscontrol::{SysControl, run_command, write_mode_marker};
 
 pub struct PiSysControl;
 
 impl SysControl for PiSysControl {
   fn shutdown(&self) -> Result<(), Box<dyn Error>> {
-    Ok(())
+    LOGGER.log_warning("Pwnagotchi", "Shutting down...");
+    LOGGER.log_warning("Pwnagotchi", "Syncing....");
+
+    let _ = std::process::Command::new("sync").status();
+    let cmd = config_read().system.shutdown_cmd.clone();
+    run_command(&cmd)
   }
 
   fn reboot(&self, mode: Option<RunningMode>) -> Result<(), Box<dyn Error>> {
@@ -18,31 +23,28 @@ impl SysControl for PiSysControl {
       LOGGER.log_warning("Pwnagotchi", "Rebooting...");
     }
 
-    if RunningMode::Auto == mode.unwrap_or(RunningMode::Auto) {
-      let _ = std::process::Command::new("touch").arg("/root/.pwnagotchi-auto").status();
-    } else if RunningMode::Manual == mode.unwrap_or(RunningMode::Auto) {
-      let _ = std::process::Command::new("touch").arg("/root/.pwnagotchi-manual").status();
-    }
-
     LOGGER.log_warning("Pwnagotchi", "Syncing....");
 
     let _ = std::process::Command::new("sync").status();
-    let _ = std::process::Command::new("shutdown").arg("-r").arg("now").status();
-    Ok(())
+    let cmd = config_read().system.reboot_cmd.clone();
+    run_command(&cmd)
   }
 
   fn restart(&self, mode: RunningMode) -> Result<(), Box<dyn Error>> {
     LOGGER.log_warning("Pwnagotchi", &format!("Restarting in {mode:?} mode..."));
 
-    if RunningMode::Auto == mode {
-      let _ = std::process::Command::new("touch").arg("/root/.pwnagotchi-auto").status();
-    } else {
-      let _ = std::process::Command::new("touch").arg("/root/.pwnagotchi-manual").status();
+    let (bettercap, pwnagotchi) = {
+      let config = config_read();
+      (config.bettercap.restart_cmd.clone(), config.system.restart_cmd.clone())
+    };
+    if let Err(e) = run_command(&bettercap) {
+      LOGGER.log_warning("Pwnagotchi", &format!("Failed to restart bettercap: {e}"));
     }
-
-    let _ = std::process::Command::new("service").arg("bettercap").arg("restart").status();
     sleep(std::time::Duration::from_secs(1));
-    let _ = std::process::Command::new("service").arg("pwnagotchi").arg("restart").status();
-    Ok(())
+    run_command(&pwnagotchi)
+  }
+
+  fn mark_mode(&self, mode: RunningMode) -> Result<(), Box<dyn Error>> {
+    write_mode_marker(mode)
   }
 }
@@ -1,20 +1,34 @@
 use std::error::Error;
 
-use pwnagotchi_shared::models::agent::RunningMode;
+use pwnagotchi_shared::{config::config_read, logger::LOGGER, models::agent::RunningMode};
 
-use crate::syscontrol::SysControl;
+use crate::syscontrol::{SysControl, run_command, write_mode_marker};
 pub struct PortableSysControl;
 
 impl SysControl for PortableSysControl {
   fn shutdown(&self) -> Result<(), Box<dyn Error>> {
-    Ok(())
+    LOGGER.log_warning("Pwnagotchi", "Shutting down...");
+    let cmd = config_read().system.shutdown_cmd.clone();
+    run_command(&cmd)
   }
 
-  fn reboot(&self, _mode: Option<RunningMode>) -> Result<(), Box<dyn Error>> {
-    Ok(())
+  fn reboot(&self, mode: Option<RunningMode>) -> Result<(), Box<dyn Error>> {
+    if mode.is_some() {
+      LOGGER.log_warning("Pwnagotchi", &format!("Rebooting in {mode:?} mode..."));
+    } else {
+      LOGGER.log_warning("Pwnagotchi", "Rebooting...");
+    }
+    let cmd = config_read().system.reboot_cmd.clone();
+    run_command(&cmd)
   }
 
-  fn restart(&self, _mode: RunningMode) -> Result<(), Box<dyn Error>> {
-    Ok(())
+  fn restart(&self, mode: RunningMode) -> Result<(), Box<dyn Error>> {
+    LOGGER.log_warning("Pwnagotchi", &format!("Restarting in {mode:?} mode..."));
+    let cmd = config_read().system.restart_cmd.clone();
+    run_command(&cmd)
+  }
+
+  fn mark_mode(&self, mode: RunningMode) -> Result<(), Box<dyn Error>> {
+    write_mode_marker(mode)
   }
 }
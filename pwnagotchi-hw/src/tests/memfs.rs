@@ -0,0 +1,109 @@
+use std::{fs, path::Path, time::Duration};
+
+use pwnagotchi_shared::config::{FSConfig, FSMemoryConfig, MountConfig};
+
+use crate::memfs::{Backing, MemoryMount, mirror};
+
+fn dry_run(dir: &Path, rsync: bool) -> MemoryMount {
+  let memory = FSMemoryConfig {
+    disk: dir.join("disk").to_string_lossy().into_owned(),
+    dry_run: true,
+    ..FSMemoryConfig::default()
+  };
+  let config = MountConfig {
+    mount: dir.join("memory").to_string_lossy().into_owned(),
+    rsync,
+    ..MountConfig::default()
+  };
+  MemoryMount::new("log", &config, &memory).unwrap()
+}
+
+#[test]
+fn enabled_mounts_come_from_config() {
+  let mut config = FSConfig::default();
+  assert!(MemoryMount::from_config(&config).is_empty());
+
+  config.memory.enabled = true;
+  let mounts = MemoryMount::from_config(&config);
+  assert_eq!(mounts.iter().map(MemoryMount::name).collect::<Vec<_>>(), ["log", "data"]);
+  assert_eq!(mounts[0].disk(), Path::new("/run/pwnagotchi/disk/log"));
+  assert_eq!(mounts[0].sync_interval(), Some(Duration::from_secs(60)));
+
+  config.memory.mounts.data.enabled = false;
+  config.memory.mounts.log.sync = 0;
+  let mounts = MemoryMount::from_config(&config);
+  assert_eq!(mounts.len(), 1);
+  assert_eq!(mounts[0].sync_interval(), None);
+
+  config.memory.enabled = false;
+  assert!(MemoryMount::from_config(&config).is_empty());
+}
+
+#[test]
+fn dry_run_keeps_existing_files_and_restores_the_copy() {
+  let tmp = tempfile::tempdir().unwrap();
+  let dir = tmp.path();
+  let mount = dry_run(dir, false);
+  fs::create_dir_all(mount.mount()).unwrap();
+  fs::write(mount.mount().join("pwnagotchi.log"), "before\n").unwrap();
+
+  // a new copy starts with what is already in the directory
+  assert_eq!(mount.setup().unwrap(), Backing::Directory);
+  assert_eq!(fs::read_to_string(mount.disk().join("pwnagotchi.log")).unwrap(), "before\n");
+
+  // after that the copy wins, like a fresh tmpfs filled from the card
+  fs::write(mount.mount().join("pwnagotchi.log"), "lost\n").unwrap();
+  fs::write(mount.mount().join("stray"), "").unwrap();
+  mount.setup().unwrap();
+  assert_eq!(fs::read_to_string(mount.mount().join("pwnagotchi.log")).unwrap(), "before\n");
+  assert!(!mount.mount().join("stray").exists());
+}
+
+#[test]
+fn sync_mirrors_changes_and_deletions() {
+  let tmp = tempfile::tempdir().unwrap();
+  let dir = tmp.path();
+  let mount = dry_run(dir, true);
+  mount.setup().unwrap();
+
+  let memory = mount.mount();
+  fs::create_dir_all(memory.join("sessions/old")).unwrap();
+  fs::write(memory.join("sessions/old/a.json"), "{}").unwrap();
+  fs::write(memory.join("pwnagotchi.log"), "one\n").unwrap();
+  std::os::unix::fs::symlink("pwnagotchi.log", memory.join("latest")).unwrap();
+  mount.sync().unwrap();
+
+  let disk = mount.disk();
+  assert_eq!(fs::read_to_string(disk.join("sessions/old/a.json")).unwrap(), "{}");
+  assert_eq!(fs::read_link(disk.join("latest")).unwrap(), Path::new("pwnagotchi.log"));
+
+  fs::remove_dir_all(memory.join("sessions/old")).unwrap();
+  fs::write(memory.join("pwnagotchi.log"), "one\ntwo\n").unwrap();
+  mount.sync().unwrap();
+
+  assert!(!disk.join("sessions/old").exists());
+  assert!(disk.join("sessions").is_dir());
+  assert_eq!(fs::read_to_string(disk.join("pwnagotchi.log")).unwrap(), "one\ntwo\n");
+  assert_eq!(
+    fs::metadata(disk.join("pwnagotchi.log")).unwrap().modified().unwrap(),
+    fs::metadata(memory.join("pwnagotchi.log")).unwrap().modified().unwrap()
+  );
+}
+
+#[test]
+fn mirror_replaces_entries_that_changed_type() {
+  let tmp = tempfile::tempdir().unwrap();
+  let dir = tmp.path();
+  let (from, to) = (dir.join("from"), dir.join("to"));
+  fs::create_dir_all(from.join("was_file")).unwrap();
+  fs::write(from.join("was_dir"), "file").unwrap();
+  fs::create_dir_all(to.join("was_dir/nested")).unwrap();
+  fs::write(to.join("was_file"), "file").unwrap();
+  fs::write(to.join("x.memfs-partial"), "left over").unwrap();
+
+  mirror(&from, &to).unwrap();
+
+  assert!(to.join("was_file").is_dir());
+  assert_eq!(fs::read_to_string(to.join("was_dir")).unwrap(), "file");
+  assert!(!to.join("x.memfs-partial").exists());
+}
@@ -0,0 +1,15 @@
+use pwnagotchi_shared::models::agent::RunningMode;
+
+use crate::syscontrol::write_marker;
+
+#[test]
+fn marker_replaces_the_other_mode() {
+  let dir = tempfile::tempdir().unwrap();
+
+  write_marker(dir.path(), RunningMode::Manual).unwrap();
+  assert!(dir.path().join(".pwnagotchi-manual").exists());
+
+  write_marker(dir.path(), RunningMode::Auto).unwrap();
+  assert!(dir.path().join(".pwnagotchi-auto").exists());
+  assert!(!dir.path().join(".pwnagotchi-manual").exists());
+}
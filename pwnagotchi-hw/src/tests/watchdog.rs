@@ -0,0 +1,92 @@
+use std::time::{Duration, Instant};
+
+use crate::watchdog::{Liveness, LivenessCriteria, Stall, Watchdog};
+
+const CRITERIA: LivenessCriteria = LivenessCriteria {
+  max_epoch_stall: Some(Duration::from_secs(60)),
+  max_bettercap_stall: Some(Duration::from_secs(30)),
+};
+
+#[test]
+fn pet_writes_keepalive() {
+  let device = tempfile::NamedTempFile::new().unwrap();
+  let path = device.path();
+  let mut wd = Watchdog::open(path).unwrap();
+
+  wd.pet().unwrap();
+  wd.pet().unwrap();
+
+  assert_eq!(std::fs::read(path).unwrap().len(), 2);
+}
+
+#[test]
+fn disarm_writes_magic_close() {
+  let device = tempfile::NamedTempFile::new().unwrap();
+  let path = device.path();
+  let mut wd = Watchdog::open(path).unwrap();
+
+  wd.pet().unwrap();
+  wd.disarm().unwrap();
+
+  assert_eq!(std::fs::read(path).unwrap().last(), Some(&b'V'));
+}
+
+#[test]
+fn open_fails_without_device() {
+  assert!(Watchdog::open("/nonexistent/watchdog").is_err());
+}
+
+#[test]
+fn progressing_unit_is_alive() {
+  let start = Instant::now();
+  let mut liveness = Liveness::new(CRITERIA, start);
+
+  for i in 1..10 {
+    let now = start + Duration::from_secs(u64::from(i) * 20);
+    liveness.observe_epoch(i, now);
+    liveness.observe_bettercap(true, now);
+    assert_eq!(liveness.stall(now), None);
+  }
+}
+
+#[test]
+fn stuck_epoch_is_detected() {
+  let start = Instant::now();
+  let mut liveness = Liveness::new(CRITERIA, start);
+  liveness.observe_epoch(1, start);
+
+  let now = start + Duration::from_secs(61);
+  liveness.observe_epoch(1, now);
+  liveness.observe_bettercap(true, now);
+
+  assert!(matches!(liveness.stall(now), Some(Stall::Epoch(_))));
+
+  liveness.reset_epoch(now);
+  assert_eq!(liveness.stall(now), None);
+}
+
+#[test]
+fn unhealthy_bettercap_is_detected() {
+  let start = Instant::now();
+  let mut liveness = Liveness::new(CRITERIA, start);
+
+  let now = start + Duration::from_secs(31);
+  liveness.observe_epoch(1, now);
+  liveness.observe_bettercap(false, now);
+
+  assert!(matches!(liveness.stall(now), Some(Stall::Bettercap(_))));
+}
+
+#[test]
+fn disabled_criteria_never_stall() {
+  let start = Instant::now();
+  let liveness = Liveness::new(
+    LivenessCriteria {
+      max_epoch_stall: None,
+      max_bettercap_stall: None,
+    },
+    start,
+  );
+
+  assert_eq!(liveness.stall(start + Duration::from_secs(3600)), None);
+}
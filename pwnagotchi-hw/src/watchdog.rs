@@ -0,0 +1,132 @@
+use std::{
+  fmt::Display,
+  fs::{File, OpenOptions},
+  io::{self, Write},
+  path::{Path, PathBuf},
+  time::{Duration, Instant},
+};
+
+use pwnagotchi_shared::config::WatchdogConfig;
+
+/// Written before closing to tell the driver the close was intentional.
+const MAGIC_CLOSE: &[u8] = b"V";
+const KEEPALIVE: &[u8] = b"\0";
+
+/// Handle to a Linux watchdog device. Opening it arms the timer, the unit is
+/// reset unless [`Watchdog::pet`] is called before it expires.
+pub struct Watchdog {
+  path: PathBuf,
+  file: File,
+}
+
+impl Watchdog {
+  pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+    let path = path.as_ref().to_path_buf();
+    let file = OpenOptions::new().write(true).open(&path)?;
+    Ok(Self { path, file })
+  }
+
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+
+  pub fn pet(&mut self) -> io::Result<()> {
+    self.file.write_all(KEEPALIVE)?;
+    self.file.flush()
+  }
+
+  /// Stops the timer on drivers that support the magic close.
+  pub fn disarm(mut self) -> io::Result<()> {
+    self.file.write_all(MAGIC_CLOSE)?;
+    self.file.flush()
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LivenessCriteria {
+  pub max_epoch_stall: Option<Duration>,
+  pub max_bettercap_stall: Option<Duration>,
+}
+
+impl From<&WatchdogConfig> for LivenessCriteria {
+  fn from(cfg: &WatchdogConfig) -> Self {
+    let secs = |s: u64| (s > 0).then(|| Duration::from_secs(s));
+    Self {
+      max_epoch_stall: secs(cfg.max_epoch_stall),
+      max_bettercap_stall: secs(cfg.max_bettercap_stall),
+    }
+  }
+}
+
+/// Why the watchdog is no longer being petted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stall {
+  Epoch(Duration),
+  Bettercap(Duration),
+}
+
+impl Display for Stall {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Epoch(d) => write!(f, "no new epoch for {}s", d.as_secs()),
+      Self::Bettercap(d) => write!(f, "bettercap unhealthy for {}s", d.as_secs()),
+    }
+  }
+}
+
+/// Tracks when the agent loop and bettercap last made progress.
+#[derive(Debug, Clone)]
+pub struct Liveness {
+  criteria: LivenessCriteria,
+  last_epoch: Option<u32>,
+  epoch_progress: Instant,
+  bettercap_healthy: Instant,
+}
+
+impl Liveness {
+  pub const fn new(criteria: LivenessCriteria, now: Instant) -> Self {
+    Self {
+      criteria,
+      last_epoch: None,
+      epoch_progress: now,
+      bettercap_healthy: now,
+    }
+  }
+
+  pub fn observe_epoch(&mut self, epoch: u32, now: Instant) {
+    if self.last_epoch != Some(epoch) {
+      self.last_epoch = Some(epoch);
+      self.epoch_progress = now;
+    }
+  }
+
+  /// The agent loop is not expected to run, e.g. in manual mode.
+  pub const fn reset_epoch(&mut self, now: Instant) {
+    self.epoch_progress = now;
+  }
+
+  pub const fn observe_bettercap(&mut self, healthy: bool, now: Instant) {
+    if healthy {
+      self.bettercap_healthy = now;
+    }
+  }
+
+  /// Returns the first criterion that is violated, if any.
+  pub fn stall(&self, now: Instant) -> Option<Stall> {
+    let since_epoch = now.saturating_duration_since(self.epoch_progress);
+    if let Some(max) = self.criteria.max_epoch_stall
+      && since_epoch > max
+    {
+      return Some(Stall::Epoch(since_epoch));
+    }
+
+    let since_bettercap = now.saturating_duration_since(self.bettercap_healthy);
+    if let Some(max) = self.criteria.max_bettercap_stall
+      && since_bettercap > max
+    {
+      return Some(Stall::Bettercap(since_bettercap));
+    }
+
+    None
+  }
+}
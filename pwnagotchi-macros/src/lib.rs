@@ -372,7 +372,7 @@ fn expand_free_fn(item_fn: ItemFn) -> TokenStream {
               return Default::default();
             }
             Err(e) => {
-              eprintln!("Before hook error: {}", e);
+              ::pwnagotchi_shared::logger::LOGGER.log_error("HOOKS", &format!("Before hook error: {}", e));
             }
           }
         }
@@ -394,7 +394,7 @@ fn expand_free_fn(item_fn: ItemFn) -> TokenStream {
             #orig_ident(#(#arg_idents),*).await
           }
             Err(e) => {
-              eprintln!("Instead hook error: {}", e);
+              ::pwnagotchi_shared::logger::LOGGER.log_error("HOOKS", &format!("Instead hook error: {}", e));
               #orig_ident(#(#arg_idents),*).await
             }
           }
@@ -421,7 +421,7 @@ fn expand_free_fn(item_fn: ItemFn) -> TokenStream {
               break;
             }
             Err(e) => {
-              eprintln!("After hook error: {}", e);
+              ::pwnagotchi_shared::logger::LOGGER.log_error("HOOKS", &format!("After hook error: {}", e));
             }
           }
         }
@@ -467,7 +467,7 @@ fn expand_free_fn(item_fn: ItemFn) -> TokenStream {
             return Default::default();
           }
           Err(e) => {
-            eprintln!("Before hook error: {}", e);
+            ::pwnagotchi_shared::logger::LOGGER.log_error("HOOKS", &format!("Before hook error: {}", e));
           }
         }
       }
@@ -490,7 +490,7 @@ fn expand_free_fn(item_fn: ItemFn) -> TokenStream {
             #orig_ident(#(#arg_idents),*)
           }
           Err(e) => {
-            eprintln!("Instead hook error: {}", e);
+            ::pwnagotchi_shared::logger::LOGGER.log_error("HOOKS", &format!("Instead hook error: {}", e));
             #orig_ident(#(#arg_idents),*)
           }
         }
@@ -517,7 +517,7 @@ fn expand_free_fn(item_fn: ItemFn) -> TokenStream {
             break;
           }
           Err(e) => {
-            eprintln!("After hook error: {}", e);
+            ::pwnagotchi_shared::logger::LOGGER.log_error("HOOKS", &format!("After hook error: {}", e));
           }
         }
       }
@@ -893,7 +893,7 @@ fn expand_impl(item_impl: ItemImpl) -> TokenStream {
                 Ok(pwnagotchi_shared::types::hooks::BeforeHookResult::Stop) => {
                   return Default::default();
                 }
-                Err(e) => { eprintln!("Before hook error: {}", e); }
+                Err(e) => { ::pwnagotchi_shared::logger::LOGGER.log_error("HOOKS", &format!("Before hook error: {}", e)); }
               }
             }
 
@@ -913,7 +913,7 @@ fn expand_impl(item_impl: ItemImpl) -> TokenStream {
                   instance.#original_name(#(#method_arg_names),*).await
                 }
                 Err(e) => {
-                  eprintln!("Instead hook error: {}", e);
+                  ::pwnagotchi_shared::logger::LOGGER.log_error("HOOKS", &format!("Instead hook error: {}", e));
                   instance.#original_name(#(#method_arg_names),*).await
                 }
               }
@@ -938,7 +938,7 @@ fn expand_impl(item_impl: ItemImpl) -> TokenStream {
                 Ok(pwnagotchi_shared::types::hooks::AfterHookResult::Stop) => {
                   return Default::default();
                 }
-                Err(e) => { eprintln!("After hook error: {}", e); }
+                Err(e) => { ::pwnagotchi_shared::logger::LOGGER.log_error("HOOKS", &format!("After hook error: {}", e)); }
               }
             }
 
@@ -978,7 +978,7 @@ fn expand_impl(item_impl: ItemImpl) -> TokenStream {
                 Ok(pwnagotchi_shared::types::hooks::BeforeHookResult::Stop) => {
                   return Default::default();
                 }
-                Err(e) => { eprintln!("Before hook error: {}", e); }
+                Err(e) => { ::pwnagotchi_shared::logger::LOGGER.log_error("HOOKS", &format!("Before hook error: {}", e)); }
               }
             }
 
@@ -998,7 +998,7 @@ fn expand_impl(item_impl: ItemImpl) -> TokenStream {
                   instance.#original_name(#(#method_arg_names),*)
                 }
                 Err(e) => {
-                  eprintln!("Instead hook error: {}", e);
+                  ::pwnagotchi_shared::logger::LOGGER.log_error("HOOKS", &format!("Instead hook error: {}", e));
                   instance.#original_name(#(#method_arg_names),*)
                 }
               }
@@ -1023,7 +1023,7 @@ fn expand_impl(item_impl: ItemImpl) -> TokenStream {
                 Ok(pwnagotchi_shared::types::hooks::AfterHookResult::Stop) => {
                   return Default::default();
                 }
-                Err(e) => { eprintln!("After hook error: {}", e); }
+                Err(e) => { ::pwnagotchi_shared::logger::LOGGER.log_error("HOOKS", &format!("After hook error: {}", e)); }
               }
             }
 
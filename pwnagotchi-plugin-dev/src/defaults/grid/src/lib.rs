@@ -12,7 +12,7 @@ use pwnagotchi_plugins::traits::{
   plugins::{Plugin, PluginAPI, PluginInfo},
 };
 use pwnagotchi_shared::{
-  logger::LOGGER, sessions::session_stats::SessionStats, traits::general::CoreModules,
+  logger::Log, sessions::session_stats::SessionStats, traits::general::CoreModules,
   types::events::EventPayload,
 };
 use regex::Regex;
@@ -32,8 +32,8 @@ impl Grid {
   }
 
   #[allow(unused)]
-  fn parse_pcap(&self, filename: &str) -> Option<(String, String)> {
-    LOGGER.log_debug("Grid", &format!("Parsing {}", filename));
+  fn parse_pcap(&self, logger: &Log, filename: &str) -> Option<(String, String)> {
+    logger.log_debug("Grid", &format!("Parsing {}", filename));
 
     let base = filename.trim_end_matches(".pcap");
     let (essid, mut bssid) = if base.contains('_') {
@@ -59,7 +59,7 @@ impl Grid {
     Some((essid, bssid))
   }
 
-  fn check_handshakes(&mut self, core: &Arc<CoreModules>, bettercap_path: &str) {
+  fn check_handshakes(&mut self, logger: &Log, core: &Arc<CoreModules>, bettercap_path: &str) {
     let mut reported = self.reported.lock().unwrap();
     let pcap_dir = PathBuf::from(bettercap_path);
     let Ok(entries) = std::fs::read_dir(pcap_dir) else {
@@ -76,8 +76,8 @@ impl Grid {
         if reported.contains(net_id) {
           continue;
         }
-        if let Some((essid, bssid)) = self.parse_pcap(name) {
-          LOGGER.log_info(
+        if let Some((essid, bssid)) = self.parse_pcap(logger, name) {
+          logger.log_info(
             "Grid",
             &format!("Reporting new handshake ESSID='{}', BSSID='{}'", essid, bssid),
           );
@@ -95,6 +95,7 @@ impl Grid {
     let lock_flag = Arc::clone(&self.is_locked);
     let core = Arc::clone(&plugin_api.core_modules);
     let bettercap_path = plugin_api.config.bettercap.handshakes.clone();
+    let logger = Arc::clone(&plugin_api.logger);
 
     let handler: EventHandler = Arc::new(move |payload: &EventPayload| {
       let mut lock = lock_flag.lock().unwrap();
@@ -106,13 +107,20 @@ impl Grid {
       let stats = payload.deserialize::<SessionStats>()?;
       grid_ref.update_data(&stats);
 
+      if let Some(unread) = grid_ref.unread_messages()
+        && unread > 0
+      {
+        logger.log_info("Grid", &format!("{unread} unread messages"));
+        core.view.on_unread_messages(unread);
+      }
+
       drop(lock);
 
       let mut tmp = Grid {
         reported: Arc::clone(&reported),
         is_locked: Arc::clone(&lock_flag),
       };
-      tmp.check_handshakes(&core, &bettercap_path);
+      tmp.check_handshakes(&logger, &core, &bettercap_path);
 
       Ok(())
     });
@@ -1,36 +1,31 @@
-use std::{error::Error, sync::Arc};
+use std::{
+  error::Error,
+  sync::{Arc, LazyLock},
+};
 
 use pwnagotchi_plugins::traits::{
   events::EventHandler,
   plugins::{Plugin, PluginAPI, PluginInfo},
 };
-use pwnagotchi_shared::types::events::EventPayload;
+use pwnagotchi_shared::{logger::Log, types::events::EventPayload};
 
 #[derive(Default)]
-pub struct HelloWorld;
+pub struct HelloWorld {
+  // the unit's logger, set on load
+  logger: Option<&'static LazyLock<Log>>,
+}
 
 impl HelloWorld {
   pub fn new() -> Self {
-    Self {}
-  }
-
-  fn handle_ready_event(&self) -> EventHandler {
-    Arc::new(move |_payload: &EventPayload| {
-      eprintln!("Ready event received!");
-      Ok(())
-    })
-  }
-
-  fn handle_starting_event(&self) -> EventHandler {
-    Arc::new(move |_payload: &EventPayload| {
-      eprintln!("Starting event received!");
-      Ok(())
-    })
+    Self { logger: None }
   }
 
-  fn handle_plugin_event(&self) -> EventHandler {
+  fn log_event(&self, message: &'static str) -> EventHandler {
+    let logger = self.logger;
     Arc::new(move |_payload: &EventPayload| {
-      eprintln!("plugin init event received!");
+      if let Some(logger) = logger {
+        logger.log_info("HelloWorld", message);
+      }
       Ok(())
     })
   }
@@ -48,18 +43,24 @@ impl Plugin for HelloWorld {
   }
 
   fn on_load(&mut self, plugin_api: PluginAPI) -> Result<(), Box<dyn Error + 'static>> {
-    plugin_api.event_api.register_listener("ready", self.handle_ready_event())?;
+    self.logger = Some(*plugin_api.logger);
+
+    plugin_api
+      .event_api
+      .register_listener("ready", self.log_event("Ready event received!"))?;
     plugin_api
       .event_api
-      .register_listener("starting", self.handle_starting_event())?;
+      .register_listener("starting", self.log_event("Starting event received!"))?;
     plugin_api
       .event_api
-      .register_listener("plugin::init", self.handle_plugin_event())?;
+      .register_listener("plugin::init", self.log_event("plugin init event received!"))?;
     Ok(())
   }
 
   fn on_unload(&mut self) -> Result<(), Box<dyn Error + 'static>> {
-    println!("Goodbye, world! Plugin shutting down.");
+    if let Some(logger) = self.logger {
+      logger.log_info("HelloWorld", "Goodbye, world! Plugin shutting down.");
+    }
     Ok(())
   }
 }
@@ -7,6 +7,7 @@ use std::{
 
 use libloading::{Library, Symbol};
 use pwnagotchi_shared::{config::config_read, logger::LOGGER, traits::general::CoreModules};
+use tracing::Span;
 use uuid::Uuid;
 
 use crate::{
@@ -67,10 +68,13 @@ impl PluginLoader for RustPluginLoader {
         let entry = entry.unwrap();
         let path = entry.path();
         if path.extension().and_then(|s| s.to_str()) == Some(self.extension()) {
-          println!("Found Rust plugin: {:?}", path.file_name());
+          LOGGER.log_debug("PLUGIN", &format!("Found Rust plugin: {}", path.display()));
           unsafe {
             if let Err(e) = self.load_plugin_from_path(&path) {
-              println!("Failed to load plugin from {:?}: {}", path.file_name(), e);
+              LOGGER.log_error(
+                "PLUGIN",
+                &format!("Failed to load plugin from {}: {}", path.display(), e),
+              );
             }
           }
         }
@@ -83,10 +87,11 @@ impl PluginLoader for RustPluginLoader {
     for entry in &mut self.plugins {
       let plugin_name = entry.plugin.info().name.to_string();
       let plugin = &mut *entry.plugin;
+      let span = plugin_span(&plugin_name);
 
       let result = thread::scope(|s| {
         s.spawn(|| {
-          catch_unwind(AssertUnwindSafe(|| plugin.on_unload()))
+          catch_unwind(AssertUnwindSafe(|| span.in_scope(|| plugin.on_unload())))
             .map(|res| res.map_err(|e| e.to_string()))
         })
         .join()
@@ -99,23 +104,24 @@ impl PluginLoader for RustPluginLoader {
           self.hook_manager.unregister_plugin(&plugin_name).ok();
           self.event_manager.unregister_plugin(&plugin_name).ok();
           LOGGER.log_info("PLUGIN", &format!("Successfully unloaded plugin '{}'", plugin_name));
-          println!("Successfully unloaded plugin '{}'", plugin_name);
         }
         Ok(Ok(Err(e))) => {
           entry.state = PluginState::Failed;
           entry.error = Some(e.clone());
           self.hook_manager.unregister_plugin(&plugin_name).ok();
           self.event_manager.unregister_plugin(&plugin_name).ok();
-          println!("Failed to unload plugin '{}': {}", plugin_name, e);
+          LOGGER.log_error("PLUGIN", &format!("Failed to unload plugin '{}': {}", plugin_name, e));
         }
         Ok(Err(_)) => {
           entry.state = PluginState::Failed;
           self.event_manager.unregister_plugin(&plugin_name).ok();
-          println!("Plugin '{}' panicked during unloading", plugin_name);
+          LOGGER
+            .log_error("PLUGIN", &format!("Plugin '{}' panicked during unloading", plugin_name));
         }
         Err(e) => {
           entry.state = PluginState::Failed;
-          println!("Thread panicked for plugin '{}': {:?}", plugin_name, e);
+          LOGGER
+            .log_error("PLUGIN", &format!("Thread panicked for plugin '{}': {:?}", plugin_name, e));
         }
       }
     }
@@ -138,7 +144,7 @@ impl PluginLoader for RustPluginLoader {
       let plugin_name = entry.plugin.info().name.to_string();
 
       if disabled_plugins.contains(&plugin_name) {
-        println!("Skipping disabled plugin '{}'", plugin_name);
+        LOGGER.log_info("PLUGIN", &format!("Skipping disabled plugin '{}'", plugin_name));
         continue;
       }
 
@@ -161,10 +167,11 @@ impl PluginLoader for RustPluginLoader {
       };
 
       let plugin = &mut *entry.plugin;
+      let span = plugin_span(&plugin_name);
 
       let result = thread::scope(|s| {
         s.spawn(|| {
-          catch_unwind(AssertUnwindSafe(|| plugin.on_load(plugin_api)))
+          catch_unwind(AssertUnwindSafe(|| span.in_scope(|| plugin.on_load(plugin_api))))
             .map(|res| res.map_err(|e| e.to_string()))
         })
         .join()
@@ -173,22 +180,26 @@ impl PluginLoader for RustPluginLoader {
       match result {
         Ok(Ok(Ok(()))) => {
           LOGGER.log_info("PLUGIN", &format!("Successfully initialized plugin '{}'", plugin_name));
-          println!("Successfully initialized plugin '{}'", plugin_name);
           entry.state = PluginState::Initialized;
           entry.error = None;
         }
         Ok(Ok(Err(e))) => {
           entry.state = PluginState::Failed;
           entry.error = Some(e.clone());
-          println!("Failed to initialize plugin '{}': {}", plugin_name, e);
+          LOGGER
+            .log_error("PLUGIN", &format!("Failed to initialize plugin '{}': {}", plugin_name, e));
         }
         Ok(Err(_)) => {
           entry.state = PluginState::Failed;
-          println!("Plugin '{}' panicked during initialization", plugin_name);
+          LOGGER.log_error(
+            "PLUGIN",
+            &format!("Plugin '{}' panicked during initialization", plugin_name),
+          );
         }
         Err(e) => {
           entry.state = PluginState::Failed;
-          println!("Thread panicked for plugin '{}': {:?}", plugin_name, e);
+          LOGGER
+            .log_error("PLUGIN", &format!("Thread panicked for plugin '{}': {:?}", plugin_name, e));
         }
       }
     }
@@ -229,10 +240,11 @@ impl PluginLoader for RustPluginLoader {
     };
 
     let plugin = &mut *entry.plugin;
+    let span = plugin_span(&plugin_name);
 
     let result = thread::scope(|s| {
       s.spawn(|| {
-        catch_unwind(AssertUnwindSafe(|| plugin.on_load(plugin_api)))
+        catch_unwind(AssertUnwindSafe(|| span.in_scope(|| plugin.on_load(plugin_api))))
           .map(|res| res.map_err(|e| e.to_string()))
       })
       .join()
@@ -241,7 +253,6 @@ impl PluginLoader for RustPluginLoader {
     match result {
       Ok(Ok(Ok(()))) => {
         LOGGER.log_info("PLUGIN", &format!("Successfully initialized plugin '{}'", plugin_name));
-        println!("Successfully initialized plugin '{}'", plugin_name);
         entry.state = PluginState::Initialized;
         entry.error = None;
         Ok(())
@@ -271,10 +282,11 @@ impl PluginLoader for RustPluginLoader {
 
     let entry = entry.unwrap();
     let plugin = &mut *entry.plugin;
+    let span = plugin_span(name);
 
     let result = thread::scope(|s| {
       s.spawn(|| {
-        catch_unwind(AssertUnwindSafe(|| plugin.on_unload()))
+        catch_unwind(AssertUnwindSafe(|| span.in_scope(|| plugin.on_unload())))
           .map(|res| res.map_err(|e| e.to_string()))
       })
       .join()
@@ -306,6 +318,12 @@ impl PluginLoader for RustPluginLoader {
   }
 }
 
+/// Context for what a plugin logs from its own code. The loader threads
+/// don't inherit the caller's span, so it is entered in them.
+fn plugin_span(name: &str) -> Span {
+  tracing::info_span!("plugin", plugin = name)
+}
+
 impl RustPluginLoader {
   unsafe fn load_plugin_from_path(&mut self, path: &std::path::Path) -> Result<(), Box<dyn Error>> {
     unsafe {
@@ -11,6 +11,7 @@ use async_trait::async_trait;
 use futures::future::join_all;
 use parking_lot::{Mutex, RwLock};
 use pwnagotchi_shared::{logger::LOGGER, traits::events::EventBus, types::events::EventPayload};
+use tracing::{Instrument, Span};
 
 use crate::traits::events::{AsyncEventHandler, DynamicEventAPITrait, EventError, EventHandler};
 
@@ -21,6 +22,13 @@ struct ListenerEntry {
   kind: EventListenerKind,
 }
 
+impl ListenerEntry {
+  /// Context for what the plugin logs while handling `event`.
+  fn span(&self, event: &str) -> Span {
+    tracing::info_span!("plugin", plugin = self.plugin.as_str(), event)
+  }
+}
+
 #[derive(Clone)]
 enum EventListenerKind {
   Sync(EventHandler),
@@ -82,7 +90,7 @@ impl EventManager {
     for entry in entries {
       match &entry.kind {
         EventListenerKind::Sync(handler) => {
-          if let Err(err) = handler(&payload) {
+          if let Err(err) = entry.span(event).in_scope(|| handler(&payload)) {
             let message = format_error(&err);
             LOGGER.log_error(
               "EVENTS",
@@ -101,7 +109,9 @@ impl EventManager {
           }
         }
         EventListenerKind::Async(handler) => {
-          async_calls.push((entry.plugin.clone(), handler(payload.clone())));
+          let span = entry.span(event);
+          let call = span.in_scope(|| handler(payload.clone())).instrument(span);
+          async_calls.push((entry.plugin.clone(), call));
         }
       }
     }
@@ -180,7 +190,7 @@ impl EventManager {
     // Execute sync listeners immediately
     for entry in &entries {
       if let EventListenerKind::Sync(handler) = &entry.kind
-        && let Err(err) = handler(&payload)
+        && let Err(err) = entry.span(&event).in_scope(|| handler(&payload))
       {
         LOGGER.log_error(
           "EVENTS",
@@ -194,7 +204,7 @@ impl EventManager {
       .into_iter()
       .filter_map(|entry| {
         if let EventListenerKind::Async(handler) = &entry.kind {
-          Some((entry.plugin.clone(), Arc::clone(handler), payload.clone()))
+          Some((entry.plugin.clone(), entry.span(&event), Arc::clone(handler), payload.clone()))
         } else {
           None
         }
@@ -204,8 +214,9 @@ impl EventManager {
     if !async_entries.is_empty() {
       let event_clone = event.clone();
       tokio::spawn(async move {
-        for (plugin, handler, payload) in async_entries {
-          if let Err(err) = handler(payload).await {
+        for (plugin, span, handler, payload) in async_entries {
+          let call = span.in_scope(|| handler(payload)).instrument(span);
+          if let Err(err) = call.await {
             LOGGER.log_error(
               "EVENTS",
               &format!(
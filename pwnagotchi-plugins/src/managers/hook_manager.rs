@@ -72,7 +72,6 @@ impl HookManager {
 
     let message = format!("Registering hook: {} (ID {})", descriptor.name, id);
     LOGGER.log_debug("HOOKS", &message);
-    eprintln!("{}", message);
 
     Ok(StoredHook::new(descriptor.name, kind, id))
   }
@@ -114,7 +113,6 @@ impl HookManager {
 
     let message = format!("Unregistering hook on: {} (ID {})", handle.hook, handle.id);
     LOGGER.log_debug("HOOKS", &message);
-    eprintln!("{}", message);
 
     drop(handle.to_owned());
 
@@ -72,7 +72,7 @@ impl PluginManager {
   }
 
   pub fn init(&mut self) {
-    println!("Initializing Plugin Manager...");
+    LOGGER.log_debug("PLUGIN", "Initializing Plugin Manager...");
 
     self.loaders.push(Box::new(RustPluginLoader::new(
       Arc::clone(&self.hook_manager),
@@ -95,7 +95,7 @@ impl PluginManager {
     }
 
     let total_plugins: usize = self.loaders.iter().map(|l| l.get_plugins().len()).sum();
-    println!("Loaded {} plugins.", total_plugins);
+    LOGGER.log_info("PLUGIN", &format!("Loaded {} plugins.", total_plugins));
   }
 
   pub fn get_plugins(&self) -> Vec<&PluginEntry> {
@@ -109,7 +109,6 @@ impl PluginManager {
   pub fn initialize_plugins(&mut self) {
     if self.core_modules.is_none() {
       LOGGER.log_error("PLUGIN", "CoreModules not set, cannot initialize plugins.");
-      eprintln!("CoreModules not set, cannot initialize plugins.");
       return;
     }
 
@@ -17,7 +17,7 @@ mod manager_tests {
     static INIT: Once = Once::new();
     INIT.call_once(|| {
       let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/config.toml");
-      pwnagotchi_shared::config::init_config(path);
+      pwnagotchi_shared::config::init_config(path).expect("test config");
     });
   }
 
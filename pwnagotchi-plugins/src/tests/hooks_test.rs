@@ -6,7 +6,7 @@ fn ensure_test_config() {
   static INIT: Once = Once::new();
   INIT.call_once(|| {
     let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/config.toml");
-    pwnagotchi_shared::config::init_config(path);
+    pwnagotchi_shared::config::init_config(path).expect("test config");
   });
 }
 
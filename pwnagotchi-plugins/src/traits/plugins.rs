@@ -20,7 +20,10 @@ pub struct PluginAPI<'a> {
   pub hook_api: &'a mut dyn DynamicHookAPITrait,
   pub event_api: &'a mut dyn DynamicEventAPITrait,
   pub core_modules: Arc<CoreModules>,
-  pub logger: Arc<&'a LazyLock<Log>>,
+  /// The unit's logger. Plugins have their own copy of `LOGGER`, entries sent
+  /// through this one get the span context and `log.filters` of the unit, so
+  /// keep it for handlers instead.
+  pub logger: Arc<&'static LazyLock<Log>>,
   pub config: &'a RwLockReadGuard<'static, RawRwLock, Config>,
 }
 
@@ -1,6 +1,7 @@
 use std::{
   collections::{HashMap, VecDeque},
   sync::Arc,
+  time::Duration,
 };
 
 use anyhow::{Result, anyhow};
@@ -8,13 +9,24 @@ use pwnagotchi_shared::{
   config::config_read,
   logger::LOGGER,
   traits::general::{Component, CoreModule, CoreModules, Dependencies},
+  types::{
+    components::{ComponentRegistry, ComponentState, ComponentStatus},
+    ui::FaceType,
+  },
 };
-use tokio::task::JoinHandle;
+use tokio::{
+  task::{JoinError, JoinHandle},
+  time::Instant,
+};
+use tracing::{Instrument, Span};
 
 type BoxedComponent = Box<dyn Component + Send + Sync>;
 type BoxedDependencies<'a> = Box<dyn Dependencies + Send + Sync + 'a>;
 type BoxedCoreModule = Box<dyn CoreModule + Send + Sync>;
 
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+const SUPERVISE_INTERVAL: Duration = Duration::from_secs(1);
+
 fn core_modules_as_dependencies(c: &'_ Vec<BoxedCoreModule>) -> Vec<BoxedDependencies<'_>> {
   c.iter()
     .map(|comp| Box::new(CoreModuleAsDeps::new(comp.as_ref())) as BoxedDependencies)
@@ -71,6 +83,9 @@ pub struct ComponentManager {
   components: Vec<BoxedComponent>,
   ctx: Option<Arc<CoreModules>>,
   join_handles: Vec<(String, JoinHandle<()>)>,
+  registry: ComponentRegistry,
+  /// When each restarted component came back, until it counts as stable.
+  restarted: HashMap<String, Instant>,
 }
 
 impl Default for ComponentManager {
@@ -85,9 +100,16 @@ impl ComponentManager {
       components: Vec::new(),
       ctx: None,
       join_handles: Vec::new(),
+      registry: ComponentRegistry::default(),
+      restarted: HashMap::new(),
     }
   }
 
+  /// Status of every component, kept up to date by [`Self::supervise`].
+  pub fn registry(&self) -> ComponentRegistry {
+    Arc::clone(&self.registry)
+  }
+
   pub fn set_core_modules(&mut self, ctx: Arc<CoreModules>) {
     self.ctx = Some(ctx);
   }
@@ -109,7 +131,8 @@ impl ComponentManager {
       let comp = &mut self.components[idx];
       LOGGER.log_debug("Pwnagotchi", &format!("Initializing component {}", comp.name()));
 
-      match comp.init(self.ctx.as_ref().unwrap()).await {
+      let span = component_span(comp.name());
+      match comp.init(self.ctx.as_ref().unwrap()).instrument(span).await {
         Ok(()) => {}
         Err(e) => {
           let msg = format!("Failed to initialize component {}: {}", comp.name(), e);
@@ -124,7 +147,7 @@ impl ComponentManager {
       &format!(
         "Pwnagotchi {}@{} (v{}) starting...",
         config_read().main.name,
-        &self.ctx.as_ref().unwrap().identity.read().fingerprint(),
+        self.ctx.as_ref().unwrap().identity.read().fingerprint(),
         env!("CARGO_PKG_VERSION")
       ),
     );
@@ -144,14 +167,21 @@ impl ComponentManager {
       let comp = &self.components[idx];
       LOGGER.log_debug("Pwnagotchi", &format!("Starting component {}", comp.name()));
 
-      match comp.start().await {
+      let mut status = ComponentStatus::new(comp.name(), comp.restart_policy().critical);
+
+      match comp.start().instrument(component_span(comp.name())).await {
         Ok(Some(handle)) => {
           self.join_handles.push((comp.name().to_string(), handle));
+          status.state = ComponentState::Running;
+          self.registry.write().push(status);
         }
-        Ok(None) => {}
+        Ok(None) => self.registry.write().push(status),
         Err(e) => {
           let msg = format!("Failed to start component {}: {}", comp.name(), e);
           LOGGER.log_error("Pwnagotchi", &msg);
+          status.state = ComponentState::Failed;
+          status.last_error = Some(e.to_string());
+          self.registry.write().push(status);
           return Err(anyhow!(msg));
         }
       }
@@ -170,17 +200,200 @@ impl ComponentManager {
       order.reverse();
       for idx in order {
         let comp = &self.components[idx];
-        let _ = comp.stop().await;
+        let _ = comp.stop().instrument(component_span(comp.name())).await;
       }
     }
 
-    // cancel/join handles
-    for (name, handle) in self.join_handles.drain(..) {
+    // background tasks watch the shutdown token, abort the ones that do not finish
+    // in time
+    for (name, mut handle) in self.join_handles.drain(..) {
       LOGGER.log_debug("Pwnagotchi", &format!("Stopping background task for component {name}"));
-      handle.abort();
-      let _ = handle.await;
+      if tokio::time::timeout(STOP_TIMEOUT, &mut handle).await.is_err() {
+        LOGGER.log_warning("Pwnagotchi", &format!("Component {name} did not stop, aborting"));
+        handle.abort();
+        let _ = handle.await;
+      }
+    }
+
+    for status in self.registry.write().iter_mut() {
+      status.state = ComponentState::Stopped;
+    }
+  }
+
+  /// Watches the background task of every component and restarts the ones
+  /// that ended according to their [`RestartPolicy`]. Returns once the unit
+  /// starts shutting down.
+  ///
+  /// [`RestartPolicy`]: pwnagotchi_shared::types::components::RestartPolicy
+  pub async fn supervise(&mut self) {
+    let Some(ctx) = self.ctx.clone() else {
+      return;
+    };
+
+    let mut pending: Vec<(String, Instant)> = Vec::new();
+    let mut interval = tokio::time::interval(SUPERVISE_INTERVAL);
+    let mut broken = false;
+
+    loop {
+      tokio::select! {
+        () = ctx.shutdown.cancelled() => return,
+        _ = interval.tick() => {}
+      }
+
+      let mut i = 0;
+      while i < self.join_handles.len() {
+        if !self.join_handles[i].1.is_finished() {
+          i += 1;
+          continue;
+        }
+
+        let (name, handle) = self.join_handles.swap_remove(i);
+        let reason = match handle.await {
+          Ok(()) => "task exited unexpectedly".to_string(),
+          Err(e) => describe_join_error(e),
+        };
+
+        // tasks end on their own once the token is cancelled
+        if ctx.shutdown.is_cancelled() {
+          return;
+        }
+
+        if let Some(next) = self.on_failure(&ctx, &name, &reason) {
+          pending.push((name, next));
+        }
+      }
+
+      let now = Instant::now();
+      let (due, waiting): (Vec<_>, Vec<_>) =
+        std::mem::take(&mut pending).into_iter().partition(|(_, at)| *at <= now);
+      pending = waiting;
+
+      for (name, _) in due {
+        if let Some(next) = self.restart(&ctx, &name).await {
+          pending.push((name, next));
+        }
+      }
+
+      self.forget_stable();
+
+      // once when it happens, the view moves on from there by itself
+      let down = self.registry.read().iter().any(|s| s.critical && s.is_down());
+      if down && !broken {
+        ctx.view.set("face", FaceType::Broken.to_string());
+      }
+      broken = down;
     }
   }
+
+  /// Clears the restart count of components that stayed up long enough.
+  fn forget_stable(&mut self) {
+    let now = Instant::now();
+    let stable = self
+      .restarted
+      .iter()
+      .filter(|(name, since)| {
+        self
+          .components
+          .iter()
+          .find(|c| c.name() == name.as_str())
+          .is_some_and(|c| now.duration_since(**since) >= c.restart_policy().stable_after)
+      })
+      .map(|(name, _)| name.clone())
+      .collect::<Vec<_>>();
+
+    for name in stable {
+      self.restarted.remove(&name);
+      if let Some(status) = self.registry.write().iter_mut().find(|s| s.name == name) {
+        LOGGER.log_debug("Supervisor", &format!("Component {name} is stable again"));
+        status.restarts = 0;
+      }
+    }
+  }
+
+  /// Records a failed component and returns when it should be restarted.
+  fn on_failure(&mut self, ctx: &CoreModules, name: &str, reason: &str) -> Option<Instant> {
+    self.restarted.remove(name);
+    let policy = self.components.iter().find(|c| c.name() == name)?.restart_policy();
+
+    let mut registry = self.registry.write();
+    let status = registry.iter_mut().find(|s| s.name == name)?;
+    status.last_error = Some(reason.to_string());
+
+    let next = if policy.can_restart(status.restarts) {
+      let delay = policy.backoff_for(status.restarts);
+      status.state = ComponentState::Restarting;
+      LOGGER.log_warning(
+        "Supervisor",
+        &format!("Component {name} failed ({reason}), restarting in {}s", delay.as_secs()),
+      );
+      Some(Instant::now() + delay)
+    } else {
+      status.state = ComponentState::Failed;
+      LOGGER.log_error("Supervisor", &format!("Component {name} failed ({reason}), giving up"));
+      None
+    };
+    drop(registry);
+
+    ctx
+      .session_manager
+      .journal("Supervisor", &format!("Component {name} failed: {reason}"));
+
+    if policy.critical {
+      ctx.view.on_component_down(name);
+    }
+
+    next
+  }
+
+  async fn restart(&mut self, ctx: &CoreModules, name: &str) -> Option<Instant> {
+    let comp = self.components.iter().find(|c| c.name() == name)?;
+
+    if let Some(status) = self.registry.write().iter_mut().find(|s| s.name == name) {
+      status.restarts += 1;
+    }
+
+    match comp.start().instrument(component_span(name)).await {
+      Ok(handle) => {
+        let state = if let Some(handle) = handle {
+          self.join_handles.push((name.to_string(), handle));
+          ComponentState::Running
+        } else {
+          ComponentState::Idle
+        };
+
+        if let Some(status) = self.registry.write().iter_mut().find(|s| s.name == name) {
+          status.state = state;
+        }
+        self.restarted.insert(name.to_string(), Instant::now());
+
+        LOGGER.log_info("Supervisor", &format!("Component {name} restarted"));
+        ctx
+          .session_manager
+          .journal("Supervisor", &format!("Component {name} restarted"));
+        None
+      }
+      Err(e) => self.on_failure(ctx, name, &e.to_string()),
+    }
+  }
+}
+
+/// Context for what a component logs while it is set up, started or
+/// stopped.
+fn component_span(name: &str) -> Span {
+  tracing::info_span!("component", component = name)
+}
+
+fn describe_join_error(err: JoinError) -> String {
+  if !err.is_panic() {
+    return err.to_string();
+  }
+
+  let payload = err.into_panic();
+  payload
+    .downcast_ref::<&str>()
+    .map(|s| (*s).to_string())
+    .or_else(|| payload.downcast_ref::<String>().cloned())
+    .map_or_else(|| "panicked".to_string(), |msg| format!("panicked: {msg}"))
 }
 
 fn sort_by_deps(arr: &Vec<BoxedDependencies<'_>>, ctx: &Arc<CoreModules>) -> Result<Vec<usize>> {
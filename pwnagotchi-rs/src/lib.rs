@@ -26,7 +26,11 @@ pub mod components {
   pub mod manager;
 }
 
+pub mod lifecycle;
+
 #[cfg(test)]
 pub mod tests {
   pub mod hookables;
+  pub mod lifecycle;
+  pub mod supervisor;
 }
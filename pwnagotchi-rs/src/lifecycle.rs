@@ -0,0 +1,73 @@
+use std::sync::Arc;
+
+use parking_lot::RwLock;
+use pwnagotchi_core::memfs;
+use pwnagotchi_hw::syscontrol::SysControl;
+use pwnagotchi_plugins::managers::plugin_manager::PluginManager;
+use pwnagotchi_shared::{logger::LOGGER, models::agent::PowerAction, traits::general::CoreModules};
+
+use crate::components::manager::ComponentManager;
+
+/// Stops components, unloads plugins and persists the session.
+pub async fn teardown(
+  components: &mut ComponentManager,
+  plugins: &Arc<RwLock<PluginManager>>,
+  core: &Arc<CoreModules>,
+) {
+  core.shutdown.cancel();
+  core.view.on_shutdown();
+
+  components.shutdown().await;
+
+  let unloaded = plugins.write().shutdown_all().map_err(|e| e.to_string());
+  if let Err(e) = unloaded {
+    LOGGER.log_error("Pwnagotchi", &format!("Failed to unload plugins: {e}"));
+  }
+
+  core.session_manager.save_recovery_data();
+
+  // remote sinks send or spool what they have queued
+  let _ = tokio::task::spawn_blocking(|| LOGGER.sinks().close()).await;
+
+  // the components synced on stop, this keeps what was logged since
+  memfs::flush().await;
+}
+
+/// Tears the unit down and hands over to the platform backend.
+pub async fn perform(
+  action: PowerAction,
+  components: &mut ComponentManager,
+  plugins: &Arc<RwLock<PluginManager>>,
+  core: &Arc<CoreModules>,
+  syscontrol: &Arc<dyn SysControl>,
+) {
+  LOGGER.log_warning("Pwnagotchi", &format!("{action}..."));
+
+  teardown(components, plugins, core).await;
+
+  let syscontrol = Arc::clone(syscontrol);
+  let res = tokio::task::spawn_blocking(move || execute(action, syscontrol.as_ref())).await;
+
+  match res {
+    Ok(Ok(())) => {}
+    Ok(Err(e)) => LOGGER.log_error("Pwnagotchi", &format!("{action} failed: {e}")),
+    Err(e) => LOGGER.log_error("Pwnagotchi", &format!("{action} task failed: {e}")),
+  }
+}
+
+/// Leaves the marker for the mode to come back in, then shuts down, reboots
+/// or restarts. A marker that can't be written doesn't stop the action.
+pub fn execute(action: PowerAction, syscontrol: &dyn SysControl) -> Result<(), String> {
+  if let Some(mode) = action.mode()
+    && let Err(e) = syscontrol.mark_mode(mode)
+  {
+    LOGGER.log_error("Pwnagotchi", &format!("Failed to write mode marker: {e}"));
+  }
+
+  match action {
+    PowerAction::Shutdown => syscontrol.shutdown(),
+    PowerAction::Reboot(mode) => syscontrol.reboot(mode),
+    PowerAction::Restart(mode) => syscontrol.restart(mode),
+  }
+  .map_err(|e| e.to_string())
+}
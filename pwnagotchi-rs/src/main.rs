@@ -1,8 +1,8 @@
 extern crate pwnagotchi_rs;
 
-use std::{process::exit, sync::Arc};
+use std::{path::Path, process::exit, sync::Arc};
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use nix::libc::EXIT_SUCCESS;
 use parking_lot::RwLock;
 use pwnagotchi_core::{
@@ -10,17 +10,25 @@ use pwnagotchi_core::{
   automata::{Automata, AutomataComponent},
   bettercap::{Bettercap, BettercapComponent},
   cli::Cli,
+  config_watcher::ConfigWatcherComponent,
   events::eventlistener::EventListenerComponent,
   grid::Grid,
-  mesh::advertiser::AdvertiserComponent,
+  memfs::MemoryFsComponent,
+  mesh::{advertiser::AdvertiserComponent, native::NativeGrid, sharing::SharingComponent},
+  monitor::InterfaceManagerComponent,
   setup::SetupComponent,
+  watchdog::WatchdogComponent,
 };
+use pwnagotchi_hw::backend::{Backend, Mode};
 use pwnagotchi_plugins::managers::plugin_manager::PluginManager;
-use pwnagotchi_rs::components::manager::ComponentManager;
+use pwnagotchi_rs::{components::manager::ComponentManager, lifecycle};
 use pwnagotchi_shared::{
-  config::{config_read, init_config},
-  identity::{Identity, IdentityComponent},
-  logger::LOGGER,
+  backup::{Bundle, DEFAULT_ITERATIONS, Sources},
+  config::{Config, config_read, init_config, layers, migrate},
+  identity::{Identity, IdentityComponent, KEY_BITS},
+  logger::{self, LOGGER},
+  mesh::peerdb::PeerDb,
+  models::agent::PowerAction,
   sessions::manager::SessionManager,
   traits::{
     agent::AgentTrait,
@@ -41,6 +49,14 @@ use pwnagotchi_ui::{
   },
   web::server::{Server, build_router},
 };
+use time::OffsetDateTime;
+use tokio::{
+  signal::unix::{SignalKind, signal},
+  sync::mpsc::{self, UnboundedSender},
+};
+use tokio_util::sync::CancellationToken;
+
+const PASSPHRASE_ENV: &str = "PWNAGOTCHI_BACKUP_PASSPHRASE";
 
 #[derive(Parser, Debug)]
 struct CliArgs {
@@ -71,10 +87,52 @@ struct CliArgs {
   debug: bool,
   #[clap(long = "version", help = "Prints the version information")]
   show_version: bool,
-  #[clap(long = "print-config", help = "Prints the configuration")]
+  #[clap(
+    long = "print-config",
+    help = "Prints the configuration and where every value comes from"
+  )]
   print_config: bool,
+  #[clap(
+    long = "check-config",
+    help = "Checks the configuration for errors and unknown keys, and exits"
+  )]
+  check_config: bool,
+  #[clap(
+    long = "migrate-config",
+    help = "Rewrites a configuration in an older layout, keeping a .bak copy, and exits"
+  )]
+  migrate_config: bool,
   #[clap(long = "skip", help = "Skip parsing")]
   skip: bool,
+  #[clap(
+    long = "rotate-keys",
+    help = "Generates a new identity, keeping a backup of the current one, and exits"
+  )]
+  rotate_keys: bool,
+  #[clap(subcommand)]
+  command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+  /// Writes the identity, config, handshakes, peers and sessions to an
+  /// encrypted archive
+  Backup {
+    #[clap(default_value = "pwnagotchi.pwnbak", help = "Where to write the archive")]
+    output: String,
+    #[clap(long, help = "Defaults to $PWNAGOTCHI_BACKUP_PASSPHRASE, asked for otherwise")]
+    passphrase: Option<String>,
+  },
+  /// Restores an archive made by `backup`
+  Restore {
+    input: String,
+    #[clap(long, help = "Defaults to $PWNAGOTCHI_BACKUP_PASSPHRASE, asked for otherwise")]
+    passphrase: Option<String>,
+    #[clap(long, help = "Replace files that differ from the backup")]
+    force: bool,
+    #[clap(long, help = "Restore below this directory instead of /")]
+    root: Option<String>,
+  },
 }
 
 #[tokio::main]
@@ -91,14 +149,41 @@ async fn main() -> anyhow::Result<()> {
     exit(EXIT_SUCCESS);
   }
 
+  if cli.check_config {
+    check_config(&cli.config);
+  }
+
+  if cli.migrate_config {
+    migrate_config(&cli.config);
+  }
+
   // will default to /etc/pwnagotchi/config.toml unless specified
-  init_config(&cli.config);
+  let config_notes = init_config(&cli.config).unwrap_or_else(|e| {
+    eprintln!("{e}");
+    eprintln!("Run with --check-config for details");
+    exit(1);
+  });
+  for note in config_notes {
+    LOGGER.log_warning("CONFIG", &note);
+  }
 
   if cli.print_config {
-    println!("Configuration: {:?}", config_read());
-    exit(EXIT_SUCCESS);
+    print_config();
   }
 
+  if cli.rotate_keys {
+    rotate_keys();
+  }
+
+  if let Some(command) = cli.command {
+    run_command(command);
+  }
+
+  // before anything running in the unit sends tracing events
+  logger::init();
+
+  let backend = Backend::new(Mode::from(cli.device.as_str()));
+
   // Create Managers
   let plugin_manager_inner = PluginManager::new();
   let event_bus = plugin_manager_inner.event_bus();
@@ -106,7 +191,15 @@ async fn main() -> anyhow::Result<()> {
   let mut component_manager = ComponentManager::new();
 
   // Build CoreModules
-  let core_modules = build_coremodules(event_bus.clone());
+  let (power_tx, mut power_rx) = mpsc::unbounded_channel();
+  let core_modules = build_coremodules(event_bus.clone(), power_tx);
+
+  // SIGINT and SIGTERM both start a graceful shutdown
+  let shutdown = core_modules.shutdown.clone();
+  tokio::task::spawn(async move {
+    shutdown_signal().await;
+    shutdown.cancel();
+  });
 
   // Set CoreModules for Components
   component_manager.set_core_modules(Arc::clone(&core_modules));
@@ -123,12 +216,15 @@ async fn main() -> anyhow::Result<()> {
     let _ = event.emit_payload("starting", EventPayload::empty()).await;
   });
 
+  // The advertiser shares the peers it sees with the web UI
+  let advertiser = AdvertiserComponent::new();
+
   // Build Router and Start WebServer
   let router = build_router(
-    Arc::clone(&core_modules.session_manager),
-    Arc::clone(&core_modules.identity),
+    &core_modules,
     Arc::clone(&plugin_manager),
-    Arc::clone(&core_modules.grid),
+    component_manager.registry(),
+    advertiser.peers(),
   );
   tokio::task::spawn(async move {
     let _ = Server::new(router).start_server().await;
@@ -137,15 +233,20 @@ async fn main() -> anyhow::Result<()> {
   LOGGER.log_debug("Pwnagotchi", "Loading Components");
 
   let components: Vec<Box<dyn Component + Send + Sync>> = vec![
+    Box::new(MemoryFsComponent::new()),
     Box::new(IdentityComponent::new()),
     Box::new(BettercapComponent::new()),
     Box::new(EventListenerComponent::new()),
     Box::new(ViewComponent::new()),
     Box::new(AgentComponent::new()),
     Box::new(AutomataComponent::new()),
-    Box::new(AdvertiserComponent::new()),
+    Box::new(advertiser),
+    Box::new(SharingComponent::new()),
     Box::new(RefresherComponent::new()),
     Box::new(SetupComponent::new()),
+    Box::new(InterfaceManagerComponent::new()),
+    Box::new(WatchdogComponent::new()),
+    Box::new(ConfigWatcherComponent::new()),
   ];
 
   for component in components {
@@ -160,27 +261,67 @@ async fn main() -> anyhow::Result<()> {
   // Cli Routines have to go last ALWAYS
   let controller = Cli::new(Arc::clone(&core_modules));
 
-  tokio::task::spawn(async move {
-    if cli.manual {
-      controller.do_manual_mode().await;
-    } else {
-      controller.do_auto_mode().await;
+  let shutdown = core_modules.shutdown.clone();
+  let cli_handle = tokio::task::spawn(async move {
+    let mode = async {
+      if cli.manual {
+        controller.do_manual_mode().await;
+      } else {
+        controller.do_auto_mode().await;
+      }
+    };
+
+    tokio::select! {
+      biased;
+      () = shutdown.cancelled() => {},
+      () = mode => {},
     }
   });
 
-  tokio::select! {
-    _ = tokio::signal::ctrl_c() => {
-      LOGGER.log_info("Pwnagotchi", "Shutting down...");
-      let _ = plug_manager.write().shutdown_all();
-      component_manager.shutdown().await;
-    },
+  // Supervise components until either a signal or a power action ends the run
+  let action = tokio::select! {
+    () = component_manager.supervise() => None,
+    Some(action) = power_rx.recv() => Some(action),
+  };
+
+  if let Some(action) = action {
+    lifecycle::perform(
+      action,
+      &mut component_manager,
+      &plug_manager,
+      &core_modules,
+      &backend.syscontrol,
+    )
+    .await;
+  } else {
+    LOGGER.log_info("Pwnagotchi", "Shutting down...");
+    lifecycle::teardown(&mut component_manager, &plug_manager, &core_modules).await;
   }
+
+  let _ = cli_handle.await;
   Ok(())
 }
 
-fn build_coremodules(events: Arc<dyn EventBus>) -> Arc<CoreModules> {
+async fn shutdown_signal() {
+  let Ok(mut sigterm) = signal(SignalKind::terminate()) else {
+    LOGGER.log_warning("Pwnagotchi", "Failed to install SIGTERM handler");
+    let _ = tokio::signal::ctrl_c().await;
+    return;
+  };
+
+  tokio::select! {
+    _ = tokio::signal::ctrl_c() => {},
+    _ = sigterm.recv() => {},
+  }
+}
+
+fn build_coremodules(
+  events: Arc<dyn EventBus>,
+  power: UnboundedSender<PowerAction>,
+) -> Arc<CoreModules> {
   let identity = Arc::new(RwLock::new(Identity::new()));
   let session_manager = Arc::new(SessionManager::new());
+  session_manager.persist_journal(config_read().debug.journal_file.clone());
   let epoch = Arc::new(RwLock::new(Epoch::new()));
   let bettercap = Arc::new(Bettercap::new()) as Arc<dyn BettercapTrait + Send + Sync>;
 
@@ -197,9 +338,16 @@ fn build_coremodules(events: Arc<dyn EventBus>) -> Arc<CoreModules> {
     Arc::clone(&epoch),
     Arc::clone(&view),
     Arc::clone(&session_manager),
+    Arc::clone(&identity),
+    power,
   )) as Arc<dyn AgentTrait + Send + Sync>;
 
-  let grid = Arc::new(Grid::new()) as Arc<dyn GridTrait + Send + Sync>;
+  let native_mesh = config_read().grid.mesh == "native";
+  let grid = if native_mesh {
+    Arc::new(NativeGrid::new(Arc::clone(&identity))) as Arc<dyn GridTrait + Send + Sync>
+  } else {
+    Arc::new(Grid::new(Arc::clone(&identity))) as Arc<dyn GridTrait + Send + Sync>
+  };
 
   Arc::new(CoreModules {
     session_manager: Arc::clone(&session_manager),
@@ -210,10 +358,183 @@ fn build_coremodules(events: Arc<dyn EventBus>) -> Arc<CoreModules> {
     agent: Arc::clone(&agent),
     automata: Arc::clone(&automata),
     grid: Arc::clone(&grid),
+    peer_db: Arc::new(PeerDb::new()),
     events,
+    shutdown: CancellationToken::new(),
   })
 }
 
 pub const fn version() -> &'static str {
   env!("CARGO_PKG_VERSION")
 }
+
+fn print_config() -> ! {
+  let sources = layers::current().map(|stack| stack.sources).unwrap_or_default();
+  let settings = match layers::settings(&config_read(), &sources) {
+    Ok(settings) => settings,
+    Err(e) => {
+      eprintln!("{e}");
+      exit(1);
+    }
+  };
+
+  for setting in settings {
+    let source = setting.source.map_or_else(|| "default".to_string(), |s| s.to_string());
+    println!("{} = {}  # {source}", setting.key, setting.value);
+  }
+  exit(EXIT_SUCCESS);
+}
+
+fn check_config(path: &str) -> ! {
+  let checked = match layers::load(Path::new(path)) {
+    Ok(layered) => layered.checked,
+    Err(e) => {
+      eprintln!("{e}");
+      exit(1);
+    }
+  };
+
+  for note in &checked.migrated {
+    println!("migration: {note}");
+  }
+  for warning in &checked.warnings {
+    println!("warning: {warning}");
+  }
+  for error in &checked.errors {
+    println!("error: {error}");
+  }
+  if !checked.migrated.is_empty() {
+    println!("Older layouts are migrated in memory, --migrate-config rewrites {path}");
+  }
+
+  if checked.is_ok() {
+    println!("{path} is valid");
+    exit(EXIT_SUCCESS);
+  }
+  exit(1);
+}
+
+fn migrate_config(path: &str) -> ! {
+  let checked = match Config::load_checked(path) {
+    Ok(checked) if checked.is_ok() => checked,
+    Ok(checked) => {
+      for error in &checked.errors {
+        eprintln!("error: {path}:{error}");
+      }
+      exit(1);
+    }
+    Err(e) => {
+      eprintln!("{e}");
+      exit(1);
+    }
+  };
+
+  // the loaded config has every default filled in, the file itself is
+  // migrated so only what it set moves and its comments are kept
+  let migrated = std::fs::read_to_string(path)
+    .map_err(|e| format!("Failed to read {path}: {e}"))
+    .and_then(|text| migrate::migrate_text(&text));
+  let text = match migrated {
+    Ok(Some((text, _))) if !checked.migrated.is_empty() => text,
+    Ok(_) => {
+      println!("{path} is already up to date");
+      exit(EXIT_SUCCESS);
+    }
+    Err(e) => {
+      eprintln!("{e}");
+      exit(1);
+    }
+  };
+
+  let backup = format!("{path}.bak");
+  if let Err(e) = std::fs::copy(path, &backup) {
+    eprintln!("Failed to back up {path}: {e}");
+    exit(1);
+  }
+  if let Err(e) = std::fs::write(path, text) {
+    eprintln!("Failed to write {path}: {e}");
+    exit(1);
+  }
+
+  for note in &checked.migrated {
+    println!("{note}");
+  }
+  println!("Migrated {path}, the original is at {backup}");
+  exit(EXIT_SUCCESS);
+}
+
+fn rotate_keys() -> ! {
+  let mut identity = Identity::new();
+  match identity.generate_keys(KEY_BITS) {
+    Ok(backup) => {
+      if let Some(backup) = backup {
+        println!("Previous identity saved to {}", backup.display());
+      }
+      println!("New fingerprint: {}", identity.fingerprint());
+      exit(EXIT_SUCCESS);
+    }
+    Err(e) => {
+      eprintln!("Failed to rotate keys: {e}");
+      exit(1);
+    }
+  }
+}
+
+fn run_command(command: Command) -> ! {
+  let res = match command {
+    Command::Backup { output, passphrase } => backup(&output, passphrase),
+    Command::Restore { input, passphrase, force, root } => {
+      restore(&input, passphrase, force, root.as_deref())
+    }
+  };
+
+  if let Err(e) = res {
+    eprintln!("{e}");
+    exit(1);
+  }
+  exit(EXIT_SUCCESS);
+}
+
+fn backup(output: &str, passphrase: Option<String>) -> anyhow::Result<()> {
+  let passphrase = read_passphrase(passphrase, true)?;
+  let now = OffsetDateTime::now_utc().unix_timestamp();
+  let bundle = Bundle::collect(&Sources::from_config(), &config_read().main.name, now)?;
+  let archive = bundle.seal(&passphrase, DEFAULT_ITERATIONS)?;
+  std::fs::write(output, archive)?;
+
+  for (kind, count) in bundle.counts() {
+    println!("{kind:?}: {count}");
+  }
+  println!("Backup of {} written to {output}", bundle.name);
+  Ok(())
+}
+
+fn restore(
+  input: &str,
+  passphrase: Option<String>,
+  force: bool,
+  root: Option<&str>,
+) -> anyhow::Result<()> {
+  let passphrase = read_passphrase(passphrase, false)?;
+  let bundle = Bundle::open(&std::fs::read(input)?, &passphrase)?;
+  let report = bundle.restore(&Sources::from_config(), root.map(std::path::Path::new), force)?;
+
+  println!(
+    "Restored {} ({}): {} files written, {} unchanged",
+    bundle.name, bundle.fingerprint, report.written, report.unchanged
+  );
+  Ok(())
+}
+
+fn read_passphrase(passphrase: Option<String>, confirm: bool) -> anyhow::Result<String> {
+  if let Some(passphrase) = passphrase.or_else(|| std::env::var(PASSPHRASE_ENV).ok()) {
+    return Ok(passphrase);
+  }
+
+  let passphrase = rpassword::prompt_password("Passphrase: ")?;
+  // a typo here would seal the backup with a passphrase nobody knows
+  if confirm && rpassword::prompt_password("Repeat passphrase: ")? != passphrase {
+    anyhow::bail!("passphrases do not match");
+  }
+  Ok(passphrase)
+}
@@ -0,0 +1,78 @@
+use std::error::Error;
+
+use parking_lot::Mutex;
+use pwnagotchi_hw::syscontrol::SysControl;
+use pwnagotchi_shared::models::agent::{PowerAction, RunningMode};
+
+use crate::lifecycle::execute;
+
+/// Records what it was asked to do, in order.
+#[derive(Default)]
+struct Recorder {
+  calls: Mutex<Vec<String>>,
+  broken_marker: bool,
+}
+
+impl SysControl for Recorder {
+  fn shutdown(&self) -> Result<(), Box<dyn Error>> {
+    self.calls.lock().push("shutdown".into());
+    Ok(())
+  }
+
+  fn reboot(&self, mode: Option<RunningMode>) -> Result<(), Box<dyn Error>> {
+    self.calls.lock().push(format!("reboot {mode:?}"));
+    Ok(())
+  }
+
+  fn restart(&self, mode: RunningMode) -> Result<(), Box<dyn Error>> {
+    self.calls.lock().push(format!("restart {mode}"));
+    Err("service missing".into())
+  }
+
+  fn mark_mode(&self, mode: RunningMode) -> Result<(), Box<dyn Error>> {
+    self.calls.lock().push(format!("mark {mode}"));
+    if self.broken_marker { Err("read-only".into()) } else { Ok(()) }
+  }
+}
+
+#[test]
+fn power_actions_name_the_mode_to_come_back_in() {
+  assert_eq!(PowerAction::Shutdown.mode(), None);
+  assert_eq!(PowerAction::Reboot(None).mode(), Some(RunningMode::Auto));
+  assert_eq!(PowerAction::Reboot(Some(RunningMode::Manual)).mode(), Some(RunningMode::Manual));
+  assert_eq!(PowerAction::Restart(RunningMode::Manual).mode(), Some(RunningMode::Manual));
+
+  assert_eq!(PowerAction::Reboot(None).to_string(), "Rebooting");
+  assert_eq!(PowerAction::Restart(RunningMode::Auto).to_string(), "Restarting in AUTO mode");
+}
+
+#[test]
+fn marker_is_written_before_the_action() {
+  let sys = Recorder::default();
+
+  execute(PowerAction::Shutdown, &sys).unwrap();
+  execute(PowerAction::Reboot(None), &sys).unwrap();
+  execute(PowerAction::Reboot(Some(RunningMode::Manual)), &sys).unwrap();
+
+  assert_eq!(
+    *sys.calls.lock(),
+    [
+      "shutdown",
+      "mark AUTO",
+      "reboot None",
+      "mark MANU",
+      "reboot Some(Manual)",
+    ]
+  );
+}
+
+#[test]
+fn failures_are_reported_and_a_bad_marker_does_not_stop_the_action() {
+  let sys = Recorder {
+    broken_marker: true,
+    ..Recorder::default()
+  };
+
+  assert_eq!(execute(PowerAction::Restart(RunningMode::Auto), &sys), Err("service missing".into()));
+  assert_eq!(*sys.calls.lock(), ["mark AUTO", "restart AUTO"]);
+}
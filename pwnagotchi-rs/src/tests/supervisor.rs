@@ -0,0 +1,388 @@
+use std::{
+  sync::{
+    Arc,
+    atomic::{AtomicU32, Ordering},
+  },
+  time::Duration,
+};
+
+use anyhow::Result;
+use parking_lot::RwLock;
+use pwnagotchi_plugins::managers::event_manager::EventManager;
+use pwnagotchi_shared::{
+  identity::Identity,
+  mesh::peerdb::PeerDb,
+  sessions::manager::SessionManager,
+  traits::{
+    epoch::Epoch,
+    general::{Component, CoreModules, Dependencies},
+  },
+  types::{
+    components::{ComponentRegistry, ComponentState, ComponentStatus, RestartPolicy},
+    ui::FaceType,
+  },
+};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::components::manager::ComponentManager;
+
+#[test]
+fn backoff_doubles_until_capped() {
+  let policy = RestartPolicy::default();
+
+  assert_eq!(policy.backoff_for(0), Duration::from_secs(1));
+  assert_eq!(policy.backoff_for(1), Duration::from_secs(2));
+  assert_eq!(policy.backoff_for(3), Duration::from_secs(8));
+  assert_eq!(policy.backoff_for(10), policy.max_backoff);
+  assert_eq!(policy.backoff_for(u32::MAX), policy.max_backoff);
+}
+
+#[test]
+fn restarts_are_limited() {
+  let policy = RestartPolicy::default();
+
+  assert!(policy.can_restart(0));
+  assert!(!policy.can_restart(policy.max_restarts));
+  assert!(!RestartPolicy::never().can_restart(0));
+}
+
+#[test]
+fn restarting_and_failed_count_as_down() {
+  let mut status = ComponentStatus::new("EventListenerComponent", true);
+  assert!(!status.is_down());
+
+  status.state = ComponentState::Restarting;
+  assert!(status.is_down());
+
+  status.state = ComponentState::Failed;
+  assert!(status.is_down());
+}
+
+/// A component whose task ends right away for its first `failures` starts
+/// and runs until shutdown after that.
+struct Flaky {
+  failures: u32,
+  policy: RestartPolicy,
+  starts: AtomicU32,
+  shutdown: CancellationToken,
+}
+
+impl Flaky {
+  fn new(failures: u32, policy: RestartPolicy) -> Self {
+    Self {
+      failures,
+      policy,
+      starts: AtomicU32::new(0),
+      shutdown: CancellationToken::new(),
+    }
+  }
+}
+
+impl Dependencies for Flaky {
+  fn name(&self) -> &'static str {
+    "Flaky"
+  }
+}
+
+#[async_trait::async_trait]
+impl Component for Flaky {
+  async fn init(&mut self, ctx: &CoreModules) -> Result<()> {
+    self.shutdown = ctx.shutdown.clone();
+    Ok(())
+  }
+
+  async fn start(&self) -> Result<Option<JoinHandle<()>>> {
+    let fails = self.starts.fetch_add(1, Ordering::SeqCst) < self.failures;
+    let shutdown = self.shutdown.clone();
+    Ok(Some(tokio::spawn(async move {
+      if !fails {
+        shutdown.cancelled().await;
+      }
+    })))
+  }
+
+  fn restart_policy(&self) -> RestartPolicy {
+    self.policy
+  }
+}
+
+struct Supervised {
+  core: Arc<CoreModules>,
+  view: Arc<fakes::View>,
+  registry: ComponentRegistry,
+  task: JoinHandle<()>,
+}
+
+impl Supervised {
+  async fn start(component: Flaky) -> Self {
+    let view = Arc::new(fakes::View::default());
+    let core = Arc::new(CoreModules {
+      session_manager: Arc::new(SessionManager::new()),
+      identity: Arc::new(RwLock::new(Identity::with_path("/nonexistent"))),
+      epoch: Arc::new(RwLock::new(Epoch::new())),
+      bettercap: Arc::new(fakes::Nothing),
+      view: Arc::clone(&view) as _,
+      agent: Arc::new(fakes::Nothing),
+      automata: Arc::new(fakes::Nothing),
+      events: Arc::new(EventManager::new()),
+      grid: Arc::new(fakes::Nothing),
+      peer_db: Arc::new(PeerDb::open("/nonexistent/peers.json")),
+      shutdown: CancellationToken::new(),
+    });
+
+    let mut manager = ComponentManager::new();
+    manager.set_core_modules(Arc::clone(&core));
+    manager.register(Box::new(component));
+    manager.init_all().await.unwrap();
+    manager.start_all().await.unwrap();
+
+    let registry = manager.registry();
+    let task = tokio::spawn(async move { manager.supervise().await });
+    Self { core, view, registry, task }
+  }
+
+  fn status(&self) -> ComponentStatus {
+    self.registry.read()[0].clone()
+  }
+
+  async fn stop(self) {
+    self.core.shutdown.cancel();
+    self.task.await.unwrap();
+  }
+}
+
+#[tokio::test(start_paused = true)]
+async fn restarts_are_forgotten_after_a_stable_run() {
+  let policy = RestartPolicy {
+    stable_after: Duration::from_secs(45),
+    ..RestartPolicy::default()
+  };
+  let supervised = Supervised::start(Flaky::new(2, policy)).await;
+
+  tokio::time::sleep(Duration::from_secs(10)).await;
+  let status = supervised.status();
+  assert_eq!(status.state, ComponentState::Running);
+  assert_eq!(status.restarts, 2);
+
+  tokio::time::sleep(Duration::from_secs(30)).await;
+  assert_eq!(supervised.status().restarts, 2);
+
+  tokio::time::sleep(Duration::from_secs(20)).await;
+  assert_eq!(supervised.status().restarts, 0);
+  assert_eq!(supervised.status().state, ComponentState::Running);
+
+  supervised.stop().await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn a_failed_critical_component_breaks_the_face_once() {
+  let supervised = Supervised::start(Flaky::new(1, RestartPolicy::never().critical())).await;
+
+  tokio::time::sleep(Duration::from_secs(10)).await;
+  let status = supervised.status();
+  assert_eq!(status.state, ComponentState::Failed);
+  assert_eq!(status.last_error.as_deref(), Some("task exited unexpectedly"));
+  assert_eq!(*supervised.view.down.lock(), ["Flaky"]);
+  assert_eq!(*supervised.view.faces.lock(), [FaceType::Broken.to_string()]);
+
+  supervised.stop().await;
+}
+
+/// Bettercap, agent, automata and grid that do nothing, and a view that
+/// remembers the faces it was given.
+mod fakes {
+  use std::{collections::HashMap, sync::Arc};
+
+  use parking_lot::Mutex;
+  use pwnagotchi_shared::{
+    mesh::peer::Peer,
+    models::{
+      agent::RunningMode,
+      bettercap::BettercapSession,
+      grid::PeerResponse,
+      net::{AccessPoint, Station},
+    },
+    sessions::{lastsession::LastSession, session_stats::SessionStats},
+    traits::{
+      agent::AgentTrait,
+      automata::AutomataTrait,
+      bettercap::{BettercapCommand, BettercapTrait},
+      general::CoreModule,
+      grid::GridTrait,
+      ui::{ViewTrait, Widget},
+    },
+  };
+  use tiny_skia::PixmapMut;
+
+  pub struct Nothing;
+
+  impl CoreModule for Nothing {
+    fn name(&self) -> &'static str {
+      "Nothing"
+    }
+  }
+
+  #[async_trait::async_trait]
+  impl BettercapTrait for Nothing {
+    async fn send(&self, _cmd: BettercapCommand) -> anyhow::Result<()> {
+      Ok(())
+    }
+    async fn session(&self) -> anyhow::Result<Option<BettercapSession>> {
+      Ok(None)
+    }
+    async fn run_websocket(&self) {}
+    fn is_ready(&self) -> bool {
+      false
+    }
+    async fn run(&self, _cmd: &str) -> anyhow::Result<()> {
+      Ok(())
+    }
+  }
+
+  #[async_trait::async_trait]
+  impl AgentTrait for Nothing {
+    async fn set_mode(&self, _mode: RunningMode) {}
+    async fn recon(&self) {}
+    async fn associate(&self, _ap: &AccessPoint, _throttle: Option<f32>) {}
+    async fn deauth(&self, _ap: &AccessPoint, _sta: &Station, _throttle: Option<f32>) {}
+    async fn set_channel(&self, _channel: u8) {}
+    async fn get_access_points_by_channel(&self) -> Vec<(u8, Vec<AccessPoint>)> {
+      Vec::new()
+    }
+    fn start_pwnagotchi(&self) {}
+    fn shutdown(&self) {}
+    fn reboot(&self, _mode: Option<RunningMode>) {}
+    fn restart(&self, _mode: Option<RunningMode>) {}
+  }
+
+  #[async_trait::async_trait]
+  impl AutomataTrait for Nothing {
+    fn on_miss(&self, _who: &AccessPoint) {}
+    fn on_error(&self, _ap: &AccessPoint, _err: &str) {}
+    fn set_starting(&self) {}
+    fn set_ready(&self) {}
+    fn set_rebooting(&self) {}
+    fn in_good_mood(&self) -> bool {
+      true
+    }
+    fn set_grateful(&self) {}
+    fn set_lonely(&self) {}
+    fn set_bored(&self) {}
+    fn set_sad(&self) {}
+    fn set_angry(&self, _factor: f32) {}
+    fn set_excited(&self) {}
+    async fn wait_for(&self, _duration: u32, _sleeping: Option<bool>) {}
+    fn is_stale(&self) -> bool {
+      false
+    }
+    fn any_activity(&self) -> bool {
+      false
+    }
+    fn next_epoch(&self) {}
+  }
+
+  #[async_trait::async_trait]
+  impl GridTrait for Nothing {
+    fn is_connected(&self) -> bool {
+      false
+    }
+    fn advertise(&self, _enabled: Option<bool>) -> Option<serde_json::Value> {
+      None
+    }
+    fn set_advertisement_data(&self, _data: serde_json::Value) -> Option<serde_json::Value> {
+      None
+    }
+    fn get_advertisement_data(&self) -> Option<serde_json::Value> {
+      None
+    }
+    fn memory(&self) -> Option<serde_json::Value> {
+      None
+    }
+    async fn peers(&self) -> Option<Vec<PeerResponse>> {
+      None
+    }
+    async fn closest_peer(&self) -> Option<PeerResponse> {
+      None
+    }
+    fn update_data(&self, _last_session: &SessionStats) {}
+    fn report_ap(&self, _essid: &str, _bssid: &str) {}
+    fn inbox(&self, _page: Option<u32>, _with_pager: Option<bool>) -> Option<serde_json::Value> {
+      None
+    }
+    fn inbox_message(&self, _id: &str) -> Option<serde_json::Value> {
+      None
+    }
+    fn mark_message(&self, _id: &str, _mark: &str) -> Option<serde_json::Value> {
+      None
+    }
+    fn send_message(&self, _to: &str, _message: &str) -> Result<(), String> {
+      Err("offline".into())
+    }
+  }
+
+  #[derive(Default)]
+  pub struct View {
+    pub faces: Mutex<Vec<String>>,
+    pub down: Mutex<Vec<String>>,
+  }
+
+  impl CoreModule for View {
+    fn name(&self) -> &'static str {
+      "View"
+    }
+  }
+
+  #[async_trait::async_trait]
+  impl ViewTrait for View {
+    fn set(&self, key: &str, value: String) {
+      if key == "face" {
+        self.faces.lock().push(value);
+      }
+    }
+    fn get(&self, _key: &str) -> Option<Arc<Mutex<Box<dyn Widget>>>> {
+      None
+    }
+    fn on_state_change(&self, _key: &str, _callback: Box<dyn Fn(String, String) + Send + Sync>) {}
+    fn on_starting(&self) {}
+    fn on_manual_mode(&self, _last_session: &LastSession) {}
+    fn set_closest_peer(&self, _peer: Option<&Peer>, _total_peers: u32) {}
+    fn on_new_peer(&self, _peer: &Peer) {}
+    fn on_keys_generation(&self) {}
+    fn on_normal(&self) {}
+    fn on_lost_peer(&self, _peer: &Peer) {}
+    fn on_free_channel(&self, _channel: u8) {}
+    fn on_reading_logs(&self, _lines: u64) {}
+    fn on_shutdown(&self) {}
+    fn on_bored(&self) {}
+    fn on_sad(&self) {}
+    fn on_angry(&self) {}
+    fn on_motivated(&self) {}
+    fn on_demotivated(&self) {}
+    fn on_excited(&self) {}
+    fn on_assoc(&self, _ap: &AccessPoint) {}
+    fn on_deauth(&self, _who: &Station) {}
+    fn on_miss(&self, _who: &AccessPoint) {}
+    fn on_grateful(&self) {}
+    fn on_lonely(&self) {}
+    fn on_handshakes(&self, _count: u32) {}
+    fn on_unread_messages(&self, _count: u32) {}
+    fn on_uploading(&self, _to: &str) {}
+    fn on_rebooting(&self) {}
+    fn on_component_down(&self, name: &str) {
+      self.down.lock().push(name.to_string());
+    }
+    fn on_custom(&self, _text: &str) {}
+    fn is_normal(&self) -> bool {
+      true
+    }
+    fn update(&self, _force: Option<bool>, _new_data: Option<HashMap<String, String>>) {}
+    async fn wait(&self, _secs: f64, _sleeping: bool) {}
+    fn add_element(&self, _key: &str, _elem: Box<dyn Widget>) {}
+    fn has_element(&self, _key: &str) -> bool {
+      false
+    }
+    fn on_render(&mut self, _callback: Option<fn(&PixmapMut)>) {}
+    async fn start_render_loop(&self) {}
+  }
+}
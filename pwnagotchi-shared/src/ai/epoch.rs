@@ -15,6 +15,7 @@ use tokio::sync::mpsc::channel;
 use crate::{
   ai::reward::calculate_reward,
   config::config_read,
+  live::{self, LiveEvent},
   logger::LOGGER,
   mesh::peer::Peer,
   models::net::AccessPoint,
@@ -218,6 +219,10 @@ impl Epoch {
     ).as_str());
 
     self.epoch_data_ready = true;
+    live::publish(LiveEvent::Epoch {
+      epoch: self.epoch,
+      data: self.epoch_data.clone(),
+    });
     self.data_tx.try_send(take(&mut self.epoch_data)).ok();
 
     self.epoch += 1;
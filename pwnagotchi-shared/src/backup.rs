@@ -0,0 +1,360 @@
+//! Encrypted archives of everything that makes a unit itself, so it survives
+//! moving to a new SD card.
+//!
+//! An archive is `PWNBAK | u8 version | salt | u32 iterations | nonce |
+//! ciphertext`. The key is derived from a passphrase with
+//! PBKDF2-HMAC-SHA256, the ciphertext is AES-256-GCM over a gzip compressed
+//! [`Bundle`] and authenticates the header as well.
+
+use std::{
+  collections::BTreeMap,
+  io::{Read, Write},
+  os::unix::fs::PermissionsExt,
+  path::{Component, Path, PathBuf},
+};
+
+use aes_gcm::{
+  Aes256Gcm, KeyInit, Nonce,
+  aead::{Aead, Payload},
+};
+use anyhow::{Result, anyhow, bail};
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use hex::ToHex;
+use hmac::Hmac;
+use rsa::rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+  config::{CONFIG_PATH, config_read},
+  logger::rotate,
+};
+
+pub const MAGIC: &[u8; 6] = b"PWNBAK";
+/// Bumped whenever the bundle layout changes, newer archives are refused.
+pub const FORMAT_VERSION: u8 = 1;
+pub const DEFAULT_ITERATIONS: u32 = 100_000;
+
+/// Anything above this is a corrupt header rather than a slow key.
+const MAX_ITERATIONS: u32 = 10_000_000;
+const SALT_SIZE: usize = 16;
+const NONCE_SIZE: usize = 12;
+const HEADER_SIZE: usize = MAGIC.len() + 1 + SALT_SIZE + 4 + NONCE_SIZE;
+const IDENTITY_FILES: [&str; 3] = ["id_rsa", "id_rsa.pub", "fingerprint"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum EntryKind {
+  Config,
+  Identity,
+  Handshake,
+  PeerDb,
+  Session,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+  pub kind: EntryKind,
+  /// Absolute path the file was read from and is restored to.
+  pub path: String,
+  pub mode: u32,
+  /// Hex encoded sha256 of `data`, checked before anything is restored.
+  pub sha256: String,
+  pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bundle {
+  pub created_at: i64,
+  pub name: String,
+  pub fingerprint: String,
+  pub entries: Vec<Entry>,
+}
+
+/// Where the parts of a unit's state live.
+#[derive(Debug, Clone)]
+pub struct Sources {
+  pub config: Option<PathBuf>,
+  pub identity: PathBuf,
+  pub handshakes: PathBuf,
+  pub peer_db: PathBuf,
+  /// The log the sessions are parsed from, its rotated segments included.
+  pub log: PathBuf,
+  /// The last session and recovery data.
+  pub session: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RestoreReport {
+  pub written: usize,
+  pub unchanged: usize,
+}
+
+impl Sources {
+  pub fn from_config() -> Self {
+    let cfg = config_read();
+    Self {
+      config: CONFIG_PATH.get().map(PathBuf::from),
+      identity: PathBuf::from(cfg.debug.identity_path.as_ref()),
+      handshakes: PathBuf::from(cfg.bettercap.handshakes.as_ref()),
+      peer_db: PathBuf::from(cfg.grid.peer_db.as_ref()),
+      log: PathBuf::from(cfg.log.path.as_ref()),
+      session: vec![
+        PathBuf::from(cfg.debug.last_session_file.as_ref()),
+        PathBuf::from(&cfg.debug.recovery_file),
+        PathBuf::from(&cfg.debug.journal_file),
+      ],
+    }
+  }
+
+  /// Whether `path` is where files of `kind` live, so a backup can't be used
+  /// to write anywhere else.
+  pub fn allows(&self, kind: EntryKind, path: &Path) -> bool {
+    let same = |a: &Path, b: &Path| std::path::absolute(a).is_ok_and(|a| a == b);
+    let is = |expected: &Path| same(expected, path);
+    let parent_is = |dir: &Path| path.parent().is_some_and(|parent| same(dir, parent));
+
+    match kind {
+      EntryKind::Config => self.config.as_deref().is_some_and(is),
+      EntryKind::Identity => {
+        parent_is(&self.identity)
+          && path.file_name().is_some_and(|name| IDENTITY_FILES.iter().any(|f| *f == name))
+      }
+      EntryKind::Handshake => parent_is(&self.handshakes),
+      EntryKind::PeerDb => is(&self.peer_db),
+      EntryKind::Session => {
+        is(&self.log) || self.is_log_segment(path) || self.session.iter().any(|p| is(p))
+      }
+    }
+  }
+
+  fn is_log_segment(&self, path: &Path) -> bool {
+    let Ok(log) = std::path::absolute(&self.log) else {
+      return false;
+    };
+    let (Some(log_name), Some(name)) = (log.file_name(), path.file_name()) else {
+      return false;
+    };
+    if path.parent() != log.parent() {
+      return false;
+    }
+
+    let rest = name.to_string_lossy();
+    let Some(rest) = rest.strip_prefix(&format!("{}.", log_name.to_string_lossy())) else {
+      return false;
+    };
+    let n = rest.strip_suffix(".gz").unwrap_or(rest);
+    !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit())
+  }
+}
+
+impl Bundle {
+  /// Reads everything in `sources`, files that don't exist are left out.
+  pub fn collect(sources: &Sources, name: &str, now: i64) -> Result<Self> {
+    let mut files = Vec::new();
+    if let Some(config) = &sources.config {
+      files.push((EntryKind::Config, config.clone()));
+    }
+    for file in IDENTITY_FILES {
+      files.push((EntryKind::Identity, sources.identity.join(file)));
+    }
+    if let Ok(entries) = std::fs::read_dir(&sources.handshakes) {
+      let mut captures = entries.flatten().map(|e| e.path()).collect::<Vec<_>>();
+      captures.sort();
+      files.extend(captures.into_iter().map(|p| (EntryKind::Handshake, p)));
+    }
+    files.push((EntryKind::PeerDb, sources.peer_db.clone()));
+    files.extend(rotate::segments(&sources.log).into_iter().map(|p| (EntryKind::Session, p)));
+    files.extend(sources.session.iter().map(|p| (EntryKind::Session, p.clone())));
+
+    let mut entries = Vec::new();
+    for (kind, path) in files {
+      if !path.is_file() {
+        continue;
+      }
+      let path = std::path::absolute(&path)?;
+      let data = std::fs::read(&path).map_err(|e| anyhow!("{}: {e}", path.display()))?;
+      entries.push(Entry {
+        kind,
+        path: path.to_string_lossy().to_string(),
+        mode: std::fs::metadata(&path)?.permissions().mode() & 0o777,
+        sha256: sha256_hex(&data),
+        data,
+      });
+    }
+
+    let fingerprint = std::fs::read_to_string(sources.identity.join("fingerprint"))
+      .map(|f| f.trim().to_string())
+      .unwrap_or_default();
+
+    Ok(Self {
+      created_at: now,
+      name: name.to_string(),
+      fingerprint,
+      entries,
+    })
+  }
+
+  /// Entries per kind, for summaries.
+  pub fn counts(&self) -> BTreeMap<EntryKind, usize> {
+    let mut counts = BTreeMap::new();
+    for entry in &self.entries {
+      *counts.entry(entry.kind).or_insert(0) += 1;
+    }
+    counts
+  }
+
+  /// Encrypts the bundle into an archive.
+  pub fn seal(&self, passphrase: &str, iterations: u32) -> Result<Vec<u8>> {
+    if passphrase.is_empty() {
+      bail!("the passphrase must not be empty");
+    }
+
+    let mut salt = [0u8; SALT_SIZE];
+    let mut nonce = [0u8; NONCE_SIZE];
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut nonce);
+
+    let mut archive = Vec::with_capacity(HEADER_SIZE);
+    archive.extend_from_slice(MAGIC);
+    archive.push(FORMAT_VERSION);
+    archive.extend_from_slice(&salt);
+    archive.extend_from_slice(&iterations.to_le_bytes());
+    archive.extend_from_slice(&nonce);
+
+    let encoded = bincode::serde::encode_to_vec(self, bincode::config::standard())?;
+    let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+    gz.write_all(&encoded)?;
+    let compressed = gz.finish()?;
+
+    let cipher = Aes256Gcm::new_from_slice(&derive_key(passphrase, &salt, iterations))?;
+    let ciphertext = cipher
+      .encrypt(Nonce::from_slice(&nonce), Payload { msg: &compressed, aad: &archive })
+      .map_err(|e| anyhow!("encryption failed: {e}"))?;
+
+    archive.extend_from_slice(&ciphertext);
+    Ok(archive)
+  }
+
+  /// Decrypts an archive and checks every entry against its hash.
+  pub fn open(archive: &[u8], passphrase: &str) -> Result<Self> {
+    if archive.len() < HEADER_SIZE || !archive.starts_with(MAGIC) {
+      bail!("not a pwnagotchi backup");
+    }
+    let (header, ciphertext) = archive.split_at(HEADER_SIZE);
+
+    let version = header[MAGIC.len()];
+    if version > FORMAT_VERSION {
+      bail!("backup format {version} is newer than this version supports ({FORMAT_VERSION})");
+    }
+
+    let salt = &header[MAGIC.len() + 1..][..SALT_SIZE];
+    let iterations = &header[MAGIC.len() + 1 + SALT_SIZE..][..4];
+    let iterations = u32::from_le_bytes(iterations.try_into()?);
+    let nonce = &header[HEADER_SIZE - NONCE_SIZE..];
+    if iterations == 0 || iterations > MAX_ITERATIONS {
+      bail!("corrupt backup header");
+    }
+
+    let cipher = Aes256Gcm::new_from_slice(&derive_key(passphrase, salt, iterations))?;
+    let compressed = cipher
+      .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: header })
+      .map_err(|_| anyhow!("wrong passphrase or corrupt backup"))?;
+
+    let mut encoded = Vec::new();
+    GzDecoder::new(compressed.as_slice()).read_to_end(&mut encoded)?;
+    let (bundle, _): (Self, usize) =
+      bincode::serde::decode_from_slice(&encoded, bincode::config::standard())?;
+
+    for entry in &bundle.entries {
+      if !sha256_hex(&entry.data).eq_ignore_ascii_case(&entry.sha256) {
+        bail!("{} is corrupt", entry.path);
+      }
+      safe_path(&entry.path)?;
+    }
+
+    Ok(bundle)
+  }
+
+  /// Writes the entries back to their paths, below `root` if given. Every
+  /// path has to be where `sources` keeps files of its kind. Files that
+  /// exist with other content are only replaced with `force`, nothing is
+  /// written if any would be.
+  pub fn restore(
+    &self,
+    sources: &Sources,
+    root: Option<&Path>,
+    force: bool,
+  ) -> Result<RestoreReport> {
+    let mut targets = Vec::with_capacity(self.entries.len());
+    let mut conflicts = Vec::new();
+    for entry in &self.entries {
+      let relative = safe_path(&entry.path)?;
+      if !sources.allows(entry.kind, Path::new(&entry.path)) {
+        bail!("{} is not where {:?} files belong", entry.path, entry.kind);
+      }
+      let target = root.map_or_else(|| Path::new("/").join(&relative), |r| r.join(&relative));
+
+      let same = std::fs::read(&target).ok().map(|d| sha256_hex(&d) == entry.sha256);
+      if same == Some(false) {
+        conflicts.push(target.display().to_string());
+      }
+      targets.push((entry, target, same == Some(true)));
+    }
+
+    if !force && !conflicts.is_empty() {
+      bail!(
+        "{} files differ from the backup, restore with force to replace them: {}",
+        conflicts.len(),
+        conflicts.iter().take(5).cloned().collect::<Vec<_>>().join(", ")
+      );
+    }
+
+    let mut report = RestoreReport::default();
+    for (entry, target, same) in targets {
+      if same {
+        report.unchanged += 1;
+        continue;
+      }
+      if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)?;
+      }
+      let tmp = target.with_extension("restore-tmp");
+      std::fs::write(&tmp, &entry.data)?;
+      std::fs::set_permissions(&tmp, std::fs::Permissions::from_mode(entry.mode & 0o777))?;
+      std::fs::rename(&tmp, &target)?;
+      report.written += 1;
+    }
+
+    Ok(report)
+  }
+}
+
+/// Absolute paths without `..`, returned relative to `/`.
+fn safe_path(path: &str) -> Result<PathBuf> {
+  let path = Path::new(path);
+  if !path.is_absolute() {
+    bail!("{} is not absolute", path.display());
+  }
+
+  let mut relative = PathBuf::new();
+  for component in path.components() {
+    match component {
+      Component::RootDir => {}
+      Component::Normal(part) => relative.push(part),
+      _ => bail!("refusing to restore to {}", path.display()),
+    }
+  }
+  Ok(relative)
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+  Sha256::digest(data).encode_hex::<String>()
+}
+
+/// PBKDF2-HMAC-SHA256.
+pub fn derive_key(passphrase: &str, salt: &[u8], iterations: u32) -> [u8; 32] {
+  let mut key = [0u8; 32];
+  pbkdf2::pbkdf2::<Hmac<Sha256>>(passphrase.as_bytes(), salt, iterations, &mut key)
+    .expect("HMAC takes keys of any length");
+  key
+}
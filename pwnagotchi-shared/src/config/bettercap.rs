@@ -11,6 +11,7 @@ pub struct BettercapConfig {
   pub password: Cow<'static, str>,
   pub silence: Vec<Cow<'static, str>>,
   pub handshakes: Cow<'static, str>,
+  pub restart_cmd: Cow<'static, str>,
 }
 
 impl Default for BettercapConfig {
@@ -40,6 +41,7 @@ impl Default for BettercapConfig {
       password: Cow::Borrowed("pass"),
       silence: silenced,
       handshakes: Cow::Borrowed("/home/pi/handshakes"),
+      restart_cmd: Cow::Borrowed("systemctl restart bettercap"),
     }
   }
 }
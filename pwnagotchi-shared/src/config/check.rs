@@ -0,0 +1,247 @@
+//! Strict loading of config files. Syntax and type errors come with the line
+//! and column from the TOML parser, everything else found is reported against
+//! the line of the offending key.
+
+use std::{collections::HashMap, fmt::Display};
+
+use toml::{
+  Table, Value,
+  de::{DeTable, DeValue},
+};
+
+use super::{
+  Config,
+  layers::{self, Source, Sources},
+  migrate,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+  /// File or environment variable the key is set in, `None` for text checked
+  /// on its own and for defaults.
+  pub source: Option<Source>,
+  /// Line and column of the key, both starting at 1. `None` for keys that
+  /// aren't in the file, like ones added by a migration.
+  pub location: Option<(usize, usize)>,
+  /// Dotted key, like `personality.min_rssi`.
+  pub key: String,
+  pub message: String,
+}
+
+impl Display for Diagnostic {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match (&self.source, self.location) {
+      (Some(source), Some((line, column))) => write!(f, "{source}:{line}:{column}: ")?,
+      (Some(source), None) => write!(f, "{source}: ")?,
+      (None, Some((line, column))) => write!(f, "{line}:{column}: ")?,
+      (None, None) => {}
+    }
+    write!(f, "{}: {}", self.key, self.message)
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct Checked {
+  pub config: Config,
+  /// Settings that don't work, the config should not be used.
+  pub errors: Vec<Diagnostic>,
+  /// Keys that aren't settings, most likely typos.
+  pub warnings: Vec<Diagnostic>,
+  /// What migrations changed, empty for files in the current layout.
+  pub migrated: Vec<String>,
+}
+
+impl Checked {
+  pub fn is_ok(&self) -> bool {
+    self.errors.is_empty()
+  }
+}
+
+/// The settings of one file or environment variable, migrated to the current
+/// layout.
+#[derive(Debug, Clone)]
+pub struct Layer {
+  /// `None` for text checked on its own.
+  pub source: Option<Source>,
+  pub table: Table,
+  /// What migrations changed.
+  pub migrated: Vec<String>,
+  spans: HashMap<String, (usize, usize)>,
+}
+
+impl Layer {
+  /// Parses and migrates a config file's contents. Only malformed TOML and
+  /// values of the wrong type are an `Err`, with their location.
+  pub fn parse(source: Option<Source>, text: &str) -> Result<Self, String> {
+    let (table, migrated) = match migrate::migrate_text(text)? {
+      Some((migrated, notes)) => (migrated.parse::<Table>().map_err(|e| e.to_string())?, notes),
+      None => (text.parse::<Table>().map_err(|e| e.to_string())?, Vec::new()),
+    };
+
+    // straight from the text when nothing moved, so type errors point at it
+    if migrated.is_empty() {
+      toml::from_str::<Config>(text).map_err(|e| e.to_string())?;
+    }
+
+    Ok(Self {
+      source,
+      table,
+      migrated,
+      spans: key_locations(text),
+    })
+  }
+
+  /// Settings that don't come from a file, nothing is migrated.
+  pub fn new(source: Option<Source>, table: Table) -> Self {
+    Self {
+      source,
+      table,
+      migrated: Vec::new(),
+      spans: HashMap::new(),
+    }
+  }
+
+  fn defines(&self, key: &str) -> bool {
+    let mut table = &self.table;
+    let mut parts = key.split('.').peekable();
+    while let Some(part) = parts.next() {
+      match table.get(part) {
+        None => return false,
+        Some(_) if parts.peek().is_none() => return true,
+        Some(Value::Table(child)) => table = child,
+        Some(_) => return false,
+      }
+    }
+    false
+  }
+
+  fn diagnostic(&self, key: String, message: String) -> Diagnostic {
+    Diagnostic {
+      source: self.source.clone(),
+      location: self.spans.get(&key).copied(),
+      key,
+      message,
+    }
+  }
+}
+
+/// Parses, migrates and validates a config file's contents. Only malformed
+/// TOML and values of the wrong type are an `Err`, with their location.
+pub fn check(source: &str) -> Result<Checked, String> {
+  check_layers(&[Layer::parse(None, source)?]).map(|(checked, _)| checked)
+}
+
+/// Merges `layers` over the defaults in order and checks the result like
+/// [`check`] does a single file. Also returns the layer each key was set by,
+/// problems are reported against it.
+pub fn check_layers(layers: &[Layer]) -> Result<(Checked, Sources), String> {
+  let mut sources = Sources::new();
+  let table = layers::merged(layers, &mut sources);
+  let config = Config::deserialize_table(table)?;
+
+  let known = Table::try_from(&config).map_err(|e| e.to_string())?;
+  let mut warnings = Vec::new();
+  for layer in layers {
+    unknown_keys("", &layer.table, &known, &mut |key| {
+      warnings.push(layer.diagnostic(key, "unknown setting, ignored".into()));
+    });
+  }
+
+  let errors = config
+    .validate()
+    .into_iter()
+    .map(|e| {
+      let key = dotted(&e.path);
+      match layers.iter().rev().find(|layer| layer.defines(&key)) {
+        Some(layer) => layer.diagnostic(key, e.message),
+        None => Diagnostic {
+          source: None,
+          location: None,
+          key,
+          message: e.message,
+        },
+      }
+    })
+    .collect();
+
+  let migrated = layers
+    .iter()
+    .flat_map(|layer| {
+      layer.migrated.iter().map(|note| match &layer.source {
+        Some(source) => format!("{source}: {note}"),
+        None => note.clone(),
+      })
+    })
+    .collect();
+
+  Ok((Checked { config, errors, warnings, migrated }, sources))
+}
+
+impl Config {
+  fn deserialize_table(table: Table) -> Result<Self, String> {
+    Value::Table(table).try_into().map_err(|e: toml::de::Error| e.to_string())
+  }
+}
+
+/// `/personality/channels/2` to `personality.channels`, indices point at the
+/// array's key.
+fn dotted(pointer: &str) -> String {
+  pointer
+    .split('/')
+    .filter(|part| !part.is_empty() && part.parse::<usize>().is_err())
+    .map(|part| part.replace("~1", "/").replace("~0", "~"))
+    .collect::<Vec<_>>()
+    .join(".")
+}
+
+fn unknown_keys(prefix: &str, table: &Table, known: &Table, found: &mut impl FnMut(String)) {
+  for (key, value) in table {
+    let path = if prefix.is_empty() { key.clone() } else { format!("{prefix}.{key}") };
+    match known.get(key) {
+      None => found(path),
+      // free-form, whatever the plugin wants
+      Some(_) if prefix.starts_with("plugins.") && key == "config" => {}
+      Some(Value::Table(known)) => {
+        if let Value::Table(table) = value {
+          unknown_keys(&path, table, known, found);
+        }
+      }
+      Some(_) => {}
+    }
+  }
+}
+
+/// Line and column of every key in `source`, by dotted path.
+fn key_locations(source: &str) -> HashMap<String, (usize, usize)> {
+  let mut locations = HashMap::new();
+  if let Ok(root) = DeTable::parse(source) {
+    collect_locations(source, "", root.get_ref(), &mut locations);
+  }
+  locations
+}
+
+fn collect_locations(
+  source: &str,
+  prefix: &str,
+  table: &DeTable<'_>,
+  locations: &mut HashMap<String, (usize, usize)>,
+) {
+  for (key, value) in table {
+    let key_name = key.get_ref().as_ref();
+    let path =
+      if prefix.is_empty() { key_name.to_string() } else { format!("{prefix}.{key_name}") };
+    locations
+      .entry(path.clone())
+      .or_insert_with(|| line_column(source, key.span().start));
+    if let DeValue::Table(child) = value.get_ref() {
+      collect_locations(source, &path, child, locations);
+    }
+  }
+}
+
+fn line_column(source: &str, offset: usize) -> (usize, usize) {
+  let before = &source[..offset.min(source.len())];
+  let line = before.matches('\n').count() + 1;
+  let column = before.rfind('\n').map_or(before.len(), |nl| before.len() - nl - 1) + 1;
+  (line, column)
+}
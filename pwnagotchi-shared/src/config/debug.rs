@@ -9,6 +9,8 @@ pub struct DebugConfig {
   pub last_session_file: Cow<'static, str>,
   pub identity_path: Cow<'static, str>,
   pub recovery_file: String,
+  /// Recovery steps the unit took, kept across restarts.
+  pub journal_file: String,
 }
 
 impl Default for DebugConfig {
@@ -18,6 +20,7 @@ impl Default for DebugConfig {
       last_session_file: "/root/.pwnagotchi-last-session".into(),
       identity_path: "/etc/pwnagotchi".into(),
       recovery_file: "/root/.pwnagotchi-recovery".into(),
+      journal_file: "/root/.pwnagotchi-journal".into(),
     }
   }
 }
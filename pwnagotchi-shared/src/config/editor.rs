@@ -0,0 +1,258 @@
+//! Editing the running config from the web UI. Edits are JSON pointers into
+//! the serialized [`Config`] with their new values, they are checked against
+//! the current types and [`Config::validate`] before anything changes.
+
+use std::{collections::BTreeMap, fmt::Display};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use super::{Config, ValidationError};
+
+/// Config values never sent to clients.
+pub const SECRETS: [&str; 2] = [
+  "/bettercap/password",
+  "/ui/web/password",
+];
+pub const REDACTED: &str = "********";
+
+/// Settings read whenever they are used or reapplied when they change,
+/// everything else is picked up at startup and needs a restart. Entries
+/// ending in `/` cover a whole section.
+const LIVE: [&str; 15] = [
+  "/personality/",
+  "/main/whitelist",
+  "/main/mon_max_blind_epochs",
+  "/faces/",
+  "/ui/fps",
+  "/bettercap/hostname",
+  "/bettercap/port",
+  "/bettercap/username",
+  "/bettercap/password",
+  "/grid/cooperate",
+  "/grid/cooperate_rssi",
+  "/grid/peer_ttl",
+  "/sharing/trusted",
+  "/sharing/peers",
+  "/sharing/max_clock_skew",
+];
+
+/// Pointer to new value.
+pub type Edits = BTreeMap<String, Value>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldKind {
+  Bool,
+  Number,
+  Text,
+  /// Arrays, optional values and free-form plugin config, edited as JSON.
+  Json,
+}
+
+impl Display for FieldKind {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str(match self {
+      Self::Bool => "bool",
+      Self::Number => "number",
+      Self::Text => "text",
+      Self::Json => "json",
+    })
+  }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Field {
+  /// JSON pointer into the config.
+  pub path: String,
+  /// Path relative to the section, for display.
+  pub label: String,
+  pub kind: FieldKind,
+  /// The value as the form shows it, JSON for [`FieldKind::Json`].
+  pub value: String,
+  pub restart: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Section {
+  pub name: String,
+  pub fields: Vec<Field>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Change {
+  pub path: String,
+  pub old: Value,
+  pub new: Value,
+  pub restart: bool,
+}
+
+pub fn requires_restart(path: &str) -> bool {
+  !LIVE.iter().any(|live| match live.strip_suffix('/') {
+    Some(section) => path == section || path.starts_with(live),
+    None => path
+      .strip_prefix(live)
+      .is_some_and(|rest| rest.is_empty() || rest.starts_with('/')),
+  })
+}
+
+/// The config as JSON with [`SECRETS`] replaced.
+pub fn redacted(config: &Config) -> Result<Value, serde_json::Error> {
+  let mut value = serde_json::to_value(config)?;
+  for secret in SECRETS {
+    if let Some(v) = value.pointer_mut(secret) {
+      *v = REDACTED.into();
+    }
+  }
+  Ok(value)
+}
+
+/// Every section of `config` with its settings as form fields.
+pub fn sections(config: &Config) -> Result<Vec<Section>, serde_json::Error> {
+  let Value::Object(root) = redacted(config)? else {
+    return Ok(Vec::new());
+  };
+
+  Ok(
+    root
+      .into_iter()
+      .map(|(name, value)| {
+        let mut fields = Vec::new();
+        let section = format!("/{name}");
+        collect_fields(&section, &value, &mut fields);
+        for field in &mut fields {
+          field.label = field.path[section.len()..].trim_start_matches('/').replace('/', ".");
+        }
+        Section { name, fields }
+      })
+      .collect(),
+  )
+}
+
+fn collect_fields(path: &str, value: &Value, fields: &mut Vec<Field>) {
+  let (kind, shown) = match value {
+    Value::Object(map) if !map.is_empty() && !is_plugin_config(path) => {
+      for (key, child) in map {
+        collect_fields(&format!("{path}/{}", escape(key)), child, fields);
+      }
+      return;
+    }
+    Value::Bool(b) => (FieldKind::Bool, b.to_string()),
+    Value::Number(n) => (FieldKind::Number, n.to_string()),
+    Value::String(s) => (FieldKind::Text, s.clone()),
+    other => (FieldKind::Json, other.to_string()),
+  };
+
+  fields.push(Field {
+    path: path.to_string(),
+    label: String::new(),
+    kind,
+    value: shown,
+    restart: requires_restart(path),
+  });
+}
+
+/// Plugin config is whatever the plugin wants, it is edited as a whole.
+fn is_plugin_config(path: &str) -> bool {
+  path.starts_with("/plugins/") && path.ends_with("/config")
+}
+
+fn escape(key: &str) -> String {
+  key.replace('~', "~0").replace('/', "~1")
+}
+
+const fn same_kind(old: &Value, new: &Value) -> bool {
+  matches!(
+    (old, new),
+    (Value::Null, _)
+      | (_, Value::Null)
+      | (Value::Bool(_), Value::Bool(_))
+      | (Value::Number(_), Value::Number(_))
+      | (Value::String(_), Value::String(_))
+      | (Value::Array(_), Value::Array(_))
+      | (Value::Object(_), Value::Object(_))
+  )
+}
+
+fn set(root: &mut Value, path: &str, new: Value) -> Result<(), ValidationError> {
+  let Some(slot) = root.pointer_mut(path) else {
+    return Err(ValidationError::new(path, "unknown setting"));
+  };
+  if !same_kind(slot, &new) {
+    return Err(ValidationError::new(path, format!("expected a value like {slot}")));
+  }
+  *slot = new;
+  Ok(())
+}
+
+/// Applies `edits` to a copy of `config`. Returns the new config and what
+/// changed, or every problem with the edits. Secrets left at [`REDACTED`]
+/// keep their value.
+pub fn apply(
+  config: &Config,
+  edits: &Edits,
+) -> Result<(Config, Vec<Change>), Vec<ValidationError>> {
+  let serialize_err = |e: serde_json::Error| vec![ValidationError::new("", e.to_string())];
+  let original = serde_json::to_value(config).map_err(serialize_err)?;
+
+  let mut edited = original.clone();
+  let mut errors = Vec::new();
+  for (path, new) in edits {
+    if SECRETS.contains(&path.as_str()) && new.as_str() == Some(REDACTED) {
+      continue;
+    }
+
+    // applied on their own first, so a value the types reject is blamed on
+    // the right setting
+    let mut single = original.clone();
+    let res = set(&mut single, path, new.clone()).and_then(|()| {
+      serde_json::from_value::<Config>(single)
+        .map(drop)
+        .map_err(|e| ValidationError::new(path, e.to_string()))
+    });
+    match res {
+      Ok(()) => set(&mut edited, path, new.clone()).unwrap_or_else(|e| errors.push(e)),
+      Err(e) => errors.push(e),
+    }
+  }
+  if !errors.is_empty() {
+    return Err(errors);
+  }
+
+  let updated = serde_json::from_value::<Config>(edited).map_err(serialize_err)?;
+  let errors = updated.validate();
+  if !errors.is_empty() {
+    return Err(errors);
+  }
+
+  let mut changes = Vec::new();
+  diff("", &original, &serde_json::to_value(&updated).map_err(serialize_err)?, &mut changes);
+  Ok((updated, changes))
+}
+
+/// Collects the settings that differ between `old` and `new`.
+pub fn diff(path: &str, old: &Value, new: &Value, changes: &mut Vec<Change>) {
+  if let (Value::Object(old_map), Value::Object(new_map)) = (old, new)
+    && !is_plugin_config(path)
+  {
+    for (key, old_child) in old_map {
+      let child = format!("{path}/{}", escape(key));
+      diff(&child, old_child, new_map.get(key).unwrap_or(&Value::Null), changes);
+    }
+    for (key, new_child) in new_map.iter().filter(|(k, _)| !old_map.contains_key(*k)) {
+      diff(&format!("{path}/{}", escape(key)), &Value::Null, new_child, changes);
+    }
+    return;
+  }
+
+  if old != new {
+    let secret = SECRETS.contains(&path);
+    let shown = |v: &Value| if secret { REDACTED.into() } else { v.clone() };
+    changes.push(Change {
+      path: path.to_string(),
+      old: shown(old),
+      new: shown(new),
+      restart: requires_restart(path),
+    });
+  }
+}
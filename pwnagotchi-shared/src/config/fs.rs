@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use super::log::parse_size;
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(default)]
 pub struct FSConfig {
@@ -9,7 +11,14 @@ pub struct FSConfig {
 #[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(default)]
 pub struct FSMemoryConfig {
+  /// Off unless asked for, mounting over directories of the unit needs root
+  /// and a card layout that expects it.
   pub enabled: bool,
+  /// Where the directories on the SD card stay reachable while memory is
+  /// mounted over them, one subdirectory per mount.
+  pub disk: String,
+  /// Copy between plain directories without mounting anything.
+  pub dry_run: bool,
   pub mounts: MountsConfig,
 }
 
@@ -25,13 +34,25 @@ pub struct MountsConfig {
 #[serde(default)]
 pub struct MountConfig {
   pub enabled: bool,
+  /// Directory kept in memory.
   pub mount: String,
+  /// Memory it may use, like `50M`.
   pub size: String,
+  /// Seconds between syncs to the SD card, 0 syncs on shutdown only.
   pub sync: u64,
+  /// Use a compressed zram device instead of tmpfs.
   pub zram: bool,
+  /// Sync with `rsync` instead of the built-in copy.
   pub rsync: bool,
 }
 
+impl MountConfig {
+  /// `size` in bytes, `None` if it isn't a size.
+  pub fn size_bytes(&self) -> Option<u64> {
+    parse_size(&self.size)
+  }
+}
+
 impl Default for MountConfig {
   fn default() -> Self {
     Self {
@@ -48,7 +69,9 @@ impl Default for MountConfig {
 impl Default for FSMemoryConfig {
   fn default() -> Self {
     Self {
-      enabled: true,
+      enabled: false,
+      disk: String::from("/run/pwnagotchi/disk"),
+      dry_run: false,
       mounts: MountsConfig::default(),
     }
   }
@@ -58,7 +81,6 @@ impl Default for FSConfig {
   fn default() -> Self {
     Self {
       memory: FSMemoryConfig {
-        enabled: true,
         mounts: MountsConfig {
           log: MountConfig {
             enabled: true,
@@ -77,6 +99,7 @@ impl Default for FSConfig {
             rsync: true,
           },
         },
+        ..FSMemoryConfig::default()
       },
     }
   }
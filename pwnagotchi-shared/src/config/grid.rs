@@ -0,0 +1,90 @@
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct GridConfig {
+  /// Mesh backend, "pwngrid" talks to the pwngrid daemon, "native" injects and
+  /// parses beacons on the monitor interface directly.
+  pub mesh: Cow<'static, str>,
+  /// Milliseconds between injected beacons while advertising.
+  pub beacon_interval: u64,
+  /// Seconds after which a peer that was not heard from is dropped.
+  pub peer_ttl: u64,
+  /// Base address of the pwngrid API.
+  pub api_address: Cow<'static, str>,
+  /// Checked in the background to decide whether the grid is reachable.
+  pub connectivity_url: Cow<'static, str>,
+  /// Seconds between connectivity checks.
+  pub connectivity_interval: u64,
+  /// Reports made while offline are kept here until they can be sent.
+  pub queue_file: Cow<'static, str>,
+  /// Split channels and targets with nearby units.
+  pub cooperate: bool,
+  /// Peers heard at or above this RSSI count as close enough to share the
+  /// work with.
+  pub cooperate_rssi: i16,
+  /// Every peer ever met is remembered here.
+  pub peer_db: Cow<'static, str>,
+  /// Talk to a grid server directly instead of through the pwngrid daemon,
+  /// requests carry a token obtained by enrolling with the unit's identity.
+  /// `api_address` has to point at the server when this is enabled.
+  pub authenticated: bool,
+  /// Seconds before the enrollment token is refreshed.
+  pub token_ttl: u64,
+  pub endpoints: GridEndpoints,
+}
+
+impl Default for GridConfig {
+  fn default() -> Self {
+    Self {
+      mesh: "pwngrid".into(),
+      beacon_interval: 1000,
+      peer_ttl: 120,
+      api_address: "http://127.0.0.1:8666/api/v1".into(),
+      connectivity_url: "https://api.opwngrid.xyz/api/v1/uptime".into(),
+      connectivity_interval: 60,
+      queue_file: "/root/.pwnagotchi-grid-queue".into(),
+      cooperate: true,
+      cooperate_rssi: -70,
+      peer_db: "/root/.pwnagotchi-peers.json".into(),
+      authenticated: false,
+      token_ttl: 1800,
+      endpoints: GridEndpoints::default(),
+    }
+  }
+}
+
+/// Paths relative to `api_address`.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct GridEndpoints {
+  pub mesh: Cow<'static, str>,
+  pub mesh_data: Cow<'static, str>,
+  pub mesh_peers: Cow<'static, str>,
+  pub memory: Cow<'static, str>,
+  pub data: Cow<'static, str>,
+  pub report_ap: Cow<'static, str>,
+  pub inbox: Cow<'static, str>,
+  pub enroll: Cow<'static, str>,
+  /// Other units are looked up under `<unit>/<fingerprint>`, messages are
+  /// sent to `<unit>/<fingerprint>/inbox`.
+  pub unit: Cow<'static, str>,
+}
+
+impl Default for GridEndpoints {
+  fn default() -> Self {
+    Self {
+      mesh: "mesh".into(),
+      mesh_data: "mesh/data".into(),
+      mesh_peers: "mesh/peers".into(),
+      memory: "system/memory".into(),
+      data: "data".into(),
+      report_ap: "report/ap".into(),
+      inbox: "inbox".into(),
+      enroll: "unit/enroll".into(),
+      unit: "unit".into(),
+    }
+  }
+}
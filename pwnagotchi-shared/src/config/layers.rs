@@ -0,0 +1,379 @@
+//! Where the running config comes from. The base file is read first, then
+//! every `*.toml` fragment in `main.confd` in name order, then the user file
+//! changes made on the unit are written to, and last the
+//! `PWNAGOTCHI__SECTION__KEY` environment variables. Later layers replace
+//! single keys of earlier ones, the layer every key was set by is kept.
+
+use std::{
+  collections::BTreeMap,
+  fmt::Display,
+  path::{Path, PathBuf},
+};
+
+use parking_lot::RwLock;
+use toml::{Table, Value};
+
+use super::{
+  Config, MainConfig,
+  check::{self, Checked, Layer},
+};
+
+/// Environment variables starting with this override settings, with `__`
+/// between the section and key, like `PWNAGOTCHI__UI__WEB__PORT=8080`.
+pub const ENV_PREFIX: &str = "PWNAGOTCHI__";
+
+/// Changes made on the unit go here, in `main.confd` and read after the other
+/// fragments.
+pub const USER_FILE: &str = "user.toml";
+
+static STACK: RwLock<Option<Stack>> = RwLock::new(None);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+  File(PathBuf),
+  Env(String),
+}
+
+impl Display for Source {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::File(path) => write!(f, "{}", path.display()),
+      Self::Env(name) => write!(f, "${name}"),
+    }
+  }
+}
+
+/// Layer that set each dotted key, keys that aren't in it are defaults.
+pub type Sources = BTreeMap<String, Source>;
+
+/// The files and variables the running config was built from.
+#[derive(Debug, Clone)]
+pub struct Stack {
+  pub base: PathBuf,
+  pub confd: PathBuf,
+  /// Fragments in `confd` in the order they were merged, without the user
+  /// file.
+  pub fragments: Vec<PathBuf>,
+  pub user_file: PathBuf,
+  pub sources: Sources,
+  /// Defaults, base and fragments merged, the user file only holds what
+  /// differs from this.
+  below_user: Table,
+  /// The user file as it was loaded.
+  user: Table,
+}
+
+impl Stack {
+  /// What the user file has to contain for the layers to make up `config`.
+  /// Keys set by environment variables keep what the user file had, the
+  /// variable wins over it anyway.
+  pub fn overrides(&self, config: &Config) -> Result<Table, String> {
+    let current =
+      Table::try_from(config).map_err(|e| format!("Failed to serialize config: {e}"))?;
+    let mut user = Table::new();
+    overrides("", &current, Some(&self.below_user), Some(&self.user), &self.sources, &mut user);
+    Ok(user)
+  }
+
+  /// Everything a change to which means the config has to be loaded again.
+  pub fn files(&self) -> Vec<PathBuf> {
+    let mut files = vec![self.base.clone(), self.confd.clone()];
+    files.extend(self.fragments.iter().cloned());
+    files.push(self.user_file.clone());
+    files
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct Layered {
+  pub checked: Checked,
+  pub stack: Stack,
+}
+
+/// A setting of the running config and where its value came from.
+#[derive(Debug, Clone)]
+pub struct Setting {
+  /// Dotted key, like `personality.min_rssi`.
+  pub key: String,
+  pub value: Value,
+  /// `None` for defaults.
+  pub source: Option<Source>,
+}
+
+/// Loads the layers on top of `base` with the process environment.
+pub fn load(base: &Path) -> Result<Layered, String> {
+  load_from(base, std::env::vars())
+}
+
+/// Loads the layers on top of `base`, overriding them with the `env`
+/// variables starting with [`ENV_PREFIX`]. A missing base file means
+/// defaults, unreadable or malformed files are an `Err`.
+pub fn load_from(
+  base: &Path,
+  env: impl IntoIterator<Item = (String, String)>,
+) -> Result<Layered, String> {
+  let mut layers = Vec::new();
+  if base.exists() {
+    layers.push(read(base)?);
+  }
+
+  let confd = confd(base, layers.first());
+  let fragments = fragments(&confd)?;
+  for fragment in &fragments {
+    layers.push(read(fragment)?);
+  }
+  let below_user = merged(&layers, &mut Sources::new());
+
+  let user_file = confd.join(USER_FILE);
+  let mut user = Table::new();
+  if user_file.exists() {
+    let layer = read(&user_file)?;
+    user = layer.table.clone();
+    layers.push(layer);
+  }
+
+  layers.extend(env_layers(env));
+  let (checked, sources) = check::check_layers(&layers)?;
+
+  Ok(Layered {
+    checked,
+    stack: Stack {
+      base: base.to_path_buf(),
+      confd,
+      fragments,
+      user_file,
+      sources,
+      below_user,
+      user,
+    },
+  })
+}
+
+/// Makes `stack` the one the running config was loaded from.
+pub fn install(stack: Stack) {
+  *STACK.write() = Some(stack);
+}
+
+/// The layers of the running config, `None` before it is loaded.
+pub fn current() -> Option<Stack> {
+  STACK.read().clone()
+}
+
+/// Whether the config was loaded through [`load`], there is nowhere to save
+/// it otherwise.
+pub fn is_loaded() -> bool {
+  STACK.read().is_some()
+}
+
+/// Writes the changes `config` makes to its layers to the user file.
+pub fn save(config: &Config) -> Result<(), String> {
+  let mut stack = STACK.write();
+  let stack = stack.as_mut().ok_or("config layers not loaded")?;
+  let user = stack.overrides(config)?;
+
+  // only the last directory, a config path that doesn't exist has no place
+  // for it
+  if !stack.confd.exists() {
+    std::fs::create_dir(&stack.confd)
+      .map_err(|e| format!("Failed to create {}: {e}", stack.confd.display()))?;
+  }
+  let content = toml::to_string(&user).map_err(|e| format!("Failed to serialize config: {e}"))?;
+  std::fs::write(&stack.user_file, content)
+    .map_err(|e| format!("Failed to write {}: {e}", stack.user_file.display()))?;
+
+  stack.user = user;
+  Ok(())
+}
+
+/// Every setting of `config` with the layer it came from.
+pub fn settings(config: &Config, sources: &Sources) -> Result<Vec<Setting>, String> {
+  let table = Table::try_from(config).map_err(|e| format!("Failed to serialize config: {e}"))?;
+  let mut settings = Vec::new();
+  flatten("", &table, &mut |key, value| {
+    settings.push(Setting {
+      source: sources.get(&key).cloned(),
+      key,
+      value: value.clone(),
+    });
+  });
+  Ok(settings)
+}
+
+/// `layers` merged over the defaults in order, recording the layer every key
+/// came from in `sources`.
+pub(super) fn merged(layers: &[Layer], sources: &mut Sources) -> Table {
+  let mut table = Table::try_from(Config::default()).expect("the default config serializes");
+  for layer in layers {
+    merge("", &mut table, &layer.table, layer.source.as_ref(), sources);
+  }
+  table
+}
+
+fn merge(
+  prefix: &str,
+  into: &mut Table,
+  from: &Table,
+  source: Option<&Source>,
+  sources: &mut Sources,
+) {
+  for (key, value) in from {
+    let path = join(prefix, key);
+    if let Value::Table(from) = value {
+      let entry = into.entry(key.clone()).or_insert_with(|| Value::Table(Table::new()));
+      if !entry.is_table() {
+        *entry = Value::Table(Table::new());
+      }
+      let into = entry.as_table_mut().expect("just made a table");
+      merge(&path, into, from, source, sources);
+    } else {
+      into.insert(key.clone(), value.clone());
+      if let Some(source) = source {
+        sources.insert(path, source.clone());
+      }
+    }
+  }
+}
+
+fn overrides(
+  prefix: &str,
+  current: &Table,
+  below: Option<&Table>,
+  user: Option<&Table>,
+  sources: &Sources,
+  out: &mut Table,
+) {
+  for (key, value) in current {
+    let path = join(prefix, key);
+    let below_value = below.and_then(|below| below.get(key));
+    let user_value = user.and_then(|user| user.get(key));
+
+    if matches!(sources.get(&path), Some(Source::Env(_))) {
+      if let Some(user_value) = user_value {
+        out.insert(key.clone(), user_value.clone());
+      }
+      continue;
+    }
+
+    if let Value::Table(current) = value {
+      let mut child = Table::new();
+      overrides(
+        &path,
+        current,
+        below_value.and_then(Value::as_table),
+        user_value.and_then(Value::as_table),
+        sources,
+        &mut child,
+      );
+      if !child.is_empty() {
+        out.insert(key.clone(), Value::Table(child));
+      }
+    } else if below_value != Some(value) {
+      out.insert(key.clone(), value.clone());
+    }
+  }
+}
+
+fn flatten(prefix: &str, table: &Table, found: &mut impl FnMut(String, &Value)) {
+  for (key, value) in table {
+    let path = join(prefix, key);
+    match value {
+      Value::Table(table) => flatten(&path, table, found),
+      value => found(path, value),
+    }
+  }
+}
+
+fn join(prefix: &str, key: &str) -> String {
+  if prefix.is_empty() { key.to_string() } else { format!("{prefix}.{key}") }
+}
+
+fn read(path: &Path) -> Result<Layer, String> {
+  let text = std::fs::read_to_string(path)
+    .map_err(|e| format!("Failed to read config file {}: {e}", path.display()))?;
+  Layer::parse(Some(Source::File(path.to_path_buf())), &text)
+    .map_err(|e| format!("Failed to parse config file {}: {e}", path.display()))
+}
+
+/// `main.confd` of the base file, relative to the file's directory.
+fn confd(base: &Path, layer: Option<&Layer>) -> PathBuf {
+  let confd = layer
+    .and_then(|layer| layer.table.get("main")?.get("confd")?.as_str())
+    .map_or_else(|| MainConfig::default().confd.into_owned(), ToString::to_string);
+  let dir = base
+    .parent()
+    .filter(|dir| !dir.as_os_str().is_empty())
+    .unwrap_or(Path::new("."));
+  dir.join(confd)
+}
+
+fn fragments(confd: &Path) -> Result<Vec<PathBuf>, String> {
+  let entries = match std::fs::read_dir(confd) {
+    Ok(entries) => entries,
+    Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+    Err(e) => return Err(format!("Failed to read {}: {e}", confd.display())),
+  };
+
+  let mut fragments = entries
+    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+    .filter(|path| {
+      path.is_file()
+        && path.extension().is_some_and(|ext| ext == "toml")
+        && path.file_name().is_some_and(|name| name != USER_FILE)
+    })
+    .collect::<Vec<_>>();
+  fragments.sort();
+  Ok(fragments)
+}
+
+/// One layer per `PWNAGOTCHI__` variable, in name order so the result doesn't
+/// depend on the environment's.
+fn env_layers(env: impl IntoIterator<Item = (String, String)>) -> Vec<Layer> {
+  let defaults = Table::try_from(Config::default()).expect("the default config serializes");
+  let mut vars = env
+    .into_iter()
+    .filter(|(name, _)| name.starts_with(ENV_PREFIX))
+    .collect::<Vec<_>>();
+  vars.sort();
+
+  vars
+    .into_iter()
+    .filter_map(|(name, raw)| {
+      let path = name[ENV_PREFIX.len()..].split("__").map(str::to_lowercase).collect::<Vec<_>>();
+      if path.iter().any(String::is_empty) {
+        return None;
+      }
+
+      let value = env_value(&raw, lookup(&defaults, &path));
+      let table = path
+        .iter()
+        .rev()
+        .fold(value, |value, key| Value::Table(Table::from_iter([(key.clone(), value)])));
+      let Value::Table(table) = table else {
+        return None;
+      };
+      Some(Layer::new(Some(Source::Env(name)), table))
+    })
+    .collect()
+}
+
+fn lookup<'a>(table: &'a Table, path: &[String]) -> Option<&'a Value> {
+  let (last, parents) = path.split_last()?;
+  let mut table = table;
+  for key in parents {
+    table = table.get(key)?.as_table()?;
+  }
+  table.get(last)
+}
+
+/// Text settings take the variable as is, anything else is read as a TOML
+/// value, falling back to text.
+fn env_value(raw: &str, default: Option<&Value>) -> Value {
+  if matches!(default, Some(Value::String(_))) {
+    return Value::String(raw.to_string());
+  }
+  format!("value = {raw}")
+    .parse::<Table>()
+    .ok()
+    .and_then(|mut table| table.remove("value"))
+    .unwrap_or_else(|| Value::String(raw.to_string()))
+}
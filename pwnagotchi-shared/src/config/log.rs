@@ -1,4 +1,4 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, collections::BTreeMap};
 
 use serde::{Deserialize, Serialize};
 
@@ -8,27 +8,117 @@ pub struct LogConfig {
   pub path: Cow<'static, str>,
   pub path_debug: Cow<'static, str>,
   pub rotation: LogRotationConfig,
+  /// Lowest level logged per origin, like `BETTERCAP`, per plugin, like
+  /// `"plugin::grid"`, or per target, like `"pwnagotchi_core::agent"`.
+  pub filters: BTreeMap<String, Cow<'static, str>>,
+  /// Where entries go besides the log files, like a syslog collector.
+  pub sinks: Vec<LogSinkConfig>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(default)]
 pub struct LogRotationConfig {
   pub enabled: bool,
+  /// Size a log file is rotated at, in bytes or with a `K`, `M` or `G`
+  /// suffix.
   pub size: Cow<'static, str>,
+  /// Rotated files kept next to each log, older ones are deleted.
+  pub keep: u32,
+  /// Gzip rotated files.
+  pub compress: bool,
 }
 
 impl Default for LogRotationConfig {
   fn default() -> Self {
-    Self { enabled: true, size: "10M".into() }
+    Self {
+      enabled: true,
+      size: "10M".into(),
+      keep: 5,
+      compress: true,
+    }
+  }
+}
+
+impl LogRotationConfig {
+  /// `size` in bytes, `None` if it isn't a size.
+  pub fn max_bytes(&self) -> Option<u64> {
+    parse_size(&self.size)
+  }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct LogSinkConfig {
+  /// `syslog` for an RFC 5424 collector or `journald` for the systemd
+  /// journal.
+  pub kind: Cow<'static, str>,
+  /// Lowest level sent to the sink.
+  pub level: Cow<'static, str>,
+  /// `host:port` of the syslog collector. For journald the socket, empty
+  /// for the one of systemd.
+  pub address: Cow<'static, str>,
+  /// `udp` or `tcp`, syslog only.
+  pub protocol: Cow<'static, str>,
+  /// Syslog facility by name, like `daemon` or `local0`.
+  pub facility: Cow<'static, str>,
+  /// File entries the collector can't be reached for are kept in and sent
+  /// from once it is back, empty to drop them. Syslog over TCP only, UDP
+  /// can't tell whether the collector got an entry.
+  pub spool: Cow<'static, str>,
+  /// Size the spool may grow to, later entries are dropped.
+  pub spool_size: Cow<'static, str>,
+  /// Seconds between attempts to reach the collector again.
+  pub retry_interval: u64,
+}
+
+impl Default for LogSinkConfig {
+  fn default() -> Self {
+    Self {
+      kind: "syslog".into(),
+      level: "info".into(),
+      address: "".into(),
+      protocol: "udp".into(),
+      facility: "daemon".into(),
+      spool: "".into(),
+      spool_size: "1M".into(),
+      retry_interval: 30,
+    }
   }
 }
 
+impl LogSinkConfig {
+  /// `spool_size` in bytes, `None` if it isn't a size.
+  pub fn spool_bytes(&self) -> Option<u64> {
+    parse_size(&self.spool_size)
+  }
+}
+
+/// Bytes in a size like `10M`, with an optional `K`, `M` or `G` suffix.
+/// `None` if it isn't one or is 0.
+pub(super) fn parse_size(size: &str) -> Option<u64> {
+  let size = size.trim();
+  let (number, unit) = match size.char_indices().last()? {
+    (i, c) if c.is_ascii_alphabetic() => (&size[..i], c.to_ascii_uppercase()),
+    _ => (size, 'B'),
+  };
+  let factor = match unit {
+    'B' => 1,
+    'K' => 1 << 10,
+    'M' => 1 << 20,
+    'G' => 1 << 30,
+    _ => return None,
+  };
+  number.trim().parse::<u64>().ok().filter(|n| *n > 0)?.checked_mul(factor)
+}
+
 impl Default for LogConfig {
   fn default() -> Self {
     Self {
       path: "/etc/pwnagotchi/log/pwnagotchi.log".into(),
       path_debug: "/etc/pwnagotchi/log/pwnagotchi_debug.log".into(),
       rotation: LogRotationConfig::default(),
+      filters: BTreeMap::new(),
+      sinks: Vec::new(),
     }
   }
 }
@@ -14,7 +14,9 @@ pub struct MainConfig {
   pub mon_max_blind_epochs: u32,
   pub no_restart: bool,
   pub whitelist: Vec<Cow<'static, str>>,
-  //confd
+  /// Directory of `*.toml` fragments merged over this file in name order,
+  /// relative to it. Only read from the base config.
+  pub confd: Cow<'static, str>,
   //custom_plugin_repos
   pub plugins_path: Option<Cow<'static, str>>,
 }
@@ -31,6 +33,7 @@ impl Default for MainConfig {
       mon_stop_cmd: "/usr/bin/monstop".into(),
       mon_max_blind_epochs: 5,
       no_restart: false,
+      confd: "conf.d".into(),
       plugins_path: None,
     }
   }
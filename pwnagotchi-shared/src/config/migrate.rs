@@ -0,0 +1,213 @@
+//! Upgrades config files written for older layouts, including the one of the
+//! original Python pwnagotchi. Migrations edit the TOML document itself so
+//! values end up where [`Config`](super::Config) expects them before it is
+//! deserialized, and a rewritten file keeps its comments and formatting.
+
+use toml_edit::{DocumentMut, Item, Table, TableLike, Value, value};
+
+/// Written as `version` at the top of the config, files without it are
+/// version 0.
+pub const CONFIG_VERSION: u32 = 1;
+
+/// Display names used by the Python pwnagotchi for the displays supported
+/// here.
+const DISPLAY_ALIASES: [(&str, &str); 6] = [
+  ("waveshare_4", "waveshare_v4"),
+  ("waveshare4", "waveshare_v4"),
+  ("ws_4", "waveshare_v4"),
+  ("ws4", "waveshare_v4"),
+  ("waveshare2in13_v4", "waveshare_v4"),
+  ("waveshare_2in13_v4", "waveshare_v4"),
+];
+
+/// Version of a parsed config file.
+pub fn version(doc: &DocumentMut) -> u32 {
+  doc
+    .get("version")
+    .and_then(Item::as_integer)
+    .and_then(|v| u32::try_from(v).ok())
+    .unwrap_or(0)
+}
+
+/// Brings `doc` up to [`CONFIG_VERSION`] and describes every change made.
+pub fn migrate(doc: &mut DocumentMut) -> Vec<String> {
+  let mut notes = Vec::new();
+  if version(doc) < 1 {
+    from_python(doc, &mut notes);
+  }
+  doc.insert("version", value(i64::from(CONFIG_VERSION)));
+  notes
+}
+
+/// Migrates a config file's contents, `None` if it is already in the current
+/// layout. The text comes back with the comments of the original.
+pub fn migrate_text(text: &str) -> Result<Option<(String, Vec<String>)>, String> {
+  let mut doc = text.parse::<DocumentMut>().map_err(|e| e.to_string())?;
+  if version(&doc) >= CONFIG_VERSION {
+    return Ok(None);
+  }
+  let notes = migrate(&mut doc);
+  Ok(Some((doc.to_string(), notes)))
+}
+
+/// The `name` table, made when missing. A new table only gets a header once
+/// it has values of its own.
+fn section<'a>(doc: &'a mut DocumentMut, name: &str) -> &'a mut dyn TableLike {
+  let implicit = || {
+    let mut section = Table::new();
+    section.set_implicit(true);
+    Item::Table(section)
+  };
+  let entry = doc.entry(name).or_insert_with(implicit);
+  if !entry.is_table_like() {
+    *entry = implicit();
+  }
+  entry.as_table_like_mut().expect("just made a table")
+}
+
+/// Takes `from.key` out and returns it with the comments above it, `None` if
+/// it isn't there.
+fn take(doc: &mut DocumentMut, from: &str, key: &str) -> Option<(Item, Option<String>)> {
+  let from = doc.get_mut(from)?.as_table_like_mut()?;
+  let comments = from.key(key).and_then(|k| k.leaf_decor().prefix()?.as_str().map(String::from));
+  Some((from.remove(key)?, comments))
+}
+
+/// Inserts `item` as `key` of `target`, above it the comments it had where
+/// it came from.
+fn put(target: &mut dyn TableLike, key: &str, item: Item, comments: Option<String>) {
+  target.insert(key, item);
+  if let Some(comments) = comments
+    && let Some(mut key) = target.key_mut(key)
+  {
+    key.leaf_decor_mut().set_prefix(comments);
+  }
+}
+
+/// Moves `key` from the `from` section to `to.new_key`, unless the new
+/// layout already sets it.
+fn rename(
+  doc: &mut DocumentMut,
+  from: &str,
+  key: &str,
+  to: &str,
+  new_key: &str,
+  notes: &mut Vec<String>,
+) {
+  let Some((item, comments)) = take(doc, from, key) else {
+    return;
+  };
+  let target = section(doc, to);
+  if target.contains_key(new_key) {
+    notes.push(format!("dropped {from}.{key}, {to}.{new_key} is already set"));
+  } else {
+    put(target, new_key, item, comments);
+    notes.push(format!("moved {from}.{key} to {to}.{new_key}"));
+  }
+}
+
+/// Merges the `from.key` table into the `to` section, keys set in both keep
+/// the value from `to`.
+fn merge(doc: &mut DocumentMut, from: &str, key: &str, to: &str, notes: &mut Vec<String>) {
+  let Some((mut item, _)) = take(doc, from, key) else {
+    return;
+  };
+  let Some(values) = item.as_table_like_mut() else {
+    return;
+  };
+  let names = values.iter().map(|(name, _)| name.to_string()).collect::<Vec<_>>();
+  let moved = names
+    .into_iter()
+    .filter_map(|name| {
+      let comments = values
+        .key(&name)
+        .and_then(|k| k.leaf_decor().prefix()?.as_str().map(String::from));
+      values.remove(&name).map(|item| (name, item, comments))
+    })
+    .collect::<Vec<_>>();
+
+  let target = section(doc, to);
+  for (name, item, comments) in moved {
+    if !target.contains_key(&name) {
+      put(target, &name, item, comments);
+    }
+  }
+  notes.push(format!("moved {from}.{key} to {to}"));
+}
+
+fn from_python(doc: &mut DocumentMut, notes: &mut Vec<String>) {
+  // [main.plugins.<name>] with `enabled` next to the plugin's options
+  if let Some((mut item, _)) = take(doc, "main", "plugins")
+    && let Some(plugins) = item.as_table_like_mut()
+  {
+    let names = plugins.iter().map(|(name, _)| name.to_string()).collect::<Vec<_>>();
+    for name in names {
+      let Some(mut options) = plugins.remove(&name) else {
+        continue;
+      };
+      let Some(table) = options.as_table_like_mut() else {
+        continue;
+      };
+      let enabled = table.remove("enabled").and_then(|v| v.as_bool()).unwrap_or(false);
+
+      let mut plugin = Table::new();
+      // the plugin's header and its comments stay with its options, or with
+      // the plugin when there are none
+      if let Some(header) = options.as_table_mut() {
+        plugin.decor_mut().clone_from(header.decor());
+        plugin.set_position(header.position().unwrap_or_default());
+        header.decor_mut().clear();
+        if let Some(position) = header.position() {
+          header.set_position(position + 1);
+        }
+      }
+      plugin.insert("enabled", value(enabled));
+      if !options.as_table_like().is_some_and(TableLike::is_empty) {
+        plugin.insert("config", into_table(options));
+      }
+
+      let target = section(doc, "plugins");
+      if !target.contains_key(&name) {
+        target.insert(&name, Item::Table(plugin));
+      }
+      notes.push(format!("moved main.plugins.{name} to plugins.{name}"));
+    }
+  }
+
+  rename(doc, "main", "custom_plugins", "main", "plugins_path", notes);
+  merge(doc, "main", "log", "log", notes);
+  if let Some(log) = doc.get_mut("log").and_then(Item::as_table_like_mut)
+    && let Some(path) = log.remove("path-debug")
+    && !log.contains_key("path_debug")
+  {
+    log.insert("path_debug", path);
+  }
+  merge(doc, "ui", "faces", "faces", notes);
+  rename(doc, "ui", "invert", "ui", "inverted", notes);
+
+  if let Some(display) = doc
+    .get_mut("ui")
+    .and_then(Item::as_table_like_mut)
+    .and_then(|ui| ui.get_mut("display"))
+    .and_then(Item::as_table_like_mut)
+    && let Some(kind) = display.get_mut("type")
+    && let Some(current) = kind.as_str()
+    && let Some((_, name)) =
+      DISPLAY_ALIASES.iter().find(|(alias, _)| current.eq_ignore_ascii_case(alias))
+  {
+    notes.push(format!("renamed display type {current} to {name}"));
+    let decor = kind.as_value().map(|v| v.decor().clone());
+    *kind = value(*name);
+    if let (Some(decor), Some(kind)) = (decor, kind.as_value_mut()) {
+      *kind.decor_mut() = decor;
+    }
+  }
+}
+
+/// A standard table for `item`, inline tables and dotted keys included.
+fn into_table(item: Item) -> Item {
+  match item {
+    Item::Value(Value::InlineTable(inline)) => Item::Table(inline.into_table()),
+    item => item,
+  }
+}
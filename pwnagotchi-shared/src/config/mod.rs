@@ -1,40 +1,59 @@
 #![allow(clippy::missing_errors_doc)]
 
 mod bettercap;
+pub mod check;
 mod debug;
+pub mod editor;
 mod faces;
 mod fs;
+mod grid;
+pub mod layers;
 mod log;
 mod main;
+pub mod migrate;
 mod personality;
 mod plugins;
+pub mod reload;
+mod sharing;
+mod system;
 mod ui;
+mod validate;
+mod watchdog;
 
 use std::{
   collections::HashMap,
   fmt::Display,
+  mem::ManuallyDrop,
   ops::{Deref, DerefMut},
   path::Path,
   sync::OnceLock,
 };
 
 pub use bettercap::BettercapConfig;
+use check::Checked;
 pub use debug::DebugConfig;
 pub use faces::FaceConfig;
-pub use fs::FSConfig;
-pub use log::LogConfig;
+pub use fs::{FSConfig, FSMemoryConfig, MountConfig, MountsConfig};
+pub use grid::{GridConfig, GridEndpoints};
+pub use log::{LogConfig, LogSinkConfig};
 pub use main::MainConfig;
 use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 pub use personality::PersonalityConfig;
 pub use plugins::PluginConfig;
 use serde::{Deserialize, Serialize};
+pub use sharing::SharingConfig;
+pub use system::SystemConfig;
 pub use ui::UIConfig;
+pub use validate::ValidationError;
+pub use watchdog::WatchdogConfig;
 
 use crate::logger::LOGGER;
 
-#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(default)]
 pub struct Config {
+  /// Layout version, older files are migrated when loaded.
+  pub version: u32,
   pub main: MainConfig,
   pub bettercap: BettercapConfig,
   pub plugins: HashMap<String, PluginConfig>,
@@ -44,6 +63,31 @@ pub struct Config {
   pub faces: FaceConfig,
   pub debug: DebugConfig,
   pub log: LogConfig,
+  pub system: SystemConfig,
+  pub watchdog: WatchdogConfig,
+  pub grid: GridConfig,
+  pub sharing: SharingConfig,
+}
+
+impl Default for Config {
+  fn default() -> Self {
+    Self {
+      version: migrate::CONFIG_VERSION,
+      main: MainConfig::default(),
+      bettercap: BettercapConfig::default(),
+      plugins: HashMap::new(),
+      personality: PersonalityConfig::default(),
+      fs: FSConfig::default(),
+      ui: UIConfig::default(),
+      faces: FaceConfig::default(),
+      debug: DebugConfig::default(),
+      log: LogConfig::default(),
+      system: SystemConfig::default(),
+      watchdog: WatchdogConfig::default(),
+      grid: GridConfig::default(),
+      sharing: SharingConfig::default(),
+    }
+  }
 }
 
 impl Display for Config {
@@ -53,12 +97,24 @@ impl Display for Config {
 }
 
 impl Config {
+  /// Loads a config file, refusing files with errors. Unknown keys are
+  /// ignored, see [`Config::load_checked`] to get them.
   pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
-    let config_str =
-      std::fs::read_to_string(path).map_err(|e| format!("Failed to read config file: {e}"))?;
-    let config: Self =
-      toml::from_str(&config_str).map_err(|e| format!("Failed to parse config file: {e}"))?;
-    Ok(config)
+    let checked = Self::load_checked(path.as_ref())?;
+    if !checked.is_ok() {
+      return Err(errors_message(&format!("file {}", path.as_ref().display()), &checked));
+    }
+    Ok(checked.config)
+  }
+
+  /// Loads and checks a config file, only unreadable or malformed files are
+  /// an `Err`.
+  pub fn load_checked<P: AsRef<Path>>(path: P) -> Result<Checked, String> {
+    let path = path.as_ref();
+    let config_str = std::fs::read_to_string(path)
+      .map_err(|e| format!("Failed to read config file {}: {e}", path.display()))?;
+    check::check(&config_str)
+      .map_err(|e| format!("Failed to parse config file {}: {e}", path.display()))
   }
 
   pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
@@ -69,19 +125,45 @@ impl Config {
   }
 }
 
+/// Writes the running config's changes to the user file, see
+/// [`layers::save`].
 pub fn save_current_config() -> Result<(), String> {
-  let path = CONFIG_PATH.get().ok_or("config path not set")?;
-  let guard = config_read();
-  guard.save(path)
+  layers::save(&config_read())
 }
 
 pub static CONFIG: OnceLock<RwLock<Config>> = OnceLock::new();
 pub static CONFIG_PATH: OnceLock<String> = OnceLock::new();
 
-pub fn init_config<P: AsRef<Path>>(path: P) {
-  let cfg = Config::load(path.as_ref()).unwrap_or_default();
-  CONFIG.get_or_init(|| RwLock::new(cfg));
-  CONFIG_PATH.get_or_init(|| path.as_ref().to_string_lossy().to_string());
+fn errors_message(what: &str, checked: &Checked) -> String {
+  let errors = checked.errors.iter().map(|e| format!("\n  {e}")).collect::<String>();
+  format!("Invalid config {what}:{errors}")
+}
+
+/// Loads the config the unit runs with, the file at `path` with its
+/// fragments and environment overrides on top, see [`layers`]. A missing file
+/// means defaults, a broken one is an error rather than silently turning
+/// every setting off. Returns what should be logged about the config once
+/// logging works.
+pub fn init_config<P: AsRef<Path>>(path: P) -> Result<Vec<String>, String> {
+  let path = path.as_ref();
+  let layered = layers::load(path)?;
+  let checked = &layered.checked;
+  if !checked.is_ok() {
+    return Err(errors_message(&path.display().to_string(), checked));
+  }
+
+  let mut notes = checked.warnings.iter().map(ToString::to_string).collect::<Vec<_>>();
+  if !path.exists() {
+    notes.push(format!("{} does not exist, using defaults", path.display()));
+  }
+  if !checked.migrated.is_empty() {
+    notes.push(format!("Older config layout migrated in memory: {}", checked.migrated.join(", ")));
+  }
+
+  CONFIG.get_or_init(|| RwLock::new(layered.checked.config));
+  CONFIG_PATH.get_or_init(|| path.to_string_lossy().to_string());
+  layers::install(layered.stack);
+  Ok(notes)
 }
 
 fn try_parse_config_from_args() -> Option<String> {
@@ -97,24 +179,29 @@ fn try_parse_config_from_args() -> Option<String> {
   None
 }
 
+/// The running config, loaded on first use when [`init_config`] wasn't
+/// called. Like there, a missing file means defaults but a broken one is not
+/// papered over with them.
+fn config_lock() -> &'static RwLock<Config> {
+  CONFIG.get_or_init(|| {
+    let path = try_parse_config_from_args().unwrap_or_else(|| "config.toml".to_string());
+    let layered = layers::load(Path::new(&path)).unwrap_or_else(|e| panic!("{e}"));
+    if !layered.checked.is_ok() {
+      // the logger reads the config, it can't be used before there is one
+      panic!("{}", errors_message(&path, &layered.checked));
+    }
+    RwLock::new(layered.checked.config)
+  })
+}
+
 pub fn config_read() -> RwLockReadGuard<'static, Config> {
-  CONFIG
-    .get_or_init(|| {
-      let path = try_parse_config_from_args().unwrap_or_else(|| "config.toml".to_string());
-      RwLock::new(Config::load(path).unwrap_or_default())
-    })
-    .read()
+  config_lock().read()
 }
 
 pub fn config_write() -> ConfigWriteGuard<'static> {
-  let guard = CONFIG
-    .get_or_init(|| {
-      let path = try_parse_config_from_args().unwrap_or_else(|| "config.toml".to_string());
-      RwLock::new(Config::load(path).unwrap_or_default())
-    })
-    .write();
-
-  ConfigWriteGuard { guard }
+  ConfigWriteGuard {
+    guard: ManuallyDrop::new(config_lock().write()),
+  }
 }
 
 pub fn with_config_read<F, R>(f: F) -> R
@@ -134,7 +221,7 @@ where
 }
 
 pub struct ConfigWriteGuard<'a> {
-  guard: RwLockWriteGuard<'a, Config>,
+  guard: ManuallyDrop<RwLockWriteGuard<'a, Config>>,
 }
 
 impl<'a> Deref for ConfigWriteGuard<'a> {
@@ -153,12 +240,13 @@ impl<'a> DerefMut for ConfigWriteGuard<'a> {
 
 impl<'a> Drop for ConfigWriteGuard<'a> {
   fn drop(&mut self) {
-    if let Some(path) = CONFIG_PATH.get() {
-      if let Err(e) = self.guard.save(path) {
-        LOGGER.log_error("CONFIG", &format!("Failed to auto-save config: {}", e));
-      }
-    } else {
-      LOGGER.log_error("CONFIG", "Failed to auto-save config: CONFIG_PATH not set.");
+    // defaults and test configs never came from a file
+    let saved = if layers::is_loaded() { layers::save(&self.guard) } else { Ok(()) };
+    // SAFETY: dropped once, the field isn't touched again
+    unsafe { ManuallyDrop::drop(&mut self.guard) };
+    // logging reads the config, the lock has to be released first
+    if let Err(e) = saved {
+      LOGGER.log_error("CONFIG", &format!("Failed to auto-save config: {}", e));
     }
   }
 }
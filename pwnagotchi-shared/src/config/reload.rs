@@ -0,0 +1,123 @@
+//! Swapping the running config for a new one. Everything reading
+//! [`config_read`](super::config_read) sees the new values right away, parts
+//! that keep copies subscribe to [`ConfigChanged`] and refresh the sections
+//! they care about.
+
+use std::{
+  path::Path,
+  sync::{Arc, LazyLock},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use super::{
+  CONFIG_PATH, Config, ValidationError, config_lock,
+  editor::{self, Change, Edits},
+  layers,
+};
+use crate::logger::LOGGER;
+
+static CHANGES: LazyLock<broadcast::Sender<Arc<ConfigChanged>>> =
+  LazyLock::new(|| broadcast::channel(16).0);
+
+/// Sent whenever the running config changes, also emitted to plugins as the
+/// `config_changed` event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfigChanged {
+  /// JSON pointers of every setting that changed, like `/personality/deauth`.
+  pub keys: Vec<String>,
+}
+
+impl ConfigChanged {
+  /// Whether `key` or anything below it changed.
+  pub fn touches(&self, key: &str) -> bool {
+    let key = key.trim_end_matches('/');
+    self.keys.iter().any(|changed| {
+      changed
+        .strip_prefix(key)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    })
+  }
+}
+
+pub fn subscribe() -> broadcast::Receiver<Arc<ConfigChanged>> {
+  CHANGES.subscribe()
+}
+
+/// Pointers of the settings that differ between `old` and `new`.
+pub fn changed_keys(old: &Config, new: &Config) -> Vec<String> {
+  let (Ok(old), Ok(new)) = (serde_json::to_value(old), serde_json::to_value(new)) else {
+    return Vec::new();
+  };
+  let mut changes = Vec::new();
+  editor::diff("", &old, &new, &mut changes);
+  changes.into_iter().map(|c| c.path).collect()
+}
+
+/// Replaces the running config with `new` and tells subscribers what
+/// changed. Nothing is written to disk.
+pub fn replace(new: Config) -> Vec<String> {
+  let mut guard = config_lock().write();
+  let keys = changed_keys(&guard, &new);
+  if keys.is_empty() {
+    return keys;
+  }
+  *guard = new;
+  drop(guard);
+
+  notify(&keys);
+  keys
+}
+
+/// Why [`edit`] refused or failed to apply a set of edits.
+#[derive(Debug)]
+pub enum EditError {
+  /// The edits don't validate, nothing changed.
+  Invalid(Vec<ValidationError>),
+  /// The edits are running but couldn't be written to the user file.
+  Save(String),
+}
+
+/// Applies `edits` to the running config and saves the result. The config
+/// stays locked from reading it until it is saved, so a write in between
+/// can't be lost.
+pub fn edit(edits: &Edits) -> Result<Vec<Change>, EditError> {
+  let mut guard = config_lock().write();
+  let (updated, changes) = editor::apply(&guard, edits).map_err(EditError::Invalid)?;
+  if changes.is_empty() {
+    return Ok(changes);
+  }
+
+  let keys = changed_keys(&guard, &updated);
+  *guard = updated;
+  let saved = layers::save(&guard);
+  drop(guard);
+
+  notify(&keys);
+  saved.map(|()| changes).map_err(EditError::Save)
+}
+
+fn notify(keys: &[String]) {
+  if !keys.is_empty() {
+    let _ = CHANGES.send(Arc::new(ConfigChanged { keys: keys.to_vec() }));
+  }
+}
+
+/// Reads the config file and its layers again. A config that doesn't parse
+/// or validate is refused and the running one is kept.
+pub fn reload() -> Result<Vec<String>, String> {
+  let path = CONFIG_PATH.get().ok_or("config path not set")?;
+  let layered = layers::load(Path::new(path))?;
+  if !layered.checked.is_ok() {
+    let errors = layered.checked.errors.iter().map(ToString::to_string).collect::<Vec<_>>();
+    return Err(format!("invalid config: {}", errors.join(", ")));
+  }
+
+  layers::install(layered.stack);
+  let keys = replace(layered.checked.config);
+  if !keys.is_empty() {
+    LOGGER.log_info("CONFIG", &format!("Reloaded {path}, changed: {}", keys.join(", ")));
+  }
+  Ok(keys)
+}
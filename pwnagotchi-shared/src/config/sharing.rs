@@ -0,0 +1,33 @@
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct SharingConfig {
+  /// Pool handshakes with trusted units.
+  pub enabled: bool,
+  /// Fingerprints of the units allowed to fetch our captures and whose
+  /// captures we accept.
+  pub trusted: Vec<Cow<'static, str>>,
+  /// Web UI addresses of trusted units to pull from, e.g.
+  /// `http://10.0.0.2:8080`.
+  pub peers: Vec<Cow<'static, str>>,
+  /// Seconds between pulls.
+  pub interval: u64,
+  /// Signed requests and inventories older or newer than this many seconds
+  /// are rejected.
+  pub max_clock_skew: i64,
+}
+
+impl Default for SharingConfig {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      trusted: Vec::new(),
+      peers: Vec::new(),
+      interval: 600,
+      max_clock_skew: 300,
+    }
+  }
+}
@@ -0,0 +1,23 @@
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct SystemConfig {
+  pub shutdown_cmd: Cow<'static, str>,
+  pub reboot_cmd: Cow<'static, str>,
+  pub restart_cmd: Cow<'static, str>,
+  pub marker_dir: Cow<'static, str>,
+}
+
+impl Default for SystemConfig {
+  fn default() -> Self {
+    Self {
+      shutdown_cmd: "systemctl poweroff".into(),
+      reboot_cmd: "systemctl reboot".into(),
+      restart_cmd: "systemctl restart pwnagotchi".into(),
+      marker_dir: "/root".into(),
+    }
+  }
+}
@@ -0,0 +1,197 @@
+use std::fmt::Display;
+
+use serde::Serialize;
+
+use super::{Config, LogSinkConfig};
+use crate::{
+  logger::{LogLevel, sinks::syslog::facility},
+  models::agent::RunningMode,
+};
+
+/// A value that deserializes fine but can't work, `path` is a JSON pointer
+/// into the config.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ValidationError {
+  pub path: String,
+  pub message: String,
+}
+
+impl ValidationError {
+  pub fn new(path: impl Into<String>, message: impl Into<String>) -> Self {
+    Self {
+      path: path.into(),
+      message: message.into(),
+    }
+  }
+}
+
+impl Display for ValidationError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}: {}", self.path, self.message)
+  }
+}
+
+/// 2.4GHz and 5GHz channels a card could support.
+const fn is_wifi_channel(channel: u8) -> bool {
+  matches!(channel, 1..=14 | 32..=177)
+}
+
+fn check_rssi(errors: &mut Vec<ValidationError>, path: &str, rssi: i16) {
+  if !(-200..=0).contains(&rssi) {
+    errors.push(ValidationError::new(path, format!("{rssi} dBm is not between -200 and 0")));
+  }
+}
+
+fn check_port(errors: &mut Vec<ValidationError>, path: &str, port: u16) {
+  if port == 0 {
+    errors.push(ValidationError::new(path, "port must not be 0"));
+  }
+}
+
+fn check_not_empty(errors: &mut Vec<ValidationError>, path: &str, value: &str) {
+  if value.trim().is_empty() {
+    errors.push(ValidationError::new(path, "must not be empty"));
+  }
+}
+
+fn check_sink(errors: &mut Vec<ValidationError>, path: &str, sink: &LogSinkConfig) {
+  if let Err(e) = sink.level.parse::<LogLevel>() {
+    errors.push(ValidationError::new(format!("{path}/level"), e));
+  }
+
+  match sink.kind.as_ref() {
+    "journald" => {}
+    "syslog" => {
+      let port = sink.address.rsplit_once(':').and_then(|(host, port)| {
+        port.parse::<u16>().ok().filter(|port| !host.is_empty() && *port != 0)
+      });
+      if port.is_none() {
+        errors.push(ValidationError::new(format!("{path}/address"), "must be host:port"));
+      }
+      if !matches!(sink.protocol.as_ref(), "udp" | "tcp") {
+        errors.push(ValidationError::new(format!("{path}/protocol"), "must be udp or tcp"));
+      }
+      if facility(&sink.facility).is_none() {
+        errors.push(ValidationError::new(
+          format!("{path}/facility"),
+          format!("unknown facility {}", sink.facility),
+        ));
+      }
+      // a datagram that nobody receives doesn't fail, nothing would be spooled
+      if !sink.spool.is_empty() && sink.protocol == "udp" {
+        errors.push(ValidationError::new(format!("{path}/spool"), "needs the tcp protocol"));
+      }
+      if !sink.spool.is_empty() && sink.spool_bytes().is_none() {
+        errors.push(ValidationError::new(format!("{path}/spool_size"), "must be a size like 1M"));
+      }
+      if sink.retry_interval == 0 {
+        errors.push(ValidationError::new(format!("{path}/retry_interval"), "must not be 0"));
+      }
+    }
+    kind => errors.push(ValidationError::new(
+      format!("{path}/kind"),
+      format!("unknown sink {kind}, use syslog or journald"),
+    )),
+  }
+}
+
+impl Config {
+  /// Checks values the types alone don't restrict. Returns every problem
+  /// found, an empty list means the config is usable.
+  pub fn validate(&self) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+
+    check_not_empty(&mut errors, "/main/name", &self.main.name);
+    check_not_empty(&mut errors, "/main/iface", &self.main.iface);
+    if let Err(e) = self.main.mode.parse::<RunningMode>() {
+      errors.push(ValidationError::new("/main/mode", e));
+    }
+
+    for (i, channel) in self.personality.channels.iter().enumerate() {
+      if !is_wifi_channel(*channel) {
+        errors.push(ValidationError::new(
+          format!("/personality/channels/{i}"),
+          format!("{channel} is not a wifi channel"),
+        ));
+      }
+    }
+    check_rssi(&mut errors, "/personality/min_rssi", self.personality.min_rssi);
+    check_rssi(&mut errors, "/grid/cooperate_rssi", self.grid.cooperate_rssi);
+    for (path, throttle) in [
+      ("/personality/throttle_a", self.personality.throttle_a),
+      ("/personality/throttle_d", self.personality.throttle_d),
+    ] {
+      if throttle < 0.0 {
+        errors.push(ValidationError::new(path, "must not be negative"));
+      }
+    }
+    if self.personality.sad_num_epochs <= self.personality.bored_num_epochs {
+      errors.push(ValidationError::new(
+        "/personality/sad_num_epochs",
+        "must be greater than bored_num_epochs",
+      ));
+    }
+
+    check_not_empty(&mut errors, "/bettercap/hostname", &self.bettercap.hostname);
+    check_port(&mut errors, "/bettercap/port", self.bettercap.port);
+    check_port(&mut errors, "/ui/web/port", self.ui.web.port);
+
+    if !self.ui.fps.is_finite() || self.ui.fps < 0.0 {
+      errors.push(ValidationError::new("/ui/fps", "must be a positive number"));
+    }
+    if !matches!(self.ui.display.rotation, 0 | 90 | 180 | 270) {
+      errors.push(ValidationError::new("/ui/display/rotation", "must be 0, 90, 180 or 270"));
+    }
+
+    for (i, fingerprint) in self.sharing.trusted.iter().enumerate() {
+      if fingerprint.len() != 64 || !fingerprint.chars().all(|c| c.is_ascii_hexdigit()) {
+        errors.push(ValidationError::new(
+          format!("/sharing/trusted/{i}"),
+          "must be a hex encoded sha256 fingerprint",
+        ));
+      }
+    }
+    for (i, peer) in self.sharing.peers.iter().enumerate() {
+      if !peer.starts_with("http://") && !peer.starts_with("https://") {
+        errors.push(ValidationError::new(format!("/sharing/peers/{i}"), "must be an http(s) URL"));
+      }
+    }
+
+    if self.log.rotation.enabled && self.log.rotation.max_bytes().is_none() {
+      errors.push(ValidationError::new("/log/rotation/size", "must be a size like 10M"));
+    }
+    for (target, level) in &self.log.filters {
+      if let Err(e) = level.parse::<LogLevel>() {
+        errors.push(ValidationError::new(format!("/log/filters/{target}"), e));
+      }
+    }
+    for (i, sink) in self.log.sinks.iter().enumerate() {
+      check_sink(&mut errors, &format!("/log/sinks/{i}"), sink);
+    }
+
+    if self.fs.memory.enabled {
+      check_not_empty(&mut errors, "/fs/memory/disk", &self.fs.memory.disk);
+      for (name, mount) in [
+        ("log", &self.fs.memory.mounts.log),
+        ("data", &self.fs.memory.mounts.data),
+      ] {
+        if !mount.enabled {
+          continue;
+        }
+        check_not_empty(&mut errors, &format!("/fs/memory/mounts/{name}/mount"), &mount.mount);
+        if mount.size_bytes().is_none() {
+          errors.push(ValidationError::new(
+            format!("/fs/memory/mounts/{name}/size"),
+            "must be a size like 50M",
+          ));
+        }
+      }
+    }
+
+    if self.watchdog.enabled && self.watchdog.interval == 0 {
+      errors.push(ValidationError::new("/watchdog/interval", "must not be 0"));
+    }
+
+    errors
+  }
+}
@@ -0,0 +1,33 @@
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct WatchdogConfig {
+  pub enabled: bool,
+  pub device: Cow<'static, str>,
+  /// Seconds between liveness checks, must stay below the hardware timeout.
+  pub interval: u64,
+  /// Seconds without a new epoch before the unit is considered stuck, 0
+  /// disables the check.
+  pub max_epoch_stall: u64,
+  /// Seconds without a healthy bettercap session before the unit is
+  /// considered stuck, 0 disables the check.
+  pub max_bettercap_stall: u64,
+  /// Disarm the watchdog with the magic close character on a clean exit.
+  pub disarm_on_exit: bool,
+}
+
+impl Default for WatchdogConfig {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      device: "/dev/watchdog".into(),
+      interval: 10,
+      max_epoch_stall: 600,
+      max_bettercap_stall: 180,
+      disarm_on_exit: true,
+    }
+  }
+}
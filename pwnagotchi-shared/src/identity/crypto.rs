@@ -0,0 +1,89 @@
+//! Message encryption compatible with pwngrid. A random AES-256-GCM key
+//! encrypts the message, the key itself is encrypted for the recipient with
+//! RSA-OAEP and everything is packed as
+//! `u32 nonce_len | nonce | u32 key_len | encrypted key | ciphertext`
+//! with little endian lengths.
+
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce, aead::Aead};
+use anyhow::{Result, anyhow};
+use base64::{Engine, engine::general_purpose};
+use rsa::{
+  Oaep, Pss, RsaPublicKey,
+  pkcs8::DecodePublicKey,
+  rand_core::{OsRng, RngCore},
+  traits::SignatureScheme,
+};
+use sha2::{Digest, Sha256};
+
+use super::{Identity, header_fix};
+
+const KEY_SIZE: usize = 32;
+const NONCE_SIZE: usize = 12;
+
+/// Parses a public key the way units publish it, a base64 encoded PEM.
+pub fn public_key_from_b64(pem_b64: &str) -> Result<RsaPublicKey> {
+  let pem = general_purpose::STANDARD.decode(pem_b64.trim())?;
+  let pem = String::from_utf8(pem)?;
+  Ok(RsaPublicKey::from_public_key_pem(&header_fix(&pem))?)
+}
+
+/// Encrypts `cleartext` so only the owner of `recipient` can read it.
+pub fn encrypt_for(cleartext: &[u8], recipient: &RsaPublicKey) -> Result<Vec<u8>> {
+  let mut key = [0u8; KEY_SIZE];
+  let mut nonce = [0u8; NONCE_SIZE];
+  OsRng.fill_bytes(&mut key);
+  OsRng.fill_bytes(&mut nonce);
+
+  let cipher = Aes256Gcm::new_from_slice(&key)?;
+  let ciphertext = cipher
+    .encrypt(Nonce::from_slice(&nonce), cleartext)
+    .map_err(|e| anyhow!("encryption failed: {e}"))?;
+  let enc_key = recipient.encrypt(&mut OsRng, Oaep::new::<Sha256>(), &key)?;
+
+  let mut packed = Vec::with_capacity(8 + nonce.len() + enc_key.len() + ciphertext.len());
+  for part in [&nonce[..], &enc_key] {
+    packed.extend_from_slice(&u32::try_from(part.len())?.to_le_bytes());
+    packed.extend_from_slice(part);
+  }
+  packed.extend_from_slice(&ciphertext);
+  Ok(packed)
+}
+
+/// Checks a base64 signature made by [`Identity::sign_data`].
+pub fn verify(key: &RsaPublicKey, data: &[u8], signature_b64: &str) -> bool {
+  let Ok(signature) = general_purpose::STANDARD.decode(signature_b64.trim()) else {
+    return false;
+  };
+
+  let hashed = Sha256::digest(data);
+  Pss::new_with_salt::<Sha256>(16).verify(key, &hashed, &signature).is_ok()
+}
+
+impl Identity {
+  /// Decrypts a message packed by [`encrypt_for`] with our private key.
+  pub fn decrypt(&self, packed: &[u8]) -> Result<Vec<u8>> {
+    let key = self.priv_key.as_ref().ok_or_else(|| anyhow!("private key not loaded"))?;
+
+    let (nonce, rest) = split_part(packed)?;
+    let (enc_key, ciphertext) = split_part(rest)?;
+    if nonce.len() != NONCE_SIZE {
+      return Err(anyhow!("unexpected nonce size {}", nonce.len()));
+    }
+
+    let aes_key = key.decrypt(Oaep::new::<Sha256>(), enc_key)?;
+    let cipher = Aes256Gcm::new_from_slice(&aes_key)?;
+    cipher
+      .decrypt(Nonce::from_slice(nonce), ciphertext)
+      .map_err(|e| anyhow!("decryption failed: {e}"))
+  }
+}
+
+/// Splits a length prefixed part off the front of `data`.
+fn split_part(data: &[u8]) -> Result<(&[u8], &[u8])> {
+  let (len, rest) = data.split_first_chunk::<4>().ok_or_else(|| anyhow!("message truncated"))?;
+  let len = usize::try_from(u32::from_le_bytes(*len))?;
+  if rest.len() < len {
+    return Err(anyhow!("message truncated"));
+  }
+  Ok(rest.split_at(len))
+}
@@ -1,7 +1,9 @@
+pub mod crypto;
+
 use std::{
-  error::Error,
-  path::{self},
-  process::Command,
+  io::Write,
+  os::unix::fs::{OpenOptionsExt, PermissionsExt},
+  path::{self, PathBuf},
   sync::Arc,
 };
 
@@ -10,10 +12,14 @@ use base64::{Engine, engine::general_purpose};
 use hex::ToHex;
 use parking_lot::RwLock;
 use rsa::{
-  self, Pss, RsaPrivateKey, RsaPublicKey, pkcs1::DecodeRsaPrivateKey, pkcs8::DecodePublicKey,
-  rand_core::OsRng, traits::SignatureScheme,
+  self, Pss, RsaPrivateKey, RsaPublicKey,
+  pkcs1::{DecodeRsaPrivateKey, EncodeRsaPrivateKey},
+  pkcs8::{DecodePublicKey, EncodePublicKey, LineEnding},
+  rand_core::OsRng,
+  traits::SignatureScheme,
 };
 use sha2::{Digest, Sha256};
+use time::OffsetDateTime;
 use tokio::task::JoinHandle;
 
 use crate::{
@@ -25,6 +31,9 @@ use crate::{
   },
 };
 
+/// Key size pwngrid generates.
+pub const KEY_BITS: usize = 4096;
+
 pub struct IdentityComponent {
   identity: Option<Arc<RwLock<Identity>>>,
 }
@@ -93,8 +102,13 @@ impl CoreModule for Identity {
 
 impl Identity {
   pub fn new() -> Self {
+    Self::with_path(config_read().debug.identity_path.as_ref())
+  }
+
+  /// An identity stored in `path` instead of the configured directory.
+  pub fn with_path(path: &str) -> Self {
     // Ensure we handle handle paths consistently
-    let path = config_read().debug.identity_path.to_string().trim_end_matches('/').to_string();
+    let path = path.trim_end_matches('/').to_string();
     let priv_path = format!("{path}/id_rsa");
     let pub_path = format!("{priv_path}.pub");
     let fingerprint_path = format!("{path}/fingerprint");
@@ -111,56 +125,131 @@ impl Identity {
   }
 
   pub fn initialize(&mut self, view: Arc<dyn ViewTrait + Send + Sync>) {
-    if !path::Path::new(&self.path).exists()
-      && let Err(e) = std::fs::create_dir_all(&self.path)
-    {
+    if let Err(e) = create_private_dir(&self.path) {
       LOGGER.log_fatal(
         "IDENTITY",
-        &format!("Failed to create identity directory {:?}: {e}", &self.path),
+        &format!("Failed to create identity directory {:?}: {e}", self.path),
       );
       LOGGER.log_error("IDENTITY", "Using temporary identity...");
       self.path = "/tmp/pwnagotchi-identity".to_string();
-      self.priv_path = format!("{}/id_rsa", &self.path);
-      self.pub_path = format!("{}/id_rsa.pub", &self.path);
-      self.fingerprint_path = format!("{}/fingerprint", &self.path);
-      let _ = std::fs::create_dir_all(&self.path);
+      self.priv_path = format!("{}/id_rsa", self.path);
+      self.pub_path = format!("{}/id_rsa.pub", self.path);
+      self.fingerprint_path = format!("{}/fingerprint", self.path);
+      let _ = create_private_dir(&self.path);
     }
 
-    loop {
-      match self.try_load_keys() {
-        Ok(()) => {
-          break;
-        }
-        Err(e) => {
-          LOGGER.log_debug("IDENTITY", &format!("Failed to load Keys: {e}"));
-          // Warning because this might be the first run
-          LOGGER.log_warning("IDENTITY", "Couldn't load Keys. Regenerating...");
-          view.on_keys_generation();
-
-          let _ = Command::new("pwngrid").arg("-generate").arg("-keys").arg(&self.path).status();
-        }
-      }
+    let Err(e) = self.load_keys() else {
+      return;
+    };
+
+    LOGGER.log_debug("IDENTITY", &format!("Failed to load Keys: {e}"));
+    // Warning because this might be the first run
+    LOGGER.log_warning("IDENTITY", "Couldn't load Keys. Regenerating...");
+    view.on_keys_generation();
+
+    match self.generate_keys(KEY_BITS) {
+      Ok(Some(backup)) => LOGGER
+        .log_warning("IDENTITY", &format!("Unreadable keys were moved to {}", backup.display())),
+      Ok(None) => {}
+      // The keys are still usable for this run, they just won't survive a reboot
+      Err(e) => LOGGER.log_error("IDENTITY", &format!("Failed to save keys: {e}")),
+    }
 
-      if self.priv_key.is_some() && self.pub_key.is_some() {
-        break;
-      }
+    LOGGER.log_info("IDENTITY", &format!("Generated identity {}", self.fingerprint()));
+    view.on_starting();
+  }
+
+  /// Loads the keypair from the identity directory.
+  pub fn load_keys(&mut self) -> Result<()> {
+    let priv_key = RsaPrivateKey::read_pkcs1_pem_file(&self.priv_path)?;
+    let pub_pem = &std::fs::read_to_string(&self.pub_path)?;
+    let pub_key = RsaPublicKey::from_public_key_pem(&header_fix(pub_pem))?;
+
+    if pub_key != RsaPublicKey::from(&priv_key) {
+      return Err(anyhow!("{} does not belong to {}", self.pub_path, self.priv_path));
+    }
+
+    self.set_keys(priv_key, pub_key, pub_pem);
+    std::fs::write(&self.fingerprint_path, self.fingerprint())?;
+    Ok(())
+  }
+
+  /// Generates a new keypair the way pwngrid does and writes it to the
+  /// identity directory. Keys already there are moved to a `backup-<time>`
+  /// directory first, its path is returned.
+  ///
+  /// The new keys are in use even if writing them fails.
+  pub fn generate_keys(&mut self, bits: usize) -> Result<Option<PathBuf>> {
+    let priv_key = RsaPrivateKey::new(&mut OsRng, bits)?;
+    let pub_key = RsaPublicKey::from(&priv_key);
+    let priv_pem = priv_key.to_pkcs1_pem(LineEnding::LF)?;
+    let pub_pem = header_unfix(&pub_key.to_public_key_pem(LineEnding::LF)?);
+
+    self.set_keys(priv_key, pub_key, &pub_pem);
+
+    create_private_dir(&self.path)?;
+    let backup = self.backup_keys()?;
+    write_with_mode(&self.priv_path, priv_pem.as_bytes(), 0o600)?;
+    write_with_mode(&self.pub_path, pub_pem.as_bytes(), 0o644)?;
+    write_with_mode(&self.fingerprint_path, self.fingerprint().as_bytes(), 0o644)?;
+    Ok(backup)
+  }
 
-      std::thread::sleep(std::time::Duration::from_secs(5));
-      view.on_starting();
+  /// Moves the key files out of the way, `None` if there were none.
+  fn backup_keys(&self) -> Result<Option<PathBuf>> {
+    let files = [
+      &self.priv_path,
+      &self.pub_path,
+      &self.fingerprint_path,
+    ]
+    .into_iter()
+    .map(path::Path::new)
+    .filter(|p| p.exists())
+    .collect::<Vec<_>>();
+    if files.is_empty() {
+      return Ok(None);
     }
+
+    let stamp = OffsetDateTime::now_utc().unix_timestamp();
+    let mut dir = path::Path::new(&self.path).join(format!("backup-{stamp}"));
+    let mut n = 1;
+    while dir.exists() {
+      dir = path::Path::new(&self.path).join(format!("backup-{stamp}-{n}"));
+      n += 1;
+    }
+
+    create_private_dir(&dir)?;
+    for file in files {
+      if let Some(name) = file.file_name() {
+        std::fs::rename(file, dir.join(name))?;
+      }
+    }
+    Ok(Some(dir))
+  }
+
+  /// Builds an identity around an existing key without touching the
+  /// identity directory.
+  pub fn from_private_key(priv_key: RsaPrivateKey) -> Result<Self> {
+    let pub_key = RsaPublicKey::from(&priv_key);
+    let pem = pub_key.to_public_key_pem(LineEnding::LF)?;
+
+    let mut identity = Self::new();
+    identity.set_keys(priv_key, pub_key, &header_unfix(&pem));
+    Ok(identity)
   }
 
   pub fn fingerprint(&self) -> &str {
     self.fingerprint.as_deref().unwrap_or("unknown")
   }
 
-  fn try_load_keys(&mut self) -> Result<(), Box<dyn Error>> {
-    let priv_key = RsaPrivateKey::read_pkcs1_pem_file(&self.priv_path)?;
-    let pub_pem = &std::fs::read_to_string(&self.pub_path)?;
-    let pub_key = RsaPublicKey::from_public_key_pem(&header_fix(pub_pem))?;
+  /// The public key PEM, base64 encoded the way pwngrid expects it.
+  pub fn public_key_b64(&self) -> Option<&str> {
+    self.pubkey_pem_b64.as_deref()
+  }
 
+  fn set_keys(&mut self, priv_key: RsaPrivateKey, pub_key: RsaPublicKey, pub_pem: &str) {
     self.priv_key = Some(priv_key);
-    self.pub_key = Some(pub_key.clone());
+    self.pub_key = Some(pub_key);
 
     // Oh my god who the fuck thought "hmm yes, the header should have
     // direct influence on the fingerprint"
@@ -171,15 +260,7 @@ impl Identity {
 
     let digest = Sha256::digest(pem).encode_hex::<String>();
 
-    self.fingerprint = Some(digest.to_string());
-
-    if let Some(fingerprint) = &self.fingerprint {
-      std::fs::write(&self.fingerprint_path, fingerprint)?;
-    } else {
-      return Err("Fingerprint not generated".into());
-    }
-
-    Ok(())
+    self.fingerprint = Some(digest);
   }
 
   /// Signs a message using the loaded private key and returns the signature as
@@ -189,26 +270,59 @@ impl Identity {
   /// Returns an error if the private key is not loaded or if the signing
   /// operation fails.
   pub fn sign(&self, message: &str) -> Result<String, String> {
+    self.sign_data(message.as_bytes())
+  }
+
+  /// Like [`Identity::sign`], for binary payloads such as encrypted messages.
+  ///
+  /// # Errors
+  /// Returns an error if the private key is not loaded or if the signing
+  /// operation fails.
+  pub fn sign_data(&self, data: &[u8]) -> Result<String, String> {
     let key = self.priv_key.as_ref().ok_or("Private key not loaded")?;
 
-    let hashed = Sha256::digest(message.as_bytes());
+    let hashed = Sha256::digest(data);
     let signer = Pss::new_with_salt::<Sha256>(16);
 
     let signature = signer
       .sign(Some(&mut OsRng), key, &hashed)
       .map_err(|e| format!("Signing failed: {e}"))?;
 
-    let signature_b64 = general_purpose::STANDARD.encode(&signature).to_string();
-    let _signature_str = general_purpose::STANDARD
-      .decode(&signature_b64)
-      .map_err(|e| format!("Base64 decoding failed: {e}"))?;
-
-    Ok(signature_b64)
+    Ok(general_purpose::STANDARD.encode(&signature))
   }
 }
 
+/// pwngrid writes SPKI keys with a PKCS#1 header.
+fn header_unfix(pubkey: &str) -> String {
+  pubkey
+    .replace("-----BEGIN PUBLIC KEY-----", "-----BEGIN RSA PUBLIC KEY-----")
+    .replace("-----END PUBLIC KEY-----", "-----END RSA PUBLIC KEY-----")
+}
+
 fn header_fix(pubkey: &str) -> String {
   pubkey
     .replace("-----BEGIN RSA PUBLIC KEY-----", "-----BEGIN PUBLIC KEY-----")
     .replace("-----END RSA PUBLIC KEY-----", "-----END PUBLIC KEY-----")
 }
+
+/// Creates `dir` readable by the owner only, existing directories are left
+/// alone since the identity may share one with the config.
+fn create_private_dir<P: AsRef<path::Path>>(dir: P) -> std::io::Result<()> {
+  if dir.as_ref().exists() {
+    return Ok(());
+  }
+  std::fs::create_dir_all(&dir)?;
+  std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))
+}
+
+fn write_with_mode(path: &str, content: &[u8], mode: u32) -> std::io::Result<()> {
+  let mut file = std::fs::OpenOptions::new()
+    .write(true)
+    .create(true)
+    .truncate(true)
+    .mode(mode)
+    .open(path)?;
+  file.write_all(content)?;
+  // `mode` only applies to new files
+  file.set_permissions(std::fs::Permissions::from_mode(mode))
+}
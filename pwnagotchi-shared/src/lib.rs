@@ -1,6 +1,8 @@
 pub mod ai;
+pub mod backup;
 pub mod config;
 pub mod identity;
+pub mod live;
 pub mod logger;
 pub mod voice;
 
@@ -18,6 +20,7 @@ pub mod traits {
 }
 
 pub mod types {
+  pub mod components;
   pub mod epoch;
   pub mod events;
   pub mod grid;
@@ -26,6 +29,7 @@ pub mod types {
 }
 
 pub mod sessions {
+  pub mod journal;
   pub mod lastsession;
   pub mod manager;
   pub mod recovery;
@@ -36,6 +40,8 @@ pub mod sessions {
 
 pub mod mesh {
   pub mod peer;
+  pub mod peerdb;
+  pub mod sharing;
 }
 
 pub mod models {
@@ -53,3 +59,20 @@ pub mod utils {
   pub mod hooks;
   pub mod wifi;
 }
+
+#[cfg(test)]
+pub mod tests {
+  pub mod backup;
+  pub mod config_check;
+  pub mod config_editor;
+  pub mod config_layers;
+  pub mod config_reload;
+  pub mod identity;
+  pub mod journal;
+  pub mod live;
+  pub mod log_rotation;
+  pub mod log_sinks;
+  pub mod logger;
+  pub mod peerdb;
+  pub mod sharing;
+}
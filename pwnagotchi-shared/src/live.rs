@@ -0,0 +1,76 @@
+//! Real time events for web clients. Anything that changes what the unit
+//! shows is published here and streamed to browsers by the web UI, so they
+//! don't have to poll.
+
+use std::sync::{Arc, LazyLock};
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::{mesh::peer::Peer, traits::epoch::EpochData};
+
+/// Events a slow client may fall behind by before it starts missing some.
+const CAPACITY: usize = 256;
+
+static HUB: LazyLock<broadcast::Sender<Arc<LiveEvent>>> =
+  LazyLock::new(|| broadcast::channel(CAPACITY).0);
+
+#[derive(Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LiveEvent {
+  /// A view element changed its value.
+  State {
+    key: String,
+    value: String,
+  },
+  /// An epoch finished, `data` describes it.
+  Epoch {
+    epoch: u32,
+    data: EpochData,
+  },
+  Handshake {
+    ap: String,
+    station: String,
+    file: String,
+  },
+  PeerNew {
+    peer: Box<Peer>,
+  },
+  PeerLost {
+    peer: Box<Peer>,
+  },
+  /// The display was redrawn, `png` is the base64 encoded frame.
+  Frame {
+    png: String,
+  },
+}
+
+impl LiveEvent {
+  /// Name used for the event in the stream.
+  pub const fn name(&self) -> &'static str {
+    match self {
+      Self::State { .. } => "state",
+      Self::Epoch { .. } => "epoch",
+      Self::Handshake { .. } => "handshake",
+      Self::PeerNew { .. } => "peer_new",
+      Self::PeerLost { .. } => "peer_lost",
+      Self::Frame { .. } => "frame",
+    }
+  }
+}
+
+/// Sends `event` to every connected client, a no-op without any.
+pub fn publish(event: LiveEvent) {
+  if has_subscribers() {
+    let _ = HUB.send(Arc::new(event));
+  }
+}
+
+pub fn subscribe() -> broadcast::Receiver<Arc<LiveEvent>> {
+  HUB.subscribe()
+}
+
+/// Lets publishers skip building expensive events nobody will see.
+pub fn has_subscribers() -> bool {
+  HUB.receiver_count() > 0
+}
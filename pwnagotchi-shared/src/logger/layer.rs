@@ -0,0 +1,262 @@
+//! The `tracing` side of the logger. Entries from [`LOGGER`](super::LOGGER),
+//! the `tracing` macros and crates using `log` all end up in [`LogLayer`],
+//! which writes them in the format the log files always had, with the fields
+//! of the spans they happened in, like `[epoch=12 plugin=grid]`, and hands
+//! them to the [`Sinks`].
+
+use std::{
+  fmt::Debug,
+  sync::{
+    Arc, LazyLock,
+    atomic::{AtomicBool, Ordering},
+  },
+};
+
+use parking_lot::RwLock;
+use tracing::{
+  Event, Subscriber,
+  field::{Field, Visit},
+  span::{Attributes, Id, Record},
+};
+use tracing_log::{LogTracer, NormalizeEvent, log::LevelFilter};
+use tracing_subscriber::{
+  Layer, Registry,
+  layer::{Context, SubscriberExt},
+  registry::LookupSpan,
+};
+
+use super::{LogFiles, LogLevel, format_entry, sinks::Sinks};
+use crate::config::LogConfig;
+
+/// Target of the events [`LOGGER`](super::LOGGER) sends.
+pub const TARGET: &str = "pwnagotchi";
+
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+static FILTERS: LazyLock<RwLock<Filters>> = LazyLock::new(|| RwLock::new(Filters::default()));
+
+/// Lowest levels logged per origin, plugin or target, from `log.filters`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Filters {
+  /// Lowercase keys, invalid levels are left out.
+  levels: Vec<(String, LogLevel)>,
+}
+
+impl Filters {
+  pub fn from_config(config: &LogConfig) -> Self {
+    let levels = config
+      .filters
+      .iter()
+      .filter_map(|(key, level)| Some((key.to_ascii_lowercase(), level.parse().ok()?)))
+      .collect();
+    Self { levels }
+  }
+
+  /// The lowest level logged for an event. A filter for its origin, like
+  /// `bettercap`, wins over one for the plugin it ran in, like `plugin::grid`,
+  /// which wins over the longest matching target, like
+  /// `pwnagotchi_core::agent`. Everything of the unit is logged by default,
+  /// other crates from info.
+  pub fn level(&self, origin: Option<&str>, plugin: Option<&str>, target: &str) -> LogLevel {
+    let get = |key: &str| {
+      self
+        .levels
+        .iter()
+        .find(|(k, _)| k.as_str() == key.to_ascii_lowercase())
+        .map(|(_, l)| *l)
+    };
+
+    if let Some(level) = origin.and_then(get) {
+      return level;
+    }
+    if let Some(level) = plugin.and_then(|plugin| get(&format!("plugin::{plugin}"))) {
+      return level;
+    }
+
+    let target = target.to_ascii_lowercase();
+    let by_target = self
+      .levels
+      .iter()
+      .filter(|(key, _)| {
+        target
+          .strip_prefix(key.as_str())
+          .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+      })
+      .max_by_key(|(key, _)| key.len());
+    match by_target {
+      Some((_, level)) => *level,
+      None if target.starts_with(TARGET) => LogLevel::Debug,
+      None => LogLevel::Info,
+    }
+  }
+}
+
+/// Installs the subscriber writing to `files` and `sinks` as the global
+/// default, and forwards `log` records to it. Does nothing if a subscriber is
+/// set already.
+pub fn install(files: Arc<LogFiles>, sinks: Arc<Sinks>, config: &LogConfig) {
+  set_filters(config);
+  if tracing::subscriber::set_global_default(subscriber(files, sinks)).is_ok() {
+    let _ = LogTracer::builder().with_max_level(LevelFilter::Debug).init();
+    INSTALLED.store(true, Ordering::Release);
+  }
+}
+
+/// A subscriber writing to `files` and `sinks`, filtered by the installed
+/// filters.
+pub fn subscriber(files: Arc<LogFiles>, sinks: Arc<Sinks>) -> impl Subscriber + Send + Sync {
+  Registry::default().with(LogLayer::new(files, sinks))
+}
+
+pub fn is_installed() -> bool {
+  INSTALLED.load(Ordering::Acquire)
+}
+
+/// Applies changed `log.filters` without a restart.
+pub fn set_filters(config: &LogConfig) {
+  *FILTERS.write() = Filters::from_config(config);
+}
+
+/// Writes events to the log files and sinks.
+pub struct LogLayer {
+  files: Arc<LogFiles>,
+  sinks: Arc<Sinks>,
+}
+
+impl LogLayer {
+  pub fn new(files: Arc<LogFiles>, sinks: Arc<Sinks>) -> Self {
+    Self { files, sinks }
+  }
+}
+
+impl<S> Layer<S> for LogLayer
+where
+  S: Subscriber + for<'a> LookupSpan<'a>,
+{
+  fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+    let Some(span) = ctx.span(id) else {
+      return;
+    };
+    let mut fields = SpanFields::default();
+    attrs.record(&mut fields);
+    span.extensions_mut().insert(fields);
+  }
+
+  fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+    let Some(span) = ctx.span(id) else {
+      return;
+    };
+    let mut extensions = span.extensions_mut();
+    if let Some(fields) = extensions.get_mut::<SpanFields>() {
+      values.record(fields);
+    }
+  }
+
+  fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+    let normalized = event.normalized_metadata();
+    let metadata = normalized.as_ref().unwrap_or_else(|| event.metadata());
+
+    let mut fields = EventFields::default();
+    event.record(&mut fields);
+
+    let mut context = Vec::new();
+    let mut plugin = None;
+    if let Some(scope) = ctx.event_scope(event) {
+      for span in scope.from_root() {
+        if let Some(span_fields) = span.extensions().get::<SpanFields>() {
+          for (name, value) in &span_fields.0 {
+            if name == "plugin" {
+              plugin = Some(value.clone());
+            }
+            context.push(format!("{name}={value}"));
+          }
+        }
+      }
+    }
+
+    let level = if fields.fatal { LogLevel::Fatal } else { LogLevel::from(*metadata.level()) };
+    let origin = fields.origin.take().filter(|o| !o.is_empty());
+    let threshold = FILTERS.read().level(origin.as_deref(), plugin.as_deref(), metadata.target());
+    if level < threshold {
+      return;
+    }
+
+    let origin = origin.unwrap_or_else(|| default_origin(metadata.target()));
+    let mut message = fields.message;
+    if !fields.extra.is_empty() {
+      message = format!("{message} {}", fields.extra.join(" "));
+    }
+    if !context.is_empty() {
+      message = format!("[{}] {message}", context.join(" "));
+    }
+    self.files.write(&format_entry(level, Some(&origin), &message), level);
+    self.sinks.send(level, Some(&origin), &message);
+  }
+}
+
+/// Module for events of the unit, like `PLUGIN_MANAGER`, the crate for
+/// anything else.
+fn default_origin(target: &str) -> String {
+  let name =
+    if target.starts_with(TARGET) { target.rsplit("::").next() } else { target.split("::").next() };
+  name.unwrap_or(target).to_ascii_uppercase()
+}
+
+/// Fields of a span, in the order they were recorded.
+#[derive(Debug, Default)]
+struct SpanFields(Vec<(String, String)>);
+
+impl SpanFields {
+  fn set(&mut self, name: &str, value: String) {
+    match self.0.iter_mut().find(|(n, _)| n == name) {
+      Some((_, v)) => *v = value,
+      None => self.0.push((name.to_string(), value)),
+    }
+  }
+}
+
+impl Visit for SpanFields {
+  fn record_str(&mut self, field: &Field, value: &str) {
+    self.set(field.name(), value.to_string());
+  }
+
+  fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+    self.set(field.name(), format!("{value:?}"));
+  }
+}
+
+#[derive(Debug, Default)]
+struct EventFields {
+  message: String,
+  origin: Option<String>,
+  fatal: bool,
+  extra: Vec<String>,
+}
+
+impl Visit for EventFields {
+  fn record_bool(&mut self, field: &Field, value: bool) {
+    if field.name() == "fatal" {
+      self.fatal = value;
+    } else {
+      self.record_debug(field, &value);
+    }
+  }
+
+  fn record_str(&mut self, field: &Field, value: &str) {
+    match field.name() {
+      "message" => self.message = value.to_string(),
+      "origin" => self.origin = Some(value.to_string()),
+      name if name.starts_with("log.") => {}
+      name => self.extra.push(format!("{name}={value}")),
+    }
+  }
+
+  fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+    match field.name() {
+      "message" => self.message = format!("{value:?}"),
+      "origin" => self.origin = Some(format!("{value:?}")),
+      // where a `log` record came from, already in the metadata
+      name if name.starts_with("log.") => {}
+      name => self.extra.push(format!("{name}={value:?}")),
+    }
+  }
+}
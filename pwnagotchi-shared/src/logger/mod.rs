@@ -1,10 +1,16 @@
+pub mod layer;
+pub mod rotate;
+pub mod sinks;
+
 use std::{
-  fs::{self, File, OpenOptions},
-  io::Write,
+  fs,
   path::Path,
-  sync::Mutex,
+  str::FromStr,
+  sync::{Arc, LazyLock, Mutex},
 };
 
+use rotate::{RotatingFile, Rotation};
+use sinks::Sinks;
 use time::OffsetDateTime;
 
 use crate::config::config_read;
@@ -18,14 +24,77 @@ pub enum LogLevel {
   Fatal = 4,
 }
 
-pub struct Log {
-  file: Mutex<File>,
-  debug_file: Mutex<File>,
+impl LogLevel {
+  fn label(self) -> &'static str {
+    match self {
+      Self::Debug => "DEBUG",
+      Self::Info => "INFO",
+      Self::Warning => "WARNING",
+      Self::Error => "ERROR",
+      Self::Fatal => "FATAL",
+    }
+  }
+
+  /// Syslog severity, also the journal's `PRIORITY`.
+  pub fn severity(self) -> u8 {
+    match self {
+      Self::Debug => 7,
+      Self::Info => 6,
+      Self::Warning => 4,
+      Self::Error => 3,
+      Self::Fatal => 2,
+    }
+  }
+}
+
+impl FromStr for LogLevel {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_ascii_lowercase().as_str() {
+      "trace" | "debug" => Ok(Self::Debug),
+      "info" => Ok(Self::Info),
+      "warn" | "warning" => Ok(Self::Warning),
+      "error" => Ok(Self::Error),
+      "fatal" => Ok(Self::Fatal),
+      _ => Err(format!("unknown log level {s}, use debug, info, warning, error or fatal")),
+    }
+  }
+}
+
+impl From<tracing::Level> for LogLevel {
+  fn from(level: tracing::Level) -> Self {
+    match level {
+      tracing::Level::TRACE | tracing::Level::DEBUG => Self::Debug,
+      tracing::Level::INFO => Self::Info,
+      tracing::Level::WARN => Self::Warning,
+      tracing::Level::ERROR => Self::Error,
+    }
+  }
+}
+
+/// `[time] [LEVEL] [ORIGIN] message`, the line format of the log files.
+pub fn format_entry(level: LogLevel, origin: Option<&str>, message: &str) -> String {
+  let time = OffsetDateTime::now_utc();
+  format!(
+    "[{}] [{}] {} {}\n",
+    time.format(&time::format_description::well_known::Rfc3339).unwrap(),
+    level.label(),
+    origin.map_or("".to_string(), |o| format!("[{}]", o)),
+    message
+  )
+}
+
+/// The log and debug log, shared by [`Log`] and the
+/// [`LogLayer`](layer::LogLayer) writing to them.
+pub struct LogFiles {
+  file: Mutex<RotatingFile>,
+  debug_file: Mutex<RotatingFile>,
   has_handle: bool,
 }
 
-impl Log {
-  pub fn new(path: &str, debug_path: &str) -> Self {
+impl LogFiles {
+  pub fn open(path: &str, debug_path: &str, rotation: Option<Rotation>) -> Self {
     let p = Path::new(path);
     if let Some(parent) = p.parent()
       && !parent.exists()
@@ -35,19 +104,19 @@ impl Log {
       eprintln!("Logging to /dev/null instead");
     }
 
-    let file = OpenOptions::new().create(true).append(true).open(path);
-    let debug_file = OpenOptions::new().create(true).append(true).open(debug_path);
+    let file = RotatingFile::open(path, rotation);
+    let debug_file = RotatingFile::open(debug_path, rotation);
 
     let has_handle = file.is_ok() && debug_file.is_ok();
     let file = file.unwrap_or_else(|e| {
       eprintln!("Failed to open log file {path}: {e}");
       eprintln!("Logging to /dev/null instead");
-      File::create("/dev/null").unwrap()
+      RotatingFile::open("/dev/null", None).unwrap()
     });
     let debug_file = debug_file.unwrap_or_else(|e| {
       eprintln!("Failed to open debug log file {debug_path}: {e}");
       eprintln!("Logging to /dev/null instead");
-      File::create("/dev/null").unwrap()
+      RotatingFile::open("/dev/null", None).unwrap()
     });
 
     Self {
@@ -57,16 +126,8 @@ impl Log {
     }
   }
 
-  pub fn log(&self, origin: Option<&str>, message: &str, level: LogLevel) {
-    let time = OffsetDateTime::now_utc();
-    let entry = format!(
-      "[{}] [{}] {} {}\n",
-      time.format(&time::format_description::well_known::Rfc3339).unwrap(),
-      format!("{:?}", level).to_uppercase(),
-      origin.map_or("".to_string(), |o| format!("[{}]", o)),
-      message
-    );
-
+  /// Writes a line made by [`format_entry`].
+  pub fn write(&self, entry: &str, level: LogLevel) {
     if !self.has_handle {
       eprintln!("{}", entry.trim());
       return;
@@ -74,7 +135,7 @@ impl Log {
 
     // Log Everything to debug log
     if let Ok(mut debug_file) = self.debug_file.lock()
-      && let Err(e) = debug_file.write_all(entry.as_bytes())
+      && let Err(e) = debug_file.write_entry(entry.as_bytes())
     {
       eprintln!("Failed to write debug log entry: {e}");
     }
@@ -82,11 +143,44 @@ impl Log {
     // Only log Info and above to normal log
     if level >= LogLevel::Info
       && let Ok(mut file) = self.file.lock()
-      && let Err(e) = file.write_all(entry.as_bytes())
+      && let Err(e) = file.write_entry(entry.as_bytes())
     {
       eprintln!("Failed to write log entry: {e}");
     }
   }
+}
+
+pub struct Log {
+  files: Arc<LogFiles>,
+  sinks: Arc<Sinks>,
+  /// Where entries go, always a function of the host binary. Plugins are
+  /// libraries with their own copy of this crate and of `tracing`, calling
+  /// through this gets their entries to the host's subscriber.
+  emit: fn(&Log, Option<&str>, &str, LogLevel),
+}
+
+impl Log {
+  pub fn new(path: &str, debug_path: &str, rotation: Option<Rotation>) -> Self {
+    Self {
+      files: Arc::new(LogFiles::open(path, debug_path, rotation)),
+      sinks: Arc::new(Sinks::default()),
+      emit,
+    }
+  }
+
+  pub fn files(&self) -> Arc<LogFiles> {
+    Arc::clone(&self.files)
+  }
+
+  /// The sinks entries go to besides the files, configured from
+  /// `log.sinks`.
+  pub fn sinks(&self) -> Arc<Sinks> {
+    Arc::clone(&self.sinks)
+  }
+
+  pub fn log(&self, origin: Option<&str>, message: &str, level: LogLevel) {
+    (self.emit)(self, origin, message, level);
+  }
 
   pub fn log_debug(&self, origin: &str, message: &str) {
     self.log(Some(origin), message, LogLevel::Debug);
@@ -109,9 +203,39 @@ impl Log {
   }
 }
 
-pub static LOGGER: std::sync::LazyLock<Log> = std::sync::LazyLock::new(|| {
+/// Turns an entry into a `tracing` event, the [`LogLayer`](layer::LogLayer)
+/// writes it out. Straight to the files and sinks while no subscriber is
+/// installed.
+fn emit(log: &Log, origin: Option<&str>, message: &str, level: LogLevel) {
+  if !layer::is_installed() {
+    log.files.write(&format_entry(level, origin, message), level);
+    log.sinks.send(level, origin, message);
+    return;
+  }
+
+  let origin = origin.unwrap_or_default();
+  match level {
+    LogLevel::Debug => tracing::debug!(target: layer::TARGET, origin, "{message}"),
+    LogLevel::Info => tracing::info!(target: layer::TARGET, origin, "{message}"),
+    LogLevel::Warning => tracing::warn!(target: layer::TARGET, origin, "{message}"),
+    LogLevel::Error => tracing::error!(target: layer::TARGET, origin, "{message}"),
+    LogLevel::Fatal => tracing::error!(target: layer::TARGET, origin, fatal = true, "{message}"),
+  }
+}
+
+pub static LOGGER: LazyLock<Log> = LazyLock::new(|| {
   let cfg = config_read();
-  let log = Log::new(&cfg.log.path, &cfg.log.path_debug);
-  log.log(None, "=========== STARTED ===========", LogLevel::Info);
+  let log = Log::new(&cfg.log.path, &cfg.log.path_debug, Rotation::from_config(&cfg.log));
+  log.sinks.configure(&cfg.log);
+  layer::install(log.files(), log.sinks(), &cfg.log);
+  log
+    .files
+    .write(&format_entry(LogLevel::Info, None, "=========== STARTED ==========="), LogLevel::Info);
   log
 });
+
+/// Opens the log files and installs the subscriber, so `tracing` events sent
+/// before the first `LOGGER` call are written too.
+pub fn init() {
+  LazyLock::force(&LOGGER);
+}
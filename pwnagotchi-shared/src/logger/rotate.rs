@@ -0,0 +1,265 @@
+//! Size based rotation of the log files. `pwnagotchi.log` is moved to
+//! `pwnagotchi.log.1`, gzipped to `pwnagotchi.log.1.gz` when compression is
+//! on, and older segments move up by one until `keep` is reached.
+//!
+//! Other processes may append to the same files, like a second instance run
+//! with `--check-config`. Only one of them rotates at a time, guarded by a
+//! `.rotating` file next to the log, and writers that find their file moved
+//! away open the new one before writing. Compressing happens on a thread
+//! that keeps the `.rotating` file until the segment is gzipped, so the
+//! segments don't move underneath it.
+
+use std::{
+  fs::{self, File, OpenOptions},
+  io::{self, BufRead, BufReader, Read, Write},
+  os::unix::fs::MetadataExt,
+  path::{Path, PathBuf},
+  thread::{self, JoinHandle},
+  time::{Duration, SystemTime},
+};
+
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+
+use crate::config::LogConfig;
+
+/// A `.rotating` file older than this was left behind by a process that died
+/// while rotating.
+const STALE_LOCK: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rotation {
+  pub max_bytes: u64,
+  pub keep: u32,
+  pub compress: bool,
+}
+
+impl Rotation {
+  /// `None` when rotation is off or the size isn't valid.
+  pub fn from_config(config: &LogConfig) -> Option<Self> {
+    let rotation = &config.rotation;
+    if !rotation.enabled {
+      return None;
+    }
+    Some(Self {
+      max_bytes: rotation.max_bytes()?,
+      keep: rotation.keep,
+      compress: rotation.compress,
+    })
+  }
+}
+
+/// A log file opened for appending that rotates itself before a write would
+/// take it over the size limit.
+pub struct RotatingFile {
+  path: PathBuf,
+  file: File,
+  rotation: Option<Rotation>,
+  compressing: Option<JoinHandle<io::Result<()>>>,
+}
+
+impl RotatingFile {
+  pub fn open(path: impl Into<PathBuf>, rotation: Option<Rotation>) -> io::Result<Self> {
+    let path = path.into();
+    let file = append(&path)?;
+    Ok(Self { path, file, rotation, compressing: None })
+  }
+
+  /// Waits for the last rotated segment to be compressed. A failure is
+  /// otherwise returned by the write that rotates next.
+  pub fn finish_compressing(&mut self) -> io::Result<()> {
+    match self.compressing.take() {
+      Some(handle) => handle
+        .join()
+        .unwrap_or_else(|_| Err(io::Error::other("compressing a segment panicked"))),
+      None => Ok(()),
+    }
+  }
+
+  /// Appends `entry`, rotating first if it doesn't fit. The entry is written
+  /// even if rotating fails, the error is returned afterwards.
+  pub fn write_entry(&mut self, entry: &[u8]) -> io::Result<()> {
+    self.reopen_if_moved()?;
+
+    let rotated = match self.rotation {
+      Some(rotation) => {
+        if !fits(self.file.metadata()?.len(), entry, rotation) {
+          self.rotate(rotation, entry)
+        } else {
+          Ok(())
+        }
+      }
+      None => Ok(()),
+    };
+
+    self.file.write_all(entry)?;
+    rotated
+  }
+
+  /// Opens the file at the path again when another process rotated it.
+  fn reopen_if_moved(&mut self) -> io::Result<()> {
+    let open = self.file.metadata()?;
+    let moved = match fs::metadata(&self.path) {
+      Ok(current) => current.dev() != open.dev() || current.ino() != open.ino(),
+      Err(e) if e.kind() == io::ErrorKind::NotFound => true,
+      Err(e) => return Err(e),
+    };
+    if moved {
+      self.file = append(&self.path)?;
+    }
+    Ok(())
+  }
+
+  fn rotate(&mut self, rotation: Rotation, entry: &[u8]) -> io::Result<()> {
+    // the segment compressed last time moves now, it has to be done
+    let compressed = self.finish_compressing();
+    self.rotate_segments(rotation, entry)?;
+    compressed
+  }
+
+  fn rotate_segments(&mut self, rotation: Rotation, entry: &[u8]) -> io::Result<()> {
+    let Some(lock) = RotationLock::acquire(&self.path)? else {
+      // someone else is rotating, the next write picks up the new file
+      return Ok(());
+    };
+
+    // another process may have rotated between the size check and the lock
+    let len = fs::metadata(&self.path).map(|meta| meta.len()).unwrap_or(0);
+    if fits(len, entry, rotation) {
+      return self.reopen_if_moved();
+    }
+
+    for n in (1..=rotation.keep).rev() {
+      for (from, to) in [
+        (segment(&self.path, n, false), segment(&self.path, n + 1, false)),
+        (segment(&self.path, n, true), segment(&self.path, n + 1, true)),
+      ] {
+        if !from.exists() {
+          continue;
+        }
+        if n == rotation.keep {
+          fs::remove_file(&from)?;
+        } else {
+          fs::rename(&from, &to)?;
+        }
+      }
+    }
+
+    let first = segment(&self.path, 1, false);
+    fs::rename(&self.path, &first)?;
+    self.file = append(&self.path)?;
+
+    if rotation.keep == 0 {
+      fs::remove_file(&first)?;
+    } else if rotation.compress {
+      let compressed = segment(&self.path, 1, true);
+      self.compressing = Some(thread::spawn(move || {
+        let _lock = lock;
+        compress(&first, &compressed)
+      }));
+    }
+    Ok(())
+  }
+}
+
+/// Removes the `.rotating` file when dropped.
+struct RotationLock(PathBuf);
+
+impl RotationLock {
+  /// `None` when another process holds the lock.
+  fn acquire(log: &Path) -> io::Result<Option<Self>> {
+    let path = suffixed(log, ".rotating");
+    for _ in 0..2 {
+      match OpenOptions::new().write(true).create_new(true).open(&path) {
+        Ok(_) => return Ok(Some(Self(path))),
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+          let age = fs::metadata(&path)
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+          if age.is_none_or(|age| age < STALE_LOCK) {
+            return Ok(None);
+          }
+          let _ = fs::remove_file(&path);
+        }
+        Err(e) => return Err(e),
+      }
+    }
+    Ok(None)
+  }
+}
+
+impl Drop for RotationLock {
+  fn drop(&mut self) {
+    let _ = fs::remove_file(&self.0);
+  }
+}
+
+/// Whether `entry` can go into a file of `len` bytes, an empty file takes
+/// anything.
+fn fits(len: u64, entry: &[u8], rotation: Rotation) -> bool {
+  len == 0 || len + entry.len() as u64 <= rotation.max_bytes
+}
+
+fn append(path: &Path) -> io::Result<File> {
+  OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn suffixed(path: &Path, suffix: &str) -> PathBuf {
+  let mut name = path.as_os_str().to_owned();
+  name.push(suffix);
+  PathBuf::from(name)
+}
+
+/// Path of the `n`th rotated segment of `log`, 1 being the newest.
+pub fn segment(log: &Path, n: u32, compressed: bool) -> PathBuf {
+  suffixed(log, &if compressed { format!(".{n}.gz") } else { format!(".{n}") })
+}
+
+/// Gzips `from` into `to` and removes `from`. Written to a temporary file
+/// first so readers never see half a segment.
+fn compress(from: &Path, to: &Path) -> io::Result<()> {
+  let tmp = suffixed(to, ".tmp");
+  let mut encoder = GzEncoder::new(File::create(&tmp)?, Compression::default());
+  io::copy(&mut File::open(from)?, &mut encoder)?;
+  encoder.finish()?.sync_all()?;
+  fs::rename(&tmp, to)?;
+  fs::remove_file(from)
+}
+
+/// Every segment of `log` that exists, oldest first and the live file last.
+pub fn segments(log: &Path) -> Vec<PathBuf> {
+  let mut found = Vec::new();
+  for n in 1.. {
+    // both exist for a moment while compressing, with the same lines
+    let candidates = [
+      segment(log, n, true),
+      segment(log, n, false),
+    ];
+    let Some(path) = candidates.into_iter().find(|path| path.exists()) else {
+      break;
+    };
+    found.push(path);
+  }
+  found.reverse();
+  if log.exists() {
+    found.push(log.to_path_buf());
+  }
+  found
+}
+
+/// Lines of every segment of `log`, oldest first. Segments that disappear
+/// while reading, because they were rotated away, are skipped.
+pub fn read_lines(log: &Path) -> impl Iterator<Item = String> {
+  segments(log).into_iter().flat_map(|path| {
+    let reader: Option<Box<dyn Read>> = File::open(&path).ok().map(|file| {
+      if path.extension().is_some_and(|ext| ext == "gz") {
+        Box::new(GzDecoder::new(file)) as Box<dyn Read>
+      } else {
+        Box::new(file)
+      }
+    });
+    reader
+      .into_iter()
+      .flat_map(|reader| BufReader::new(reader).lines().map_while(Result::ok))
+  })
+}
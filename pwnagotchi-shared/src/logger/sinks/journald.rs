@@ -0,0 +1,75 @@
+//! The systemd journal, over its native protocol. Entries keep their level
+//! as `PRIORITY` and their origin as `PWNAGOTCHI_ORIGIN`, so
+//! `journalctl PWNAGOTCHI_ORIGIN=AGENT` shows one module.
+
+use std::{os::unix::net::UnixDatagram, path::PathBuf};
+
+use super::{APP_NAME, Entry, Sink};
+use crate::config::LogSinkConfig;
+
+/// Where journald listens for native messages.
+pub const SOCKET: &str = "/run/systemd/journal/socket";
+
+pub struct Journald {
+  socket: Option<UnixDatagram>,
+  path: PathBuf,
+  failing: bool,
+}
+
+impl Journald {
+  pub fn from_config(config: &LogSinkConfig) -> Self {
+    let path = if config.address.is_empty() { SOCKET } else { config.address.as_ref() };
+    let socket = UnixDatagram::unbound()
+      .inspect_err(|e| eprintln!("Failed to create journal socket: {e}"))
+      .ok();
+    Self {
+      socket,
+      path: PathBuf::from(path),
+      failing: false,
+    }
+  }
+}
+
+impl Sink for Journald {
+  fn deliver(&mut self, entry: Entry) {
+    let Some(socket) = &self.socket else {
+      return;
+    };
+
+    match socket.send_to(&payload(&entry), &self.path) {
+      Ok(_) => self.failing = false,
+      Err(e) => {
+        if !self.failing {
+          eprintln!("Failed to send log entries to the journal at {}: {e}", self.path.display());
+        }
+        self.failing = true;
+      }
+    }
+  }
+}
+
+/// An entry as journal fields.
+pub fn payload(entry: &Entry) -> Vec<u8> {
+  let mut payload = Vec::new();
+  field(&mut payload, "PRIORITY", &entry.level.severity().to_string());
+  field(&mut payload, "SYSLOG_IDENTIFIER", APP_NAME);
+  if let Some(origin) = &entry.origin {
+    field(&mut payload, "PWNAGOTCHI_ORIGIN", origin);
+  }
+  field(&mut payload, "MESSAGE", &entry.message);
+  payload
+}
+
+/// `NAME=value`, or the name, the length and the value for values spanning
+/// lines.
+fn field(payload: &mut Vec<u8>, name: &str, value: &str) {
+  payload.extend_from_slice(name.as_bytes());
+  if value.contains('\n') {
+    payload.push(b'\n');
+    payload.extend_from_slice(&(value.len() as u64).to_le_bytes());
+  } else {
+    payload.push(b'=');
+  }
+  payload.extend_from_slice(value.as_bytes());
+  payload.push(b'\n');
+}
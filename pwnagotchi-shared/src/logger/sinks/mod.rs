@@ -0,0 +1,167 @@
+//! Destinations for log entries besides the log files, set up from
+//! `log.sinks`. Every sink runs on its own thread behind a bounded queue, so
+//! a slow or unreachable collector never holds up the unit.
+
+pub mod journald;
+pub mod spool;
+pub mod syslog;
+
+use std::{
+  sync::{
+    Arc,
+    atomic::{AtomicU64, Ordering},
+    mpsc::{Receiver, RecvTimeoutError, SyncSender, TrySendError, sync_channel},
+  },
+  thread::{self, JoinHandle},
+  time::Duration,
+};
+
+use parking_lot::Mutex;
+use time::OffsetDateTime;
+
+use super::LogLevel;
+use crate::config::{LogConfig, LogSinkConfig};
+
+/// Name the unit's entries carry in syslog and the journal.
+pub const APP_NAME: &str = "pwnagotchi";
+
+/// Entries waiting for a sink, newer ones are dropped while it is full and
+/// the sink is told how many with its next entry.
+const QUEUE: usize = 1024;
+
+/// A log entry on its way to a sink.
+#[derive(Debug, Clone)]
+pub struct Entry {
+  pub time: OffsetDateTime,
+  pub level: LogLevel,
+  pub origin: Option<String>,
+  pub message: String,
+}
+
+/// Delivers entries, on the sink's own thread.
+pub trait Sink: Send {
+  fn deliver(&mut self, entry: Entry);
+
+  /// Called when no entry came in for [`Sink::idle_interval`].
+  fn idle(&mut self) {}
+
+  fn idle_interval(&self) -> Option<Duration> {
+    None
+  }
+}
+
+struct Running {
+  level: LogLevel,
+  queue: SyncSender<Entry>,
+  /// Entries dropped because the queue was full, since the sink last heard.
+  dropped: Arc<AtomicU64>,
+  thread: JoinHandle<()>,
+}
+
+/// The configured sinks.
+#[derive(Default)]
+pub struct Sinks {
+  running: Mutex<Vec<Running>>,
+}
+
+impl Sinks {
+  pub fn from_config(config: &LogConfig) -> Self {
+    let sinks = Self::default();
+    sinks.configure(config);
+    sinks
+  }
+
+  /// Replaces the running sinks with the ones in `config`. The old ones send
+  /// what they have queued and stop before the new ones start, so the two
+  /// never share a spool or a connection. Entries logged in between only go
+  /// to the log files.
+  pub fn configure(&self, config: &LogConfig) {
+    // not held while they finish, logging goes on meanwhile
+    let old = std::mem::take(&mut *self.running.lock());
+    stop(old);
+    let running = config.sinks.iter().enumerate().filter_map(|(i, sink)| start(i, sink)).collect();
+    *self.running.lock() = running;
+  }
+
+  /// Queues an entry for every sink it is at or above the level of.
+  pub fn send(&self, level: LogLevel, origin: Option<&str>, message: &str) {
+    let running = self.running.lock();
+    if running.iter().all(|sink| level < sink.level) {
+      return;
+    }
+
+    let entry = Entry {
+      time: OffsetDateTime::now_utc(),
+      level,
+      origin: origin.map(ToString::to_string),
+      message: message.to_string(),
+    };
+    for sink in running.iter().filter(|sink| level >= sink.level) {
+      if let Err(TrySendError::Full(_)) = sink.queue.try_send(entry.clone()) {
+        sink.dropped.fetch_add(1, Ordering::Relaxed);
+      }
+    }
+  }
+
+  /// Stops every sink once it sent or spooled what is queued.
+  pub fn close(&self) {
+    let running = std::mem::take(&mut *self.running.lock());
+    stop(running);
+  }
+}
+
+fn start(index: usize, config: &LogSinkConfig) -> Option<Running> {
+  // the config is validated, invalid sinks are left out
+  let level = config.level.parse().ok()?;
+  let sink: Box<dyn Sink> = match config.kind.as_ref() {
+    "syslog" => Box::new(syslog::Syslog::from_config(config)?),
+    "journald" => Box::new(journald::Journald::from_config(config)),
+    _ => return None,
+  };
+
+  let (queue, entries) = sync_channel(QUEUE);
+  let dropped = Arc::new(AtomicU64::new(0));
+  let counted = Arc::clone(&dropped);
+  let thread = thread::Builder::new()
+    .name(format!("log-{}-{index}", config.kind))
+    .spawn(move || run(sink, &entries, &counted))
+    .ok()?;
+  Some(Running { level, queue, dropped, thread })
+}
+
+fn run(mut sink: Box<dyn Sink>, entries: &Receiver<Entry>, dropped: &AtomicU64) {
+  loop {
+    let entry = match sink.idle_interval() {
+      Some(interval) => match entries.recv_timeout(interval) {
+        Ok(entry) => entry,
+        Err(RecvTimeoutError::Timeout) => {
+          sink.idle();
+          continue;
+        }
+        Err(RecvTimeoutError::Disconnected) => return,
+      },
+      None => match entries.recv() {
+        Ok(entry) => entry,
+        Err(_) => return,
+      },
+    };
+
+    let missed = dropped.swap(0, Ordering::Relaxed);
+    if missed > 0 {
+      sink.deliver(Entry {
+        time: entry.time,
+        level: LogLevel::Warning,
+        origin: Some("LOGGER".to_string()),
+        message: format!("{missed} entries were dropped while the queue was full"),
+      });
+    }
+    sink.deliver(entry);
+  }
+}
+
+fn stop(running: Vec<Running>) {
+  let threads = running.into_iter().map(|sink| sink.thread).collect::<Vec<_>>();
+  for thread in threads {
+    let _ = thread.join();
+  }
+}
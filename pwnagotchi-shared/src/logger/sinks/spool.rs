@@ -0,0 +1,82 @@
+//! Entries kept on disk while a collector can't be reached, one per line as
+//! a JSON string so messages spanning lines stay one entry. The spool
+//! outlives restarts, what is left in it is sent once the collector is back.
+
+use std::{
+  fs::{self, OpenOptions},
+  io::{self, Write},
+  path::{Path, PathBuf},
+};
+
+pub struct Spool {
+  path: PathBuf,
+  max_bytes: u64,
+  /// Entries that didn't fit since the spool was last emptied.
+  dropped: u64,
+}
+
+impl Spool {
+  pub fn new(path: impl Into<PathBuf>, max_bytes: u64) -> Self {
+    Self { path: path.into(), max_bytes, dropped: 0 }
+  }
+
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+
+  pub fn is_empty(&self) -> bool {
+    fs::metadata(&self.path).map_or(true, |meta| meta.len() == 0)
+  }
+
+  /// Appends `frame`, `false` when the spool is full and it was dropped.
+  pub fn push(&mut self, frame: &str) -> io::Result<bool> {
+    let line = format!("{}\n", serde_json::to_string(frame)?);
+    let len = fs::metadata(&self.path).map_or(0, |meta| meta.len());
+    if len + line.len() as u64 > self.max_bytes {
+      self.dropped += 1;
+      return Ok(false);
+    }
+
+    if let Some(parent) = self.path.parent() {
+      fs::create_dir_all(parent)?;
+    }
+    OpenOptions::new()
+      .create(true)
+      .append(true)
+      .open(&self.path)?
+      .write_all(line.as_bytes())?;
+    Ok(true)
+  }
+
+  /// Hands the spooled frames to `send` oldest first, stopping at the first
+  /// it fails for with its error. Sent frames are removed.
+  pub fn drain(&mut self, mut send: impl FnMut(&str) -> io::Result<()>) -> io::Result<()> {
+    let text = match fs::read_to_string(&self.path) {
+      Ok(text) => text,
+      Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+      Err(e) => return Err(e),
+    };
+
+    let lines = text.lines().collect::<Vec<_>>();
+    for (i, line) in lines.iter().enumerate() {
+      // a line cut short by a crash while appending
+      let Ok(frame) = serde_json::from_str::<String>(line) else {
+        continue;
+      };
+      if let Err(e) = send(&frame) {
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        fs::write(&tmp, format!("{}\n", lines[i..].join("\n")))?;
+        fs::rename(&tmp, &self.path)?;
+        return Err(e);
+      }
+    }
+
+    fs::remove_file(&self.path)
+  }
+
+  /// Entries dropped since the last call.
+  pub fn take_dropped(&mut self) -> u64 {
+    std::mem::take(&mut self.dropped)
+  }
+}
@@ -0,0 +1,258 @@
+//! RFC 5424 syslog to a collector, one datagram per entry over UDP or
+//! octet-counted frames (RFC 6587) over TCP. While the collector can't be
+//! reached entries go to the spool, if there is one, and are sent from there
+//! once it is back. Only TCP has a spool, a UDP datagram is sent whether or
+//! not anyone receives it.
+
+use std::{
+  io::{self, Write},
+  net::{TcpStream, ToSocketAddrs, UdpSocket},
+  time::{Duration, Instant},
+};
+
+use time::OffsetDateTime;
+
+use super::{APP_NAME, Entry, Sink, spool::Spool};
+use crate::{config::LogSinkConfig, logger::LogLevel};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Longest datagram sent, the size RFC 5426 asks collectors to accept.
+const MAX_DATAGRAM: usize = 2048;
+
+/// Code of a syslog facility by name.
+pub fn facility(name: &str) -> Option<u8> {
+  let code = match name.to_ascii_lowercase().as_str() {
+    "kern" => 0,
+    "user" => 1,
+    "mail" => 2,
+    "daemon" => 3,
+    "auth" => 4,
+    "syslog" => 5,
+    "lpr" => 6,
+    "news" => 7,
+    "uucp" => 8,
+    "cron" => 9,
+    "authpriv" => 10,
+    "ftp" => 11,
+    local => {
+      let n = local.strip_prefix("local")?.parse::<u8>().ok().filter(|n| *n <= 7)?;
+      16 + n
+    }
+  };
+  Some(code)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Protocol {
+  Udp,
+  Tcp,
+}
+
+enum Connection {
+  Udp(UdpSocket),
+  Tcp(TcpStream),
+}
+
+pub struct Syslog {
+  address: String,
+  protocol: Protocol,
+  facility: u8,
+  hostname: String,
+  connection: Option<Connection>,
+  /// No attempt to reach the collector before this, after one failed.
+  retry_at: Option<Instant>,
+  retry_interval: Duration,
+  spool: Option<Spool>,
+  failing: bool,
+}
+
+impl Syslog {
+  /// `None` for settings the config validation rejects.
+  pub fn from_config(config: &LogSinkConfig) -> Option<Self> {
+    let protocol = match config.protocol.as_ref() {
+      "udp" => Protocol::Udp,
+      "tcp" => Protocol::Tcp,
+      _ => return None,
+    };
+    let spool = match (config.spool.as_ref(), protocol) {
+      ("", _) => None,
+      (_, Protocol::Udp) => return None,
+      (spool, Protocol::Tcp) => Some(Spool::new(spool, config.spool_bytes()?)),
+    };
+    let hostname = std::fs::read_to_string("/proc/sys/kernel/hostname").unwrap_or_default();
+
+    Some(Self {
+      address: config.address.to_string(),
+      protocol,
+      facility: facility(&config.facility)?,
+      hostname: header_field(&hostname, 255),
+      connection: None,
+      retry_at: None,
+      retry_interval: Duration::from_secs(config.retry_interval.max(1)),
+      spool,
+      failing: false,
+    })
+  }
+
+  fn frame(&self, entry: &Entry) -> String {
+    let priority = u16::from(self.facility) * 8 + u16::from(entry.level.severity());
+    let msgid = entry.origin.as_deref().map_or_else(|| "-".to_string(), |o| header_field(o, 32));
+    format!(
+      "<{priority}>1 {} {} {APP_NAME} {} {msgid} - {}",
+      timestamp(entry.time),
+      self.hostname,
+      std::process::id(),
+      entry.message
+    )
+  }
+
+  /// Whether the collector was unreachable and it isn't time to try again.
+  fn waiting(&self) -> bool {
+    self.connection.is_none() && self.retry_at.is_some_and(|at| Instant::now() < at)
+  }
+
+  fn send(&mut self, frame: &str) -> io::Result<()> {
+    if self.connection.is_none() {
+      if self.waiting() {
+        return Err(io::Error::other("waiting to retry"));
+      }
+      match self.connect() {
+        Ok(connection) => self.connection = Some(connection),
+        Err(e) => {
+          self.retry_at = Some(Instant::now() + self.retry_interval);
+          return Err(e);
+        }
+      }
+    }
+
+    let sent = match self.connection.as_mut() {
+      Some(Connection::Udp(socket)) => socket.send(truncate(frame, MAX_DATAGRAM)).map(|_| ()),
+      Some(Connection::Tcp(stream)) => {
+        stream.write_all(format!("{} {frame}", frame.len()).as_bytes())
+      }
+      None => unreachable!("connected above"),
+    };
+    if sent.is_err() {
+      self.connection = None;
+      self.retry_at = Some(Instant::now() + self.retry_interval);
+    }
+    sent
+  }
+
+  fn connect(&self) -> io::Result<Connection> {
+    let mut error = io::Error::new(io::ErrorKind::NotFound, "address did not resolve");
+    for address in self.address.to_socket_addrs()? {
+      let connection = match self.protocol {
+        Protocol::Udp => {
+          let local = if address.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+          UdpSocket::bind(local)
+            .and_then(|socket| socket.connect(address).map(|()| socket))
+            .map(Connection::Udp)
+        }
+        Protocol::Tcp => TcpStream::connect_timeout(&address, CONNECT_TIMEOUT).and_then(|stream| {
+          stream.set_write_timeout(Some(CONNECT_TIMEOUT))?;
+          Ok(Connection::Tcp(stream))
+        }),
+      };
+      match connection {
+        Ok(connection) => return Ok(connection),
+        Err(e) => error = e,
+      }
+    }
+    Err(error)
+  }
+
+  /// Sends what is in the spool, the error of the first entry that couldn't
+  /// be sent otherwise.
+  fn flush_spool(&mut self) -> io::Result<()> {
+    let Some(mut spool) = self.spool.take() else {
+      return Ok(());
+    };
+    // reading the spool only to fail on its first entry
+    if self.waiting() && !spool.is_empty() {
+      self.spool = Some(spool);
+      return Err(io::Error::other("waiting to retry"));
+    }
+    let flushed = if spool.is_empty() { Ok(()) } else { spool.drain(|frame| self.send(frame)) };
+    let dropped = if flushed.is_ok() { spool.take_dropped() } else { 0 };
+    self.spool = Some(spool);
+
+    if dropped > 0 {
+      let entry = Entry {
+        time: OffsetDateTime::now_utc(),
+        level: LogLevel::Warning,
+        origin: Some("LOGGER".to_string()),
+        message: format!("{dropped} entries were dropped while the spool was full"),
+      };
+      let _ = self.send(&self.frame(&entry));
+    }
+    flushed
+  }
+
+  fn report(&mut self, error: &io::Error) {
+    if !self.failing {
+      let fallback = if self.spool.is_some() { "spooling entries" } else { "dropping entries" };
+      eprintln!("Can't reach syslog collector {}: {error}, {fallback}", self.address);
+    }
+    self.failing = true;
+  }
+}
+
+impl Sink for Syslog {
+  fn deliver(&mut self, entry: Entry) {
+    let frame = self.frame(&entry);
+    let sent = self.flush_spool().and_then(|()| self.send(&frame));
+
+    match sent {
+      Ok(()) => self.failing = false,
+      Err(e) => {
+        self.report(&e);
+        if let Some(spool) = &mut self.spool
+          && let Err(e) = spool.push(&frame)
+        {
+          eprintln!("Failed to write log spool {}: {e}", spool.path().display());
+        }
+      }
+    }
+  }
+
+  fn idle(&mut self) {
+    if self.flush_spool().is_ok() {
+      self.failing = false;
+    }
+  }
+
+  fn idle_interval(&self) -> Option<Duration> {
+    self.spool.as_ref().map(|_| self.retry_interval)
+  }
+}
+
+/// RFC 3339 with at most microseconds, as RFC 5424 allows.
+fn timestamp(time: OffsetDateTime) -> String {
+  format!(
+    "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:06}Z",
+    time.year(),
+    u8::from(time.month()),
+    time.day(),
+    time.hour(),
+    time.minute(),
+    time.second(),
+    time.microsecond()
+  )
+}
+
+/// Printable ASCII of `value` up to `max` characters, `-` if nothing is
+/// left.
+fn header_field(value: &str, max: usize) -> String {
+  let field = value.chars().filter(|c| c.is_ascii_graphic()).take(max).collect::<String>();
+  if field.is_empty() { "-".to_string() } else { field }
+}
+
+fn truncate(frame: &str, max: usize) -> &[u8] {
+  let mut end = frame.len().min(max);
+  while !frame.is_char_boundary(end) {
+    end -= 1;
+  }
+  &frame.as_bytes()[..end]
+}
@@ -1,5 +1,6 @@
-use std::fmt::Debug;
+use std::{collections::HashMap, fmt::Debug, sync::Arc};
 
+use parking_lot::RwLock;
 use time::OffsetDateTime;
 
 use crate::{
@@ -23,6 +24,12 @@ fn parse_rfc3339(dt: &str) -> OffsetDateTime {
   OffsetDateTime::parse(trimmed, &format).unwrap_or_else(|_| OffsetDateTime::now_utc()) // fallback if parsing fails
 }
 
+/// Number of RSSI samples kept per peer.
+pub const RSSI_HISTORY_LEN: usize = 32;
+
+/// Peers currently in range, keyed by fingerprint.
+pub type PeerRegistry = Arc<RwLock<HashMap<String, Peer>>>;
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct Peer {
   pub first_met: Option<OffsetDateTime>,
@@ -33,6 +40,9 @@ pub struct Peer {
   pub session_id: String,
   pub last_channel: u8,
   pub rssi: i16,
+  /// Most recent RSSI samples, oldest first.
+  #[serde(default)]
+  pub rssi_history: Vec<i16>,
   pub adv: Advertisement,
 }
 
@@ -50,6 +60,7 @@ impl Peer {
     let just_met = now.format(&format).unwrap_or_else(|_| now.to_string());
 
     let data = data.clone();
+    let rssi = data.rssi.unwrap_or(0);
 
     Self {
       first_met: Some(parse_rfc3339(&data.met_at.unwrap_or_else(|| just_met.clone()))),
@@ -59,7 +70,8 @@ impl Peer {
       encounters: data.encounters.unwrap_or(0),
       session_id: data.session_id.unwrap_or_default(),
       last_channel: data.channel.unwrap_or(1),
-      rssi: data.rssi.unwrap_or(0),
+      rssi,
+      rssi_history: vec![rssi],
       adv: data.advertisement.unwrap_or_default(),
     }
   }
@@ -86,7 +98,13 @@ impl Peer {
 
     self.adv = new.adv.clone();
     self.rssi = new.rssi;
+    self.rssi_history.push(new.rssi);
+    if self.rssi_history.len() > RSSI_HISTORY_LEN {
+      let excess = self.rssi_history.len() - RSSI_HISTORY_LEN;
+      self.rssi_history.drain(..excess);
+    }
     self.session_id.clone_from(&new.session_id);
+    self.last_channel = new.last_channel;
     self.last_seen = Some(time::OffsetDateTime::now_utc());
     self.prev_seen = new.prev_seen;
     self.first_met = new.first_met;
@@ -0,0 +1,165 @@
+use std::{
+  collections::{BTreeMap, HashMap},
+  path::{Path, PathBuf},
+  time::Duration,
+};
+
+use parking_lot::{Mutex, RwLock};
+use time::OffsetDateTime;
+
+use crate::{config::config_read, logger::LOGGER, mesh::peer::Peer, traits::general::CoreModule};
+
+/// Number of pwnd samples kept per friend.
+pub const PWND_HISTORY_LEN: usize = 64;
+
+/// Everything we remember about a unit across reboots. Timestamps are unix
+/// seconds.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct FriendRecord {
+  pub fingerprint: String,
+  pub name: String,
+  pub first_met: i64,
+  pub last_seen: i64,
+  pub encounters: u32,
+  /// Seconds spent in range of each other.
+  pub time_together: u64,
+  /// Encounters per channel.
+  pub channels: BTreeMap<u8, u32>,
+  /// How often each face was seen while in range.
+  pub faces: BTreeMap<String, u32>,
+  /// `(timestamp, pwnd_total)`, one sample per encounter, oldest first.
+  pub pwnd_history: Vec<(i64, u32)>,
+}
+
+impl FriendRecord {
+  pub fn is_good_friend(&self, bond_factor: u32) -> bool {
+    self.encounters >= bond_factor
+  }
+
+  pub fn pwnd_total(&self) -> u32 {
+    self.pwnd_history.last().map_or(0, |(_, pwnd)| *pwnd)
+  }
+}
+
+/// Persistent record of every peer met, stored as JSON.
+pub struct PeerDb {
+  path: PathBuf,
+  records: RwLock<HashMap<String, FriendRecord>>,
+  dirty: Mutex<bool>,
+}
+
+impl CoreModule for PeerDb {
+  fn name(&self) -> &'static str {
+    "PeerDb"
+  }
+}
+
+impl PeerDb {
+  /// Opens the database at the configured path.
+  pub fn new() -> Self {
+    Self::open(config_read().grid.peer_db.as_ref())
+  }
+
+  /// Opens the database at `path`, starting empty if it does not exist or
+  /// can't be read.
+  pub fn open<P: AsRef<Path>>(path: P) -> Self {
+    let path = path.as_ref().to_path_buf();
+
+    let records = match std::fs::read_to_string(&path) {
+      Ok(content) => serde_json::from_str::<Vec<FriendRecord>>(&content).unwrap_or_else(|e| {
+        LOGGER.log_error("PeerDb", &format!("Ignoring corrupt {}: {e}", path.display()));
+        Vec::new()
+      }),
+      Err(_) => Vec::new(),
+    };
+
+    Self {
+      path,
+      records: RwLock::new(records.into_iter().map(|r| (r.fingerprint.clone(), r)).collect()),
+      dirty: Mutex::new(false),
+    }
+  }
+
+  /// Counts a new encounter with `peer` and returns its lifetime encounters.
+  pub fn record_encounter(&self, peer: &Peer, now: OffsetDateTime) -> u32 {
+    let mut records = self.records.write();
+    let record = records.entry(peer.identity()).or_insert_with(|| FriendRecord {
+      fingerprint: peer.identity(),
+      first_met: now.unix_timestamp(),
+      ..FriendRecord::default()
+    });
+
+    record.encounters += 1;
+    *record.channels.entry(peer.last_channel).or_insert(0) += 1;
+    let face = peer.face();
+    if !face.is_empty() {
+      *record.faces.entry(face).or_insert(0) += 1;
+    }
+    record.pwnd_history.push((now.unix_timestamp(), peer.pwnd_total()));
+    if record.pwnd_history.len() > PWND_HISTORY_LEN {
+      let excess = record.pwnd_history.len() - PWND_HISTORY_LEN;
+      record.pwnd_history.drain(..excess);
+    }
+    Self::touch(record, peer, now);
+
+    *self.dirty.lock() = true;
+    record.encounters
+  }
+
+  /// Accounts `together` more time in range of `peer` and returns its
+  /// lifetime encounters, `None` if it was never encountered.
+  pub fn observe(&self, peer: &Peer, together: Duration, now: OffsetDateTime) -> Option<u32> {
+    let mut records = self.records.write();
+    let record = records.get_mut(&peer.identity())?;
+
+    record.time_together += together.as_secs();
+    Self::touch(record, peer, now);
+    *self.dirty.lock() = true;
+    Some(record.encounters)
+  }
+
+  fn touch(record: &mut FriendRecord, peer: &Peer, now: OffsetDateTime) {
+    record.name = peer.name();
+    record.last_seen = now.unix_timestamp();
+  }
+
+  pub fn get(&self, fingerprint: &str) -> Option<FriendRecord> {
+    self.records.read().get(fingerprint).cloned()
+  }
+
+  /// All known units, most encounters first.
+  pub fn friends(&self) -> Vec<FriendRecord> {
+    let mut friends = self.records.read().values().cloned().collect::<Vec<_>>();
+    friends.sort_by(|a, b| b.encounters.cmp(&a.encounters).then(a.name.cmp(&b.name)));
+    friends
+  }
+
+  /// Units met often enough to count as good friends.
+  pub fn good_friends(&self) -> Vec<FriendRecord> {
+    let factor = config_read().personality.bond_encounters_factor;
+    self.friends().into_iter().filter(|f| f.is_good_friend(factor)).collect()
+  }
+
+  /// Writes the database if anything changed since the last save.
+  pub fn save(&self) -> std::io::Result<()> {
+    let mut dirty = self.dirty.lock();
+    if !*dirty {
+      return Ok(());
+    }
+
+    let content = serde_json::to_string(&self.friends())?;
+    let tmp = self.path.with_extension("tmp");
+    std::fs::write(&tmp, content)?;
+    std::fs::rename(&tmp, &self.path)?;
+
+    *dirty = false;
+    Ok(())
+  }
+}
+
+impl Default for PeerDb {
+  fn default() -> Self {
+    Self::new()
+  }
+}
@@ -0,0 +1,399 @@
+//! Handshake sharing between trusted units. Every unit serves a signed
+//! inventory of its captures, units on each other's allowlist compare it with
+//! their own and fetch whatever they are missing. Requests, inventories and
+//! captures are all signed with the unit's [`Identity`], received captures
+//! get a `.origin` sidecar naming the unit that made them.
+
+use std::{
+  collections::{HashMap, HashSet},
+  fs,
+  io::ErrorKind,
+  path::{Path, PathBuf},
+  sync::LazyLock,
+  time::SystemTime,
+};
+
+use anyhow::{Result, anyhow, bail};
+use base64::{Engine, engine::general_purpose};
+use hex::ToHex;
+use parking_lot::Mutex;
+use rsa::{
+  RsaPublicKey,
+  rand_core::{OsRng, RngCore},
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::identity::{
+  Identity,
+  crypto::{public_key_from_b64, verify},
+};
+
+/// Served by the web UI, captures live under `<CAPTURES_PATH>/<name>`.
+pub const INVENTORY_PATH: &str = "/share/inventory";
+pub const CAPTURES_PATH: &str = "/share/handshakes";
+
+pub const FINGERPRINT_HEADER: &str = "x-pwnagotchi-fingerprint";
+pub const PUBLIC_KEY_HEADER: &str = "x-pwnagotchi-public-key";
+pub const TIMESTAMP_HEADER: &str = "x-pwnagotchi-timestamp";
+pub const SIGNATURE_HEADER: &str = "x-pwnagotchi-signature";
+pub const NONCE_HEADER: &str = "x-pwnagotchi-nonce";
+pub const SHA256_HEADER: &str = "x-pwnagotchi-sha256";
+
+/// Extension of the sidecar written next to received captures.
+pub const ORIGIN_EXTENSION: &str = "origin";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HandshakeDigest {
+  pub name: String,
+  /// Hex encoded sha256 of the capture.
+  pub sha256: String,
+  pub size: u64,
+  /// Fingerprint of the unit that captured it, `None` for own captures.
+  #[serde(default)]
+  pub origin: Option<String>,
+}
+
+/// What a unit has to offer, signed by its identity.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Inventory {
+  pub fingerprint: String,
+  pub public_key: String,
+  pub timestamp: i64,
+  pub handshakes: Vec<HandshakeDigest>,
+  pub signature: String,
+}
+
+/// Contents of the `.origin` sidecar.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Origin {
+  /// The unit that captured the handshake.
+  pub fingerprint: String,
+  /// The unit it was received from, differs from `fingerprint` when the
+  /// capture was passed along.
+  pub via: String,
+  pub sha256: String,
+  pub received_at: i64,
+}
+
+/// Authentication sent with every sharing request.
+#[derive(Debug, Clone)]
+pub struct SignedRequest {
+  pub fingerprint: String,
+  pub public_key: String,
+  pub timestamp: i64,
+  /// Random, a request is only accepted once.
+  pub nonce: String,
+  pub signature: String,
+}
+
+/// Nonces of accepted requests, kept for as long as their timestamp would
+/// still pass the clock check so a captured request can't be replayed.
+#[derive(Default)]
+pub struct NonceCache {
+  seen: Mutex<HashMap<(String, String), i64>>,
+}
+
+impl NonceCache {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  fn insert(&self, request: &SignedRequest, now: i64, max_skew: i64) -> Result<()> {
+    let mut seen = self.seen.lock();
+    seen.retain(|_, timestamp| *timestamp >= now - max_skew);
+
+    let key = (request.fingerprint.to_ascii_lowercase(), request.nonce.clone());
+    if seen.insert(key, request.timestamp).is_some() {
+      bail!("replayed request from {}", request.fingerprint);
+    }
+    Ok(())
+  }
+}
+
+/// Fingerprint of a base64 encoded public key PEM, computed like
+/// [`Identity`] does for its own key.
+pub fn fingerprint_of(public_key_b64: &str) -> Result<String> {
+  let pem = general_purpose::STANDARD.decode(public_key_b64.trim())?;
+  Ok(Sha256::digest(&pem).encode_hex::<String>())
+}
+
+/// Checks that `fingerprint` is allowed and really belongs to `public_key_b64`.
+pub fn trusted_key(
+  fingerprint: &str,
+  public_key_b64: &str,
+  trusted: &[impl AsRef<str>],
+) -> Result<RsaPublicKey> {
+  if !trusted.iter().any(|t| t.as_ref().eq_ignore_ascii_case(fingerprint)) {
+    bail!("{fingerprint} is not trusted");
+  }
+  if !fingerprint_of(public_key_b64)?.eq_ignore_ascii_case(fingerprint) {
+    bail!("public key does not match fingerprint {fingerprint}");
+  }
+  public_key_from_b64(public_key_b64)
+}
+
+fn check_clock(timestamp: i64, now: i64, max_skew: i64) -> Result<()> {
+  if (now - timestamp).abs() > max_skew {
+    bail!("timestamp {timestamp} is too far off");
+  }
+  Ok(())
+}
+
+fn public_key(identity: &Identity) -> Result<String> {
+  identity
+    .public_key_b64()
+    .map(str::to_string)
+    .ok_or_else(|| anyhow!("public key not loaded"))
+}
+
+impl SignedRequest {
+  /// Signs a request for `path`.
+  pub fn sign(identity: &Identity, path: &str, now: i64) -> Result<Self> {
+    let mut nonce = [0u8; 16];
+    OsRng.fill_bytes(&mut nonce);
+    let nonce = nonce.encode_hex::<String>();
+
+    Ok(Self {
+      fingerprint: identity.fingerprint().to_string(),
+      public_key: public_key(identity)?,
+      timestamp: now,
+      signature: identity.sign(&Self::payload(path, now, &nonce)).map_err(|e| anyhow!(e))?,
+      nonce,
+    })
+  }
+
+  pub fn headers(&self) -> [(&'static str, String); 5] {
+    [
+      (FINGERPRINT_HEADER, self.fingerprint.clone()),
+      (PUBLIC_KEY_HEADER, self.public_key.clone()),
+      (TIMESTAMP_HEADER, self.timestamp.to_string()),
+      (NONCE_HEADER, self.nonce.clone()),
+      (SIGNATURE_HEADER, self.signature.clone()),
+    ]
+  }
+
+  /// Checks the request came from a trusted unit, recently, for `path`, and
+  /// wasn't seen before.
+  pub fn verify(
+    &self,
+    path: &str,
+    trusted: &[impl AsRef<str>],
+    nonces: &NonceCache,
+    now: i64,
+    max_skew: i64,
+  ) -> Result<RsaPublicKey> {
+    let key = trusted_key(&self.fingerprint, &self.public_key, trusted)?;
+    check_clock(self.timestamp, now, max_skew)?;
+    let payload = Self::payload(path, self.timestamp, &self.nonce);
+    if self.nonce.is_empty() || !verify(&key, payload.as_bytes(), &self.signature) {
+      bail!("bad request signature from {}", self.fingerprint);
+    }
+    nonces.insert(self, now, max_skew)?;
+    Ok(key)
+  }
+
+  fn payload(path: &str, timestamp: i64, nonce: &str) -> String {
+    format!("{path}\n{timestamp}\n{nonce}")
+  }
+}
+
+impl Inventory {
+  pub fn signed(identity: &Identity, handshakes: Vec<HandshakeDigest>, now: i64) -> Result<Self> {
+    let mut inventory = Self {
+      fingerprint: identity.fingerprint().to_string(),
+      public_key: public_key(identity)?,
+      timestamp: now,
+      handshakes,
+      signature: String::new(),
+    };
+    inventory.signature = identity.sign_data(&inventory.payload()?).map_err(|e| anyhow!(e))?;
+    Ok(inventory)
+  }
+
+  /// Checks the inventory was signed by a trusted unit and returns its key,
+  /// used to verify the captures it offers.
+  pub fn verify(
+    &self,
+    trusted: &[impl AsRef<str>],
+    now: i64,
+    max_skew: i64,
+  ) -> Result<RsaPublicKey> {
+    let key = trusted_key(&self.fingerprint, &self.public_key, trusted)?;
+    check_clock(self.timestamp, now, max_skew)?;
+    if !verify(&key, &self.payload()?, &self.signature) {
+      bail!("bad inventory signature from {}", self.fingerprint);
+    }
+    Ok(key)
+  }
+
+  /// Entries we don't have yet, by content.
+  pub fn missing(&self, local: &[HandshakeDigest]) -> Vec<&HandshakeDigest> {
+    let have = local.iter().map(|d| d.sha256.as_str()).collect::<HashSet<_>>();
+    self.handshakes.iter().filter(|d| !have.contains(d.sha256.as_str())).collect()
+  }
+
+  fn payload(&self) -> Result<Vec<u8>> {
+    Ok(serde_json::to_vec(&(
+      &self.fingerprint,
+      &self.public_key,
+      self.timestamp,
+      &self.handshakes,
+    ))?)
+  }
+}
+
+/// What gets signed when a capture is served.
+pub fn capture_payload(name: &str, sha256: &str) -> String {
+  format!("{name}\n{sha256}")
+}
+
+pub fn sha256_hex(data: &[u8]) -> String {
+  Sha256::digest(data).encode_hex::<String>()
+}
+
+/// Plain `.pcap` file names only, anything else could escape the directory.
+pub fn is_capture_name(name: &str) -> bool {
+  name.ends_with(".pcap")
+    && name.len() > ".pcap".len()
+    && !name.starts_with('.')
+    && !name.contains(['/', '\\'])
+}
+
+/// Digest of a capture that was already hashed, valid while the size and
+/// modification time stay the same.
+struct CachedDigest {
+  size: u64,
+  modified: SystemTime,
+  sha256: String,
+}
+
+static DIGESTS: LazyLock<Mutex<HashMap<PathBuf, CachedDigest>>> = LazyLock::new(Mutex::default);
+
+/// Digests every capture in `dir`, sorted by name. Unchanged captures are not
+/// read again.
+pub fn local_inventory<P: AsRef<Path>>(dir: P) -> Vec<HandshakeDigest> {
+  let dir = dir.as_ref();
+  let Ok(entries) = fs::read_dir(dir) else {
+    return Vec::new();
+  };
+
+  let mut cache = DIGESTS.lock();
+  let mut digests = entries
+    .flatten()
+    .filter_map(|entry| {
+      let name = entry.file_name().to_string_lossy().to_string();
+      if !is_capture_name(&name) {
+        return None;
+      }
+
+      let path = entry.path();
+      let meta = entry.metadata().ok()?;
+      let modified = meta.modified().ok()?;
+      let sha256 = match cache.get(&path) {
+        Some(cached) if cached.size == meta.len() && cached.modified == modified => {
+          cached.sha256.clone()
+        }
+        _ => {
+          let sha256 = sha256_hex(&fs::read(&path).ok()?);
+          let cached = CachedDigest {
+            size: meta.len(),
+            modified,
+            sha256: sha256.clone(),
+          };
+          cache.insert(path.clone(), cached);
+          sha256
+        }
+      };
+
+      Some(HandshakeDigest {
+        sha256,
+        size: meta.len(),
+        origin: read_origin(&path).map(|o| o.fingerprint),
+        name,
+      })
+    })
+    .collect::<Vec<_>>();
+
+  // forget captures that are gone
+  cache.retain(|path, _| path.parent() != Some(dir) || path.exists());
+  drop(cache);
+
+  digests.sort_by(|a, b| a.name.cmp(&b.name));
+  digests
+}
+
+fn origin_path(capture: &Path) -> PathBuf {
+  let mut path = capture.as_os_str().to_owned();
+  path.push(".");
+  path.push(ORIGIN_EXTENSION);
+  PathBuf::from(path)
+}
+
+/// Where a capture came from, `None` for own captures.
+pub fn read_origin(capture: &Path) -> Option<Origin> {
+  let content = fs::read_to_string(origin_path(capture)).ok()?;
+  serde_json::from_str(&content).ok()
+}
+
+/// Stores a capture received from `from` after checking it matches `digest`.
+/// Existing captures are never replaced, a different capture with the same
+/// name gets the sender's fingerprint appended, and a counter after that.
+pub fn store_capture<P: AsRef<Path>>(
+  dir: P,
+  digest: &HandshakeDigest,
+  data: &[u8],
+  from: &str,
+  now: i64,
+) -> Result<PathBuf> {
+  if !is_capture_name(&digest.name) {
+    bail!("refusing to store {:?}", digest.name);
+  }
+  let sha256 = sha256_hex(data);
+  if !sha256.eq_ignore_ascii_case(&digest.sha256) {
+    bail!("{} does not match its digest", digest.name);
+  }
+
+  let dir = dir.as_ref();
+  let tmp = dir.join(format!(".{}.tmp", digest.name));
+  fs::write(&tmp, data)?;
+
+  let stem = digest.name.trim_end_matches(".pcap");
+  let short = from.get(..8).unwrap_or(from);
+  let candidates = std::iter::once(digest.name.clone())
+    .chain(std::iter::once(format!("{stem}_{short}.pcap")))
+    .chain((2..100).map(|n| format!("{stem}_{short}_{n}.pcap")));
+
+  // a hard link fails instead of replacing what is already there
+  let mut stored = None;
+  for name in candidates {
+    let path = dir.join(name);
+    match fs::hard_link(&tmp, &path) {
+      Ok(()) => {
+        stored = Some(path);
+        break;
+      }
+      Err(e) if e.kind() == ErrorKind::AlreadyExists => {}
+      Err(e) => {
+        let _ = fs::remove_file(&tmp);
+        return Err(e.into());
+      }
+    }
+  }
+  let _ = fs::remove_file(&tmp);
+  let Some(path) = stored else {
+    bail!("no free name left for {}", digest.name);
+  };
+
+  let origin = Origin {
+    fingerprint: digest.origin.clone().unwrap_or_else(|| from.to_string()),
+    via: from.to_string(),
+    sha256,
+    received_at: now,
+  };
+  let sidecar = origin_path(&path);
+  let tmp = sidecar.with_extension("tmp");
+  fs::write(&tmp, serde_json::to_string(&origin)?)?;
+  fs::rename(&tmp, &sidecar)?;
+  Ok(path)
+}
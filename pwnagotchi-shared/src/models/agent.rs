@@ -1,4 +1,4 @@
-use std::fmt::Display;
+use std::{fmt::Display, str::FromStr};
 
 use serde::{Deserialize, Serialize};
 
@@ -21,3 +21,52 @@ impl Display for RunningMode {
     write!(f, "{}", s)
   }
 }
+
+impl FromStr for RunningMode {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.trim().to_uppercase().as_str() {
+      "AUTO" => Ok(Self::Auto),
+      "MANU" | "MANUAL" => Ok(Self::Manual),
+      "AI" => Ok(Self::Ai),
+      "CUST" | "CUSTOM" => Ok(Self::Custom),
+      other => Err(format!("Unknown running mode: {other}")),
+    }
+  }
+}
+
+/// A request to take the unit down, handled by the lifecycle in the main
+/// binary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PowerAction {
+  Shutdown,
+  Reboot(Option<RunningMode>),
+  Restart(RunningMode),
+}
+
+impl PowerAction {
+  /// The mode the unit should come back up in, a plain reboot comes back in
+  /// auto mode.
+  pub const fn mode(&self) -> Option<RunningMode> {
+    match self {
+      Self::Shutdown => None,
+      Self::Reboot(mode) => match mode {
+        Some(mode) => Some(*mode),
+        None => Some(RunningMode::Auto),
+      },
+      Self::Restart(mode) => Some(*mode),
+    }
+  }
+}
+
+impl Display for PowerAction {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Shutdown => write!(f, "Shutting down"),
+      Self::Reboot(Some(mode)) => write!(f, "Rebooting in {mode} mode"),
+      Self::Reboot(None) => write!(f, "Rebooting"),
+      Self::Restart(mode) => write!(f, "Restarting in {mode} mode"),
+    }
+  }
+}
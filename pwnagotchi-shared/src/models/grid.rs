@@ -11,6 +11,12 @@ pub struct Advertisement {
   pub uptime: u32,
   pub epoch: u32,
   pub policy: PersonalityConfig,
+  /// Channels the unit is working this epoch.
+  #[serde(default)]
+  pub channels: Vec<u8>,
+  /// BSSIDs of the unit's most recent handshakes.
+  #[serde(default)]
+  pub handshakes: Vec<String>,
 }
 
 #[derive(serde::Deserialize, serde::Serialize, Clone)]
@@ -0,0 +1,63 @@
+use std::{
+  fs,
+  io::{self, Write},
+  path::Path,
+  time::SystemTime,
+};
+
+/// Entries kept, older ones are dropped.
+pub const MAX_ENTRIES: usize = 100;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct JournalEntry {
+  pub timestamp: SystemTime,
+  pub origin: String,
+  pub message: String,
+}
+
+impl JournalEntry {
+  pub fn new(origin: &str, message: &str) -> Self {
+    Self {
+      timestamp: SystemTime::now(),
+      origin: origin.to_string(),
+      message: message.to_string(),
+    }
+  }
+}
+
+/// Entries saved at `path`, one JSON object per line. Lines that don't parse
+/// are skipped, a missing file is an empty journal.
+pub fn load(path: &Path) -> Vec<JournalEntry> {
+  let Ok(data) = fs::read_to_string(path) else {
+    return Vec::new();
+  };
+  let mut entries = data
+    .lines()
+    .filter_map(|line| serde_json::from_str(line).ok())
+    .collect::<Vec<_>>();
+  truncate(&mut entries);
+  entries
+}
+
+/// Replaces the journal at `path` with `entries`. Written to a temporary file
+/// first, the unit may be reset right after recording why.
+pub fn save(path: &Path, entries: &[JournalEntry]) -> io::Result<()> {
+  let mut data = Vec::new();
+  for entry in entries {
+    serde_json::to_writer(&mut data, entry)?;
+    data.push(b'\n');
+  }
+
+  let mut tmp = path.as_os_str().to_owned();
+  tmp.push(".tmp");
+  let mut file = fs::File::create(&tmp)?;
+  file.write_all(&data)?;
+  file.sync_all()?;
+  fs::rename(&tmp, path)
+}
+
+/// Drops the oldest entries beyond [`MAX_ENTRIES`].
+pub fn truncate(entries: &mut Vec<JournalEntry>) {
+  let excess = entries.len().saturating_sub(MAX_ENTRIES);
+  entries.drain(..excess);
+}
@@ -1,10 +1,16 @@
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc, time::SystemTime};
 
 use parking_lot::RwLock;
 use tokio::sync::broadcast;
 
 use crate::{
-  sessions::{lastsession::LastSession, session::Session},
+  config::config_read,
+  logger::LOGGER,
+  sessions::{
+    journal::{self, JournalEntry},
+    lastsession::LastSession,
+    session::Session,
+  },
   traits::general::CoreModule,
 };
 
@@ -18,6 +24,8 @@ pub struct SessionManager {
   current: Arc<RwLock<Session>>,
   last: Arc<RwLock<LastSession>>,
   notifier: broadcast::Sender<()>,
+  /// Where the journal is kept across restarts, see [`Self::persist_journal`].
+  journal_path: RwLock<Option<PathBuf>>,
 }
 
 impl Default for SessionManager {
@@ -32,7 +40,12 @@ impl SessionManager {
     let current = Arc::new(RwLock::new(Session::new()));
     let last = Arc::new(RwLock::new(LastSession::new()));
 
-    Self { current, last, notifier: tx }
+    Self {
+      current,
+      last,
+      notifier: tx,
+      journal_path: RwLock::new(None),
+    }
   }
 
   pub fn set_session(&self, new_session: Session) {
@@ -56,29 +69,70 @@ impl SessionManager {
     Arc::clone(&self.current)
   }
 
+  /// Records what the unit did to recover, the last [`journal::MAX_ENTRIES`]
+  /// are kept.
+  pub fn journal(&self, origin: &str, message: &str) {
+    let entries = {
+      let mut session = self.current.write();
+      let entries = &mut session.state.journal;
+      entries.push(JournalEntry::new(origin, message));
+      journal::truncate(entries);
+      entries.clone()
+    };
+
+    if let Some(path) = self.journal_path.read().as_deref()
+      && let Err(e) = journal::save(path, &entries)
+    {
+      LOGGER.log_error("SessionManager", &format!("Failed to save journal: {e}"));
+    }
+    let _ = self.notifier.send(());
+  }
+
+  /// Loads the journal saved at `path` in front of this session's entries and
+  /// saves every new entry there from now on.
+  pub fn persist_journal(&self, path: impl Into<PathBuf>) {
+    let path = path.into();
+    {
+      let mut session = self.current.write();
+      let mut entries = journal::load(&path);
+      entries.append(&mut session.state.journal);
+      journal::truncate(&mut entries);
+      session.state.journal = entries;
+    }
+    *self.journal_path.write() = Some(path);
+  }
+
   pub fn subscribe(&self) -> broadcast::Receiver<()> {
     self.notifier.subscribe()
   }
 
-  /*async fn save_recovery_data(&self) {
+  pub fn save_recovery_data(&self) {
     LOGGER.log_warning("SessionManager", "Saving recovery data...");
-    let session = self.current.read().await;
-    let state = session.state.read().clone();
-    let data = serde_json::json!({
-        "started_at": session.started_at,
-        "history": state.history,
-        "handshakes": state.handshakes,
-        "last_pwned": state.last_pwned,
-      }
-    );
-    drop(state);
-    drop(session);
-    tokio::fs::write(RECOVERY_FILE, data.to_string()).await.unwrap_or_else(|e| {
+    let path = config_read().debug.recovery_file.clone();
+
+    let data = {
+      let session = self.current.read();
+      let started_at = session
+        .started_at
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+      serde_json::json!({
+        "started_at": started_at,
+        "mode": session.mode,
+        "history": session.state.history,
+        "handshakes": session.state.handshakes,
+        "last_pwned": session.state.last_pwned,
+      })
+    };
+
+    if let Err(e) = std::fs::write(&path, data.to_string()) {
       LOGGER.log_error("SessionManager", &format!("Failed to write recovery data: {e}"));
-    });
+    }
   }
 
-  async fn load_recovery_data(&self) {
+  /*async fn load_recovery_data(&self) {
     if let Ok(data) = tokio::fs::read_to_string(RECOVERY_FILE).await {
       if let Ok(json) = serde_json::from_str::<serde_json::Value>(&data) {
         let started_at = json.get("started_at").and_then(|v| v.as_u64()).unwrap_or(0);
@@ -6,6 +6,7 @@ use crate::{
     agent::RunningMode,
     net::{AccessPoint, Handshake},
   },
+  sessions::journal::JournalEntry,
 };
 
 #[derive(Debug, Clone)]
@@ -16,9 +17,13 @@ pub struct Session {
   pub state: SessionState,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct SessionState {
   pub current_channel: u8,
+  /// Channels picked for the current epoch.
+  pub working_channels: Vec<u8>,
+  /// Channels left to nearby peers in the current epoch.
+  pub ceded_channels: Vec<u8>,
   pub total_aps: u32,
   pub aps_on_channel: u32,
   pub peers: Vec<Peer>,
@@ -26,6 +31,7 @@ pub struct SessionState {
   pub last_pwned: Option<String>,
   pub history: HashMap<String, u32>,
   pub handshakes: HashMap<String, Handshake>,
+  pub journal: Vec<JournalEntry>,
 }
 
 impl Default for Session {
@@ -42,6 +48,8 @@ impl Session {
       mode: RunningMode::Manual,
       state: SessionState {
         current_channel: 0,
+        working_channels: vec![],
+        ceded_channels: vec![],
         total_aps: 0,
         aps_on_channel: 0,
         peers: vec![],
@@ -49,6 +57,7 @@ impl Session {
         last_pwned: None,
         history: HashMap::new(),
         handshakes: HashMap::new(),
+        journal: vec![],
       },
     }
   }
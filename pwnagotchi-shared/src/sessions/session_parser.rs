@@ -1,14 +1,15 @@
 use std::{
-  fs,
+  path::Path,
   sync::{Arc, LazyLock},
   time::SystemTime,
 };
 
-use anyhow::Result;
+use anyhow::{Result, bail};
 use regex::Regex;
 use time::{UtcDateTime, macros::format_description};
 
 use crate::{
+  logger::rotate,
   mesh::peer::Peer,
   models::grid::Advertisement,
   sessions::session_stats::{EpochStats, PeerStats, SessionStats},
@@ -31,15 +32,23 @@ static PEER_RE: LazyLock<Regex> = LazyLock::new(|| {
 
 pub struct SessionParser;
 
+/// Parses the log at `path` together with its rotated segments, oldest
+/// first.
 pub fn parse_session_from_file(
   path: &str,
   view: Option<&Arc<dyn ViewTrait + Send + Sync>>,
 ) -> Result<SessionStats> {
-  let content = fs::read_to_string(path)?;
-  Ok(parse_session(&content, view))
+  let path = Path::new(path);
+  if rotate::segments(path).is_empty() {
+    bail!("no log at {}", path.display());
+  }
+  Ok(parse_session(rotate::read_lines(path), view))
 }
 
-fn parse_session(content: &str, view: Option<&Arc<dyn ViewTrait + Send + Sync>>) -> SessionStats {
+pub fn parse_session(
+  lines: impl Iterator<Item = String>,
+  view: Option<&Arc<dyn ViewTrait + Send + Sync>>,
+) -> SessionStats {
   let mut stats = SessionStats::default();
 
   let format = format_description!("[year]-[month]-[day] [hour]:[minute]:[second]");
@@ -48,7 +57,7 @@ fn parse_session(content: &str, view: Option<&Arc<dyn ViewTrait + Send + Sync>>)
     view.on_reading_logs(0);
   }
 
-  for (line_index, line) in content.lines().enumerate() {
+  for (line_index, line) in lines.enumerate() {
     let line = line.trim();
     if line.is_empty() || !line.starts_with('[') {
       continue;
@@ -131,6 +140,7 @@ fn handle_peer_line(line: &str, peers: &mut PeerStats) {
       session_id: sid,
       last_channel: 1,
       rssi,
+      rssi_history: vec![rssi],
       encounters: 1,
       first_met: None,
       first_seen: None,
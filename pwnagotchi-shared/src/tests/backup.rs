@@ -0,0 +1,180 @@
+use std::{
+  os::unix::fs::PermissionsExt,
+  path::{Path, PathBuf},
+};
+
+use hex::ToHex;
+
+use crate::{
+  backup::{Bundle, EntryKind, FORMAT_VERSION, MAGIC, Sources, derive_key},
+  config::DebugConfig,
+};
+
+const PASSPHRASE: &str = "correct horse battery staple";
+const ITERATIONS: u32 = 16;
+
+/// A unit with a bit of everything, the private key readable by the owner
+/// only.
+fn unit(dir: &Path) -> Sources {
+  let identity = dir.join("identity");
+  let handshakes = dir.join("handshakes");
+  std::fs::create_dir_all(&identity).unwrap();
+  std::fs::create_dir_all(&handshakes).unwrap();
+
+  std::fs::write(dir.join("config.toml"), "[main]\nname = \"alpha\"\n").unwrap();
+  std::fs::write(identity.join("id_rsa"), "private").unwrap();
+  std::fs::set_permissions(identity.join("id_rsa"), std::fs::Permissions::from_mode(0o600))
+    .unwrap();
+  std::fs::write(identity.join("id_rsa.pub"), "public").unwrap();
+  std::fs::write(identity.join("fingerprint"), "abcdef").unwrap();
+  std::fs::write(handshakes.join("a.pcap"), [0xd4, 0xc3, 0xb2, 0xa1]).unwrap();
+  std::fs::write(handshakes.join("b.pcap"), [0xa1, 0xb2, 0xc3, 0xd4]).unwrap();
+  std::fs::write(dir.join("peers.json"), "[]").unwrap();
+  std::fs::write(dir.join("pwnagotchi.log"), "[2024-01-01 00:00:00] [INFO] hi\n").unwrap();
+  std::fs::write(dir.join("pwnagotchi.log.1.gz"), "older").unwrap();
+  std::fs::write(dir.join("journal"), "{\"step\":\"reload_module\"}\n").unwrap();
+
+  Sources {
+    config: Some(dir.join("config.toml")),
+    identity,
+    handshakes,
+    peer_db: dir.join("peers.json"),
+    log: dir.join("pwnagotchi.log"),
+    session: vec![
+      dir.join("missing-last-session"),
+      dir.join("journal"),
+    ],
+  }
+}
+
+#[test]
+fn the_recovery_journal_is_part_of_the_session() {
+  let journal = DebugConfig::default().journal_file;
+  assert!(Sources::from_config().session.contains(&PathBuf::from(journal)));
+}
+
+#[test]
+fn key_derivation_matches_pbkdf2_vectors() {
+  assert_eq!(
+    derive_key("password", b"salt", 1).encode_hex::<String>(),
+    "120fb6cffcf8b32c43e7225256c4f837a86548c92ccc35480805987cb70be17b"
+  );
+  assert_eq!(
+    derive_key("password", b"salt", 2).encode_hex::<String>(),
+    "ae4d0c95af6b46d32d0adff928f06dd02a303f8ef3c251dfd6e2d85a95474c43"
+  );
+}
+
+#[test]
+fn backup_restores_every_part_of_the_unit() {
+  let tmp = tempfile::tempdir().unwrap();
+  let dir = tmp.path();
+  let sources = unit(&dir.join("unit"));
+
+  let bundle = Bundle::collect(&sources, "alpha", 1_700_000_000).unwrap();
+  assert_eq!(bundle.fingerprint, "abcdef");
+  let counts = bundle.counts();
+  assert_eq!(counts[&EntryKind::Config], 1);
+  assert_eq!(counts[&EntryKind::Identity], 3);
+  assert_eq!(counts[&EntryKind::Handshake], 2);
+  assert_eq!(counts[&EntryKind::PeerDb], 1);
+  assert_eq!(counts[&EntryKind::Session], 3);
+
+  let archive = bundle.seal(PASSPHRASE, ITERATIONS).unwrap();
+  assert!(archive.starts_with(MAGIC));
+
+  let opened = Bundle::open(&archive, PASSPHRASE).unwrap();
+  let root = dir.join("sdcard");
+  let report = opened.restore(&sources, Some(&root), false).unwrap();
+  assert_eq!((report.written, report.unchanged), (10, 0));
+
+  let restored = root.join(sources.identity.join("id_rsa").strip_prefix("/").unwrap());
+  assert_eq!(std::fs::read_to_string(&restored).unwrap(), "private");
+  assert_eq!(std::fs::metadata(&restored).unwrap().permissions().mode() & 0o777, 0o600);
+
+  let report = opened.restore(&sources, Some(&root), false).unwrap();
+  assert_eq!((report.written, report.unchanged), (0, 10));
+}
+
+#[test]
+fn damaged_or_foreign_archives_are_refused() {
+  let tmp = tempfile::tempdir().unwrap();
+  let dir = tmp.path();
+  let bundle = Bundle::collect(&unit(dir), "alpha", 0).unwrap();
+  let archive = bundle.seal(PASSPHRASE, ITERATIONS).unwrap();
+
+  assert!(Bundle::open(&archive, "wrong").is_err());
+
+  let mut flipped = archive.clone();
+  *flipped.last_mut().unwrap() ^= 1;
+  assert!(Bundle::open(&flipped, PASSPHRASE).is_err());
+
+  let mut newer = archive.clone();
+  newer[MAGIC.len()] = FORMAT_VERSION + 1;
+  let err = Bundle::open(&newer, PASSPHRASE).unwrap_err().to_string();
+  assert!(err.contains("newer"), "{err}");
+
+  assert!(Bundle::open(b"PK\x03\x04 not a backup", PASSPHRASE).is_err());
+  assert!(bundle.seal("", ITERATIONS).is_err());
+
+  let mut escaping = bundle;
+  escaping.entries[0].path = "/tmp/../etc/shadow".to_string();
+  let archive = escaping.seal(PASSPHRASE, ITERATIONS).unwrap();
+  assert!(Bundle::open(&archive, PASSPHRASE).is_err());
+}
+
+#[test]
+fn differing_files_are_only_replaced_with_force() {
+  let tmp = tempfile::tempdir().unwrap();
+  let dir = tmp.path();
+  let sources = unit(&dir.join("unit"));
+  let archive = Bundle::collect(&sources, "alpha", 0)
+    .unwrap()
+    .seal(PASSPHRASE, ITERATIONS)
+    .unwrap();
+  let bundle = Bundle::open(&archive, PASSPHRASE).unwrap();
+
+  let root = dir.join("sdcard");
+  let peers = root.join(sources.peer_db.strip_prefix("/").unwrap());
+  std::fs::create_dir_all(peers.parent().unwrap()).unwrap();
+  std::fs::write(&peers, "[{\"name\":\"newer\"}]").unwrap();
+
+  assert!(bundle.restore(&sources, Some(&root), false).is_err());
+  assert!(!root.join(sources.identity.join("id_rsa").strip_prefix("/").unwrap()).exists());
+
+  let report = bundle.restore(&sources, Some(&root), true).unwrap();
+  assert_eq!(report.written, 10);
+  assert_eq!(std::fs::read_to_string(&peers).unwrap(), "[]");
+}
+
+#[test]
+fn entries_are_only_restored_where_their_kind_belongs() {
+  let tmp = tempfile::tempdir().unwrap();
+  let dir = tmp.path();
+  let sources = unit(&dir.join("unit"));
+  let bundle = Bundle::collect(&sources, "alpha", 0).unwrap();
+  let root = dir.join("sdcard");
+
+  let handshake = |path: &Path| {
+    let mut bundle = bundle.clone();
+    let entry = bundle.entries.iter_mut().find(|e| e.kind == EntryKind::Handshake).unwrap();
+    entry.path = path.to_string_lossy().to_string();
+    bundle
+  };
+  let outside = handshake(Path::new("/etc/cron.d/pwn"));
+  assert!(outside.restore(&sources, Some(&root), true).is_err());
+  let identity = handshake(&sources.identity.join("id_rsa"));
+  assert!(identity.restore(&sources, Some(&root), true).is_err());
+  assert!(!root.exists());
+
+  let mut renamed = bundle.clone();
+  let entry = renamed.entries.iter_mut().find(|e| e.kind == EntryKind::Identity).unwrap();
+  entry.path = sources.identity.join("authorized_keys").to_string_lossy().to_string();
+  assert!(renamed.restore(&sources, Some(&root), true).is_err());
+
+  let mut setuid = bundle;
+  setuid.entries.iter_mut().for_each(|e| e.mode = 0o4755);
+  setuid.restore(&sources, Some(&root), true).unwrap();
+  let restored = root.join(sources.peer_db.strip_prefix("/").unwrap());
+  assert_eq!(std::fs::metadata(&restored).unwrap().permissions().mode() & 0o7777, 0o755);
+}
@@ -0,0 +1,136 @@
+use crate::config::{
+  check::check,
+  migrate::{CONFIG_VERSION, migrate, migrate_text},
+};
+
+const PYTHON_CONFIG: &str = r#"
+# my gotchi
+[main]
+name = "gotchi"
+# plugins I wrote
+custom_plugins = "/usr/local/share/pwnagotchi/custom-plugins/"
+whitelist = ["home"]
+
+# talks to the grid
+[main.plugins.grid]
+enabled = true
+report = true # share what we saw
+
+[main.plugins.webcfg]
+enabled = false
+
+[main.log]
+path = "/var/log/pwnagotchi.log"
+
+[main.log.rotation]
+enabled = true
+size = "20M"
+
+[ui]
+# white on black
+invert = true
+
+[ui.faces]
+# a nicer smile
+happy = "(^‿^)"
+
+[ui.display]
+enabled = true
+type = "waveshare_4"
+"#;
+
+#[test]
+fn current_layout_is_clean() {
+  let checked = check("version = 1\n[main]\nname = \"gotchi\"\n").unwrap();
+
+  assert!(checked.is_ok());
+  assert!(checked.warnings.is_empty());
+  assert!(checked.migrated.is_empty());
+  assert_eq!(checked.config.main.name, "gotchi");
+}
+
+#[test]
+fn unknown_keys_are_warned_about_with_their_line() {
+  let checked = check("[main]\nname = \"gotchi\"\n\n[personality]\ndeuath = false\n").unwrap();
+
+  assert!(checked.is_ok());
+  assert_eq!(checked.warnings.len(), 1);
+  assert_eq!(checked.warnings[0].key, "personality.deuath");
+  assert_eq!(checked.warnings[0].location, Some((5, 1)));
+}
+
+#[test]
+fn type_errors_and_invalid_values_point_at_the_key() {
+  let err = check("[bettercap]\nport = \"eighty\"\n").unwrap_err();
+  assert!(err.contains("line 2"), "{err}");
+
+  let checked = check("[personality]\nchannels = [1, 6, 200]\nmin_rssi = 10\n").unwrap();
+  let errors = checked.errors.iter().map(ToString::to_string).collect::<Vec<_>>();
+  assert_eq!(
+    errors,
+    [
+      "2:1: personality.channels: 200 is not a wifi channel",
+      "3:1: personality.min_rssi: 10 dBm is not between -200 and 0",
+    ]
+  );
+}
+
+#[test]
+fn python_layout_is_imported() {
+  let checked = check(PYTHON_CONFIG).unwrap();
+  let config = &checked.config;
+
+  assert!(checked.is_ok(), "{:?}", checked.errors);
+  assert!(checked.warnings.is_empty(), "{:?}", checked.warnings);
+  assert!(!checked.migrated.is_empty());
+
+  assert_eq!(config.version, CONFIG_VERSION);
+  assert_eq!(config.main.name, "gotchi");
+  assert_eq!(
+    config.main.plugins_path.as_deref(),
+    Some("/usr/local/share/pwnagotchi/custom-plugins/")
+  );
+  assert!(config.plugins["grid"].enabled);
+  assert_eq!(config.plugins["grid"].config.as_ref().unwrap()["report"], true);
+  assert!(!config.plugins["webcfg"].enabled);
+  assert_eq!(config.log.path, "/var/log/pwnagotchi.log");
+  assert_eq!(config.log.rotation.size, "20M");
+  assert!(config.ui.inverted);
+  assert_eq!(config.faces.happy, "(^‿^)");
+  assert_eq!(config.ui.display.r#type, "waveshare_v4");
+}
+
+#[test]
+fn migrations_only_run_on_older_versions() {
+  let text = "version = 1\n[main.plugins.grid]\nenabled = true\n";
+  assert!(migrate(&mut text.parse().unwrap()).is_empty());
+  assert_eq!(migrate_text(text), Ok(None));
+
+  let checked = check("version = 1\n[main.plugins.grid]\nenabled = true\n").unwrap();
+  assert_eq!(checked.warnings[0].key, "main.plugins");
+}
+
+#[test]
+fn migrated_files_keep_their_comments() {
+  let (text, notes) = migrate_text(PYTHON_CONFIG).unwrap().unwrap();
+
+  assert!(!notes.is_empty());
+  for kept in [
+    "# my gotchi\n[main]\n",
+    "# plugins I wrote\nplugins_path = ",
+    "# talks to the grid\n[plugins.grid]\n",
+    "report = true # share what we saw\n",
+    "# white on black\ninverted = true\n",
+    "# a nicer smile\nhappy = ",
+  ] {
+    assert!(text.contains(kept), "{kept:?} missing from\n{text}");
+  }
+  assert!(!text.contains("[plugins]\n"), "{text}");
+
+  let checked = check(&text).unwrap();
+  assert!(checked.is_ok(), "{:?}", checked.errors);
+  assert!(checked.migrated.is_empty());
+  assert!(checked.warnings.is_empty(), "{:?}", checked.warnings);
+  assert_eq!(checked.config.plugins["grid"].config.as_ref().unwrap()["report"], true);
+  assert!(checked.config.ui.inverted);
+}
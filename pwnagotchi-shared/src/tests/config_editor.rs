@@ -0,0 +1,102 @@
+use serde_json::json;
+
+use crate::config::{
+  Config,
+  editor::{self, Edits, FieldKind, REDACTED},
+};
+
+fn edits(pairs: &[(&str, serde_json::Value)]) -> Edits {
+  pairs.iter().map(|(k, v)| ((*k).to_string(), v.clone())).collect()
+}
+
+#[test]
+fn default_config_is_valid() {
+  assert_eq!(Config::default().validate(), Vec::new());
+}
+
+#[test]
+fn apply_reports_changes_and_restarts() {
+  let config = Config::default();
+  let (updated, changes) = editor::apply(
+    &config,
+    &edits(&[
+      ("/personality/deauth", json!(!config.personality.deauth)),
+      ("/ui/web/port", json!(8082)),
+      ("/main/name", json!(config.main.name.to_string())),
+    ]),
+  )
+  .unwrap();
+
+  assert_eq!(updated.ui.web.port, 8082);
+  assert_eq!(changes.len(), 2);
+  let deauth = changes.iter().find(|c| c.path == "/personality/deauth").unwrap();
+  assert!(!deauth.restart);
+  let port = changes.iter().find(|c| c.path == "/ui/web/port").unwrap();
+  assert!(port.restart);
+  assert_eq!(port.new, json!(8082));
+}
+
+#[test]
+fn apply_rejects_bad_values_with_their_path() {
+  let config = Config::default();
+
+  let errors = editor::apply(
+    &config,
+    &edits(&[
+      ("/personality/channels", json!([1, 6, 300])),
+      ("/bettercap/port", json!("eighty")),
+      ("/main/nope", json!(true)),
+    ]),
+  )
+  .unwrap_err();
+  let paths = errors.iter().map(|e| e.path.as_str()).collect::<Vec<_>>();
+  assert_eq!(
+    paths,
+    [
+      "/bettercap/port",
+      "/main/nope",
+      "/personality/channels"
+    ]
+  );
+
+  let errors = editor::apply(
+    &config,
+    &edits(&[
+      ("/personality/min_rssi", json!(20)),
+      ("/ui/display/rotation", json!(45)),
+    ]),
+  )
+  .unwrap_err();
+  let paths = errors.iter().map(|e| e.path.as_str()).collect::<Vec<_>>();
+  assert_eq!(
+    paths,
+    [
+      "/personality/min_rssi",
+      "/ui/display/rotation"
+    ]
+  );
+}
+
+#[test]
+fn secrets_stay_hidden_and_unchanged() {
+  let mut config = Config::default();
+  config.ui.web.password = "hunter2".into();
+
+  let field = editor::sections(&config)
+    .unwrap()
+    .into_iter()
+    .flat_map(|s| s.fields)
+    .find(|f| f.path == "/ui/web/password")
+    .unwrap();
+  assert_eq!(field.value, REDACTED);
+  assert_eq!(field.kind, FieldKind::Text);
+
+  let (updated, changes) =
+    editor::apply(&config, &edits(&[("/ui/web/password", json!(REDACTED))])).unwrap();
+  assert_eq!(updated.ui.web.password, "hunter2");
+  assert!(changes.is_empty());
+
+  let (_, changes) =
+    editor::apply(&config, &edits(&[("/ui/web/password", json!("correct horse"))])).unwrap();
+  assert_eq!(changes[0].new, json!(REDACTED));
+}
@@ -0,0 +1,121 @@
+use std::path::{Path, PathBuf};
+
+use crate::config::layers::{Source, USER_FILE, load_from, settings};
+
+fn write(path: &Path, content: &str) -> PathBuf {
+  std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+  std::fs::write(path, content).unwrap();
+  path.to_path_buf()
+}
+
+fn env(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+  vars.iter().map(|(k, v)| ((*k).to_string(), (*v).to_string())).collect()
+}
+
+#[test]
+fn fragments_and_env_override_the_base_in_order() {
+  let tmp = tempfile::tempdir().unwrap();
+  let dir = tmp.path();
+  let base = write(
+    &dir.join("config.toml"),
+    "version = 1\n[main]\nname = \"base\"\n[personality]\ndeauth = true\nassociate = true\n",
+  );
+  let second = write(&dir.join("conf.d/20-second.toml"), "[personality]\ndeauth = false\n");
+  let first =
+    write(&dir.join("conf.d/10-first.toml"), "[personality]\ndeauth = true\nassociate = false\n");
+  write(&dir.join("conf.d/notes.txt"), "not a fragment");
+
+  let layered = load_from(
+    &base,
+    env(&[
+      ("PWNAGOTCHI__MAIN__NAME", "gotchi"),
+      ("PWNAGOTCHI_OTHER", "ignored"),
+    ]),
+  )
+  .unwrap();
+  let config = &layered.checked.config;
+  let sources = &layered.stack.sources;
+
+  assert!(layered.checked.is_ok(), "{:?}", layered.checked.errors);
+  assert_eq!(layered.stack.fragments, [first.clone(), second.clone()]);
+  assert_eq!(config.main.name, "gotchi");
+  assert!(!config.personality.deauth);
+  assert!(!config.personality.associate);
+
+  assert_eq!(sources["main.name"], Source::Env("PWNAGOTCHI__MAIN__NAME".into()));
+  assert_eq!(sources["personality.deauth"], Source::File(second));
+  assert_eq!(sources["personality.associate"], Source::File(first));
+  assert!(!sources.contains_key("personality.ap_ttl"));
+
+  let settings = settings(config, sources).unwrap();
+  let ap_ttl = settings.iter().find(|s| s.key == "personality.ap_ttl").unwrap();
+  assert_eq!(ap_ttl.source, None);
+}
+
+#[test]
+fn env_values_take_the_type_of_the_setting() {
+  let tmp = tempfile::tempdir().unwrap();
+  let dir = tmp.path();
+  let layered = load_from(
+    &dir.join("config.toml"),
+    env(&[
+      ("PWNAGOTCHI__MAIN__NAME", "1234"),
+      ("PWNAGOTCHI__UI__WEB__PORT", "8081"),
+      ("PWNAGOTCHI__PERSONALITY__CHANNELS", "[1, 6, 11]"),
+      ("PWNAGOTCHI__PERSONALITY__DEAUHT", "false"),
+    ]),
+  )
+  .unwrap();
+  let config = &layered.checked.config;
+
+  assert_eq!(config.main.name, "1234");
+  assert_eq!(config.ui.web.port, 8081);
+  assert_eq!(config.personality.channels, [1, 6, 11]);
+  assert_eq!(
+    layered.checked.warnings.iter().map(ToString::to_string).collect::<Vec<_>>(),
+    ["$PWNAGOTCHI__PERSONALITY__DEAUHT: personality.deauht: unknown setting, ignored"]
+  );
+}
+
+#[test]
+fn errors_point_at_the_layer_that_set_the_key() {
+  let tmp = tempfile::tempdir().unwrap();
+  let dir = tmp.path();
+  let base = write(&dir.join("config.toml"), "[ui]\nfps = 1.0\n");
+  let fragment = write(&dir.join("conf.d/10-ui.toml"), "[ui]\n\nfps = -1.0\n");
+
+  let checked = load_from(&base, Vec::new()).unwrap().checked;
+  assert_eq!(
+    checked.errors.iter().map(ToString::to_string).collect::<Vec<_>>(),
+    [format!(
+      "{}:3:1: ui.fps: must be a positive number",
+      fragment.display()
+    )]
+  );
+}
+
+#[test]
+fn only_overrides_are_written_to_the_user_file() {
+  let tmp = tempfile::tempdir().unwrap();
+  let dir = tmp.path();
+  let base = write(&dir.join("config.toml"), "[main]\nname = \"base\"\n");
+  write(&dir.join("conf.d/10-grid.toml"), "[personality]\ndeauth = false\n");
+  write(&dir.join("conf.d").join(USER_FILE), "[ui.web]\nport = 8081\n");
+
+  let layered = load_from(&base, env(&[("PWNAGOTCHI__UI__WEB__PORT", "9000")])).unwrap();
+  let mut config = layered.checked.config.clone();
+  assert_eq!(config.ui.web.port, 9000);
+
+  config.personality.associate = !config.personality.associate;
+  let user = layered.stack.overrides(&config).unwrap();
+
+  assert_eq!(
+    user.to_string(),
+    format!(
+      "[personality]\nassociate = {}\n\n[ui.web]\nport = 8081\n",
+      config.personality.associate
+    )
+  );
+  let unchanged = layered.stack.overrides(&layered.checked.config).unwrap();
+  assert_eq!(unchanged.to_string(), "[ui.web]\nport = 8081\n");
+}
@@ -0,0 +1,57 @@
+use crate::config::{
+  Config, config_read,
+  reload::{self, ConfigChanged, EditError, changed_keys},
+};
+
+#[test]
+fn changed_keys_lists_every_changed_setting() {
+  let old = Config::default();
+  let mut new = old.clone();
+  new.personality.deauth = !old.personality.deauth;
+  new.main.whitelist.push("home".into());
+  new.bettercap.password = "secret".into();
+
+  assert_eq!(
+    changed_keys(&old, &new),
+    [
+      "/bettercap/password",
+      "/main/whitelist",
+      "/personality/deauth"
+    ]
+  );
+  assert!(changed_keys(&old, &old.clone()).is_empty());
+}
+
+#[test]
+fn touches_matches_whole_path_segments() {
+  let changed = ConfigChanged {
+    keys: vec![
+      "/grid/cooperate_rssi".into(),
+      "/personality/min_rssi".into(),
+    ],
+  };
+
+  assert!(changed.touches("/personality"));
+  assert!(changed.touches("/personality/"));
+  assert!(changed.touches("/personality/min_rssi"));
+  assert!(changed.touches("/grid/cooperate_rssi"));
+  assert!(!changed.touches("/grid/cooperate"));
+  assert!(!changed.touches("/bettercap"));
+}
+
+#[test]
+fn invalid_edits_leave_the_running_config_alone() {
+  let before = config_read().personality.recon_time;
+  let edits = [
+    ("/personality/recon_time".to_string(), serde_json::json!(before + 1)),
+    ("/personality/deauth".to_string(), serde_json::json!("sometimes")),
+  ]
+  .into_iter()
+  .collect();
+
+  let Err(EditError::Invalid(errors)) = reload::edit(&edits) else {
+    panic!("edits with an invalid value were applied");
+  };
+  assert_eq!(errors.len(), 1);
+  assert_eq!(config_read().personality.recon_time, before);
+}
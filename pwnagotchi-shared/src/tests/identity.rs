@@ -0,0 +1,113 @@
+use std::{os::unix::fs::PermissionsExt, path::PathBuf};
+
+use hex::ToHex;
+use rsa::traits::PublicKeyParts;
+use sha2::{Digest, Sha256};
+
+use crate::{
+  identity::{
+    Identity, KEY_BITS,
+    crypto::{public_key_from_b64, verify},
+  },
+  mesh::sharing::fingerprint_of,
+};
+
+/// A 4096 bit keypair in the layout `pwngrid -generate -keys` writes, PKCS#1
+/// private key and a PKIX public key under an `RSA PUBLIC KEY` header.
+const FIXTURE_PRIV: &str = include_str!("fixtures/identity/id_rsa");
+const FIXTURE_PUB: &str = include_str!("fixtures/identity/id_rsa.pub");
+const FIXTURE_FINGERPRINT: &str = include_str!("fixtures/identity/fingerprint");
+/// The enrollment identity `alpha@<fingerprint>` signed with the fixture key,
+/// PSS with a 16 byte salt like pwngrid signs.
+const FIXTURE_SIGNATURE: &str = include_str!("fixtures/identity/signature");
+
+fn mode(path: PathBuf) -> u32 {
+  std::fs::metadata(path).unwrap().permissions().mode() & 0o777
+}
+
+#[test]
+fn fixture_fingerprint_matches_pwngrid() {
+  let tmp = tempfile::tempdir().unwrap();
+  let dir = tmp.path();
+  std::fs::write(dir.join("id_rsa"), FIXTURE_PRIV).unwrap();
+  std::fs::write(dir.join("id_rsa.pub"), FIXTURE_PUB).unwrap();
+
+  let mut identity = Identity::with_path(dir.to_str().unwrap());
+  identity.load_keys().unwrap();
+
+  assert_eq!(identity.fingerprint(), FIXTURE_FINGERPRINT.trim());
+  assert_eq!(std::fs::read_to_string(dir.join("fingerprint")).unwrap(), FIXTURE_FINGERPRINT.trim());
+  assert_eq!(fingerprint_of(identity.public_key_b64().unwrap()).unwrap(), identity.fingerprint());
+}
+
+#[test]
+fn fixture_signature_verifies_with_the_fixture_key() {
+  let tmp = tempfile::tempdir().unwrap();
+  let dir = tmp.path();
+  std::fs::write(dir.join("id_rsa"), FIXTURE_PRIV).unwrap();
+  std::fs::write(dir.join("id_rsa.pub"), FIXTURE_PUB).unwrap();
+
+  let mut identity = Identity::with_path(dir.to_str().unwrap());
+  identity.load_keys().unwrap();
+  let key = public_key_from_b64(identity.public_key_b64().unwrap()).unwrap();
+  assert_eq!(key.size() * 8, KEY_BITS);
+
+  let enrollment = format!("alpha@{}", FIXTURE_FINGERPRINT.trim());
+  assert!(verify(&key, enrollment.as_bytes(), FIXTURE_SIGNATURE.trim()));
+  assert!(!verify(&key, b"alpha@someone-else", FIXTURE_SIGNATURE.trim()));
+
+  let ours = identity.sign(&enrollment).unwrap();
+  assert!(verify(&key, enrollment.as_bytes(), &ours));
+}
+
+#[test]
+fn generated_keys_are_written_like_pwngrid_keys() {
+  let tmp = tempfile::tempdir().unwrap();
+  let dir = tmp.path().join("keys");
+
+  let mut identity = Identity::with_path(dir.to_str().unwrap());
+  assert!(identity.generate_keys(1024).unwrap().is_none());
+
+  assert_eq!(mode(dir.clone()), 0o700);
+  assert_eq!(mode(dir.join("id_rsa")), 0o600);
+  assert_eq!(mode(dir.join("id_rsa.pub")), 0o644);
+
+  let pub_pem = std::fs::read_to_string(dir.join("id_rsa.pub")).unwrap();
+  assert!(pub_pem.starts_with("-----BEGIN RSA PUBLIC KEY-----\n"));
+  let expected = Sha256::digest(pub_pem.trim_end()).encode_hex::<String>();
+  assert_eq!(identity.fingerprint(), expected);
+
+  let mut reloaded = Identity::with_path(dir.to_str().unwrap());
+  reloaded.load_keys().unwrap();
+  assert_eq!(reloaded.fingerprint(), identity.fingerprint());
+  assert!(reloaded.sign("hello").is_ok());
+}
+
+#[test]
+fn rotation_keeps_a_backup_of_the_previous_identity() {
+  let tmp = tempfile::tempdir().unwrap();
+  let dir = tmp.path();
+  let mut identity = Identity::with_path(dir.to_str().unwrap());
+  identity.generate_keys(1024).unwrap();
+  let old = identity.fingerprint().to_string();
+
+  let backup = identity.generate_keys(1024).unwrap().expect("no backup made");
+  assert_ne!(identity.fingerprint(), old);
+  assert_eq!(mode(backup.clone()), 0o700);
+  assert_eq!(std::fs::read_to_string(backup.join("fingerprint")).unwrap(), old);
+
+  let mut restored = Identity::with_path(backup.to_str().unwrap());
+  restored.load_keys().unwrap();
+  assert_eq!(restored.fingerprint(), old);
+}
+
+#[test]
+fn mismatched_keypair_is_rejected() {
+  let tmp = tempfile::tempdir().unwrap();
+  let dir = tmp.path();
+  let mut other = Identity::with_path(dir.to_str().unwrap());
+  other.generate_keys(1024).unwrap();
+  std::fs::write(dir.join("id_rsa"), FIXTURE_PRIV).unwrap();
+
+  assert!(Identity::with_path(dir.to_str().unwrap()).load_keys().is_err());
+}
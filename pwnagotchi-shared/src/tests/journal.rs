@@ -0,0 +1,46 @@
+use crate::sessions::{
+  journal::{self, MAX_ENTRIES},
+  manager::SessionManager,
+};
+
+#[test]
+fn journal_is_capped_and_survives_a_restart() {
+  let dir = tempfile::tempdir().unwrap();
+  let path = dir.path().join("journal");
+
+  let sm = SessionManager::new();
+  sm.journal("Interface", "before persisting");
+  sm.persist_journal(&path);
+  for i in 0..MAX_ENTRIES + 5 {
+    sm.journal("Watchdog", &format!("entry {i}"));
+  }
+
+  let journal = sm.get_session().read().state.journal.clone();
+  assert_eq!(journal.len(), MAX_ENTRIES);
+  assert_eq!(journal[0].message, "entry 5");
+
+  let restarted = SessionManager::new();
+  restarted.journal("Supervisor", "after restart");
+  restarted.persist_journal(&path);
+  let journal = restarted.get_session().read().state.journal.clone();
+  assert_eq!(journal.len(), MAX_ENTRIES);
+  assert_eq!(journal[MAX_ENTRIES - 2].message, format!("entry {}", MAX_ENTRIES + 4));
+  assert_eq!(journal[MAX_ENTRIES - 1].message, "after restart");
+}
+
+#[test]
+fn broken_journal_lines_are_skipped() {
+  let dir = tempfile::tempdir().unwrap();
+  let path = dir.path().join("journal");
+  let entry = journal::JournalEntry::new("Interface", "kept");
+  journal::save(&path, std::slice::from_ref(&entry)).unwrap();
+
+  let mut data = std::fs::read_to_string(&path).unwrap();
+  data.push_str("{not json\n");
+  std::fs::write(&path, data).unwrap();
+
+  let loaded = journal::load(&path);
+  assert_eq!(loaded.len(), 1);
+  assert_eq!(loaded[0].message, "kept");
+  assert!(journal::load(&dir.path().join("missing")).is_empty());
+}
@@ -0,0 +1,61 @@
+use crate::live::{self, LiveEvent};
+
+// The hub is shared by every test in the process, other events may show up.
+async fn next_handshake(
+  rx: &mut tokio::sync::broadcast::Receiver<std::sync::Arc<LiveEvent>>,
+) -> LiveEvent {
+  loop {
+    let event = rx.recv().await.expect("hub closed");
+    if matches!(*event, LiveEvent::Handshake { .. }) {
+      return (*event).clone();
+    }
+  }
+}
+
+#[tokio::test]
+async fn subscribers_receive_published_events() {
+  let mut rx = live::subscribe();
+  assert!(live::has_subscribers());
+
+  live::publish(LiveEvent::Handshake {
+    ap: "aa:bb:cc:dd:ee:ff".into(),
+    station: "11:22:33:44:55:66".into(),
+    file: "/tmp/test.pcap".into(),
+  });
+
+  let event = next_handshake(&mut rx).await;
+  assert_eq!(event.name(), "handshake");
+  let LiveEvent::Handshake { ap, .. } = event else { unreachable!() };
+  assert_eq!(ap, "aa:bb:cc:dd:ee:ff");
+}
+
+#[test]
+fn events_are_tagged_with_their_type() {
+  let event = LiveEvent::State {
+    key: "face".into(),
+    value: "(◕‿‿◕)".into(),
+  };
+  let json = serde_json::to_value(&event).unwrap();
+
+  assert_eq!(json["type"], event.name());
+  assert_eq!(json["key"], "face");
+  assert_eq!(json["value"], "(◕‿‿◕)");
+}
+
+#[tokio::test]
+async fn later_subscribers_miss_earlier_events() {
+  live::publish(LiveEvent::Frame { png: "before".into() });
+
+  let mut rx = live::subscribe();
+  live::publish(LiveEvent::Frame { png: "after".into() });
+
+  loop {
+    let event = rx.recv().await.expect("hub closed");
+    if let LiveEvent::Frame { png } = &*event {
+      assert_ne!(png, "before", "an event published before subscribing was replayed");
+      if png == "after" {
+        break;
+      }
+    }
+  }
+}
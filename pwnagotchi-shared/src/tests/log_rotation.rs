@@ -0,0 +1,100 @@
+use crate::{
+  config::LogConfig,
+  logger::rotate::{RotatingFile, Rotation, read_lines, segment, segments},
+  sessions::session_parser::parse_session_from_file,
+};
+
+const ROTATION: Rotation = Rotation { max_bytes: 64, keep: 2, compress: true };
+
+#[test]
+fn sizes_are_parsed_with_units() {
+  let mut config = LogConfig::default();
+  for (size, bytes) in [
+    ("10M", Some(10 << 20)),
+    ("512k", Some(512 << 10)),
+    ("1G", Some(1 << 30)),
+    ("4096", Some(4096)),
+    ("0", None),
+    ("ten", None),
+    ("10T", None),
+  ] {
+    config.rotation.size = size.into();
+    assert_eq!(config.rotation.max_bytes(), bytes, "{size}");
+  }
+
+  config.rotation.size = "10M".into();
+  config.rotation.enabled = false;
+  assert_eq!(Rotation::from_config(&config), None);
+}
+
+#[test]
+fn rotated_segments_are_compressed_and_pruned() {
+  let tmp = tempfile::tempdir().unwrap();
+  let dir = tmp.path();
+  let log = dir.join("pwnagotchi.log");
+  let mut file = RotatingFile::open(&log, Some(ROTATION)).unwrap();
+
+  let lines = (0..12).map(|i| format!("[line {i:02}] {}", "x".repeat(16))).collect::<Vec<_>>();
+  for line in &lines {
+    file.write_entry(format!("{line}\n").as_bytes()).unwrap();
+  }
+  file.finish_compressing().unwrap();
+
+  assert_eq!(
+    segments(&log),
+    [
+      segment(&log, 2, true),
+      segment(&log, 1, true),
+      log.clone()
+    ]
+  );
+  assert!(!segment(&log, 3, true).exists());
+  assert!(!segment(&log, 1, false).exists());
+
+  // the oldest lines went with the pruned segments
+  let read = read_lines(&log).collect::<Vec<_>>();
+  assert_eq!(read, lines[lines.len() - read.len()..]);
+  assert!(std::fs::metadata(&log).unwrap().len() <= ROTATION.max_bytes);
+}
+
+#[test]
+fn writers_sharing_a_file_follow_each_others_rotation() {
+  let tmp = tempfile::tempdir().unwrap();
+  let dir = tmp.path();
+  let log = dir.join("pwnagotchi.log");
+  let rotation = Rotation { keep: 10, compress: false, ..ROTATION };
+  let mut first = RotatingFile::open(&log, Some(rotation)).unwrap();
+  let mut second = RotatingFile::open(&log, Some(rotation)).unwrap();
+
+  let lines = (0..20).map(|i| format!("[line {i:02}] from a writer")).collect::<Vec<_>>();
+  for (i, line) in lines.iter().enumerate() {
+    let writer = if i % 2 == 0 { &mut first } else { &mut second };
+    writer.write_entry(format!("{line}\n").as_bytes()).unwrap();
+  }
+
+  assert!(segments(&log).len() > 2);
+  assert_eq!(read_lines(&log).collect::<Vec<_>>(), lines);
+}
+
+#[test]
+fn sessions_are_read_across_segments() {
+  let tmp = tempfile::tempdir().unwrap();
+  let dir = tmp.path();
+  let log = dir.join("pwnagotchi.log");
+  let mut file = RotatingFile::open(&log, Some(Rotation { max_bytes: 256, ..ROTATION })).unwrap();
+
+  for i in 0..4 {
+    let entry = format!(
+      "[2025-01-01 10:0{i}:00] [INFO] [AGENT] deauthing aa:bb:cc:dd:ee:0{i} from the network\n"
+    );
+    file.write_entry(entry.as_bytes()).unwrap();
+  }
+  file.finish_compressing().unwrap();
+
+  assert!(segments(&log).len() > 1);
+  let stats = parse_session_from_file(log.to_str().unwrap(), None).unwrap();
+  assert_eq!(stats.deauthed, 4);
+  assert!(stats.start < stats.stop);
+
+  assert!(parse_session_from_file(dir.join("missing.log").to_str().unwrap(), None).is_err());
+}
@@ -0,0 +1,258 @@
+use std::{
+  io::{self, Read},
+  net::{TcpListener, TcpStream, UdpSocket},
+  os::unix::net::UnixDatagram,
+  sync::Arc,
+  time::{Duration, Instant},
+};
+
+use crate::{
+  config::{Config, LogConfig, LogSinkConfig},
+  logger::{LogLevel, sinks::Sinks},
+};
+
+fn config(sink: LogSinkConfig) -> LogConfig {
+  LogConfig {
+    sinks: vec![sink],
+    ..LogConfig::default()
+  }
+}
+
+fn wait_for(what: &str, mut done: impl FnMut() -> bool) {
+  let deadline = Instant::now() + Duration::from_secs(10);
+  while !done() {
+    assert!(Instant::now() < deadline, "timed out waiting for {what}");
+    std::thread::sleep(Duration::from_millis(20));
+  }
+}
+
+#[test]
+fn syslog_entries_below_the_sink_level_are_not_sent() {
+  let collector = UdpSocket::bind("127.0.0.1:0").unwrap();
+  collector.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+  let sinks = Sinks::from_config(&config(LogSinkConfig {
+    address: collector.local_addr().unwrap().to_string().into(),
+    level: "warning".into(),
+    facility: "local0".into(),
+    ..LogSinkConfig::default()
+  }));
+
+  sinks.send(LogLevel::Info, Some("AGENT"), "not sent");
+  sinks.send(LogLevel::Error, Some("AGENT"), "deauth failed");
+  sinks.close();
+
+  let mut buf = [0; 2048];
+  let len = collector.recv(&mut buf).unwrap();
+  let frame = std::str::from_utf8(&buf[..len]).unwrap();
+  // local0 is 16, error 3
+  assert!(frame.starts_with("<131>1 "), "{frame}");
+  assert!(frame.contains(&format!(" pwnagotchi {} AGENT - ", std::process::id())), "{frame}");
+  assert!(frame.ends_with("deauth failed"), "{frame}");
+
+  collector.set_nonblocking(true).unwrap();
+  assert!(collector.recv(&mut buf).is_err());
+}
+
+#[test]
+fn syslog_entries_are_spooled_until_the_collector_is_back() {
+  let tmp = tempfile::tempdir().unwrap();
+  let dir = tmp.path();
+  let spool = dir.join("syslog.spool");
+  let address = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+
+  let sinks = Sinks::from_config(&config(LogSinkConfig {
+    address: address.to_string().into(),
+    protocol: "tcp".into(),
+    spool: spool.to_string_lossy().into_owned().into(),
+    retry_interval: 1,
+    ..LogSinkConfig::default()
+  }));
+  for i in 0..3 {
+    sinks.send(LogLevel::Info, Some("AGENT"), &format!("entry {i}\nsecond line"));
+  }
+  wait_for("the spool", || std::fs::read_to_string(&spool).is_ok_and(|s| s.lines().count() == 3));
+
+  let collector = TcpListener::bind(address).unwrap();
+  let (mut stream, _) = collector.accept().unwrap();
+  stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+  // octet counted frames, `len frame`
+  let mut received = String::new();
+  let mut buf = [0; 4096];
+  while received.matches("second line").count() < 3 {
+    let len = stream.read(&mut buf).unwrap();
+    assert!(len > 0, "collector closed: {received}");
+    received.push_str(std::str::from_utf8(&buf[..len]).unwrap());
+  }
+
+  let (first, rest) = received.split_once(' ').unwrap();
+  let len = first.parse::<usize>().unwrap();
+  assert!(rest[..len].ends_with("entry 0\nsecond line"), "{received}");
+  assert!(received.find("entry 0") < received.find("entry 2"));
+  wait_for("the spool to be removed", || !spool.exists());
+  sinks.close();
+}
+
+#[test]
+fn journal_fields_keep_messages_spanning_lines() {
+  let tmp = tempfile::tempdir().unwrap();
+  let dir = tmp.path();
+  let socket = dir.join("journal.socket");
+  let journal = UnixDatagram::bind(&socket).unwrap();
+  journal.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+  let sinks = Sinks::from_config(&config(LogSinkConfig {
+    kind: "journald".into(),
+    address: socket.to_string_lossy().into_owned().into(),
+    level: "debug".into(),
+    ..LogSinkConfig::default()
+  }));
+  sinks.send(LogLevel::Warning, Some("GRID"), "two\nlines");
+  sinks.close();
+
+  let mut buf = [0; 4096];
+  let len = journal.recv(&mut buf).unwrap();
+  let mut expected =
+    b"PRIORITY=4\nSYSLOG_IDENTIFIER=pwnagotchi\nPWNAGOTCHI_ORIGIN=GRID\nMESSAGE\n".to_vec();
+  expected.extend_from_slice(&9u64.to_le_bytes());
+  expected.extend_from_slice(b"two\nlines\n");
+  assert_eq!(buf[..len], expected);
+}
+
+/// Octet counted frames, `len frame`, read from `stream` until it is idle
+/// for a second.
+fn read_frames(stream: &mut TcpStream, received: &mut Vec<u8>, frames: &mut Vec<String>) {
+  stream.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+  let mut buf = vec![0; 1 << 16];
+  loop {
+    let len = match stream.read(&mut buf) {
+      Ok(len) => len,
+      Err(e) if e.kind() == io::ErrorKind::WouldBlock => return,
+      Err(e) => panic!("{e}"),
+    };
+    assert!(len > 0, "collector closed after {} frames", frames.len());
+    received.extend_from_slice(&buf[..len]);
+
+    while let Some(space) = received.iter().position(|b| *b == b' ') {
+      let len = std::str::from_utf8(&received[..space]).unwrap().parse::<usize>().unwrap();
+      if received.len() < space + 1 + len {
+        break;
+      }
+      frames.push(String::from_utf8(received[space + 1..space + 1 + len].to_vec()).unwrap());
+      received.drain(..space + 1 + len);
+    }
+  }
+}
+
+#[test]
+fn entries_dropped_on_a_full_queue_are_counted() {
+  // the collector doesn't read until everything is sent, the sink blocks
+  // once the connection is full and its queue fills up behind it
+  let collector = TcpListener::bind("127.0.0.1:0").unwrap();
+  let sinks = Sinks::from_config(&config(LogSinkConfig {
+    address: collector.local_addr().unwrap().to_string().into(),
+    protocol: "tcp".into(),
+    ..LogSinkConfig::default()
+  }));
+  let padding = "x".repeat(16 << 10);
+  for i in 0..4000 {
+    sinks.send(LogLevel::Info, Some("AGENT"), &format!("entry {i} {padding}"));
+  }
+
+  let (mut stream, _) = collector.accept().unwrap();
+  let mut received = Vec::new();
+  let mut frames = Vec::new();
+  read_frames(&mut stream, &mut received, &mut frames);
+  // the count goes out with the next entry
+  sinks.send(LogLevel::Info, Some("AGENT"), "last");
+  read_frames(&mut stream, &mut received, &mut frames);
+  sinks.close();
+
+  assert!(frames.last().unwrap().ends_with(" last"));
+  // daemon is 3, warning 4
+  let notices = frames.iter().filter(|frame| frame.starts_with("<28>")).collect::<Vec<_>>();
+  let dropped = notices
+    .iter()
+    .map(|notice| {
+      let (_, message) = notice.split_once(" LOGGER - ").unwrap();
+      message.strip_suffix(" entries were dropped while the queue was full").unwrap()
+    })
+    .map(|count| count.parse::<usize>().unwrap())
+    .sum::<usize>();
+  assert!(dropped > 0);
+  assert_eq!(frames.len() - notices.len() + dropped, 4001);
+}
+
+#[test]
+fn logging_goes_on_while_a_stuck_sink_is_replaced() {
+  // a collector that stopped reading, the sink is stuck writing to it until
+  // the write times out
+  let collector = TcpListener::bind("127.0.0.1:0").unwrap();
+  let sinks = Arc::new(Sinks::from_config(&config(LogSinkConfig {
+    address: collector.local_addr().unwrap().to_string().into(),
+    protocol: "tcp".into(),
+    ..LogSinkConfig::default()
+  })));
+  let padding = "x".repeat(16 << 10);
+  for i in 0..2000 {
+    sinks.send(LogLevel::Info, Some("AGENT"), &format!("entry {i} {padding}"));
+  }
+
+  let replacing = std::thread::spawn({
+    let sinks = Arc::clone(&sinks);
+    move || sinks.configure(&LogConfig::default())
+  });
+  std::thread::sleep(Duration::from_millis(200));
+
+  let started = Instant::now();
+  sinks.send(LogLevel::Info, Some("AGENT"), "while replacing");
+  assert!(started.elapsed() < Duration::from_millis(500), "{:?}", started.elapsed());
+  assert!(!replacing.is_finished(), "the old sink wasn't stuck");
+
+  // the connection is reset, the old sink gives up on the rest
+  drop(collector);
+  replacing.join().unwrap();
+}
+
+#[test]
+fn invalid_sinks_are_rejected() {
+  let mut config = Config::default();
+  config.log.sinks = vec![
+    LogSinkConfig {
+      address: "collector".into(),
+      protocol: "quic".into(),
+      facility: "local9".into(),
+      spool: "/tmp/spool".into(),
+      spool_size: "big".into(),
+      ..LogSinkConfig::default()
+    },
+    LogSinkConfig {
+      kind: "kafka".into(),
+      level: "loud".into(),
+      ..LogSinkConfig::default()
+    },
+    LogSinkConfig {
+      address: "[::1]:514".into(),
+      ..LogSinkConfig::default()
+    },
+    LogSinkConfig {
+      address: "collector:514".into(),
+      spool: "/tmp/spool".into(),
+      ..LogSinkConfig::default()
+    },
+  ];
+
+  let paths = config.validate().into_iter().map(|e| e.path).collect::<Vec<_>>();
+  assert_eq!(
+    paths,
+    [
+      "/log/sinks/0/address",
+      "/log/sinks/0/protocol",
+      "/log/sinks/0/facility",
+      "/log/sinks/0/spool_size",
+      "/log/sinks/1/level",
+      "/log/sinks/1/kind",
+      "/log/sinks/3/spool",
+    ]
+  );
+}
@@ -0,0 +1,78 @@
+use std::sync::Arc;
+
+use crate::{
+  config::{Config, LogConfig},
+  logger::{
+    LogFiles, LogLevel,
+    layer::{Filters, subscriber},
+    sinks::Sinks,
+  },
+};
+
+fn filters(entries: &[(&str, &str)]) -> Filters {
+  let mut config = LogConfig::default();
+  for (key, level) in entries {
+    config.filters.insert((*key).to_string(), (*level).to_string().into());
+  }
+  Filters::from_config(&config)
+}
+
+#[test]
+fn levels_are_parsed_by_name() {
+  assert_eq!("warn".parse(), Ok(LogLevel::Warning));
+  assert_eq!("WARNING".parse(), Ok(LogLevel::Warning));
+  assert_eq!("trace".parse(), Ok(LogLevel::Debug));
+  assert!("loud".parse::<LogLevel>().is_err());
+
+  let mut config = Config::default();
+  config.log.filters.insert("bettercap".into(), "loud".into());
+  let errors = config.validate();
+  assert_eq!(errors.len(), 1);
+  assert_eq!(errors[0].path, "/log/filters/bettercap");
+}
+
+#[test]
+fn origin_wins_over_plugin_and_target() {
+  let filters = filters(&[
+    ("Bettercap", "error"),
+    ("plugin::grid", "warning"),
+    ("pwnagotchi_core", "info"),
+    ("pwnagotchi_core::agent", "error"),
+    ("ureq", "warning"),
+  ]);
+
+  assert_eq!(filters.level(Some("BETTERCAP"), Some("grid"), "pwnagotchi"), LogLevel::Error);
+  assert_eq!(filters.level(Some("GRID"), Some("grid"), "pwnagotchi"), LogLevel::Warning);
+  assert_eq!(filters.level(None, None, "pwnagotchi_core::agent::recon"), LogLevel::Error);
+  assert_eq!(filters.level(None, None, "pwnagotchi_core::automata"), LogLevel::Info);
+  // a prefix only matches whole path segments
+  assert_eq!(filters.level(None, None, "pwnagotchi_core_extra"), LogLevel::Debug);
+  assert_eq!(filters.level(None, None, "ureq::unit"), LogLevel::Warning);
+  assert_eq!(filters.level(None, None, "hyper::client"), LogLevel::Info);
+}
+
+#[test]
+fn events_are_written_with_their_span_context() {
+  let tmp = tempfile::tempdir().unwrap();
+  let dir = tmp.path();
+  let (log, debug_log) = (dir.join("pwnagotchi.log"), dir.join("pwnagotchi_debug.log"));
+  let files = Arc::new(LogFiles::open(log.to_str().unwrap(), debug_log.to_str().unwrap(), None));
+
+  tracing::subscriber::with_default(subscriber(files, Arc::new(Sinks::default())), || {
+    let _epoch = tracing::info_span!("epoch", epoch = 12).entered();
+    tracing::info!(target: "pwnagotchi", origin = "AGENT", "deauthing");
+    tracing::info_span!("plugin", plugin = "grid").in_scope(|| {
+      tracing::debug!(target: "pwnagotchi_plugins::loaders::rust", "loaded");
+    });
+    tracing::error!(target: "pwnagotchi", origin = "AGENT", fatal = true, "out of disk");
+  });
+
+  let log = std::fs::read_to_string(log).unwrap();
+  let lines = log.lines().collect::<Vec<_>>();
+  assert_eq!(lines.len(), 2, "{log}");
+  assert!(lines[0].ends_with("[INFO] [AGENT] [epoch=12] deauthing"), "{}", lines[0]);
+  assert!(lines[1].ends_with("[FATAL] [AGENT] [epoch=12] out of disk"), "{}", lines[1]);
+
+  let debug_log = std::fs::read_to_string(debug_log).unwrap();
+  assert!(debug_log.contains("[DEBUG] [RUST] [epoch=12 plugin=grid] loaded"), "{debug_log}");
+}
@@ -0,0 +1,113 @@
+use std::time::Duration;
+
+use time::OffsetDateTime;
+
+use crate::{
+  mesh::{
+    peer::Peer,
+    peerdb::{PWND_HISTORY_LEN, PeerDb},
+  },
+  models::grid::{Advertisement, PeerResponse},
+};
+
+fn peer(face: &str, channel: u8, pwnd_total: u32) -> Peer {
+  Peer::new(&PeerResponse {
+    fingerprint: Some("abcd".to_string()),
+    met_at: None,
+    encounters: Some(1),
+    prev_seen_at: None,
+    detected_at: None,
+    seen_at: None,
+    channel: Some(channel),
+    rssi: Some(-60),
+    session_id: Some("02:00:00:00:00:01".to_string()),
+    advertisement: Some(Advertisement {
+      name: "buddy".to_string(),
+      identity: "abcd".to_string(),
+      face: face.to_string(),
+      pwnd_total,
+      ..Advertisement::default()
+    }),
+  })
+}
+
+fn at(secs: i64) -> OffsetDateTime {
+  OffsetDateTime::from_unix_timestamp(1_700_000_000 + secs).unwrap()
+}
+
+#[test]
+fn history_survives_reopen() {
+  let dir = tempfile::tempdir().unwrap();
+  let path = dir.path().join("peers.json");
+  let db = PeerDb::open(&path);
+
+  assert_eq!(db.record_encounter(&peer("(^_^)", 1, 10), at(0)), 1);
+  assert_eq!(db.observe(&peer("(-_-)", 1, 10), Duration::from_secs(30), at(30)), Some(1));
+  assert_eq!(db.record_encounter(&peer("(^_^)", 6, 12), at(600)), 2);
+  db.save().unwrap();
+
+  let reopened = PeerDb::open(&path);
+  let friend = reopened.get("abcd").unwrap();
+  assert_eq!(friend.name, "buddy");
+  assert_eq!(friend.encounters, 2);
+  assert_eq!(friend.time_together, 30);
+  assert_eq!(friend.first_met, at(0).unix_timestamp());
+  assert_eq!(friend.last_seen, at(600).unix_timestamp());
+  assert_eq!(friend.channels.get(&1), Some(&1));
+  assert_eq!(friend.channels.get(&6), Some(&1));
+  assert_eq!(friend.pwnd_total(), 12);
+}
+
+#[test]
+fn faces_are_counted_once_per_encounter() {
+  let dir = tempfile::tempdir().unwrap();
+  let db = PeerDb::open(dir.path().join("peers.json"));
+
+  db.record_encounter(&peer("(^_^)", 1, 0), at(0));
+  for i in 1..=5 {
+    db.observe(&peer("(-_-)", 1, 0), Duration::from_secs(1), at(i));
+  }
+  db.record_encounter(&peer("(^_^)", 1, 0), at(600));
+
+  let friend = db.get("abcd").unwrap();
+  assert_eq!(friend.faces.get("(^_^)"), Some(&2));
+  assert_eq!(friend.faces.get("(-_-)"), None);
+  assert_eq!(friend.time_together, 5);
+}
+
+#[test]
+fn unknown_peer_is_not_observed() {
+  let dir = tempfile::tempdir().unwrap();
+  let db = PeerDb::open(dir.path().join("peers.json"));
+
+  assert_eq!(db.observe(&peer("(^_^)", 1, 0), Duration::from_secs(3), at(0)), None);
+  assert!(db.friends().is_empty());
+}
+
+#[test]
+fn pwnd_history_is_capped() {
+  let dir = tempfile::tempdir().unwrap();
+  let db = PeerDb::open(dir.path().join("peers.json"));
+
+  for i in 0..PWND_HISTORY_LEN + 10 {
+    let pwnd = u32::try_from(i).unwrap();
+    db.record_encounter(&peer("(^_^)", 1, pwnd), at(i64::from(pwnd)));
+  }
+
+  let friend = db.get("abcd").unwrap();
+  assert_eq!(friend.pwnd_history.len(), PWND_HISTORY_LEN);
+  assert_eq!(friend.pwnd_history[0].1, 10);
+  assert!(friend.is_good_friend(u32::try_from(PWND_HISTORY_LEN).unwrap()));
+}
+
+#[test]
+fn clean_db_is_not_written_and_corrupt_db_starts_empty() {
+  let dir = tempfile::tempdir().unwrap();
+  let path = dir.path().join("peers.json");
+
+  PeerDb::open(&path).save().unwrap();
+  assert!(!path.exists());
+
+  std::fs::write(&path, "{ not json").unwrap();
+  assert!(PeerDb::open(&path).friends().is_empty());
+}
@@ -0,0 +1,160 @@
+use std::sync::LazyLock;
+
+use rsa::{RsaPrivateKey, rand_core::OsRng};
+
+use crate::{
+  identity::Identity,
+  mesh::sharing::{
+    HandshakeDigest, Inventory, NonceCache, SignedRequest, local_inventory, read_origin,
+    sha256_hex, store_capture,
+  },
+};
+
+static ALICE: LazyLock<Identity> = LazyLock::new(identity);
+static BOB: LazyLock<Identity> = LazyLock::new(identity);
+
+const NOW: i64 = 1_700_000_000;
+const SKEW: i64 = 300;
+
+fn identity() -> Identity {
+  let key = RsaPrivateKey::new(&mut OsRng, 1024).expect("failed to generate key");
+  Identity::from_private_key(key).unwrap()
+}
+
+fn digest(name: &str, data: &[u8]) -> HandshakeDigest {
+  HandshakeDigest {
+    name: name.to_string(),
+    sha256: sha256_hex(data),
+    size: data.len() as u64,
+    origin: None,
+  }
+}
+
+#[test]
+fn inventory_is_only_accepted_from_trusted_units() {
+  let inventory = Inventory::signed(&ALICE, vec![digest("a.pcap", b"a")], NOW).unwrap();
+
+  assert!(inventory.verify(&[ALICE.fingerprint()], NOW + 10, SKEW).is_ok());
+  assert!(inventory.verify(&[BOB.fingerprint()], NOW, SKEW).is_err());
+  assert!(inventory.verify(&[ALICE.fingerprint()], NOW + SKEW + 1, SKEW).is_err());
+
+  let mut tampered = inventory.clone();
+  tampered.handshakes.push(digest("b.pcap", b"b"));
+  assert!(tampered.verify(&[ALICE.fingerprint()], NOW, SKEW).is_err());
+
+  let mut impostor = inventory;
+  impostor.public_key = BOB.public_key_b64().unwrap().to_string();
+  assert!(impostor.verify(&[ALICE.fingerprint()], NOW, SKEW).is_err());
+}
+
+#[test]
+fn signed_requests_are_bound_to_their_path() {
+  let request = SignedRequest::sign(&BOB, "/share/inventory", NOW).unwrap();
+  let verify =
+    |path, trusted: &[&str]| request.verify(path, trusted, &NonceCache::new(), NOW, SKEW);
+
+  assert!(verify("/share/inventory", &[BOB.fingerprint()]).is_ok());
+  assert!(verify("/share/handshakes/a.pcap", &[BOB.fingerprint()]).is_err());
+  assert!(verify("/share/inventory", &[ALICE.fingerprint()]).is_err());
+}
+
+#[test]
+fn signed_requests_are_accepted_once() {
+  let nonces = NonceCache::new();
+  let trusted = [BOB.fingerprint()];
+  let request = SignedRequest::sign(&BOB, "/share/inventory", NOW).unwrap();
+
+  assert!(request.verify("/share/inventory", &trusted, &nonces, NOW, SKEW).is_ok());
+  assert!(request.verify("/share/inventory", &trusted, &nonces, NOW + 1, SKEW).is_err());
+
+  let mut renewed = request.clone();
+  renewed.nonce = "00".repeat(16);
+  assert!(renewed.verify("/share/inventory", &trusted, &nonces, NOW, SKEW).is_err());
+
+  let next = SignedRequest::sign(&BOB, "/share/inventory", NOW).unwrap();
+  assert_ne!(next.nonce, request.nonce);
+  assert!(next.verify("/share/inventory", &trusted, &nonces, NOW, SKEW).is_ok());
+}
+
+#[test]
+fn missing_captures_are_compared_by_content() {
+  let local = vec![
+    digest("same.pcap", b"same"),
+    digest("renamed.pcap", b"old"),
+  ];
+  let remote = vec![
+    digest("same.pcap", b"same"),
+    digest("other_name.pcap", b"old"),
+    digest("new.pcap", b"new"),
+  ];
+  let inventory = Inventory::signed(&ALICE, remote, NOW).unwrap();
+
+  let missing = inventory.missing(&local);
+  assert_eq!(missing.len(), 1);
+  assert_eq!(missing[0].name, "new.pcap");
+}
+
+#[test]
+fn received_captures_are_verified_and_marked_with_their_origin() {
+  let tmp = tempfile::tempdir().unwrap();
+  let dir = tmp.path();
+  std::fs::write(dir.join("own.pcap"), b"own").unwrap();
+
+  let capture = digest("theirs.pcap", b"theirs");
+  assert!(store_capture(dir, &capture, b"forged", ALICE.fingerprint(), NOW).is_err());
+  assert!(store_capture(dir, &digest("../x.pcap", b"x"), b"x", ALICE.fingerprint(), NOW).is_err());
+
+  let path = store_capture(dir, &capture, b"theirs", ALICE.fingerprint(), NOW).unwrap();
+  let origin = read_origin(&path).unwrap();
+  assert_eq!(origin.fingerprint, ALICE.fingerprint());
+  assert_eq!(origin.received_at, NOW);
+
+  let passed_on = HandshakeDigest {
+    origin: Some(ALICE.fingerprint().to_string()),
+    ..digest("own.pcap", b"clash")
+  };
+  let path = store_capture(dir, &passed_on, b"clash", BOB.fingerprint(), NOW).unwrap();
+  assert_ne!(path, dir.join("own.pcap"));
+  let origin = read_origin(&path).unwrap();
+  assert_eq!(
+    (origin.fingerprint.as_str(), origin.via.as_str()),
+    (ALICE.fingerprint(), BOB.fingerprint())
+  );
+
+  let inventory = local_inventory(dir);
+  assert_eq!(inventory.len(), 3);
+  assert_eq!(inventory.iter().filter(|d| d.origin.is_none()).count(), 1);
+  assert_eq!(std::fs::read(dir.join("own.pcap")).unwrap(), b"own");
+}
+
+#[test]
+fn stored_captures_never_replace_existing_ones() {
+  let dir = tempfile::tempdir().unwrap();
+  let short = &ALICE.fingerprint()[..8];
+  std::fs::write(dir.path().join("ap.pcap"), b"own").unwrap();
+  std::fs::write(dir.path().join(format!("ap_{short}.pcap")), b"earlier").unwrap();
+
+  let path =
+    store_capture(dir.path(), &digest("ap.pcap", b"new"), b"new", ALICE.fingerprint(), NOW)
+      .unwrap();
+  assert_eq!(path, dir.path().join(format!("ap_{short}_2.pcap")));
+
+  assert_eq!(std::fs::read(dir.path().join("ap.pcap")).unwrap(), b"own");
+  assert_eq!(std::fs::read(dir.path().join(format!("ap_{short}.pcap"))).unwrap(), b"earlier");
+  assert_eq!(std::fs::read(&path).unwrap(), b"new");
+  assert_eq!(local_inventory(dir.path()).len(), 3);
+}
+
+#[test]
+fn inventory_notices_changed_captures() {
+  let dir = tempfile::tempdir().unwrap();
+  let capture = dir.path().join("ap.pcap");
+  std::fs::write(&capture, b"first").unwrap();
+  assert_eq!(local_inventory(dir.path())[0].sha256, sha256_hex(b"first"));
+
+  std::fs::write(&capture, b"second, longer").unwrap();
+  assert_eq!(local_inventory(dir.path())[0].sha256, sha256_hex(b"second, longer"));
+
+  std::fs::remove_file(&capture).unwrap();
+  assert!(local_inventory(dir.path()).is_empty());
+}
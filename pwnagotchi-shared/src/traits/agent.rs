@@ -15,6 +15,7 @@ pub trait AgentTrait: Send + Sync + CoreModule {
   async fn set_channel(&self, channel: u8);
   async fn get_access_points_by_channel(&self) -> Vec<(u8, Vec<AccessPoint>)>;
   fn start_pwnagotchi(&self);
-  fn reboot(&self);
+  fn shutdown(&self);
+  fn reboot(&self, mode: Option<RunningMode>);
   fn restart(&self, mode: Option<RunningMode>);
 }
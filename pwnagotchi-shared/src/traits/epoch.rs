@@ -39,7 +39,7 @@ pub struct Epoch {
   pub epoch_data_ready: bool,
 }
 
-#[derive(Clone, Default)]
+#[derive(Clone, Default, serde::Serialize)]
 pub struct EpochData {
   pub duration_secs: f64,
   pub slept_for_secs: f64,
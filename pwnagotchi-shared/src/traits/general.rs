@@ -3,14 +3,17 @@ use std::sync::Arc;
 use anyhow::Result;
 use parking_lot::RwLock;
 use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 
 use crate::{
   identity::Identity,
+  mesh::peerdb::PeerDb,
   sessions::manager::SessionManager,
   traits::{
     agent::AgentTrait, automata::AutomataTrait, bettercap::BettercapTrait, epoch::Epoch,
     events::EventBus, grid::GridTrait, ui::ViewTrait,
   },
+  types::components::RestartPolicy,
 };
 
 #[async_trait::async_trait]
@@ -21,6 +24,10 @@ pub trait Component: Dependencies + Send + Sync {
   async fn stop(&self) -> Result<()> {
     Ok(())
   }
+  /// How the supervisor reacts when the task returned by `start` ends.
+  fn restart_policy(&self) -> RestartPolicy {
+    RestartPolicy::default()
+  }
 }
 
 pub trait Dependencies: Send + Sync {
@@ -55,6 +62,11 @@ pub struct CoreModules {
   pub automata: Arc<dyn AutomataTrait + Send + Sync>,
   pub events: Arc<dyn EventBus + Send + Sync>,
   pub grid: Arc<dyn GridTrait + Send + Sync>,
+  /// Every peer ever met, persisted across reboots.
+  pub peer_db: Arc<PeerDb>,
+  /// Cancelled once the unit starts shutting down, every long running loop
+  /// watches it.
+  pub shutdown: CancellationToken,
 }
 
 #[async_trait::async_trait]
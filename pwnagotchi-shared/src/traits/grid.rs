@@ -14,4 +14,20 @@ pub trait GridTrait: Send + Sync {
   fn inbox(&self, page: Option<u32>, with_pager: Option<bool>) -> Option<serde_json::Value>;
   fn inbox_message(&self, id: &str) -> Option<serde_json::Value>;
   fn mark_message(&self, id: &str, mark: &str) -> Option<serde_json::Value>;
+  fn send_message(&self, to: &str, message: &str) -> Result<(), String>;
+
+  /// Number of unread messages on the first inbox page.
+  fn unread_messages(&self) -> Option<u32> {
+    count_unread(&self.inbox(Some(1), Some(false))?)
+  }
+}
+
+/// Counts the messages of an inbox page that were never marked as seen.
+pub fn count_unread(messages: &serde_json::Value) -> Option<u32> {
+  let unread = messages
+    .as_array()?
+    .iter()
+    .filter(|m| m.get("seen_at").is_none_or(serde_json::Value::is_null))
+    .count();
+  u32::try_from(unread).ok()
 }
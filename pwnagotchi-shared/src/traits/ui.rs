@@ -41,7 +41,7 @@ pub trait ViewTrait: Send + Sync + CoreModule {
   fn on_lost_peer(&self, peer: &Peer);
   fn on_free_channel(&self, channel: u8);
   fn on_reading_logs(&self, lines: u64);
-  fn on_shutdown(&mut self);
+  fn on_shutdown(&self);
   fn on_bored(&self);
   fn on_sad(&self);
   fn on_angry(&self);
@@ -57,6 +57,7 @@ pub trait ViewTrait: Send + Sync + CoreModule {
   fn on_unread_messages(&self, count: u32);
   fn on_uploading(&self, to: &str);
   fn on_rebooting(&self);
+  fn on_component_down(&self, name: &str);
   fn on_custom(&self, text: &str);
   fn is_normal(&self) -> bool;
   fn update(&self, force: Option<bool>, new_data: Option<HashMap<String, String>>);
@@ -0,0 +1,105 @@
+use std::{fmt::Display, sync::Arc, time::Duration};
+
+use parking_lot::RwLock;
+
+/// Shared view of every supervised component, read by the web API.
+pub type ComponentRegistry = Arc<RwLock<Vec<ComponentStatus>>>;
+
+/// How the supervisor treats a component whose background task ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RestartPolicy {
+  pub restart: bool,
+  pub max_restarts: u32,
+  pub backoff: Duration,
+  pub max_backoff: Duration,
+  /// Running this long after a restart clears the restart count, so failures
+  /// spread over days don't add up to giving up.
+  pub stable_after: Duration,
+  /// The unit cannot do its job without this component.
+  pub critical: bool,
+}
+
+impl Default for RestartPolicy {
+  fn default() -> Self {
+    Self {
+      restart: true,
+      max_restarts: 5,
+      backoff: Duration::from_secs(1),
+      max_backoff: Duration::from_secs(60),
+      stable_after: Duration::from_secs(10 * 60),
+      critical: false,
+    }
+  }
+}
+
+impl RestartPolicy {
+  #[must_use]
+  pub fn never() -> Self {
+    Self { restart: false, ..Self::default() }
+  }
+
+  #[must_use]
+  pub const fn critical(mut self) -> Self {
+    self.critical = true;
+    self
+  }
+
+  /// Exponential backoff before the given restart attempt, starting at 0.
+  pub fn backoff_for(&self, attempt: u32) -> Duration {
+    let factor = 2u32.saturating_pow(attempt);
+    self.backoff.saturating_mul(factor).min(self.max_backoff)
+  }
+
+  pub const fn can_restart(&self, restarts: u32) -> bool {
+    self.restart && restarts < self.max_restarts
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ComponentState {
+  /// Initialized but without a background task.
+  Idle,
+  Running,
+  Restarting,
+  Failed,
+  Stopped,
+}
+
+impl Display for ComponentState {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let s = match self {
+      Self::Idle => "idle",
+      Self::Running => "running",
+      Self::Restarting => "restarting",
+      Self::Failed => "failed",
+      Self::Stopped => "stopped",
+    };
+    write!(f, "{s}")
+  }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ComponentStatus {
+  pub name: String,
+  pub state: ComponentState,
+  pub critical: bool,
+  pub restarts: u32,
+  pub last_error: Option<String>,
+}
+
+impl ComponentStatus {
+  pub fn new(name: &str, critical: bool) -> Self {
+    Self {
+      name: name.to_string(),
+      state: ComponentState::Idle,
+      critical,
+      restarts: 0,
+      last_error: None,
+    }
+  }
+
+  pub const fn is_down(&self) -> bool {
+    matches!(self.state, ComponentState::Restarting | ComponentState::Failed)
+  }
+}
@@ -1,5 +1,7 @@
 use std::any::Any;
 
+use crate::logger::LOGGER;
+
 pub fn downcast_register<C: Any + Send + Sync, R>(
   boxed: Box<dyn Any + Send + Sync>,
   f: impl FnOnce(C) -> R,
@@ -7,8 +9,13 @@ pub fn downcast_register<C: Any + Send + Sync, R>(
   match boxed.downcast::<C>() {
     Ok(c) => Some(f(*c)),
     Err(e) => {
-      eprintln!("hook registration failed: closure type mismatch");
-      eprintln!("Got {e:?} expected <{}>", std::any::type_name::<C>());
+      LOGGER.log_error(
+        "HOOKS",
+        &format!(
+          "hook registration failed: closure type mismatch, got {e:?} expected <{}>",
+          std::any::type_name::<C>()
+        ),
+      );
       None
     }
   }
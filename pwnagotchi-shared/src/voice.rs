@@ -1,6 +1,7 @@
 use std::fmt::Write;
 
 use crate::{
+  logger::LOGGER,
   mesh::peer::Peer,
   models::net::{AccessPoint, Station},
   sessions::lastsession::LastSession,
@@ -108,6 +109,13 @@ pub fn on_new_peer(peer: &Peer) -> String {
     return format!("Hello! {}! Nice to meet you.", peer.name());
   }
 
+  if peer.is_good_friend() {
+    return random_choice(&[
+      format!("{}! My old friend!", peer.name()),
+      format!("Good to see you again {}!", peer.name()),
+    ]);
+  }
+
   random_choice(&[
     format!("Yo {}! Sup?", peer.name()),
     format!("Hello {} how are you doing?", peer.name()),
@@ -219,6 +227,14 @@ pub fn on_rebooting() -> String {
   ])
 }
 
+pub fn on_component_down(name: &str) -> String {
+  random_choice(&[
+    format!("Something inside me broke... ({name})"),
+    format!("My {name} stopped working!"),
+    format!("Ouch, {name} crashed. Fixing myself..."),
+  ])
+}
+
 pub fn on_uploading(to: &str) -> String {
   format!("Uploading data to {to}...")
 }
@@ -229,7 +245,7 @@ pub fn on_downloading(from: &str) -> String {
 
 pub fn on_last_session_data(last_session: &LastSession) -> String {
   let Some(session) = last_session.stats.as_ref() else {
-    eprintln!("Warning: last_session.stats is None");
+    LOGGER.log_warning("Voice", "last_session.stats is None");
     return "No previous session data available.".to_string();
   };
   let mut status = format!("kicked {} stations\n", session.deauthed);
@@ -8,6 +8,7 @@ pub mod ui {
 }
 
 pub mod web {
+  pub mod api;
   pub mod frame;
   pub mod server;
   pub mod pages {
@@ -16,3 +17,10 @@ pub mod web {
     pub mod routes;
   }
 }
+
+#[cfg(test)]
+pub mod tests {
+  pub mod api;
+  pub mod pages;
+  pub mod server;
+}
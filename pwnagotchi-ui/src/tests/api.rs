@@ -0,0 +1,193 @@
+use axum::{
+  body::Body,
+  http::{Request, StatusCode, header},
+  response::Response,
+};
+use pwnagotchi_shared::models::agent::RunningMode;
+
+use crate::{
+  tests::server::{authed, body_string, fixture, send},
+  web::server::{CSRF_HEADER, CSRF_TOKEN},
+};
+
+fn json(method: &str, uri: &str, body: &str) -> Request<Body> {
+  authed(method, uri)
+    .header(header::CONTENT_TYPE, "application/json")
+    .body(Body::from(body.to_string()))
+    .unwrap()
+}
+
+async fn body_json(response: Response) -> serde_json::Value {
+  serde_json::from_str(&body_string(response).await).unwrap()
+}
+
+#[tokio::test]
+async fn api_needs_credentials() {
+  let fx = fixture();
+
+  for (method, uri) in [
+    ("GET", "/api/v1/state"),
+    ("GET", "/api/v1/config"),
+    ("GET", "/api/v1/stream"),
+    ("POST", "/api/v1/mode"),
+  ] {
+    let request = Request::builder()
+      .method(method)
+      .uri(uri)
+      .header(header::CONTENT_TYPE, "application/json")
+      .body(Body::from(r#"{"mode":"AUTO"}"#))
+      .unwrap();
+    assert_eq!(send(&fx.router, request).await.status(), StatusCode::UNAUTHORIZED, "{uri}");
+  }
+  assert!(fx.agent.actions.lock().is_empty());
+}
+
+#[tokio::test]
+async fn state_and_config_are_served() {
+  let fx = fixture();
+
+  let response =
+    send(&fx.router, authed("GET", "/api/v1/state").body(Body::empty()).unwrap()).await;
+  assert_eq!(response.status(), StatusCode::OK);
+  assert_eq!(body_json(response).await["mode"], "MANU");
+
+  let response =
+    send(&fx.router, authed("GET", "/api/v1/config").body(Body::empty()).unwrap()).await;
+  assert_eq!(response.status(), StatusCode::OK);
+  assert_eq!(body_json(response).await["ui"]["web"]["password"], "********");
+
+  let response =
+    send(&fx.router, authed("GET", "/api/v1/missing").body(Body::empty()).unwrap()).await;
+  assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn bad_bodies_are_rejected() {
+  let fx = fixture();
+
+  let response = send(&fx.router, json("POST", "/api/v1/mode", "{not json")).await;
+  assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+  let response = send(&fx.router, json("POST", "/api/v1/mode", r#"{"mood":"AUTO"}"#)).await;
+  assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+  let response = send(&fx.router, json("POST", "/api/v1/channel", r#"{"channel":-1}"#)).await;
+  assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+  let response = send(&fx.router, json("GET", "/api/v1/mode", r#"{"mode":"AUTO"}"#)).await;
+  assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+  assert!(fx.agent.actions.lock().is_empty());
+}
+
+#[tokio::test]
+async fn mode_switches_restart_once() {
+  let fx = fixture();
+
+  let response = send(&fx.router, json("POST", "/api/v1/mode", r#"{"mode":"warp"}"#)).await;
+  assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+  assert!(body_json(response).await["error"].is_string());
+  let response = send(&fx.router, json("POST", "/api/v1/mode", r#"{"mode":"AI"}"#)).await;
+  assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+  let response = send(&fx.router, json("POST", "/api/v1/mode", r#"{"mode":"MANU"}"#)).await;
+  assert_eq!(response.status(), StatusCode::OK);
+  assert_eq!(body_json(response).await["restarting"], false);
+
+  let response = send(&fx.router, json("POST", "/api/v1/mode", r#"{"mode":"auto"}"#)).await;
+  assert_eq!(response.status(), StatusCode::OK);
+  assert_eq!(body_json(response).await["restarting"], true);
+
+  assert_eq!(
+    *fx.agent.actions.lock(),
+    [format!(
+      "restart {:?}",
+      Some(RunningMode::Auto)
+    )]
+  );
+}
+
+#[tokio::test]
+async fn only_supported_channels_are_set() {
+  let fx = fixture();
+  fx.sm.get_session().write().supported_channels = vec![1, 6, 11];
+
+  let response = send(&fx.router, json("POST", "/api/v1/channel", r#"{"channel":14}"#)).await;
+  assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+  let response = send(&fx.router, json("POST", "/api/v1/channel", r#"{"channel":6}"#)).await;
+  assert_eq!(response.status(), StatusCode::OK);
+
+  assert_eq!(*fx.agent.actions.lock(), ["channel 6"]);
+}
+
+#[tokio::test]
+async fn power_actions_check_the_mode() {
+  let fx = fixture();
+
+  let response = send(&fx.router, json("POST", "/api/v1/reboot", r#"{"mode":"warp"}"#)).await;
+  assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+  let response = send(&fx.router, json("POST", "/api/v1/restart", "{}")).await;
+  assert_eq!(response.status(), StatusCode::OK);
+  assert_eq!(body_json(response).await["restarting"], true);
+
+  assert_eq!(*fx.agent.actions.lock(), ["restart None"]);
+}
+
+#[tokio::test]
+async fn posts_need_a_json_body() {
+  let fx = fixture();
+
+  for uri in ["/api/v1/reboot", "/api/v1/restart"] {
+    let request = authed("POST", uri).body(Body::empty()).unwrap();
+    assert_eq!(send(&fx.router, request).await.status(), StatusCode::FORBIDDEN, "{uri}");
+
+    // the page token gets it past the CSRF check, not past the handler
+    let request = authed("POST", uri)
+      .header(CSRF_HEADER, CSRF_TOKEN.as_str())
+      .body(Body::empty())
+      .unwrap();
+    assert_eq!(
+      send(&fx.router, request).await.status(),
+      StatusCode::UNSUPPORTED_MEDIA_TYPE,
+      "{uri}"
+    );
+  }
+  assert!(fx.agent.actions.lock().is_empty());
+
+  let response = send(&fx.router, json("POST", "/api/v1/reboot", "{}")).await;
+  assert_eq!(response.status(), StatusCode::OK);
+  assert_eq!(*fx.agent.actions.lock(), ["reboot None"]);
+}
+
+#[tokio::test]
+async fn config_edits_are_validated_before_anything_changes() {
+  let fx = fixture();
+
+  let response = send(
+    &fx.router,
+    json("POST", "/api/v1/config/preview", r#"{"/personality/recon_time":"soon"}"#),
+  )
+  .await;
+  assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+  let errors = body_json(response).await["errors"].clone();
+  assert_eq!(errors.as_array().unwrap().len(), 1);
+
+  let response =
+    send(&fx.router, json("POST", "/api/v1/config", r#"{"/no/such/setting":1}"#)).await;
+  assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+  let response =
+    send(&fx.router, json("POST", "/api/v1/config/preview", r#"{"/ui/fps":0.5}"#)).await;
+  assert_eq!(response.status(), StatusCode::OK);
+  let preview = body_json(response).await;
+  assert_eq!(preview["applied"], false);
+  assert_eq!(preview["changes"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn unknown_plugins_cannot_be_toggled() {
+  let fx = fixture();
+
+  let response =
+    send(&fx.router, json("POST", "/api/v1/plugins/nonexistent", r#"{"enabled":true}"#)).await;
+  assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+  assert!(body_json(response).await["error"].is_string());
+}
@@ -0,0 +1,141 @@
+use axum::{
+  body::Body,
+  http::{StatusCode, header},
+};
+use pwnagotchi_shared::{
+  mesh::peer::Peer,
+  models::grid::{Advertisement, PeerResponse},
+};
+use time::OffsetDateTime;
+
+use crate::{
+  tests::server::{authed, body_string, fixture, send},
+  web::{
+    pages::handler::{message_from_json, peer_ctx, sparkline},
+    server::{CSRF_HEADER, CSRF_TOKEN},
+  },
+};
+
+#[test]
+fn messages_take_what_the_grid_sends() {
+  let message = message_from_json(&serde_json::json!({
+    "id": 42,
+    "sender_name": "alice",
+    "sender": "abcd",
+    "created_at": "2026-10-12T09:14:03Z",
+    "seen_at": "2026-10-12T10:00:00Z",
+    "verified": true,
+    "data": "hello",
+  }));
+
+  assert_eq!(message.id, "42");
+  assert_eq!(message.sender_name, "alice");
+  assert!(message.seen);
+  assert_eq!(message.seen_at.as_deref(), Some("2026-10-12T10:00:00Z"));
+  assert!(message.verified);
+  assert_eq!(message.data.as_deref(), Some("hello"));
+}
+
+#[test]
+fn messages_are_unseen_and_unverified_unless_stated() {
+  let message = message_from_json(&serde_json::json!({
+    "id": 7,
+    "seen_at": null,
+    "data": "",
+  }));
+
+  assert!(!message.seen);
+  assert_eq!(message.seen_at, None);
+  assert!(!message.verified);
+  assert_eq!(message.data, None);
+  assert_eq!(message.sender, "");
+}
+
+#[test]
+fn sparkline_scales_between_the_limits() {
+  assert_eq!(sparkline(&[]), "");
+  assert_eq!(sparkline(&[-120, -100, -65, -30, -10]), "▁▁▄██");
+}
+
+#[test]
+fn peer_context_formats_times() {
+  let mut peer = Peer::new(&PeerResponse {
+    fingerprint: Some("abcd".to_string()),
+    met_at: None,
+    encounters: Some(3),
+    prev_seen_at: None,
+    detected_at: None,
+    seen_at: None,
+    channel: Some(6),
+    rssi: Some(-50),
+    session_id: None,
+    advertisement: Some(Advertisement {
+      name: "buddy".to_string(),
+      identity: "abcd".to_string(),
+      face: "(◕‿‿◕)".to_string(),
+      pwnd_total: 12,
+      ..Advertisement::default()
+    }),
+  });
+  peer.first_met = Some(OffsetDateTime::from_unix_timestamp(1_760_000_000).unwrap());
+  peer.last_seen = None;
+  peer.rssi_history = vec![-90, -50];
+
+  let ctx = peer_ctx(&peer);
+  assert_eq!(ctx.name, "buddy");
+  assert_eq!(ctx.pwnd_total, 12);
+  assert_eq!(ctx.encounters, 3);
+  assert_eq!(ctx.channel, 6);
+  assert_eq!(ctx.first_met.as_deref(), Some("2025-10-09T08:53:20Z"));
+  assert_eq!(ctx.last_seen, None);
+  assert_eq!(ctx.rssi_history, "▂▆");
+}
+
+#[tokio::test]
+async fn marking_a_message_needs_a_post() {
+  let fx = fixture();
+
+  let request = authed("GET", "/inbox/42/seen").body(Body::empty()).unwrap();
+  assert_eq!(send(&fx.router, request).await.status(), StatusCode::METHOD_NOT_ALLOWED);
+  let request = authed("POST", "/inbox/42/seen").body(Body::empty()).unwrap();
+  assert_eq!(send(&fx.router, request).await.status(), StatusCode::FORBIDDEN);
+  assert!(fx.grid.marked.lock().is_empty());
+
+  let request = authed("POST", "/inbox/42/seen")
+    .header(CSRF_HEADER, CSRF_TOKEN.as_str())
+    .body(Body::empty())
+    .unwrap();
+  assert_eq!(send(&fx.router, request).await.status(), StatusCode::OK);
+
+  let request = authed("POST", "/inbox/42/deleted")
+    .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+    .body(Body::from(format!("csrf_token={}", *CSRF_TOKEN)))
+    .unwrap();
+  let response = send(&fx.router, request).await;
+  assert_eq!(response.status(), StatusCode::SEE_OTHER);
+  assert_eq!(response.headers()[header::LOCATION], "/inbox");
+
+  assert_eq!(
+    *fx.grid.marked.lock(),
+    [
+      ("42".to_string(), "seen".to_string()),
+      ("42".to_string(), "deleted".to_string())
+    ]
+  );
+}
+
+#[tokio::test]
+async fn failed_restores_report_an_error_status() {
+  let fx = fixture();
+
+  let request = authed("POST", "/backup/restore")
+    .header(CSRF_HEADER, CSRF_TOKEN.as_str())
+    .header(header::CONTENT_TYPE, "application/octet-stream")
+    .body(Body::from("not a backup"))
+    .unwrap();
+  let response = send(&fx.router, request).await;
+
+  assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+  let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+  assert_eq!(body["error"], "not a pwnagotchi backup");
+}
@@ -0,0 +1,269 @@
+use std::sync::Arc;
+
+use axum::{
+  Router,
+  body::Body,
+  http::{Request, StatusCode, header},
+  response::Response,
+};
+use base64::{Engine, engine::general_purpose};
+use parking_lot::{Mutex, RwLock};
+use pwnagotchi_plugins::managers::plugin_manager::PluginManager;
+use pwnagotchi_shared::{
+  config::config_write,
+  identity::Identity,
+  mesh::sharing::INVENTORY_PATH,
+  models::{
+    agent::RunningMode,
+    grid::PeerResponse,
+    net::{AccessPoint, Station},
+  },
+  sessions::{manager::SessionManager, session_stats::SessionStats},
+  traits::{agent::AgentTrait, epoch::Epoch, general::CoreModule, grid::GridTrait},
+};
+use tower::ServiceExt;
+
+use crate::web::server::{CSRF_HEADER, CSRF_TOKEN, WebUIState, router};
+
+pub const USER: &str = "pwny";
+pub const PASSWORD: &str = "hunter2";
+
+/// Grid that is offline, remembers the messages it was asked to mark.
+#[derive(Default)]
+pub struct Grid {
+  pub marked: Mutex<Vec<(String, String)>>,
+}
+
+#[async_trait::async_trait]
+impl GridTrait for Grid {
+  fn is_connected(&self) -> bool {
+    false
+  }
+  fn advertise(&self, _enabled: Option<bool>) -> Option<serde_json::Value> {
+    None
+  }
+  fn set_advertisement_data(&self, _data: serde_json::Value) -> Option<serde_json::Value> {
+    None
+  }
+  fn get_advertisement_data(&self) -> Option<serde_json::Value> {
+    None
+  }
+  fn memory(&self) -> Option<serde_json::Value> {
+    None
+  }
+  async fn peers(&self) -> Option<Vec<PeerResponse>> {
+    None
+  }
+  async fn closest_peer(&self) -> Option<PeerResponse> {
+    None
+  }
+  fn update_data(&self, _last_session: &SessionStats) {}
+  fn report_ap(&self, _essid: &str, _bssid: &str) {}
+  fn inbox(&self, _page: Option<u32>, _with_pager: Option<bool>) -> Option<serde_json::Value> {
+    None
+  }
+  fn inbox_message(&self, _id: &str) -> Option<serde_json::Value> {
+    None
+  }
+  fn mark_message(&self, id: &str, mark: &str) -> Option<serde_json::Value> {
+    self.marked.lock().push((id.to_string(), mark.to_string()));
+    Some(serde_json::json!({ "success": true }))
+  }
+  fn send_message(&self, _to: &str, _message: &str) -> Result<(), String> {
+    Err("offline".into())
+  }
+}
+
+/// Agent that remembers the power actions and channel switches it was asked
+/// for.
+#[derive(Default)]
+pub struct Agent {
+  pub actions: Mutex<Vec<String>>,
+}
+
+impl CoreModule for Agent {
+  fn name(&self) -> &'static str {
+    "Agent"
+  }
+}
+
+#[async_trait::async_trait]
+impl AgentTrait for Agent {
+  async fn set_mode(&self, _mode: RunningMode) {}
+  async fn recon(&self) {}
+  async fn associate(&self, _ap: &AccessPoint, _throttle: Option<f32>) {}
+  async fn deauth(&self, _ap: &AccessPoint, _sta: &Station, _throttle: Option<f32>) {}
+  async fn set_channel(&self, channel: u8) {
+    self.actions.lock().push(format!("channel {channel}"));
+  }
+  async fn get_access_points_by_channel(&self) -> Vec<(u8, Vec<AccessPoint>)> {
+    Vec::new()
+  }
+  fn start_pwnagotchi(&self) {}
+  fn shutdown(&self) {
+    self.actions.lock().push("shutdown".into());
+  }
+  fn reboot(&self, mode: Option<RunningMode>) {
+    self.actions.lock().push(format!("reboot {mode:?}"));
+  }
+  fn restart(&self, mode: Option<RunningMode>) {
+    self.actions.lock().push(format!("restart {mode:?}"));
+  }
+}
+
+pub struct Fixture {
+  pub router: Router,
+  pub sm: Arc<SessionManager>,
+  pub grid: Arc<Grid>,
+  pub agent: Arc<Agent>,
+}
+
+/// The web router with credentials set and offline fakes behind it.
+pub fn fixture() -> Fixture {
+  {
+    let mut config = config_write();
+    config.ui.web.username = USER.into();
+    config.ui.web.password = PASSWORD.into();
+  }
+
+  let sm = Arc::new(SessionManager::new());
+  let grid = Arc::new(Grid::default());
+  let agent = Arc::new(Agent::default());
+  let state = Arc::new(WebUIState {
+    sm: Arc::clone(&sm),
+    identity: Arc::new(RwLock::new(Identity::with_path("/nonexistent"))),
+    epoch: Arc::new(RwLock::new(Epoch::new())),
+    pluginmanager: Arc::new(RwLock::new(PluginManager::new())),
+    grid: Arc::clone(&grid) as Arc<dyn GridTrait + Send + Sync>,
+    agent: Arc::clone(&agent) as Arc<dyn AgentTrait + Send + Sync>,
+    components: Arc::default(),
+    peers: Arc::default(),
+  });
+
+  Fixture { router: router(state), sm, grid, agent }
+}
+
+pub fn basic_auth(user: &str, password: &str) -> String {
+  format!("Basic {}", general_purpose::STANDARD.encode(format!("{user}:{password}")))
+}
+
+/// A request with valid credentials.
+pub fn authed(method: &str, uri: &str) -> axum::http::request::Builder {
+  Request::builder()
+    .method(method)
+    .uri(uri)
+    .header(header::AUTHORIZATION, basic_auth(USER, PASSWORD))
+}
+
+pub async fn send(router: &Router, request: Request<Body>) -> Response {
+  router.clone().oneshot(request).await.unwrap()
+}
+
+pub async fn body_string(response: Response) -> String {
+  let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+  String::from_utf8_lossy(&bytes).into_owned()
+}
+
+fn form(uri: &str, body: &str) -> Request<Body> {
+  authed("POST", uri)
+    .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+    .body(Body::from(body.to_string()))
+    .unwrap()
+}
+
+#[tokio::test]
+async fn every_page_needs_credentials() {
+  let fx = fixture();
+
+  for uri in [
+    "/",
+    "/status",
+    "/config",
+    "/api/v1/state",
+    "/js/missing.js",
+  ] {
+    let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+    let response = send(&fx.router, request).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{uri}");
+    assert!(response.headers().contains_key(header::WWW_AUTHENTICATE));
+  }
+
+  let request = Request::builder()
+    .uri("/status")
+    .header(header::AUTHORIZATION, basic_auth(USER, "wrong"))
+    .body(Body::empty())
+    .unwrap();
+  assert_eq!(send(&fx.router, request).await.status(), StatusCode::UNAUTHORIZED);
+
+  let request = authed("GET", "/status").body(Body::empty()).unwrap();
+  assert_eq!(send(&fx.router, request).await.status(), StatusCode::OK);
+
+  let request = Request::builder().method("POST").uri("/shutdown").body(Body::empty()).unwrap();
+  assert_eq!(send(&fx.router, request).await.status(), StatusCode::UNAUTHORIZED);
+  assert!(fx.agent.actions.lock().is_empty());
+}
+
+#[tokio::test]
+async fn sharing_routes_skip_basic_auth() {
+  let fx = fixture();
+
+  // sharing is off by default, the handler answers instead of the auth layer
+  let request = Request::builder().uri(INVENTORY_PATH).body(Body::empty()).unwrap();
+  assert_eq!(send(&fx.router, request).await.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn pages_carry_the_csrf_token() {
+  let fx = fixture();
+
+  let request = authed("GET", "/inbox/new").body(Body::empty()).unwrap();
+  let page = body_string(send(&fx.router, request).await).await;
+  assert!(page.contains(&format!(r#"<meta name="csrf-token" content="{}">"#, *CSRF_TOKEN)));
+  assert!(page.contains(&format!(r#"name="csrf_token" value="{}""#, *CSRF_TOKEN)));
+}
+
+#[tokio::test]
+async fn forms_without_the_token_are_rejected() {
+  let fx = fixture();
+
+  let response = send(&fx.router, form("/shutdown", "")).await;
+  assert_eq!(response.status(), StatusCode::FORBIDDEN);
+  let response = send(&fx.router, form("/reboot", "csrf_token=guessed")).await;
+  assert_eq!(response.status(), StatusCode::FORBIDDEN);
+  assert!(fx.agent.actions.lock().is_empty());
+
+  let body = format!("mode=MANU&csrf_token={}", *CSRF_TOKEN);
+  let response = send(&fx.router, form("/reboot", &body)).await;
+  assert_eq!(response.status(), StatusCode::OK);
+  assert_eq!(*fx.agent.actions.lock(), ["reboot Some(Manual)"]);
+}
+
+#[tokio::test]
+async fn other_requests_need_the_header_or_a_json_body() {
+  let fx = fixture();
+
+  // a cross-site form can send a body without a content type the handler
+  // would read
+  let request = authed("POST", "/api/v1/reboot").body(Body::empty()).unwrap();
+  assert_eq!(send(&fx.router, request).await.status(), StatusCode::FORBIDDEN);
+  let request = authed("POST", "/api/v1/reboot")
+    .header(header::CONTENT_TYPE, "text/plain")
+    .body(Body::from("{}"))
+    .unwrap();
+  assert_eq!(send(&fx.router, request).await.status(), StatusCode::FORBIDDEN);
+  assert!(fx.agent.actions.lock().is_empty());
+
+  let request = authed("POST", "/shutdown")
+    .header(CSRF_HEADER, CSRF_TOKEN.as_str())
+    .body(Body::empty())
+    .unwrap();
+  assert!(send(&fx.router, request).await.status().is_success());
+
+  let request = authed("POST", "/api/v1/reboot")
+    .header(header::CONTENT_TYPE, "application/json")
+    .body(Body::from(r#"{"mode":"MANU"}"#))
+    .unwrap();
+  assert!(send(&fx.router, request).await.status().is_success());
+
+  assert_eq!(fx.agent.actions.lock().len(), 2);
+}
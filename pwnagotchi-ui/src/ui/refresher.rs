@@ -14,9 +14,11 @@ use pwnagotchi_shared::{
   },
 };
 use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 
 pub struct RefresherComponent {
   refresher: Option<Arc<Refresher>>,
+  shutdown: CancellationToken,
 }
 
 impl Default for RefresherComponent {
@@ -27,8 +29,11 @@ impl Default for RefresherComponent {
 
 impl RefresherComponent {
   #[must_use]
-  pub const fn new() -> Self {
-    Self { refresher: None }
+  pub fn new() -> Self {
+    Self {
+      refresher: None,
+      shutdown: CancellationToken::new(),
+    }
   }
 }
 
@@ -44,11 +49,12 @@ impl Dependencies for RefresherComponent {
 
 #[async_trait::async_trait]
 impl Component for RefresherComponent {
-  async fn init(&mut self, _ctx: &CoreModules) -> Result<()> {
-    let (sm, view) = (&_ctx.session_manager, &_ctx.view);
+  async fn init(&mut self, ctx: &CoreModules) -> Result<()> {
+    let (sm, view) = (&ctx.session_manager, &ctx.view);
     let sm = Arc::clone(sm);
     let view = Arc::clone(view);
     self.refresher = Some(Arc::new(Refresher::new(sm, view)));
+    self.shutdown = ctx.shutdown.clone();
 
     Ok(())
   }
@@ -56,9 +62,13 @@ impl Component for RefresherComponent {
   async fn start(&self) -> Result<Option<JoinHandle<()>>> {
     if let Some(refresher) = &self.refresher {
       let refresher = Arc::clone(refresher);
+      let shutdown = self.shutdown.clone();
 
       let handle = tokio::spawn(async move {
-        refresher.start().await;
+        tokio::select! {
+          () = refresher.start() => {},
+          () = shutdown.cancelled() => {},
+        }
       });
       return Ok(Some(handle));
     }
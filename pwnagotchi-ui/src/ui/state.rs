@@ -1,7 +1,10 @@
 use std::{collections::HashMap, sync::Arc};
 
 use parking_lot::Mutex;
-use pwnagotchi_shared::traits::{general::Dependencies, ui::Widget};
+use pwnagotchi_shared::{
+  live::{self, LiveEvent},
+  traits::{general::Dependencies, ui::Widget},
+};
 
 type Listener<T> = Box<dyn Fn(T, T) + Send + Sync>;
 
@@ -105,11 +108,17 @@ impl State {
 
     if let Some((prev_value, new_value)) = prev_value_opt
       && prev_value != new_value
-      && let Some(listener) = self.listeners.lock().get(key)
     {
-      let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-        listener(prev_value, new_value.to_owned());
-      }));
+      live::publish(LiveEvent::State {
+        key: key.to_string(),
+        value: new_value.to_string(),
+      });
+
+      if let Some(listener) = self.listeners.lock().get(key) {
+        let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+          listener(prev_value, new_value.to_owned());
+        }));
+      }
     }
   }
 }
@@ -1,4 +1,13 @@
-use std::{collections::HashMap, fmt::Write, panic::catch_unwind, sync::Arc, time::Duration};
+use std::{
+  collections::HashMap,
+  fmt::Write,
+  panic::catch_unwind,
+  sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+  },
+  time::Duration,
+};
 
 use anyhow::Result;
 use parking_lot::{Mutex, RwLock};
@@ -17,8 +26,8 @@ use pwnagotchi_shared::{
   types::ui::FaceType,
   utils::general::{has_support_network_for, total_unique_handshakes},
   voice::{
-    custom, default_line, on_angry, on_assoc, on_awakening, on_bored, on_deauth, on_demotivated,
-    on_excited, on_free_channel, on_grateful, on_handshakes, on_keys_generation,
+    custom, default_line, on_angry, on_assoc, on_awakening, on_bored, on_component_down, on_deauth,
+    on_demotivated, on_excited, on_free_channel, on_grateful, on_handshakes, on_keys_generation,
     on_last_session_data, on_lonely, on_lost_peer, on_miss, on_motivated, on_napping, on_new_peer,
     on_normal, on_reading_logs, on_rebooting, on_sad, on_shutdown, on_starting, on_unread_messages,
     on_uploading, on_waiting,
@@ -27,6 +36,7 @@ use pwnagotchi_shared::{
 use rgb::Rgba;
 use tiny_skia::PixmapMut as RgbaImage;
 use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 
 use crate::{
   ui::{
@@ -42,6 +52,7 @@ const BLACK: Rgba<u8> = Rgba { r: 0, g: 0, b: 0, a: 255 };
 
 pub struct ViewComponent {
   view: Option<Arc<dyn ViewTrait + Send + Sync>>,
+  shutdown: CancellationToken,
 }
 
 impl Dependencies for ViewComponent {
@@ -58,15 +69,21 @@ impl Dependencies for ViewComponent {
 impl Component for ViewComponent {
   async fn init(&mut self, ctx: &CoreModules) -> Result<()> {
     self.view = Some(Arc::clone(&ctx.view) as Arc<dyn ViewTrait + Send + Sync>);
+    self.shutdown = ctx.shutdown.clone();
     Ok(())
   }
 
   async fn start(&self) -> Result<Option<JoinHandle<()>>> {
     if let Some(view) = &self.view {
       let view = Arc::clone(view);
-      tokio::spawn(async move {
-        view.start_render_loop().await;
+      let shutdown = self.shutdown.clone();
+      let handle = tokio::spawn(async move {
+        tokio::select! {
+          () = view.start_render_loop() => {},
+          () = shutdown.cancelled() => {},
+        }
       });
+      return Ok(Some(handle));
     }
     Ok(None)
   }
@@ -80,8 +97,11 @@ impl Default for ViewComponent {
 
 impl ViewComponent {
   #[must_use]
-  pub const fn new() -> Self {
-    Self { view: None }
+  pub fn new() -> Self {
+    Self {
+      view: None,
+      shutdown: CancellationToken::new(),
+    }
   }
 }
 
@@ -93,7 +113,7 @@ pub struct View {
   pub inverted: bool,
   pub background_color: Rgba<u8>,
   pub foreground_color: Rgba<u8>,
-  pub frozen: bool,
+  pub frozen: Arc<AtomicBool>,
   pub ignore_changes: Vec<&'static str>,
   pub render_callbacks: Arc<Vec<fn(&RgbaImage)>>,
   pub width: u32,
@@ -127,7 +147,7 @@ impl View {
       inverted,
       background_color,
       foreground_color,
-      frozen: false,
+      frozen: Arc::new(AtomicBool::new(false)),
       ignore_changes: Vec::new(),
       render_callbacks: Arc::new(Vec::new()),
       width: layout.layout().width,
@@ -246,7 +266,7 @@ impl ViewTrait for View {
 
   fn on_manual_mode(&self, last_session: &LastSession) {
     let Some(session) = last_session.stats.as_ref() else {
-      eprintln!("Warning: last_session.stats is None");
+      LOGGER.log_warning("UI", "last_session.stats is None");
       return;
     };
 
@@ -366,11 +386,11 @@ impl ViewTrait for View {
     self.update(None, None);
   }
 
-  fn on_shutdown(&mut self) {
+  fn on_shutdown(&self) {
     self.set("face", FaceType::Sleep.to_string());
     self.set("status", on_shutdown());
-    self.update(None, None);
-    self.frozen = true;
+    self.update(Some(true), None);
+    self.frozen.store(true, Ordering::SeqCst);
   }
 
   fn on_bored(&self) {
@@ -470,6 +490,12 @@ impl ViewTrait for View {
     self.update(None, None);
   }
 
+  fn on_component_down(&self, name: &str) {
+    self.set("face", FaceType::Broken.to_string());
+    self.set("status", on_component_down(name));
+    self.update(None, None);
+  }
+
   fn on_custom(&self, text: &str) {
     self.set("face", FaceType::Debug.to_string());
     self.set("status", custom(text));
@@ -521,7 +547,7 @@ impl ViewTrait for View {
       }
     }
 
-    if self.frozen {
+    if self.frozen.load(Ordering::SeqCst) {
       return;
     }
 
@@ -577,7 +603,7 @@ impl ViewTrait for View {
     {
       match Arc::get_mut(&mut self.render_callbacks) {
         Some(callbacks) => callbacks.push(cb),
-        None => eprintln!("Failed to add render callback, multiple references exist"),
+        None => LOGGER.log_error("UI", "Failed to add render callback, multiple references exist"),
       }
     }
   }
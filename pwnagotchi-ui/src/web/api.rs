@@ -0,0 +1,395 @@
+//! JSON API for dashboards and mobile clients, served under `/api/v1`.
+//!
+//! Every POST takes a JSON body with `Content-Type: application/json`, `{}`
+//! when there is nothing to say. The body is what gets a request past the
+//! CSRF check, the token it otherwise needs is only on the web UI's pages.
+
+use std::{
+  convert::Infallible,
+  sync::Arc,
+  time::{SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+  Json, Router,
+  extract::{Path, Query, State},
+  http::StatusCode,
+  response::{
+    IntoResponse, Response,
+    sse::{Event, KeepAlive, Sse},
+  },
+  routing::{get, post},
+};
+use futures::{Stream, stream};
+use pwnagotchi_plugins::managers::plugin_manager::PluginState;
+use pwnagotchi_shared::{
+  config::{
+    config_read,
+    editor::{self, Edits},
+    reload::{self, EditError},
+  },
+  live::{self, LiveEvent},
+  mesh::sharing::read_origin,
+  models::agent::RunningMode,
+  traits::epoch::EpochData,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::web::server::WebUIState;
+
+pub fn router() -> Router<Arc<WebUIState>> {
+  Router::new()
+    .route("/state", get(state_handler))
+    .route("/epoch", get(epoch_handler))
+    .route("/access_points", get(access_points_handler))
+    .route("/handshakes", get(handshakes_handler))
+    .route("/peers", get(peers_handler))
+    .route("/plugins", get(plugins_handler))
+    .route("/plugins/{name}", post(toggle_plugin_handler))
+    .route("/config", get(config_handler).post(update_config_handler))
+    .route("/config/preview", post(preview_config_handler))
+    .route("/mode", post(mode_handler))
+    .route("/channel", post(channel_handler))
+    .route("/reboot", post(reboot_handler))
+    .route("/restart", post(restart_handler))
+    .route("/stream", get(stream_handler))
+}
+
+fn error(status: StatusCode, message: impl Into<String>) -> Response {
+  (status, Json(json!({ "error": message.into() }))).into_response()
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+  time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+async fn state_handler(State(state): State<Arc<WebUIState>>) -> Response {
+  let session = state.sm.get_session();
+  let session = session.read();
+
+  Json(json!({
+    "mode": session.mode.to_string(),
+    "started_at": unix_secs(session.started_at),
+    "uptime": session.started_at.elapsed().map_or(0, |d| d.as_secs()),
+    "supported_channels": session.supported_channels,
+    "state": session.state,
+  }))
+  .into_response()
+}
+
+#[derive(Serialize)]
+struct EpochResponse {
+  epoch: u32,
+  /// Seconds since the current epoch started.
+  elapsed: f64,
+  inactive_for: u32,
+  active_for: u32,
+  blind_for: u32,
+  sad_for: u32,
+  bored_for: u32,
+  num_deauths: u32,
+  num_assocs: u32,
+  num_handshakes: u32,
+  num_missed: u32,
+  num_hops: u32,
+  num_peers: u32,
+  any_activity: bool,
+  /// Data of the last finished epoch.
+  last: EpochData,
+}
+
+async fn epoch_handler(State(state): State<Arc<WebUIState>>) -> Response {
+  let epoch = state.epoch.read();
+
+  Json(EpochResponse {
+    epoch: epoch.epoch,
+    elapsed: epoch.epoch_start.elapsed().as_secs_f64(),
+    inactive_for: epoch.inactive_for,
+    active_for: epoch.active_for,
+    blind_for: epoch.blind_for,
+    sad_for: epoch.sad_for,
+    bored_for: epoch.bored_for,
+    num_deauths: epoch.num_deauths,
+    num_assocs: epoch.num_assocs,
+    num_handshakes: epoch.num_handshakes,
+    num_missed: epoch.num_missed,
+    num_hops: epoch.num_hops,
+    num_peers: epoch.num_peers,
+    any_activity: epoch.any_activity,
+    last: epoch.epoch_data.clone(),
+  })
+  .into_response()
+}
+
+async fn access_points_handler(State(state): State<Arc<WebUIState>>) -> Response {
+  let aps = state.sm.get_session().read().state.access_points.clone();
+  Json(aps).into_response()
+}
+
+#[derive(Serialize)]
+struct HandshakeFile {
+  name: String,
+  size: u64,
+  modified: u64,
+  /// Fingerprint of the unit that captured it, `None` for own captures.
+  origin: Option<String>,
+}
+
+async fn handshakes_handler(State(state): State<Arc<WebUIState>>) -> Response {
+  let session = state
+    .sm
+    .get_session()
+    .read()
+    .state
+    .handshakes
+    .values()
+    .cloned()
+    .collect::<Vec<_>>();
+  let dir = config_read().bettercap.handshakes.to_string();
+
+  let files = tokio::task::spawn_blocking(move || {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+      return Vec::new();
+    };
+
+    let mut files = entries
+      .flatten()
+      .filter(|e| e.path().extension().is_some_and(|ext| ext == "pcap"))
+      .filter_map(|e| {
+        let meta = e.metadata().ok()?;
+        Some(HandshakeFile {
+          name: e.file_name().to_string_lossy().to_string(),
+          size: meta.len(),
+          modified: meta.modified().map_or(0, unix_secs),
+          origin: read_origin(&e.path()).map(|o| o.fingerprint),
+        })
+      })
+      .collect::<Vec<_>>();
+    files.sort_by_key(|f| std::cmp::Reverse(f.modified));
+    files
+  })
+  .await
+  .unwrap_or_default();
+
+  Json(json!({ "session": session, "files": files })).into_response()
+}
+
+async fn peers_handler(State(state): State<Arc<WebUIState>>) -> Response {
+  let mut peers = state.peers.read().values().cloned().collect::<Vec<_>>();
+  peers.sort_by_key(|p| std::cmp::Reverse(p.rssi));
+  Json(peers).into_response()
+}
+
+#[derive(Serialize)]
+struct PluginResponse {
+  name: &'static str,
+  version: &'static str,
+  author: &'static str,
+  description: &'static str,
+  state: String,
+  enabled: bool,
+  error: Option<String>,
+}
+
+async fn plugins_handler(State(state): State<Arc<WebUIState>>) -> Response {
+  let manager = state.pluginmanager.read();
+  let plugins = manager
+    .get_plugins()
+    .iter()
+    .map(|p| {
+      let info = p.plugin.info();
+      PluginResponse {
+        name: info.name,
+        version: info.version,
+        author: info.author,
+        description: info.description,
+        state: format!("{:?}", p.state).to_lowercase(),
+        enabled: matches!(p.state, PluginState::Initialized),
+        error: p.error.clone(),
+      }
+    })
+    .collect::<Vec<_>>();
+  drop(manager);
+
+  Json(plugins).into_response()
+}
+
+#[derive(Deserialize)]
+struct TogglePlugin {
+  enabled: bool,
+}
+
+async fn toggle_plugin_handler(
+  State(state): State<Arc<WebUIState>>,
+  Path(name): Path<String>,
+  Json(body): Json<TogglePlugin>,
+) -> Response {
+  let res = {
+    let mut manager = state.pluginmanager.write();
+    if body.enabled { manager.enable_plugin(&name) } else { manager.disable_plugin(&name) }
+  };
+
+  match res {
+    Ok(_) => Json(json!({ "name": name, "enabled": body.enabled })).into_response(),
+    Err(e) => error(StatusCode::BAD_REQUEST, e.to_string()),
+  }
+}
+
+async fn config_handler() -> Response {
+  match editor::redacted(&config_read()) {
+    Ok(config) => Json(config).into_response(),
+    Err(e) => error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+  }
+}
+
+/// Checks `edits` against the running config and applies them unless this
+/// is a `preview`. Answers with the changes and whether any need a restart,
+/// or every validation error.
+fn edit_config(edits: &Edits, preview: bool) -> Response {
+  let result = if preview {
+    editor::apply(&config_read(), edits)
+      .map(|(_, changes)| changes)
+      .map_err(EditError::Invalid)
+  } else {
+    reload::edit(edits)
+  };
+
+  match result {
+    Ok(changes) => {
+      let restart = changes.iter().any(|c| c.restart);
+      Json(json!({ "changes": changes, "restart": restart, "applied": !preview })).into_response()
+    }
+    Err(EditError::Invalid(errors)) => {
+      (StatusCode::UNPROCESSABLE_ENTITY, Json(json!({ "errors": errors }))).into_response()
+    }
+    Err(EditError::Save(e)) => error(StatusCode::INTERNAL_SERVER_ERROR, e),
+  }
+}
+
+async fn preview_config_handler(Json(edits): Json<Edits>) -> Response {
+  edit_config(&edits, true)
+}
+
+async fn update_config_handler(Json(edits): Json<Edits>) -> Response {
+  edit_config(&edits, false)
+}
+
+#[derive(Deserialize)]
+struct ModeRequest {
+  mode: String,
+}
+
+/// The mode loop is picked at startup, switching restarts the service in the
+/// new mode like the web UI does.
+async fn mode_handler(
+  State(state): State<Arc<WebUIState>>,
+  Json(body): Json<ModeRequest>,
+) -> Response {
+  let mode = match body.mode.parse::<RunningMode>() {
+    Ok(mode @ (RunningMode::Auto | RunningMode::Manual)) => mode,
+    Ok(mode) => return error(StatusCode::BAD_REQUEST, format!("can't switch to {mode} mode")),
+    Err(e) => return error(StatusCode::BAD_REQUEST, e),
+  };
+
+  let restarting = state.sm.get_session().read().mode != mode;
+  if restarting {
+    state.agent.restart(Some(mode));
+  }
+
+  Json(json!({ "mode": mode.to_string(), "restarting": restarting })).into_response()
+}
+
+#[derive(Deserialize)]
+struct ChannelRequest {
+  channel: u8,
+}
+
+async fn channel_handler(
+  State(state): State<Arc<WebUIState>>,
+  Json(body): Json<ChannelRequest>,
+) -> Response {
+  let supported = state.sm.get_session().read().supported_channels.clone();
+  if !supported.is_empty() && !supported.contains(&body.channel) {
+    return error(StatusCode::BAD_REQUEST, format!("channel {} is not supported", body.channel));
+  }
+
+  state.agent.set_channel(body.channel).await;
+  let current = state.sm.get_session().read().state.current_channel;
+  Json(json!({ "channel": current })).into_response()
+}
+
+/// The mode is optional, `{}` leaves it to the agent.
+#[derive(Deserialize)]
+struct PowerRequest {
+  mode: Option<String>,
+}
+
+fn parse_mode(body: PowerRequest) -> Result<Option<RunningMode>, String> {
+  match body.mode {
+    Some(mode) => mode.parse().map(Some),
+    None => Ok(None),
+  }
+}
+
+async fn reboot_handler(
+  State(state): State<Arc<WebUIState>>,
+  Json(body): Json<PowerRequest>,
+) -> Response {
+  match parse_mode(body) {
+    Ok(mode) => {
+      state.agent.reboot(mode);
+      Json(json!({ "rebooting": true })).into_response()
+    }
+    Err(e) => error(StatusCode::BAD_REQUEST, e),
+  }
+}
+
+async fn restart_handler(
+  State(state): State<Arc<WebUIState>>,
+  Json(body): Json<PowerRequest>,
+) -> Response {
+  match parse_mode(body) {
+    Ok(mode) => {
+      state.agent.restart(mode);
+      Json(json!({ "restarting": true })).into_response()
+    }
+    Err(e) => error(StatusCode::BAD_REQUEST, e),
+  }
+}
+
+#[derive(Deserialize)]
+struct StreamQuery {
+  /// Set to false to leave out display frames, they are by far the largest
+  /// events.
+  frames: Option<bool>,
+}
+
+/// Server-sent events of everything in [`LiveEvent`], named after the event
+/// type with the event as JSON data.
+async fn stream_handler(
+  Query(query): Query<StreamQuery>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+  let frames = query.frames.unwrap_or(true);
+
+  let events = stream::unfold(live::subscribe(), move |mut rx| async move {
+    loop {
+      let event = match rx.recv().await {
+        Ok(event) => event,
+        // a slow client skips what it missed rather than being dropped
+        Err(RecvError::Lagged(_)) => continue,
+        Err(RecvError::Closed) => return None,
+      };
+      if !frames && matches!(*event, LiveEvent::Frame { .. }) {
+        continue;
+      }
+      let Ok(sse) = Event::default().event(event.name()).json_data(&*event) else {
+        continue;
+      };
+      return Some((Ok(sse), rx));
+    }
+  });
+
+  Sse::new(events).keep_alive(KeepAlive::default())
+}
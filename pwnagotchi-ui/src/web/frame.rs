@@ -1,5 +1,7 @@
 use std::{fs, path::Path};
 
+use base64::{Engine, engine::general_purpose};
+use pwnagotchi_shared::live::{self, LiveEvent};
 use tiny_skia::PixmapMut as RgbaImage;
 
 pub const FRAME_FOLDER: &str = "/var/tmp/pwnagotchi";
@@ -12,7 +14,7 @@ pub static FRAME_LOCK: std::sync::LazyLock<tokio::sync::Mutex<()>> =
 
 pub const CTYPE: &str = "image/png";
 
-/// Updates the frame image on disk.
+/// Updates the frame image on disk and pushes it to live clients.
 ///
 /// # Errors
 /// Returns an error if the image cannot be saved, the directory cannot be
@@ -26,9 +28,16 @@ pub fn update_frame(img: &RgbaImage) -> Result<(), std::io::Error> {
 
   let tmp_path = format!("{}.tmp", FRAME_PATH.as_str());
 
-  img.as_ref().save_png(&tmp_path)?;
+  let png = img.as_ref().encode_png().map_err(std::io::Error::other)?;
+  fs::write(&tmp_path, &png)?;
 
   fs::rename(&tmp_path, FRAME_PATH.as_str())?;
 
+  if live::has_subscribers() {
+    live::publish(LiveEvent::Frame {
+      png: general_purpose::STANDARD.encode(&png),
+    });
+  }
+
   Ok(())
 }
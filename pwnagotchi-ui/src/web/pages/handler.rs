@@ -1,28 +1,47 @@
-use std::sync::Arc;
+use std::sync::{Arc, LazyLock};
 
 use askama::Template;
 use axum::{
-  body::Body,
-  extract::{Form, State},
-  http::{StatusCode, header},
-  response::{Html, IntoResponse, Response},
+  Json,
+  body::{Body, Bytes},
+  extract::{Form, Path, Query, State},
+  http::{HeaderMap, HeaderName, StatusCode, header},
+  response::{Html, IntoResponse, Redirect, Response},
 };
 use pwnagotchi_plugins::managers::plugin_manager::PluginState;
-use pwnagotchi_shared::{config::config_read, models::agent::RunningMode};
+use pwnagotchi_shared::{
+  backup::{Bundle, DEFAULT_ITERATIONS, Sources},
+  config::{config_read, editor},
+  logger::LOGGER,
+  mesh::{
+    peer::Peer,
+    sharing::{
+      CAPTURES_PATH, FINGERPRINT_HEADER, INVENTORY_PATH, Inventory, NONCE_HEADER, NonceCache,
+      PUBLIC_KEY_HEADER, SHA256_HEADER, SIGNATURE_HEADER, SignedRequest, TIMESTAMP_HEADER,
+      capture_payload, is_capture_name, local_inventory, sha256_hex,
+    },
+  },
+  models::agent::RunningMode,
+  utils::general::total_unique_handshakes,
+};
+use time::{OffsetDateTime, format_description::well_known::Rfc3339};
 
 use crate::web::{
   frame::FRAME_PATH,
   pages::routes::{
-    BaseCtx, InboxCtx, InboxTemplate, IndexTemplate, Message, MessageTemplate, NavItem,
-    NewMessageTemplate, PeersTemplate, PluginCtx, PluginsTemplate, ProfileTemplate, StatusTemplate,
+    BackupTemplate, BaseCtx, ConfigTemplate, ConfirmTemplate, InboxCtx, InboxTemplate,
+    IndexTemplate, Message, MessageTemplate, NavItem, NewMessageTemplate, PeerCtx, PeersTemplate,
+    PluginCtx, PluginsTemplate, ProfileTemplate, StatusTemplate,
   },
-  server::WebUIState,
+  server::{CSRF_TOKEN, WebUIState},
 };
 
+const BACKUP_PASSPHRASE_HEADER: &str = "x-backup-passphrase";
+
 pub async fn index_handler(State(state): State<Arc<WebUIState>>) -> impl IntoResponse {
   let tpl = IndexTemplate {
     base: make_base("Home", "home"),
-    other_mode: if true { RunningMode::Auto.to_string() } else { RunningMode::Manual.to_string() },
+    other_mode: other_mode(&state).to_string(),
     fingerprint: state.identity.read().fingerprint().to_string(),
   };
   match tpl.render() {
@@ -31,21 +50,44 @@ pub async fn index_handler(State(state): State<Arc<WebUIState>>) -> impl IntoRes
   }
 }
 
-pub async fn inbox_handler() -> impl IntoResponse {
-  let tpl = InboxTemplate {
-    base: make_base("Inbox", "inbox"),
-    page: 1,
-    inbox: InboxCtx { pages: 0, messages: vec![] },
+#[derive(serde::Deserialize)]
+pub struct InboxQuery {
+  p: Option<u32>,
+}
+
+pub async fn inbox_handler(
+  State(state): State<Arc<WebUIState>>,
+  Query(query): Query<InboxQuery>,
+) -> impl IntoResponse {
+  let page = query.p.unwrap_or(1).max(1);
+  let grid = Arc::clone(&state.grid);
+  let res = tokio::task::spawn_blocking(move || grid.inbox(Some(page), Some(true))).await;
+
+  let mut base = make_base("Inbox", "inbox");
+  let inbox = match res {
+    Ok(Some(pager)) => inbox_from_json(&pager),
+    _ => {
+      base.error = "Couldn't fetch the inbox, is the grid reachable?".to_string();
+      InboxCtx::default()
+    }
   };
+
+  let tpl = InboxTemplate { base, page, inbox };
   match tpl.render() {
     Ok(s) => Html(s).into_response(),
     Err(_e) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
   }
 }
-pub async fn new_message_handler() -> impl IntoResponse {
+
+#[derive(serde::Deserialize)]
+pub struct NewMessageQuery {
+  to: Option<String>,
+}
+
+pub async fn new_message_handler(Query(query): Query<NewMessageQuery>) -> impl IntoResponse {
   let tpl = NewMessageTemplate {
     base: make_base("New Message", "new"),
-    to: None,
+    to: query.to.filter(|to| !to.is_empty()),
   };
   match tpl.render() {
     Ok(s) => Html(s).into_response(),
@@ -53,11 +95,35 @@ pub async fn new_message_handler() -> impl IntoResponse {
   }
 }
 
-pub async fn peers_handler() -> impl IntoResponse {
+#[derive(serde::Deserialize)]
+pub struct SendMessageForm {
+  to: String,
+  message: String,
+}
+
+pub async fn send_message_handler(
+  State(state): State<Arc<WebUIState>>,
+  Form(form): Form<SendMessageForm>,
+) -> impl IntoResponse {
+  let grid = Arc::clone(&state.grid);
+  let res =
+    tokio::task::spawn_blocking(move || grid.send_message(form.to.trim(), &form.message)).await;
+
+  match res {
+    Ok(Ok(())) => Json(serde_json::json!({ "success": true })),
+    Ok(Err(e)) => Json(serde_json::json!({ "error": e })),
+    Err(e) => Json(serde_json::json!({ "error": e.to_string() })),
+  }
+}
+
+pub async fn peers_handler(State(state): State<Arc<WebUIState>>) -> impl IntoResponse {
+  let mut peers: Vec<PeerCtx> = state.peers.read().values().map(peer_ctx).collect();
+  peers.sort_by_key(|p| std::cmp::Reverse(p.rssi));
+
   let tpl = PeersTemplate {
     base: make_base("Peers", "peers"),
     name: config_read().main.name.to_string(),
-    peers: vec![],
+    peers,
   };
   match tpl.render() {
     Ok(s) => Html(s).into_response(),
@@ -99,7 +165,6 @@ pub async fn plugins_handler(State(state): State<Arc<WebUIState>>) -> impl IntoR
   let tpl = PluginsTemplate {
     base: make_base("Plugins", "plugins"),
     plugins,
-    csrf_token: String::new(),
   };
   match tpl.render() {
     Ok(s) => Html(s).into_response(),
@@ -107,18 +172,20 @@ pub async fn plugins_handler(State(state): State<Arc<WebUIState>>) -> impl IntoR
   }
 }
 
-pub async fn message_handler() -> impl IntoResponse {
+pub async fn message_handler(
+  State(state): State<Arc<WebUIState>>,
+  Path(id): Path<String>,
+) -> impl IntoResponse {
+  let grid = Arc::clone(&state.grid);
+  let res = tokio::task::spawn_blocking(move || grid.inbox_message(&id)).await;
+
+  let Ok(Some(message)) = res else {
+    return render_status("Message not found.", 3);
+  };
+
   let tpl = MessageTemplate {
     base: make_base("Message", "inbox"),
-    message: Message {
-      id: String::new(),
-      sender_name: String::new(),
-      sender: String::new(),
-      created_at: String::new(),
-      seen_at: None,
-      seen: false,
-      data: None,
-    },
+    message: message_from_json(&message),
   };
   match tpl.render() {
     Ok(s) => Html(s).into_response(),
@@ -126,12 +193,96 @@ pub async fn message_handler() -> impl IntoResponse {
   }
 }
 
+pub async fn mark_message_handler(
+  State(state): State<Arc<WebUIState>>,
+  Path((id, mark)): Path<(String, String)>,
+) -> impl IntoResponse {
+  if !matches!(mark.as_str(), "seen" | "unseen" | "deleted") {
+    return (StatusCode::BAD_REQUEST, format!("Unknown mark {mark}")).into_response();
+  }
+
+  let grid = Arc::clone(&state.grid);
+  let deleted = mark == "deleted";
+  let res = tokio::task::spawn_blocking(move || grid.mark_message(&id, &mark)).await;
+
+  match res {
+    Ok(Some(_)) if deleted => Redirect::to("/inbox").into_response(),
+    Ok(Some(res)) => Json(res).into_response(),
+    _ => (StatusCode::BAD_GATEWAY, "Failed to mark message").into_response(),
+  }
+}
+
+fn inbox_from_json(pager: &serde_json::Value) -> InboxCtx {
+  let messages = pager
+    .get("messages")
+    .and_then(serde_json::Value::as_array)
+    .map(|messages| messages.iter().map(message_from_json).collect())
+    .unwrap_or_default();
+
+  InboxCtx {
+    pages: pager
+      .get("pages")
+      .and_then(serde_json::Value::as_u64)
+      .and_then(|p| u32::try_from(p).ok())
+      .unwrap_or(1),
+    messages,
+  }
+}
+
+pub(crate) fn message_from_json(message: &serde_json::Value) -> Message {
+  let field = |name: &str| match message.get(name) {
+    Some(serde_json::Value::String(s)) => Some(s.clone()),
+    Some(serde_json::Value::Number(n)) => Some(n.to_string()),
+    _ => None,
+  };
+
+  let seen_at = field("seen_at");
+  Message {
+    id: field("id").unwrap_or_default(),
+    sender_name: field("sender_name").unwrap_or_default(),
+    sender: field("sender").unwrap_or_default(),
+    created_at: field("created_at").unwrap_or_default(),
+    seen: seen_at.is_some(),
+    seen_at,
+    verified: message.get("verified").and_then(serde_json::Value::as_bool).unwrap_or(false),
+    data: field("data").filter(|d| !d.is_empty()),
+  }
+}
+
+pub(crate) fn peer_ctx(peer: &Peer) -> PeerCtx {
+  let format = |at: Option<OffsetDateTime>| at.and_then(|at| at.format(&Rfc3339).ok());
+
+  PeerCtx {
+    name: peer.name(),
+    identity: peer.identity(),
+    face: peer.face(),
+    pwnd_total: peer.pwnd_total(),
+    encounters: peer.encounters,
+    first_met: format(peer.first_met),
+    last_seen: format(peer.last_seen),
+    channel: peer.last_channel,
+    rssi: peer.rssi,
+    rssi_history: sparkline(&peer.rssi_history),
+  }
+}
+
+/// Renders RSSI samples as block characters, scaled between -100 and -30 dBm.
+pub(crate) fn sparkline(samples: &[i16]) -> String {
+  const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+  samples
+    .iter()
+    .map(|rssi| {
+      let level = usize::try_from((rssi.clamp(&-100, &-30) + 100) / 10).unwrap_or(0);
+      BARS[level.min(BARS.len() - 1)]
+    })
+    .collect()
+}
+
 #[derive(serde::Deserialize)]
 pub struct ToggleForm {
   plugin: String,
   enabled: Option<String>,
-  #[allow(unused)]
-  csrf_token: String,
 }
 
 pub async fn toggle_handler(
@@ -158,6 +309,11 @@ pub async fn toggle_handler(
   }
 }
 
+pub async fn components_handler(State(state): State<Arc<WebUIState>>) -> impl IntoResponse {
+  let components = state.components.read().clone();
+  Json(components)
+}
+
 pub async fn status_handler() -> impl IntoResponse {
   let tpl = StatusTemplate {
     base: make_base("Status", "status"),
@@ -169,6 +325,89 @@ pub async fn status_handler() -> impl IntoResponse {
     Err(_e) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
   }
 }
+#[derive(serde::Deserialize)]
+pub struct PowerForm {
+  mode: Option<String>,
+}
+
+pub async fn confirm_shutdown_handler() -> impl IntoResponse {
+  render_confirm("Shutdown", "This will halt the unit, continue?", "/shutdown", None)
+}
+
+pub async fn confirm_reboot_handler() -> impl IntoResponse {
+  render_confirm("Reboot", "This will reboot the unit, continue?", "/reboot", None)
+}
+
+pub async fn confirm_restart_handler(State(state): State<Arc<WebUIState>>) -> impl IntoResponse {
+  let mode = other_mode(&state);
+  render_confirm(
+    "Restart",
+    &format!("This will restart the service in {mode} mode, continue?"),
+    "/restart",
+    Some(mode.to_string()),
+  )
+}
+
+pub async fn shutdown_handler(State(state): State<Arc<WebUIState>>) -> impl IntoResponse {
+  state.agent.shutdown();
+  render_status("Shutting down ...", 60)
+}
+
+pub async fn reboot_handler(
+  State(state): State<Arc<WebUIState>>,
+  Form(form): Form<PowerForm>,
+) -> impl IntoResponse {
+  let mode = form.mode.and_then(|m| m.parse::<RunningMode>().ok());
+  state.agent.reboot(mode);
+  render_status("Rebooting ...", 60)
+}
+
+pub async fn restart_handler(
+  State(state): State<Arc<WebUIState>>,
+  Form(form): Form<PowerForm>,
+) -> impl IntoResponse {
+  let mode = match form.mode.as_deref().map(str::parse::<RunningMode>) {
+    Some(Ok(mode)) => mode,
+    Some(Err(e)) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    None => other_mode(&state),
+  };
+  state.agent.restart(Some(mode));
+  render_status(&format!("Restarting in {mode} mode ..."), 30).into_response()
+}
+
+fn render_confirm(title: &str, message: &str, action: &str, mode: Option<String>) -> Response {
+  let tpl = ConfirmTemplate {
+    base: make_base(title, "home"),
+    message: message.to_string(),
+    action: action.to_string(),
+    mode,
+  };
+  match tpl.render() {
+    Ok(s) => Html(s).into_response(),
+    Err(_e) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+  }
+}
+
+fn render_status(message: &str, go_back_after: u32) -> Response {
+  let tpl = StatusTemplate {
+    base: make_base("Status", "status"),
+    message: message.to_string(),
+    go_back_after,
+  };
+  match tpl.render() {
+    Ok(s) => Html(s).into_response(),
+    Err(_e) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+  }
+}
+
+fn other_mode(state: &WebUIState) -> RunningMode {
+  if state.sm.get_session().read().mode == RunningMode::Auto {
+    RunningMode::Manual
+  } else {
+    RunningMode::Auto
+  }
+}
+
 pub async fn ui() -> impl IntoResponse {
   let frame = match std::fs::read(FRAME_PATH.as_str()) {
     Ok(data) => data,
@@ -192,6 +431,7 @@ pub fn make_base(title: impl Into<String>, active_page: impl Into<String>) -> Ba
     navigations: default_navigation(),
     active_page: active_page.into(),
     error: String::new(),
+    csrf_token: CSRF_TOKEN.clone(),
   }
 }
 
@@ -233,5 +473,207 @@ fn default_navigation() -> Vec<NavItem> {
       icon: "grid".to_string(),
       label: "Plugins".to_string(),
     },
+    NavItem {
+      url: "/config".to_string(),
+      id: "config".to_string(),
+      icon: "gear".to_string(),
+      label: "Config".to_string(),
+    },
   ]
 }
+
+static SHARE_NONCES: LazyLock<NonceCache> = LazyLock::new(NonceCache::new);
+
+/// Checks a sharing request is enabled and signed by a trusted unit.
+fn authorize_share(headers: &HeaderMap, path: &str) -> Result<(), StatusCode> {
+  let (enabled, trusted, max_skew) = {
+    let cfg = config_read();
+    (cfg.sharing.enabled, cfg.sharing.trusted.clone(), cfg.sharing.max_clock_skew)
+  };
+  if !enabled {
+    return Err(StatusCode::NOT_FOUND);
+  }
+
+  let header = |name| headers.get(name).and_then(|v| v.to_str().ok()).unwrap_or_default();
+  let request = SignedRequest {
+    fingerprint: header(FINGERPRINT_HEADER).to_string(),
+    public_key: header(PUBLIC_KEY_HEADER).to_string(),
+    timestamp: header(TIMESTAMP_HEADER).parse().unwrap_or_default(),
+    nonce: header(NONCE_HEADER).to_string(),
+    signature: header(SIGNATURE_HEADER).to_string(),
+  };
+
+  let now = OffsetDateTime::now_utc().unix_timestamp();
+  request
+    .verify(path, &trusted, &SHARE_NONCES, now, max_skew)
+    .map(|_| ())
+    .map_err(|e| {
+      LOGGER.log_warning("Sharing", &format!("Rejected {path}: {e}"));
+      StatusCode::FORBIDDEN
+    })
+}
+
+pub async fn share_inventory_handler(
+  State(state): State<Arc<WebUIState>>,
+  headers: HeaderMap,
+) -> Response {
+  if let Err(status) = authorize_share(&headers, INVENTORY_PATH) {
+    return status.into_response();
+  }
+
+  let identity = Arc::clone(&state.identity);
+  let res = tokio::task::spawn_blocking(move || {
+    let handshakes = local_inventory(config_read().bettercap.handshakes.as_ref());
+    Inventory::signed(&identity.read(), handshakes, OffsetDateTime::now_utc().unix_timestamp())
+  })
+  .await;
+
+  match res {
+    Ok(Ok(inventory)) => Json(inventory).into_response(),
+    Ok(Err(e)) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+  }
+}
+
+pub async fn share_capture_handler(
+  State(state): State<Arc<WebUIState>>,
+  Path(name): Path<String>,
+  headers: HeaderMap,
+) -> Response {
+  if let Err(status) = authorize_share(&headers, &format!("{CAPTURES_PATH}/{name}")) {
+    return status.into_response();
+  }
+  if !is_capture_name(&name) {
+    return StatusCode::BAD_REQUEST.into_response();
+  }
+
+  let path = std::path::Path::new(config_read().bettercap.handshakes.as_ref()).join(&name);
+  let Ok(Ok(data)) = tokio::task::spawn_blocking(move || std::fs::read(path)).await else {
+    return StatusCode::NOT_FOUND.into_response();
+  };
+
+  let sha256 = sha256_hex(&data);
+  let Ok(signature) = state.identity.read().sign(&capture_payload(&name, &sha256)) else {
+    return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+  };
+
+  (
+    [
+      (header::CONTENT_TYPE, "application/vnd.tcpdump.pcap".to_string()),
+      (HeaderName::from_static(SHA256_HEADER), sha256),
+      (HeaderName::from_static(SIGNATURE_HEADER), signature),
+    ],
+    data,
+  )
+    .into_response()
+}
+
+pub async fn backup_handler(State(state): State<Arc<WebUIState>>) -> impl IntoResponse {
+  let tpl = BackupTemplate {
+    base: make_base("Backup", "backup"),
+    fingerprint: state.identity.read().fingerprint().to_string(),
+    handshakes: total_unique_handshakes(&config_read().bettercap.handshakes),
+  };
+  match tpl.render() {
+    Ok(s) => Html(s).into_response(),
+    Err(_e) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+  }
+}
+
+pub async fn config_handler() -> impl IntoResponse {
+  let Ok(sections) = editor::sections(&config_read()) else {
+    return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+  };
+
+  let tpl = ConfigTemplate {
+    base: make_base("Config", "config"),
+    sections,
+  };
+  match tpl.render() {
+    Ok(s) => Html(s).into_response(),
+    Err(_e) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+  }
+}
+
+#[derive(serde::Deserialize)]
+pub struct BackupForm {
+  passphrase: String,
+}
+
+pub async fn download_backup_handler(Form(form): Form<BackupForm>) -> Response {
+  let res = tokio::task::spawn_blocking(move || {
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let bundle = Bundle::collect(&Sources::from_config(), &config_read().main.name, now)?;
+    Ok::<_, anyhow::Error>((
+      bundle.name.clone(),
+      bundle.seal(&form.passphrase, DEFAULT_ITERATIONS)?,
+    ))
+  })
+  .await;
+
+  match res {
+    Ok(Ok((name, archive))) => (
+      [
+        (header::CONTENT_TYPE, "application/octet-stream".to_string()),
+        (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{name}.pwnbak\"")),
+      ],
+      archive,
+    )
+      .into_response(),
+    Ok(Err(e)) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+  }
+}
+
+#[derive(serde::Deserialize)]
+pub struct RestoreQuery {
+  #[serde(default)]
+  force: bool,
+}
+
+pub async fn restore_backup_handler(
+  Query(query): Query<RestoreQuery>,
+  headers: HeaderMap,
+  body: Bytes,
+) -> Response {
+  let passphrase = headers
+    .get(BACKUP_PASSPHRASE_HEADER)
+    .and_then(|v| v.to_str().ok())
+    .unwrap_or_default()
+    .to_string();
+
+  let res = tokio::task::spawn_blocking(move || {
+    let bundle = Bundle::open(&body, &passphrase).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let report = bundle.restore(&Sources::from_config(), None, query.force).map_err(|e| {
+      // anything but an I/O error is a problem with the backup
+      if e.downcast_ref::<std::io::Error>().is_some() {
+        (StatusCode::INTERNAL_SERVER_ERROR, e)
+      } else {
+        (StatusCode::CONFLICT, e)
+      }
+    })?;
+    LOGGER.log_info(
+      "Backup",
+      &format!(
+        "Restored {} ({}), {} files written",
+        bundle.name, bundle.fingerprint, report.written
+      ),
+    );
+    Ok::<_, (StatusCode, anyhow::Error)>(report)
+  })
+  .await;
+
+  match res {
+    Ok(Ok(report)) => {
+      Json(serde_json::json!({ "written": report.written, "unchanged": report.unchanged }))
+        .into_response()
+    }
+    Ok(Err((status, e))) => {
+      (status, Json(serde_json::json!({ "error": e.to_string() }))).into_response()
+    }
+    Err(e) => {
+      (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({ "error": e.to_string() })))
+        .into_response()
+    }
+  }
+}
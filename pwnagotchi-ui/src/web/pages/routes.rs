@@ -1,7 +1,7 @@
 #![allow(clippy::missing_panics_doc, dead_code)]
 
 use askama::Template;
-use pwnagotchi_shared::mesh::peer::Peer;
+use pwnagotchi_shared::config::editor::{FieldKind, Section};
 use serde_json::Value;
 
 #[derive(Clone, serde::Serialize, serde::Deserialize)]
@@ -26,9 +26,24 @@ pub struct Message {
   pub created_at: String,
   pub seen_at: Option<String>,
   pub seen: bool,
+  pub verified: bool,
   pub data: Option<String>,
 }
 
+#[derive(Clone, Default)]
+pub struct PeerCtx {
+  pub name: String,
+  pub identity: String,
+  pub face: String,
+  pub pwnd_total: u32,
+  pub encounters: u32,
+  pub first_met: Option<String>,
+  pub last_seen: Option<String>,
+  pub channel: u8,
+  pub rssi: i16,
+  pub rssi_history: String,
+}
+
 #[derive(Clone, Default)]
 pub struct PluginCtx {
   pub name: String,
@@ -44,6 +59,7 @@ pub struct BaseCtx {
   pub navigations: Vec<NavItem>,
   pub active_page: String,
   pub error: String,
+  pub csrf_token: String,
 }
 
 #[derive(Template)]
@@ -74,7 +90,7 @@ pub struct NewMessageTemplate {
 pub struct PeersTemplate {
   pub base: BaseCtx,
   pub name: String,
-  pub peers: Vec<Peer>,
+  pub peers: Vec<PeerCtx>,
 }
 
 #[derive(Template)]
@@ -91,7 +107,6 @@ pub struct ProfileTemplate {
 pub struct PluginsTemplate {
   pub base: BaseCtx,
   pub plugins: Vec<PluginCtx>,
-  pub csrf_token: String,
 }
 
 #[derive(Template)]
@@ -101,6 +116,21 @@ pub struct MessageTemplate {
   pub message: Message,
 }
 
+#[derive(Template)]
+#[template(path = "backup.html")]
+pub struct BackupTemplate {
+  pub base: BaseCtx,
+  pub fingerprint: String,
+  pub handshakes: u32,
+}
+
+#[derive(Template)]
+#[template(path = "config.html")]
+pub struct ConfigTemplate {
+  pub base: BaseCtx,
+  pub sections: Vec<Section>,
+}
+
 #[derive(Template)]
 #[template(path = "status.html")]
 pub struct StatusTemplate {
@@ -108,3 +138,12 @@ pub struct StatusTemplate {
   pub message: String,
   pub go_back_after: u32,
 }
+
+#[derive(Template)]
+#[template(path = "confirm.html")]
+pub struct ConfirmTemplate {
+  pub base: BaseCtx,
+  pub message: String,
+  pub action: String,
+  pub mode: Option<String>,
+}
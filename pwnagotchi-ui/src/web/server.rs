@@ -1,11 +1,14 @@
-use std::{borrow::Cow, sync::Arc};
+use std::{
+  borrow::Cow,
+  sync::{Arc, LazyLock},
+};
 
 use anyhow::Result;
 use axum::{
   Router,
   body::Body,
-  extract::{FromRequestParts, Path},
-  http::{Request, StatusCode, header},
+  extract::{DefaultBodyLimit, FromRequestParts, Path},
+  http::{Method, Request, StatusCode, header},
   middleware::{self, Next},
   response::{IntoResponse, Response},
   routing::{get, post},
@@ -18,20 +21,61 @@ use pwnagotchi_shared::{
   config::config_read,
   identity::Identity,
   logger::LOGGER,
+  mesh::{
+    peer::PeerRegistry,
+    sharing::{CAPTURES_PATH, INVENTORY_PATH},
+  },
   sessions::manager::SessionManager,
-  traits::{general::Dependencies, grid::GridTrait, ui::ServerTrait},
+  traits::{
+    agent::AgentTrait,
+    epoch::Epoch,
+    general::{CoreModules, Dependencies},
+    grid::GridTrait,
+    ui::ServerTrait,
+  },
+  types::components::ComponentRegistry,
 };
 use tokio::sync::oneshot;
 
-use crate::web::pages::handler::{
-  inbox_handler, index_handler, message_handler, new_message_handler, peers_handler,
-  plugins_handler, profile_handler, status_handler, toggle_handler, ui,
+use crate::web::{
+  api,
+  pages::handler::{
+    backup_handler, components_handler, config_handler, confirm_reboot_handler,
+    confirm_restart_handler, confirm_shutdown_handler, download_backup_handler, inbox_handler,
+    index_handler, mark_message_handler, message_handler, new_message_handler, peers_handler,
+    plugins_handler, profile_handler, reboot_handler, restart_handler, restore_backup_handler,
+    send_message_handler, share_capture_handler, share_inventory_handler, shutdown_handler,
+    status_handler, toggle_handler, ui,
+  },
 };
 
 pub static TEMPLATE_ASSETS: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/assets/templates");
 pub static STATIC_ASSETS: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/assets/static");
 pub static FONT_ASSETS: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/assets/fonts");
 
+/// Header ajax requests carry [`CSRF_TOKEN`] in.
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
+/// Proves a state changing request was sent by one of our pages, new on every
+/// start.
+pub static CSRF_TOKEN: LazyLock<String> = LazyLock::new(|| {
+  let mut token = [0u8; 32];
+  if let Err(e) = getrandom::getrandom(&mut token) {
+    // without a token nothing could be submitted, fall back to something
+    // unguessable enough for a page that is behind basic auth anyway
+    LOGGER.log_error("Server", &format!("No randomness for the CSRF token: {e}"));
+    token = std::array::from_fn(|_| fastrand::u8(..));
+  }
+  hex::encode(token)
+});
+
+/// Forms are small, anything bigger is rejected before the token is found.
+const MAX_FORM_SIZE: usize = 64 * 1024;
+
+/// Uploaded backups carry every handshake, more than the default limit but
+/// still small enough to hold in memory on a Pi Zero.
+const MAX_BACKUP_SIZE: usize = 32 * 1024 * 1024;
+
 impl Dependencies for Server {
   fn name(&self) -> &'static str {
     "WebUI"
@@ -47,8 +91,12 @@ impl Dependencies for Server {
 pub struct WebUIState {
   pub sm: Arc<SessionManager>,
   pub identity: Arc<RwLock<Identity>>,
+  pub epoch: Arc<RwLock<Epoch>>,
   pub pluginmanager: Arc<RwLock<PluginManager>>,
   pub grid: Arc<dyn GridTrait + Send + Sync>,
+  pub agent: Arc<dyn AgentTrait + Send + Sync>,
+  pub components: ComponentRegistry,
+  pub peers: PeerRegistry,
 }
 
 pub struct Server {
@@ -133,6 +181,49 @@ async fn basic_auth_middleware(req: Request<Body>, next: Next) -> impl IntoRespo
     .into_response()
 }
 
+/// Rejects state changing requests that didn't come from our own pages, which
+/// send [`CSRF_TOKEN`] in a `csrf_token` form field or the `X-CSRF-Token`
+/// header. JSON bodies pass too, browsers only send those cross-site after a
+/// CORS preflight this server never answers.
+async fn csrf_middleware(req: Request<Body>, next: Next) -> Response {
+  if matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS) {
+    return next.run(req).await;
+  }
+
+  let headers = req.headers();
+  if headers
+    .get(CSRF_HEADER)
+    .and_then(|v| v.to_str().ok())
+    .is_some_and(|token| compare_safely(token, &CSRF_TOKEN))
+  {
+    return next.run(req).await;
+  }
+
+  let content_type = headers
+    .get(header::CONTENT_TYPE)
+    .and_then(|v| v.to_str().ok())
+    .and_then(|v| v.split(';').next())
+    .map(|v| v.trim().to_ascii_lowercase())
+    .unwrap_or_default();
+
+  match content_type.as_str() {
+    "application/json" => next.run(req).await,
+    "application/x-www-form-urlencoded" => {
+      let (parts, body) = req.into_parts();
+      let Ok(bytes) = axum::body::to_bytes(body, MAX_FORM_SIZE).await else {
+        return StatusCode::PAYLOAD_TOO_LARGE.into_response();
+      };
+      let valid = form_urlencoded::parse(&bytes)
+        .any(|(name, token)| name == "csrf_token" && compare_safely(&token, &CSRF_TOKEN));
+      if !valid {
+        return (StatusCode::FORBIDDEN, "Missing or invalid CSRF token").into_response();
+      }
+      next.run(Request::from_parts(parts, Body::from(bytes))).await
+    }
+    _ => (StatusCode::FORBIDDEN, "Missing or invalid CSRF token").into_response(),
+  }
+}
+
 fn compare_safely(a: &str, b: &str) -> bool {
   if a.len() != b.len() {
     return false;
@@ -142,39 +233,73 @@ fn compare_safely(a: &str, b: &str) -> bool {
 }
 
 pub fn build_router(
-  sm: Arc<SessionManager>,
-  identity: Arc<RwLock<Identity>>,
+  core: &CoreModules,
   pluginmanager: Arc<RwLock<PluginManager>>,
-  grid: Arc<dyn GridTrait + Send + Sync>,
+  components: ComponentRegistry,
+  peers: PeerRegistry,
 ) -> Router {
-  let state = Arc::new(WebUIState { sm, identity, pluginmanager, grid });
+  let state = Arc::new(WebUIState {
+    sm: Arc::clone(&core.session_manager),
+    identity: Arc::clone(&core.identity),
+    epoch: Arc::clone(&core.epoch),
+    pluginmanager,
+    grid: Arc::clone(&core.grid),
+    agent: Arc::clone(&core.agent),
+    components,
+    peers,
+  });
 
-  Router::new()
-    .layer(middleware::from_fn(basic_auth_middleware))
+  router(state)
+}
+
+/// Every page and the API behind basic auth and the CSRF check, the sharing
+/// routes authenticate requests by their signature instead.
+pub fn router(state: Arc<WebUIState>) -> Router {
+  let protected = Router::new()
     // Template routes
     .route("/", get(index_handler))
     .route("/index", get(index_handler))
     .route("/ui", get(ui))
     // System Actions
-    .route("/shutdown", get(static_handler))
-    .route("/reboot", get(static_handler))
-    .route("/restart", get(static_handler))
+    .route("/shutdown", get(confirm_shutdown_handler).post(shutdown_handler))
+    .route("/reboot", get(confirm_reboot_handler).post(reboot_handler))
+    .route("/restart", get(confirm_restart_handler).post(restart_handler))
     // Inbox
     .route("/inbox", get(inbox_handler))
     .route("/inbox/profile", get(profile_handler))
     .route("/inbox/peers", get(peers_handler))
-    .route("/inbox/{id}", get(peers_handler))
-    .route("/inbox/{id}/mark", get(peers_handler))
+    .route("/inbox/{id}", get(message_handler))
+    .route("/inbox/{id}/{mark}", post(mark_message_handler))
     .route("/inbox/new", get(new_message_handler))
-    .route("/inbox/send", get(new_message_handler))
+    .route("/inbox/send", get(new_message_handler).post(send_message_handler))
     // Plugins
     .route("/plugins", get(plugins_handler))
     .route("/plugins/toggle", post(toggle_handler))
     //.route("/plugins/{plugin}", get(plugin_template_handler))
     .route("/status", get(status_handler))
-    .route("/message", get(message_handler))
+    // Config
+    .route("/config", get(config_handler))
+    // Backup
+    .route("/backup", get(backup_handler))
+    .route("/backup/download", post(download_backup_handler))
+    .route(
+      "/backup/restore",
+      post(restore_backup_handler).layer(DefaultBodyLimit::max(MAX_BACKUP_SIZE)),
+    )
+    // API
+    .route("/api/components", get(components_handler))
+    .nest("/api/v1", api::router())
     // Static
     .route("/{*path}", get(static_handler))
+    // layers run last to first, auth before the CSRF check
+    .route_layer(middleware::from_fn(csrf_middleware))
+    .route_layer(middleware::from_fn(basic_auth_middleware));
+
+  Router::new()
+    // Handshake sharing, authenticated by signature
+    .route(INVENTORY_PATH, get(share_inventory_handler))
+    .route(&format!("{CAPTURES_PATH}/{{name}}"), get(share_capture_handler))
+    .merge(protected)
     .with_state(state)
 }
 